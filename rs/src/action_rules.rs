@@ -0,0 +1,335 @@
+//! Declarative multi-step action rules (`actions:` in the CLI config).
+//!
+//! `enter` and `typingRespond` are one-shot reactions: one keystroke or one
+//! string per matched screen. Some prompts need a short script instead — arrow
+//! down twice, Space to tick a box, Enter, wait for the redraw, then type a
+//! reply. An action rule maps a screen match to such an ordered step list.
+//!
+//! Rules are compiled once with the rest of the `CliConfig`; a fired rule is
+//! driven step by step by an `ActionRun` from both `check_patterns` (new
+//! screens) and the heartbeat (timed waits), so a `wait`/`waitFor` step never
+//! blocks the main loop. The one-shot screen-hash de-dupe in `AgentContext`
+//! applies to rules exactly as it does to `enter`: a rule fires once per screen.
+
+use crate::config_loader::{compile_regex_list, ActionRuleOverride, ActionStepOverride};
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use std::time::{Duration, Instant};
+
+/// How long a `waitFor` step waits for its pattern before the run is abandoned,
+/// when the rule doesn't set `timeoutMs`. Long enough for a slow TUI redraw,
+/// short enough that a rule whose screen never arrives can't hold the keyboard
+/// (a running rule suppresses `enter`/`typingRespond`).
+pub const DEFAULT_ACTION_TIMEOUT_MS: u64 = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct ActionRule {
+    /// Label for logs; defaults to `action#<index>`.
+    pub name: String,
+    /// Fires when ANY of these matches the rendered screen…
+    pub patterns: Vec<Regex>,
+    /// …and NONE of these do (guard patterns, like `enterExclude`).
+    pub exclude: Vec<Regex>,
+    pub steps: Vec<ActionStep>,
+    /// Per-step limit for `waitFor`.
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub enum ActionStep {
+    /// Named keys, already resolved to the bytes a terminal would send.
+    Keys(String),
+    /// Literal text, typed as-is (no implicit Enter).
+    Text(String),
+    /// Pause before the next step.
+    Wait(Duration),
+    /// Hold until the rendered screen matches (or the rule times out).
+    WaitFor(Regex),
+}
+
+impl ActionRule {
+    pub fn matches(&self, screen: &str) -> bool {
+        self.patterns.iter().any(|p| p.is_match(screen))
+            && !self.exclude.iter().any(|p| p.is_match(screen))
+    }
+}
+
+/// Result of advancing a running rule by one step.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ActionPoll {
+    /// Write these bytes to the PTY, then poll again.
+    Send(String),
+    /// Waiting on a timer or a screen; poll again on the next tick.
+    Pending,
+    /// All steps done.
+    Done,
+    /// A `waitFor` step didn't see its pattern within the rule's timeout.
+    TimedOut,
+}
+
+/// Progress through one fired rule. Pure state: the caller supplies the screen
+/// and the clock, so the stepping logic is unit-testable without a PTY.
+#[derive(Debug)]
+pub struct ActionRun {
+    pub rule: usize,
+    step: usize,
+    step_started: Instant,
+}
+
+impl ActionRun {
+    pub fn new(rule: usize, now: Instant) -> Self {
+        Self {
+            rule,
+            step: 0,
+            step_started: now,
+        }
+    }
+
+    /// Start the current step's clock over: its keys were held back until
+    /// `now`, so a wait after them counts from here.
+    pub fn restart_step(&mut self, now: Instant) {
+        self.step_started = now;
+    }
+
+    fn advance(&mut self, now: Instant) {
+        self.step += 1;
+        self.step_started = now;
+    }
+
+    pub fn poll(&mut self, rule: &ActionRule, screen: &str, now: Instant) -> ActionPoll {
+        loop {
+            let Some(step) = rule.steps.get(self.step) else {
+                return ActionPoll::Done;
+            };
+            match step {
+                ActionStep::Keys(s) | ActionStep::Text(s) => {
+                    let out = s.clone();
+                    self.advance(now);
+                    return ActionPoll::Send(out);
+                }
+                ActionStep::Wait(d) => {
+                    if now.saturating_duration_since(self.step_started) < *d {
+                        return ActionPoll::Pending;
+                    }
+                    self.advance(now);
+                }
+                ActionStep::WaitFor(rx) => {
+                    if rx.is_match(screen) {
+                        self.advance(now);
+                    } else if now.saturating_duration_since(self.step_started) >= rule.timeout {
+                        return ActionPoll::TimedOut;
+                    } else {
+                        return ActionPoll::Pending;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Resolve a key name to the bytes an xterm-style terminal sends for it.
+/// Names are case-insensitive; a single character stands for itself (`"y"`,
+/// `"1"`), and `ctrl-<letter>` maps to the control byte.
+pub fn key_bytes(name: &str) -> Option<String> {
+    let lower = name.to_ascii_lowercase();
+    let named = match lower.as_str() {
+        "enter" | "return" | "cr" => "\r",
+        "tab" => "\t",
+        "shift-tab" | "backtab" => "\x1b[Z",
+        "space" => " ",
+        "esc" | "escape" => "\x1b",
+        "backspace" => "\x7f",
+        "delete" | "del" => "\x1b[3~",
+        "up" => "\x1b[A",
+        "down" => "\x1b[B",
+        "right" => "\x1b[C",
+        "left" => "\x1b[D",
+        "home" => "\x1b[H",
+        "end" => "\x1b[F",
+        "pageup" => "\x1b[5~",
+        "pagedown" => "\x1b[6~",
+        _ => "",
+    };
+    if !named.is_empty() {
+        return Some(named.to_string());
+    }
+    if let Some(letter) = lower
+        .strip_prefix("ctrl-")
+        .or_else(|| lower.strip_prefix("c-"))
+    {
+        let mut chars = letter.chars();
+        if let (Some(c @ 'a'..='z'), None) = (chars.next(), chars.next()) {
+            return Some(((c as u8 - b'a' + 1) as char).to_string());
+        }
+        return None;
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c.to_string()),
+        _ => None,
+    }
+}
+
+pub fn compile_action_rules(rules: Option<Vec<ActionRuleOverride>>) -> Result<Vec<ActionRule>> {
+    rules
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .map(|(i, raw)| {
+            let name = raw.name.clone().unwrap_or_else(|| format!("action#{}", i));
+            compile_action_rule(name.clone(), raw)
+                .with_context(|| format!("Invalid action rule '{}'", name))
+        })
+        .collect()
+}
+
+fn compile_action_rule(name: String, raw: ActionRuleOverride) -> Result<ActionRule> {
+    if raw.patterns.is_empty() {
+        return Err(anyhow!("`match` needs at least one pattern"));
+    }
+    if raw.steps.is_empty() {
+        return Err(anyhow!("`steps` is empty"));
+    }
+    let steps = raw
+        .steps
+        .into_iter()
+        .map(|step| {
+            Ok(match step {
+                ActionStepOverride::Keys { keys } => ActionStep::Keys(
+                    keys.iter()
+                        .map(|k| key_bytes(k).ok_or_else(|| anyhow!("Unknown key '{}'", k)))
+                        .collect::<Result<String>>()?,
+                ),
+                ActionStepOverride::Text { text } => ActionStep::Text(text),
+                ActionStepOverride::Wait { wait } => ActionStep::Wait(Duration::from_millis(wait)),
+                ActionStepOverride::WaitFor { wait_for } => ActionStep::WaitFor(
+                    compile_regex_list(Some(vec![wait_for]))?
+                        .pop()
+                        .expect("one source compiles to one regex"),
+                ),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ActionRule {
+        name,
+        patterns: compile_regex_list(Some(raw.patterns))?,
+        exclude: compile_regex_list(raw.exclude)?,
+        steps,
+        timeout: Duration::from_millis(raw.timeout_ms.unwrap_or(DEFAULT_ACTION_TIMEOUT_MS)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_from_yaml(yaml: &str) -> Result<Vec<ActionRule>> {
+        let raw: Vec<ActionRuleOverride> = serde_yaml::from_str(yaml).unwrap();
+        compile_action_rules(Some(raw))
+    }
+
+    #[test]
+    fn test_key_bytes() {
+        assert_eq!(key_bytes("Enter").as_deref(), Some("\r"));
+        assert_eq!(key_bytes("down").as_deref(), Some("\x1b[B"));
+        assert_eq!(key_bytes("space").as_deref(), Some(" "));
+        assert_eq!(key_bytes("ctrl-c").as_deref(), Some("\x03"));
+        assert_eq!(key_bytes("y").as_deref(), Some("y"));
+        assert_eq!(key_bytes("2").as_deref(), Some("2"));
+        assert_eq!(key_bytes("ctrl-1"), None);
+        assert_eq!(key_bytes("hyper"), None);
+    }
+
+    #[test]
+    fn test_compile_rules_from_yaml() {
+        let rules = rules_from_yaml(
+            r#"
+- name: pick-second
+  match:
+    - pattern: '❯ 1\. Keep'
+      flags: m
+  exclude: ['Do not pick']
+  timeoutMs: 500
+  steps:
+    - keys: [down, down, space, enter]
+    - wait: 200
+    - waitFor: 'Describe the change'
+    - text: "looks good"
+    - keys: [enter]
+"#,
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 1);
+        let r = &rules[0];
+        assert_eq!(r.name, "pick-second");
+        assert_eq!(r.timeout, Duration::from_millis(500));
+        assert_eq!(r.steps.len(), 5);
+        assert!(matches!(&r.steps[0], ActionStep::Keys(k) if k == "\x1b[B\x1b[B \r"));
+        assert!(matches!(r.steps[1], ActionStep::Wait(d) if d == Duration::from_millis(200)));
+        assert!(r.matches("❯ 1. Keep both"));
+        assert!(!r.matches("❯ 1. Keep both\nDo not pick"));
+        assert!(!r.matches("something else"));
+    }
+
+    #[test]
+    fn test_compile_rejects_unknown_keys_and_empty_rules() {
+        let err = rules_from_yaml("- match: ['x']\n  steps:\n    - keys: [hyper]\n").unwrap_err();
+        assert!(
+            format!("{err:#}").contains("Unknown key 'hyper'"),
+            "{err:#}"
+        );
+        assert!(rules_from_yaml("- match: []\n  steps:\n    - text: a\n").is_err());
+        assert!(rules_from_yaml("- match: ['x']\n  steps: []\n").is_err());
+        // Unnamed rules get a positional label for logs.
+        let rules = rules_from_yaml("- match: ['x']\n  steps:\n    - text: a\n").unwrap();
+        assert_eq!(rules[0].name, "action#0");
+    }
+
+    #[test]
+    fn test_run_sends_waits_and_finishes() {
+        let rules = rules_from_yaml(
+            r#"
+- match: ['menu']
+  steps:
+    - keys: [down]
+    - wait: 100
+    - waitFor: 'redrawn'
+    - text: "hi"
+"#,
+        )
+        .unwrap();
+        let rule = &rules[0];
+        let t0 = Instant::now();
+        let mut run = ActionRun::new(0, t0);
+        assert_eq!(
+            run.poll(rule, "menu", t0),
+            ActionPoll::Send("\x1b[B".into())
+        );
+        // Wait step holds until its duration elapses.
+        assert_eq!(run.poll(rule, "menu", t0), ActionPoll::Pending);
+        let t1 = t0 + Duration::from_millis(100);
+        // Wait done → falls through to waitFor, which holds on the old screen.
+        assert_eq!(run.poll(rule, "menu", t1), ActionPoll::Pending);
+        let t2 = t1 + Duration::from_millis(50);
+        assert_eq!(run.poll(rule, "redrawn", t2), ActionPoll::Send("hi".into()));
+        assert_eq!(run.poll(rule, "redrawn", t2), ActionPoll::Done);
+    }
+
+    #[test]
+    fn test_run_times_out_waiting_for_screen() {
+        let rules = rules_from_yaml(
+            "- match: ['x']\n  timeoutMs: 1000\n  steps:\n    - waitFor: 'never'\n",
+        )
+        .unwrap();
+        let t0 = Instant::now();
+        let mut run = ActionRun::new(0, t0);
+        assert_eq!(
+            run.poll(&rules[0], "x", t0 + Duration::from_millis(999)),
+            ActionPoll::Pending
+        );
+        assert_eq!(
+            run.poll(&rules[0], "x", t0 + Duration::from_millis(1000)),
+            ActionPoll::TimedOut
+        );
+    }
+}
@@ -11,8 +11,15 @@
 //! serialized wire format shared through `~/.agent-yes/pids.jsonl`).
 //!
 //! Issue #236.
+//!
+//! When the CLI runs behind an approval policy (approval_policy.rs), the stamp
+//! says so, and each decision the policy took on a permission prompt is
+//! appended beside the agent's logs as `<pid>.approvals.jsonl` — the stamp
+//! answers "could it act unattended?", the audit log "what did it approve?".
 
 use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AgentPermissions {
@@ -25,6 +32,59 @@ pub struct AgentPermissions {
     pub robust: bool,
     /// agent-yes auto-continue: resume the prior session on restart.
     pub auto_continue: bool,
+    /// Permission prompts went through an approval policy instead of blanket
+    /// auto-yes. Absent in records written before the policy existed.
+    #[serde(default)]
+    pub approval_policy: bool,
+    /// The CLI ran in a `--sandbox` (sandbox.rs): it saw only these paths.
+    /// None when it ran with the user's full filesystem and network — the
+    /// difference between "yolo but sandboxed" and "yolo unsandboxed".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxStamp>,
+}
+
+/// The sandbox profile an agent ran under.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxStamp {
+    /// The cwd first, then the configured `readWrite` paths.
+    pub read_write: Vec<String>,
+    pub read_only: Vec<String>,
+    /// False when the agent had its own network namespace (loopback only).
+    pub network: bool,
+}
+
+/// One approval-policy decision, as a line of `<pid>.approvals.jsonl`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalRecord {
+    /// Unix ms.
+    pub at: u64,
+    /// `allow` | `deny` | `ask`.
+    pub verdict: String,
+    /// The rule name that decided, `outsideCwd`, or `default`.
+    pub rule: String,
+    /// `command` | `path`.
+    pub kind: String,
+    /// The proposed command or file path as read off the screen.
+    pub subject: String,
+}
+
+/// Append a decision to `<log_dir>/<pid>.approvals.jsonl`. Best-effort: an
+/// audit write failure must never block or alter the decision itself.
+pub fn record_approval(log_dir: &Path, pid: u32, record: &ApprovalRecord) {
+    let Ok(line) = serde_json::to_string(record) else {
+        return;
+    };
+    let _ = std::fs::create_dir_all(log_dir);
+    match std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_dir.join(format!("{}.approvals.jsonl", pid)))
+    {
+        Ok(mut f) => {
+            let _ = f.write_all(format!("{}\n", line).as_bytes());
+        }
+        Err(e) => tracing::warn!("Failed to record approval decision: {}", e),
+    }
 }
 
 /// Flags that disable a CLI's confirmation gate, recognised regardless of what
@@ -63,6 +123,8 @@ pub fn derive_permissions(
         skip_permissions,
         robust,
         auto_continue,
+        approval_policy: false,
+        sandbox: None,
     }
 }
 
@@ -132,5 +194,33 @@ mod tests {
         assert!(json.contains("\"skip_permissions\":false"));
         assert!(json.contains("\"robust\":true"));
         assert!(json.contains("\"auto_continue\":false"));
+        // Records stamped before the approval policy existed still parse.
+        let old: AgentPermissions = serde_json::from_str(
+            r#"{"skip_permissions":true,"robust":false,"auto_continue":false}"#,
+        )
+        .unwrap();
+        assert!(!old.approval_policy);
+        assert_eq!(old.sandbox, None);
+        assert!(!json.contains("sandbox"));
+    }
+
+    #[test]
+    fn test_record_approval_appends_jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let rec = |verdict: &str| ApprovalRecord {
+            at: 1,
+            verdict: verdict.into(),
+            rule: "no-force-push".into(),
+            kind: "command".into(),
+            subject: "git push --force".into(),
+        };
+        record_approval(dir.path(), 7, &rec("deny"));
+        record_approval(dir.path(), 7, &rec("allow"));
+        let text = std::fs::read_to_string(dir.path().join("7.approvals.jsonl")).unwrap();
+        let back: Vec<ApprovalRecord> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(back, vec![rec("deny"), rec("allow")]);
     }
 }
@@ -0,0 +1,423 @@
+//! Approval policy for permission prompts (`approval:` in the CLI config).
+//!
+//! The `enter` patterns accept every "1. Yes" prompt whatever it approves.
+//! When a CLI declares an approval policy, a prompt that `enter` matched is
+//! first run through it: the proposed shell command or file path is pulled off
+//! the rendered screen (capture group 1 of the `command` / `path` extractors),
+//! checked against the `rules` in order, and the first matching rule decides —
+//!   - `allow`: press Enter as before;
+//!   - `deny`:  send `denyKeys` (Esc by default) to pick "No";
+//!   - `ask`:   leave the prompt up for a human (it reads as `needs_input`).
+//!
+//! A capture spanning several rows (a command the CLI wrapped inside its box)
+//! is joined back into one line before any rule sees it. A proposal that looks
+//! cut short — it ends in an ellipsis, or runs off the bottom of the screen —
+//! gets `ask` unless a `deny` rule caught it anyway: what's missing could be
+//! what a rule is looking for.
+//!
+//! A file path no rule matched that resolves outside the agent's cwd gets the
+//! `outsideCwd` verdict; anything else gets `default`. A prompt neither
+//! extractor recognises isn't a permission prompt, so it falls through to
+//! plain auto-yes. Every decision is audited next to the agent's permission
+//! stamp (see agent_permissions.rs).
+
+use crate::action_rules::key_bytes;
+use crate::config_loader::{compile_regex_list, ApprovalOverride, ApprovalRuleOverride};
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    Deny,
+    Ask,
+}
+
+impl Verdict {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Verdict::Allow => "allow",
+            Verdict::Deny => "deny",
+            Verdict::Ask => "ask",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "allow" => Ok(Verdict::Allow),
+            "deny" => Ok(Verdict::Deny),
+            "ask" => Ok(Verdict::Ask),
+            other => Err(anyhow!(
+                "Unknown verdict '{}' (expected allow, deny or ask)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProposalKind {
+    Command,
+    Path,
+}
+
+impl ProposalKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProposalKind::Command => "command",
+            ProposalKind::Path => "path",
+        }
+    }
+}
+
+/// What the permission prompt on screen asks to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proposal {
+    pub kind: ProposalKind,
+    pub subject: String,
+    /// The screen shows only part of it.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApprovalRule {
+    /// Label for the audit log; defaults to `rule#<index>`.
+    pub name: String,
+    pub verdict: Verdict,
+    /// Matched against a proposed command…
+    pub command: Option<Regex>,
+    /// …or a proposed file path. A rule with neither never matches.
+    pub path: Option<Regex>,
+}
+
+impl ApprovalRule {
+    fn matches(&self, proposal: &Proposal) -> bool {
+        let rx = match proposal.kind {
+            ProposalKind::Command => &self.command,
+            ProposalKind::Path => &self.path,
+        };
+        rx.as_ref().is_some_and(|rx| rx.is_match(&proposal.subject))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApprovalPolicy {
+    /// Extractors for a proposed shell command (capture group 1).
+    pub command: Vec<Regex>,
+    /// Extractors for a proposed file path (capture group 1).
+    pub path: Vec<Regex>,
+    pub rules: Vec<ApprovalRule>,
+    pub default: Verdict,
+    pub outside_cwd: Verdict,
+    /// Bytes that pick "No" on the prompt.
+    pub deny_keys: String,
+}
+
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            path: Vec::new(),
+            rules: Vec::new(),
+            default: Verdict::Allow,
+            outside_cwd: Verdict::Allow,
+            deny_keys: "\x1b".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub verdict: Verdict,
+    /// The rule that decided: its name, `outsideCwd`, or `default`.
+    pub rule: String,
+    pub proposal: Proposal,
+}
+
+impl ApprovalPolicy {
+    /// A policy without extractors can't see any proposal — plain auto-yes.
+    pub fn is_enabled(&self) -> bool {
+        !self.command.is_empty() || !self.path.is_empty()
+    }
+
+    /// Pull the proposed command (preferred) or file path off the screen.
+    pub fn extract(&self, screen: &str) -> Option<Proposal> {
+        let first = |rxs: &[Regex], kind| {
+            rxs.iter().find_map(|rx| {
+                let capture = rx.captures(screen)?.get(1)?;
+                let subject = join_rows(capture.as_str(), kind);
+                let off_screen =
+                    capture.as_str().contains('\n') && screen[capture.end()..].trim().is_empty();
+                let truncated = off_screen
+                    || rows(capture.as_str()).any(|row| row.ends_with('…') || row.ends_with("..."));
+                (!subject.is_empty()).then_some(Proposal {
+                    kind,
+                    subject,
+                    truncated,
+                })
+            })
+        };
+        first(&self.command, ProposalKind::Command)
+            .or_else(|| first(&self.path, ProposalKind::Path))
+    }
+
+    /// Decide a permission prompt. None when the screen carries no proposal
+    /// the extractors recognise.
+    pub fn decide(&self, screen: &str, cwd: &str) -> Option<Decision> {
+        let proposal = self.extract(screen)?;
+        let rule = self.rules.iter().find(|r| r.matches(&proposal));
+        let (verdict, rule) = if let Some(rule) =
+            rule.filter(|r| !proposal.truncated || r.verdict == Verdict::Deny)
+        {
+            (rule.verdict, rule.name.clone())
+        } else if proposal.truncated {
+            (Verdict::Ask, "truncated".to_string())
+        } else if proposal.kind == ProposalKind::Path && is_outside(&proposal.subject, cwd) {
+            (self.outside_cwd, "outsideCwd".to_string())
+        } else {
+            (self.default, "default".to_string())
+        };
+        Some(Decision {
+            verdict,
+            rule,
+            proposal,
+        })
+    }
+}
+
+/// A capture that spans rows of the CLI's box, as one line: the rows' borders
+/// and padding dropped, then joined with a space for a command (wrapped
+/// between words) and directly for a path (broken wherever the row ended).
+fn join_rows(text: &str, kind: ProposalKind) -> String {
+    let sep = match kind {
+        ProposalKind::Command => " ",
+        ProposalKind::Path => "",
+    };
+    rows(text).collect::<Vec<_>>().join(sep)
+}
+
+/// The non-empty rows of a capture, without the box's borders and padding.
+fn rows(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(|row| row.trim_matches(|c: char| c.is_whitespace() || c == '│'))
+        .filter(|row| !row.is_empty())
+}
+
+/// Lexically resolve `path` against `cwd` and report whether it escapes it.
+/// `~` paths count as outside; nothing is read from disk, so a path that
+/// doesn't exist yet (a file about to be created) still resolves.
+fn is_outside(path: &str, cwd: &str) -> bool {
+    if cwd.is_empty() || path.starts_with('~') {
+        return true;
+    }
+    let joined = Path::new(cwd).join(path);
+    let mut resolved = PathBuf::new();
+    for c in joined.components() {
+        match c {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            other => resolved.push(other),
+        }
+    }
+    !resolved.starts_with(Path::new(cwd))
+}
+
+pub fn compile_approval_policy(raw: Option<ApprovalOverride>) -> Result<ApprovalPolicy> {
+    let Some(raw) = raw else {
+        return Ok(ApprovalPolicy::default());
+    };
+    let verdict = |v: Option<String>, fallback| v.map_or(Ok(fallback), |s| Verdict::parse(&s));
+    let rules = raw
+        .rules
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .map(|(i, raw)| {
+            let name = raw.name.clone().unwrap_or_else(|| format!("rule#{}", i));
+            compile_approval_rule(name.clone(), raw)
+                .with_context(|| format!("Invalid approval rule '{}'", name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let deny_keys = match raw.deny_keys {
+        Some(keys) => keys
+            .iter()
+            .map(|k| key_bytes(k).ok_or_else(|| anyhow!("Unknown key '{}' in denyKeys", k)))
+            .collect::<Result<String>>()?,
+        None => ApprovalPolicy::default().deny_keys,
+    };
+    Ok(ApprovalPolicy {
+        command: compile_regex_list(raw.command)?,
+        path: compile_regex_list(raw.path)?,
+        rules,
+        default: verdict(raw.default, Verdict::Allow)?,
+        outside_cwd: verdict(raw.outside_cwd, Verdict::Allow)?,
+        deny_keys,
+    })
+}
+
+fn compile_approval_rule(name: String, raw: ApprovalRuleOverride) -> Result<ApprovalRule> {
+    let one = |src| compile_regex_list(Some(vec![src])).map(|mut v| v.pop().expect("one regex"));
+    Ok(ApprovalRule {
+        name,
+        verdict: Verdict::parse(&raw.verdict)?,
+        command: raw.command.map(one).transpose()?,
+        path: raw.path.map(one).transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(yaml: &str) -> ApprovalPolicy {
+        compile_approval_policy(Some(serde_yaml::from_str(yaml).unwrap())).unwrap()
+    }
+
+    /// The shipped claude policy, so the extractors are tested against the
+    /// prompts they were written for.
+    fn claude() -> ApprovalPolicy {
+        crate::config::get_cli_config("claude").unwrap().approval
+    }
+
+    const BASH_PROMPT: &str = "\
+ Bash command
+
+   git push --force origin main
+   Push the rewritten branch
+
+ Do you want to proceed?
+ ❯ 1. Yes
+   2. Yes, and don't ask again for git push commands
+   3. No, and tell Claude what to do differently (esc)";
+
+    #[test]
+    fn test_claude_policy_denies_force_push_and_allows_plain_commands() {
+        let p = claude();
+        assert!(p.is_enabled());
+        let d = p.decide(BASH_PROMPT, "/repo").unwrap();
+        assert_eq!(d.proposal.kind, ProposalKind::Command);
+        assert_eq!(
+            d.proposal.subject,
+            "git push --force origin main Push the rewritten branch"
+        );
+        assert_eq!(d.verdict, Verdict::Deny);
+        assert_eq!(d.rule, "no-force-push");
+
+        let boxed = "│ Bash command                    │\n│                                 │\n│   cargo test --offline          │\n│ ❯ 1. Yes                        │";
+        let d = p.decide(boxed, "/repo").unwrap();
+        assert_eq!(d.proposal.subject, "cargo test --offline");
+        assert_eq!((d.verdict, d.rule.as_str()), (Verdict::Allow, "default"));
+
+        let d = p.decide(
+            &BASH_PROMPT.replace("git push --force origin main", "rm -rf /"),
+            "/repo",
+        );
+        assert_eq!(d.unwrap().verdict, Verdict::Deny);
+        // A trust-folder prompt carries no proposal: plain auto-yes.
+        assert_eq!(p.decide(" ❯ 1. Yes, I trust this folder", "/repo"), None);
+    }
+
+    #[test]
+    fn test_claude_policy_reads_a_wrapped_command_whole() {
+        let p = claude();
+        let wrapped = "\
+╭──────────────────────────────╮
+│ Bash command                 │
+│                              │
+│   cd /work/repo && git push  │
+│   --force origin main        │
+│   Push the rewritten branch  │
+│                              │
+│ Do you want to proceed?      │
+│ ❯ 1. Yes                     │
+│   2. No                      │
+╰──────────────────────────────╯";
+        let d = p.decide(wrapped, "/repo").unwrap();
+        assert_eq!(
+            d.proposal.subject,
+            "cd /work/repo && git push --force origin main Push the rewritten branch"
+        );
+        assert!(!d.proposal.truncated);
+        assert_eq!(
+            (d.verdict, d.rule.as_str()),
+            (Verdict::Deny, "no-force-push")
+        );
+
+        // A box running off the bottom of the screen, or cut with an
+        // ellipsis, is only partly there: a human decides.
+        let cut =
+            "│ Bash command    │\n│                 │\n│   git push      │\n│   origin main   │\n";
+        let d = p.decide(cut, "/repo").unwrap();
+        assert!(d.proposal.truncated);
+        assert_eq!((d.verdict, d.rule.as_str()), (Verdict::Ask, "truncated"));
+        let d = p
+            .decide(
+                &BASH_PROMPT.replace("--force origin main", "origin…"),
+                "/repo",
+            )
+            .unwrap();
+        assert_eq!((d.verdict, d.rule.as_str()), (Verdict::Ask, "truncated"));
+        // A deny rule that sees enough still denies.
+        let d = p
+            .decide(&BASH_PROMPT.replace("origin main", "origin…"), "/repo")
+            .unwrap();
+        assert_eq!(d.verdict, Verdict::Deny);
+    }
+
+    #[test]
+    fn test_claude_policy_guards_paths() {
+        let p = claude();
+        let edit = |path: &str| format!(" Do you want to make this edit to {}?\n ❯ 1. Yes", path);
+        let d = p.decide(&edit("src/main.rs"), "/repo").unwrap();
+        assert_eq!((d.verdict, d.rule.as_str()), (Verdict::Allow, "default"));
+        let d = p.decide(&edit(".env"), "/repo").unwrap();
+        assert_eq!(d.verdict, Verdict::Deny);
+        let d = p.decide(&edit("../other/lib.rs"), "/repo").unwrap();
+        assert_eq!((d.verdict, d.rule.as_str()), (Verdict::Ask, "outsideCwd"));
+        let d = p
+            .decide(" Do you want to create /etc/hosts?\n ❯ 1. Yes", "/repo")
+            .unwrap();
+        assert_eq!(d.verdict, Verdict::Ask);
+    }
+
+    #[test]
+    fn test_rules_apply_in_order_and_validate() {
+        let p = policy(
+            r#"
+command: ['RUN: (.+)']
+rules:
+  - { name: allow-tmp, command: 'rm -rf /tmp/', verdict: allow }
+  - { name: rm, command: 'rm -rf', verdict: ask }
+default: deny
+denyKeys: ['3']
+"#,
+        );
+        assert_eq!(p.deny_keys, "3");
+        assert_eq!(
+            p.decide("RUN: rm -rf /tmp/x", "/r").unwrap().rule,
+            "allow-tmp"
+        );
+        assert_eq!(
+            p.decide("RUN: rm -rf ~", "/r").unwrap().verdict,
+            Verdict::Ask
+        );
+        assert_eq!(p.decide("RUN: ls", "/r").unwrap().verdict, Verdict::Deny);
+        assert!(compile_approval_policy(Some(
+            serde_yaml::from_str("rules: [{ command: x, verdict: maybe }]").unwrap()
+        ))
+        .is_err());
+        assert!(!ApprovalPolicy::default().is_enabled());
+    }
+
+    #[test]
+    fn test_is_outside() {
+        assert!(!is_outside("src/a.rs", "/repo"));
+        assert!(!is_outside("/repo/./src/../b.rs", "/repo"));
+        assert!(is_outside("../x", "/repo"));
+        assert!(is_outside("/repository/x", "/repo"));
+        assert!(is_outside("~/.ssh/config", "/repo"));
+    }
+}
@@ -6,6 +6,10 @@
 
 #[path = "../agent_permissions.rs"]
 mod agent_permissions;
+// `/api/checkpoints` and `/api/rollback` read and restore the checkpoints the
+// wrapper takes.
+#[path = "../checkpoint.rs"]
+mod checkpoint;
 #[path = "../pid_store.rs"]
 mod pid_store;
 // needs_input classification reuses the CLI `needsInput`/`working` patterns
@@ -19,10 +23,21 @@ mod fifo;
 mod log_files;
 #[path = "../reaper.rs"]
 mod reaper;
+// `/api/tail` and search redact secrets with the same detectors the wrapper
+// applies to the logs.
+#[path = "../redact.rs"]
+mod redact;
 #[path = "../supported_clis.rs"]
 mod supported_clis;
+// The pid record's `usage` field and `/api/usage` share the ledger format with
+// the wrapper that writes it.
 #[path = "../serve/mod.rs"]
 mod serve;
+// `/api/titles` reads the title history the wrapper records.
+#[path = "../title_history.rs"]
+mod title_history;
+#[path = "../usage.rs"]
+mod usage;
 #[path = "../vterm.rs"]
 mod vterm;
 
@@ -0,0 +1,741 @@
+//! Git checkpoints before auto-approved actions (`checkpoint:` in the CLI
+//! config).
+//!
+//! Auto-yes answers permission prompts nobody reads, so a destructive step is
+//! only recoverable from whatever the agent happened to commit. Each time the
+//! wrapper auto-answers a prompt or types a queued prompt, it first snapshots
+//! the working tree — tracked changes and untracked files alike, `.gitignore`d
+//! ones excepted — into a hidden ref:
+//!
+//! ```text
+//! refs/agent-yes/<agent_id>/<n>
+//! ```
+//!
+//! The snapshot is built the way `git stash` builds its untracked commit, with
+//! plumbing over a throwaway index (a copy of the real one, so `git add -A`
+//! only re-hashes what changed): `add -A`, `write-tree`, `commit-tree` with
+//! HEAD as parent, `update-ref`. The real index, HEAD and the branch are never
+//! touched. A tree identical to the previous checkpoint is not recorded again.
+//!
+//! `ay rollback <agent>` lists an agent's checkpoints and `ay rollback <agent>
+//! <n>` puts the working tree back to checkpoint `n` — the files that differ
+//! are rewritten, the ones created since are removed — after checkpointing the
+//! current tree, so a rollback can itself be rolled back. HEAD stays where it
+//! is: commits the agent made since are kept, and show up as changes.
+//!
+//! A snapshot runs before the approval it protects, so it holds the run loop
+//! up for as long as `git add -A` takes: next to nothing on a warm index,
+//! seconds on a huge or cold tree. It is cut off after `timeoutSecs` (5s by
+//! default) and the approval goes ahead without one; a run whose snapshot
+//! timed out stops taking them, rather than stalling every approval after.
+//!
+//! ```yaml
+//! clis:
+//!   claude:
+//!     checkpoint:
+//!       keep: 20        # per agent, oldest dropped first; 0 keeps all
+//!       timeoutSecs: 5  # longest a snapshot may hold up an approval
+//! ```
+
+use crate::config_loader::CheckpointOverride;
+use crate::pid_store::PidStore;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+pub const REF_PREFIX: &str = "refs/agent-yes";
+
+const DEFAULT_KEEP: u32 = 50;
+const DEFAULT_TIMEOUT_SECS: u64 = 5;
+
+/// Every call here is plumbing against one local repo; anything slower than
+/// this is a wedged git (a stale lock, a hung fsmonitor), not a big tree.
+const GIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const SUBJECT_PREFIX: &str = "agent-yes checkpoint";
+
+/// Compiled `checkpoint:` section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointConfig {
+    pub enabled: bool,
+    pub keep: u32,
+    /// Budget for one snapshot (every git call in it together).
+    pub timeout: Duration,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        compile_checkpoint(None)
+    }
+}
+
+pub fn compile_checkpoint(raw: Option<CheckpointOverride>) -> CheckpointConfig {
+    let raw = raw.unwrap_or_default();
+    CheckpointConfig {
+        enabled: raw.enabled.unwrap_or(true),
+        keep: raw.keep.unwrap_or(DEFAULT_KEEP),
+        timeout: Duration::from_secs(raw.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS)),
+    }
+}
+
+/// One recorded checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Checkpoint {
+    pub n: u32,
+    pub commit: String,
+    #[serde(skip)]
+    pub tree: String,
+    /// Unix ms.
+    pub at: i64,
+    /// What was about to happen (`approve: Bash rm -rf build`, `queue 2/5`).
+    pub reason: String,
+}
+
+/// What a rollback did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Rollback {
+    pub n: u32,
+    pub written: usize,
+    pub removed: usize,
+    /// The checkpoint of the tree as it was before the rollback, if it
+    /// differed from every existing one.
+    pub undo: Option<u32>,
+}
+
+/// Takes one agent's checkpoints in one repo.
+#[derive(Debug)]
+pub struct Checkpointer {
+    repo: PathBuf,
+    agent: String,
+    keep: u32,
+    timeout: Duration,
+}
+
+impl Checkpointer {
+    /// None when checkpoints are off or `cwd` isn't inside a git work tree.
+    pub fn open(cwd: &str, agent: &str, config: CheckpointConfig) -> Option<Self> {
+        if !config.enabled || !valid_agent(agent) {
+            return None;
+        }
+        let repo = repo_root(Path::new(cwd)).ok()?;
+        Some(Self {
+            repo,
+            agent: agent.to_string(),
+            keep: config.keep,
+            timeout: config.timeout,
+        })
+    }
+
+    /// Snapshot the working tree. None when it hasn't changed since the last
+    /// checkpoint. The refs are re-read each time: a rollback adds one too.
+    /// Fails with `TimedOut` past the configured timeout.
+    pub fn snapshot(&self, reason: &str) -> Result<Option<Checkpoint>> {
+        let deadline = Instant::now() + self.timeout;
+        let tree = worktree_tree(&self.repo, deadline)?;
+        let existing = list(&self.repo, &self.agent)?;
+        let last = existing.last();
+        if last.is_some_and(|c| c.tree == tree) {
+            return Ok(None);
+        }
+        let n = last.map_or(1, |c| c.n + 1);
+        let checkpoint = record(&self.repo, &self.agent, n, &tree, reason, deadline)?;
+        if self.keep > 0 {
+            for old in existing.iter().filter(|c| c.n + self.keep <= n) {
+                let dropped = ref_name(&self.agent, old.n);
+                let _ = git(&self.repo, None, &["update-ref", "-d", &dropped], None);
+            }
+        }
+        Ok(Some(checkpoint))
+    }
+}
+
+/// An agent's checkpoints in `repo`, oldest first.
+pub fn list(repo: &Path, agent: &str) -> Result<Vec<Checkpoint>> {
+    if !valid_agent(agent) {
+        bail!("invalid agent id {agent:?}");
+    }
+    let prefix = format!("{REF_PREFIX}/{agent}/");
+    let out = git(
+        repo,
+        None,
+        &[
+            "for-each-ref",
+            "--format=%(refname)%00%(objectname)%00%(tree)%00%(committerdate:unix)%00%(subject)",
+            &prefix,
+        ],
+        None,
+    )?;
+    let mut checkpoints: Vec<Checkpoint> = out
+        .lines()
+        .filter_map(|l| parse_ref_line(l, &prefix))
+        .collect();
+    checkpoints.sort_by_key(|c| c.n);
+    Ok(checkpoints)
+}
+
+fn parse_ref_line(line: &str, prefix: &str) -> Option<Checkpoint> {
+    let mut fields = line.split('\0');
+    let n = fields.next()?.strip_prefix(prefix)?.parse().ok()?;
+    let commit = fields.next()?.to_string();
+    let tree = fields.next()?.to_string();
+    let at = fields.next()?.parse::<i64>().ok()? * 1000;
+    let subject = fields.next().unwrap_or("");
+    let reason = subject
+        .strip_prefix(SUBJECT_PREFIX)
+        .and_then(|s| s.split_once(": "))
+        .map_or(subject, |(_, reason)| reason)
+        .to_string();
+    Some(Checkpoint {
+        n,
+        commit,
+        tree,
+        at,
+        reason,
+    })
+}
+
+/// Put `repo`'s working tree back to the agent's checkpoint `n`. See the
+/// module docs.
+pub fn restore(repo: &Path, agent: &str, n: u32) -> Result<Rollback> {
+    let checkpoints = list(repo, agent)?;
+    let Some(target) = checkpoints.iter().find(|c| c.n == n) else {
+        bail!("agent {agent} has no checkpoint {n}");
+    };
+    let target_tree = target.tree.clone();
+    let current_tree = worktree_tree(repo, Instant::now() + GIT_TIMEOUT)?;
+    let mut undo = None;
+    if !checkpoints.iter().any(|c| c.tree == current_tree) {
+        let next = checkpoints.last().map_or(1, |c| c.n + 1);
+        let reason = format!("before rollback to {n}");
+        let deadline = Instant::now() + GIT_TIMEOUT;
+        undo = Some(record(repo, agent, next, &current_tree, &reason, deadline)?.n);
+    }
+
+    // In the checkpoint but changed or gone now: write back. Created since:
+    // remove.
+    let changed = |filter: &str| -> Result<Vec<String>> {
+        let out = git(
+            repo,
+            None,
+            &[
+                "diff-tree",
+                "-r",
+                "-z",
+                "--name-only",
+                "--no-renames",
+                &format!("--diff-filter={filter}"),
+                &target_tree,
+                &current_tree,
+            ],
+            None,
+        )?;
+        Ok(out
+            .split('\0')
+            .filter(|p| !p.is_empty())
+            .map(String::from)
+            .collect())
+    };
+    let to_write = changed("DMT")?;
+    let to_remove = changed("A")?;
+
+    for path in &to_remove {
+        remove_path(repo, &repo.join(path))?;
+    }
+    if !to_write.is_empty() {
+        let index = TempIndex::new(repo)?;
+        git(repo, Some(&index.path), &["read-tree", &target_tree], None)?;
+        let mut paths = to_write.join("\0").into_bytes();
+        paths.push(0);
+        git(
+            repo,
+            Some(&index.path),
+            &["checkout-index", "-f", "-z", "--stdin"],
+            Some(&paths),
+        )?;
+    }
+    Ok(Rollback {
+        n,
+        written: to_write.len(),
+        removed: to_remove.len(),
+        undo,
+    })
+}
+
+/// Remove a path created since the checkpoint, then the directories that
+/// left empty, up to `repo`. Already gone is fine; by now it may also be a
+/// directory (a file replaced by one), which goes with everything in it.
+fn remove_path(repo: &Path, full: &Path) -> Result<()> {
+    let removed = match std::fs::symlink_metadata(full) {
+        Ok(meta) if meta.is_dir() => std::fs::remove_dir_all(full),
+        Ok(_) => std::fs::remove_file(full),
+        Err(e) => Err(e),
+    };
+    match removed {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("remove {}", full.display()));
+        }
+        _ => {}
+    }
+    let mut dir = full.parent();
+    while let Some(d) = dir.filter(|d| *d != repo) {
+        if std::fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+    Ok(())
+}
+
+/// Agent ids are short hex; anything else would escape the ref namespace.
+fn valid_agent(agent: &str) -> bool {
+    !agent.is_empty() && agent.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+fn ref_name(agent: &str, n: u32) -> String {
+    format!("{REF_PREFIX}/{agent}/{n}")
+}
+
+/// The top of the work tree `dir` is in.
+pub fn repo_root(dir: &Path) -> Result<PathBuf> {
+    let out = git(dir, None, &["rev-parse", "--show-toplevel"], None)?;
+    Ok(PathBuf::from(out.trim()))
+}
+
+/// The working tree (untracked files included) written as a tree object,
+/// through a copy of the real index.
+fn worktree_tree(repo: &Path, deadline: Instant) -> Result<String> {
+    let index = TempIndex::new(repo)?;
+    git_until(repo, Some(&index.path), &["add", "-A"], None, deadline)?;
+    let out = git_until(repo, Some(&index.path), &["write-tree"], None, deadline)?;
+    Ok(out.trim().to_string())
+}
+
+/// Commit `tree` on top of HEAD (when there is one) as checkpoint `n`.
+fn record(
+    repo: &Path,
+    agent: &str,
+    n: u32,
+    tree: &str,
+    reason: &str,
+    deadline: Instant,
+) -> Result<Checkpoint> {
+    let git = |args: &[&str]| git_until(repo, None, args, None, deadline);
+    let head = git(&["rev-parse", "--verify", "-q", "HEAD"])
+        .ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty());
+    let message = format!("{SUBJECT_PREFIX} {n}: {reason}");
+    let mut args = vec!["commit-tree", tree, "-m", &message];
+    if let Some(head) = head.as_deref() {
+        args.extend(["-p", head]);
+    }
+    let commit = git(&args)?.trim().to_string();
+    git(&["update-ref", &ref_name(agent, n), &commit])?;
+    Ok(Checkpoint {
+        n,
+        commit,
+        tree: tree.to_string(),
+        at: chrono::Utc::now().timestamp_millis(),
+        reason: reason.to_string(),
+    })
+}
+
+/// A scratch index in the repo's git dir, seeded from the real one and
+/// deleted on drop.
+struct TempIndex {
+    path: PathBuf,
+}
+
+impl TempIndex {
+    fn new(repo: &Path) -> Result<Self> {
+        let git_dir = git(repo, None, &["rev-parse", "--absolute-git-dir"], None)?;
+        let git_dir = Path::new(git_dir.trim());
+        let path = git_dir.join(format!("agent-yes-index.{}", std::process::id()));
+        match std::fs::copy(git_dir.join("index"), &path) {
+            Ok(_) => {}
+            // A repo with nothing staged yet has no index; start empty.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let _ = std::fs::remove_file(&path);
+            }
+            Err(e) => return Err(e).context("copy the git index"),
+        }
+        Ok(Self { path })
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// A snapshot ran past its `timeoutSecs`; the git call in flight was killed.
+#[derive(Debug)]
+pub struct TimedOut;
+
+impl std::fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("timed out")
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Run git in `repo` with a deadline, optionally over a scratch index and
+/// with `input` on stdin; its stdout on success.
+fn git(repo: &Path, index: Option<&Path>, args: &[&str], input: Option<&[u8]>) -> Result<String> {
+    git_until(repo, index, args, input, Instant::now() + GIT_TIMEOUT)
+}
+
+/// `git`, killed at `deadline` with a `TimedOut` error.
+fn git_until(
+    repo: &Path,
+    index: Option<&Path>,
+    args: &[&str],
+    input: Option<&[u8]>,
+    deadline: Instant,
+) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C")
+        .arg(repo)
+        .args(args)
+        .env("GIT_AUTHOR_NAME", "agent-yes")
+        .env("GIT_AUTHOR_EMAIL", "agent-yes@localhost")
+        .env("GIT_COMMITTER_NAME", "agent-yes")
+        .env("GIT_COMMITTER_EMAIL", "agent-yes@localhost")
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(index) = index {
+        cmd.env("GIT_INDEX_FILE", index);
+    }
+    let mut child = cmd.spawn().context("run git")?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        let input = input.to_vec();
+        std::thread::spawn(move || stdin.write_all(&input));
+    }
+    // Read both pipes off-thread so a chatty git can't fill one and stall.
+    let read_bg = |pipe: Option<Box<dyn Read + Send>>| {
+        let (tx, rx) = std::sync::mpsc::channel::<String>();
+        if let Some(mut p) = pipe {
+            std::thread::spawn(move || {
+                let mut s = String::new();
+                let _ = p.read_to_string(&mut s);
+                let _ = tx.send(s);
+            });
+        }
+        rx
+    };
+    let rx_out = read_bg(child.stdout.take().map(|p| Box::new(p) as _));
+    let rx_err = read_bg(child.stderr.take().map(|p| Box::new(p) as _));
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow::Error::new(TimedOut).context(format!("git {}", args[0])));
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    };
+    let grab = |rx: std::sync::mpsc::Receiver<String>| {
+        rx.recv_timeout(Duration::from_secs(2)).unwrap_or_default()
+    };
+    let stdout = grab(rx_out);
+    if !status.success() {
+        bail!("git {} failed: {}", args[0], grab(rx_err).trim());
+    }
+    Ok(stdout)
+}
+
+// ---- `ay rollback` -----------------------------------------------------------
+
+const USAGE: &str = "usage: ay rollback <agent> [n] [--json]";
+
+/// Run `ay rollback …` natively when argv asks for it. Returns the exit code,
+/// or None when this isn't a `rollback` invocation.
+pub fn maybe_run_subcommand() -> Option<i32> {
+    let raw: Vec<String> = std::env::args().collect();
+    if raw.get(1).map(String::as_str) != Some("rollback") {
+        return None;
+    }
+    let json = raw.iter().any(|a| a == "--json");
+    let positional: Vec<&str> = raw[2..]
+        .iter()
+        .map(String::as_str)
+        .filter(|a| !a.starts_with("--"))
+        .collect();
+    let (keyword, n) = match positional[..] {
+        [keyword] => (keyword, None),
+        [keyword, n] => match n.parse::<u32>() {
+            Ok(n) => (keyword, Some(n)),
+            Err(_) => {
+                eprintln!("{USAGE}");
+                return Some(2);
+            }
+        },
+        _ => {
+            eprintln!("{USAGE}");
+            return Some(2);
+        }
+    };
+    match run_rollback(keyword, n, json) {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("ay rollback: {:#}", e);
+            Some(1)
+        }
+    }
+}
+
+fn run_rollback(keyword: &str, n: Option<u32>, json: bool) -> Result<()> {
+    let (agent, cwd) = resolve_agent(keyword)?;
+    let repo =
+        repo_root(Path::new(&cwd)).with_context(|| format!("{cwd} is not in a git work tree"))?;
+    let Some(n) = n else {
+        let checkpoints = list(&repo, &agent)?;
+        if json {
+            println!("{}", serde_json::to_string(&checkpoints)?);
+        } else if checkpoints.is_empty() {
+            println!("no checkpoints for agent {agent} in {}", repo.display());
+        } else {
+            for c in &checkpoints {
+                let at = chrono::DateTime::from_timestamp_millis(c.at)
+                    .map(|t| {
+                        t.with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                println!(
+                    "{:>4}  {}  {}  {}",
+                    c.n,
+                    &c.commit[..c.commit.len().min(10)],
+                    at,
+                    c.reason
+                );
+            }
+            eprintln!(
+                "\n  ay rollback {keyword} <n>        # restore the working tree to checkpoint n"
+            );
+        }
+        return Ok(());
+    };
+    let done = restore(&repo, &agent, n)?;
+    if json {
+        println!("{}", serde_json::to_string(&done)?);
+    } else {
+        println!(
+            "rolled {} back to checkpoint {n}: {} file(s) restored, {} removed",
+            repo.display(),
+            done.written,
+            done.removed
+        );
+        if let Some(undo) = done.undo {
+            eprintln!("\n  ay rollback {keyword} {undo}        # undo this rollback");
+        }
+    }
+    Ok(())
+}
+
+/// `(agent key, cwd)` of the newest agent whose pid or agent id (prefix)
+/// matches `keyword`, exited ones included.
+fn resolve_agent(keyword: &str) -> Result<(String, String)> {
+    let kw = keyword.to_ascii_lowercase();
+    let mut records = PidStore::new().read_all()?;
+    records.sort_by_key(|r| -r.started_at);
+    let record = records
+        .iter()
+        .find(|r| r.pid.to_string() == kw)
+        .or_else(|| {
+            records
+                .iter()
+                .find(|r| r.agent_id.as_deref().is_some_and(|id| id.starts_with(&kw)))
+        })
+        .ok_or_else(|| anyhow!("no agent matches {keyword:?}"))?;
+    Ok((
+        agent_key(record.agent_id.as_deref(), record.pid),
+        record.cwd.clone(),
+    ))
+}
+
+/// The ref namespace of an agent: its agent id, or its pid for a record that
+/// predates agent ids.
+pub fn agent_key(agent_id: Option<&str>, pid: u32) -> String {
+    agent_id.map_or_else(|| pid.to_string(), str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(dir: &Path, script: &str) {
+        let ok = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_NAME", "t")
+            .env("GIT_AUTHOR_EMAIL", "t@t")
+            .env("GIT_COMMITTER_NAME", "t")
+            .env("GIT_COMMITTER_EMAIL", "t@t")
+            .status()
+            .unwrap()
+            .success();
+        assert!(ok, "{script}");
+    }
+
+    fn repo() -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ay-checkpoint-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        sh(
+            &dir,
+            "git init -q && echo one > a.txt && echo keep > b.txt && git add . && git commit -qm init",
+        );
+        dir
+    }
+
+    fn git_out(dir: &Path, args: &[&str]) -> String {
+        git(dir, None, args, None).unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_leaves_index_and_head_alone() {
+        let dir = repo();
+        let head = git_out(&dir, &["rev-parse", "HEAD"]);
+        sh(
+            &dir,
+            "echo two > a.txt && echo staged > s.txt && git add s.txt && echo new > u.txt",
+        );
+        let status = git_out(&dir, &["status", "--porcelain"]);
+
+        let cp = Checkpointer::open(dir.to_str().unwrap(), "abc123", CheckpointConfig::default())
+            .unwrap();
+        let first = cp.snapshot("approve: Bash rm -rf build").unwrap().unwrap();
+        assert_eq!(first.n, 1);
+        // Unchanged tree: no second checkpoint.
+        assert_eq!(cp.snapshot("queue 1/2").unwrap(), None);
+
+        assert_eq!(git_out(&dir, &["rev-parse", "HEAD"]), head);
+        assert_eq!(git_out(&dir, &["status", "--porcelain"]), status);
+        let files = git_out(
+            &dir,
+            &["ls-tree", "-r", "--name-only", "refs/agent-yes/abc123/1"],
+        );
+        assert_eq!(files, "a.txt\nb.txt\ns.txt\nu.txt\n");
+        assert_eq!(
+            git_out(&dir, &["show", "refs/agent-yes/abc123/1:a.txt"]),
+            "two\n"
+        );
+        assert_eq!(
+            git_out(&dir, &["rev-parse", "refs/agent-yes/abc123/1^"]),
+            head
+        );
+
+        let listed = list(&dir, "abc123").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].reason, "approve: Bash rm -rf build");
+        assert_eq!(listed[0].commit, first.commit);
+
+        // A reopened checkpointer continues the numbering.
+        sh(&dir, "echo three > a.txt");
+        let cp = Checkpointer::open(dir.to_str().unwrap(), "abc123", CheckpointConfig::default())
+            .unwrap();
+        assert_eq!(cp.snapshot("queue 2/2").unwrap().unwrap().n, 2);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_restore_rewrites_and_removes_then_can_undo() {
+        let dir = repo();
+        let cp = Checkpointer::open(dir.to_str().unwrap(), "abc123", CheckpointConfig::default())
+            .unwrap();
+        sh(&dir, "echo draft > notes.txt");
+        cp.snapshot("approve").unwrap().unwrap();
+        // The approved step: clobber a file, delete one, create some.
+        sh(&dir, "echo clobbered > a.txt && rm b.txt notes.txt && mkdir -p out/x && echo o > out/x/o.txt");
+
+        let done = restore(&dir, "abc123", 1).unwrap();
+        assert_eq!((done.written, done.removed, done.undo), (3, 1, Some(2)));
+        let read = |p: &str| std::fs::read_to_string(dir.join(p)).ok();
+        assert_eq!(read("a.txt").as_deref(), Some("one\n"));
+        assert_eq!(read("b.txt").as_deref(), Some("keep\n"));
+        assert_eq!(read("notes.txt").as_deref(), Some("draft\n"));
+        assert!(!dir.join("out").exists());
+        // The real index still has notes.txt untracked.
+        assert_eq!(git_out(&dir, &["status", "--porcelain"]), "?? notes.txt\n");
+
+        let undone = restore(&dir, "abc123", 2).unwrap();
+        assert_eq!(undone.undo, None);
+        assert_eq!(read("a.txt").as_deref(), Some("clobbered\n"));
+        assert_eq!(read("out/x/o.txt").as_deref(), Some("o\n"));
+        assert!(restore(&dir, "abc123", 9).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_remove_path_tolerates_gone_and_directories() {
+        let dir = repo();
+        sh(
+            &dir,
+            "mkdir -p new/sub/deep && echo x > new/sub/deep/f && echo y > new/g",
+        );
+        remove_path(&dir, &dir.join("new/sub")).unwrap();
+        assert!(!dir.join("new/sub").exists());
+        remove_path(&dir, &dir.join("new/g")).unwrap();
+        assert!(!dir.join("new").exists());
+        remove_path(&dir, &dir.join("new/g")).unwrap();
+        assert!(dir.join("a.txt").exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_snapshot_past_its_timeout_fails_as_timed_out() {
+        let dir = repo();
+        sh(&dir, "echo two > a.txt");
+        let config = CheckpointConfig {
+            timeout: Duration::ZERO,
+            ..CheckpointConfig::default()
+        };
+        let cp = Checkpointer::open(dir.to_str().unwrap(), "abc123", config).unwrap();
+        let err = cp.snapshot("approve").unwrap_err();
+        assert!(err.is::<TimedOut>());
+        assert!(list(&dir, "abc123").unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_keep_drops_the_oldest() {
+        let dir = repo();
+        let config = CheckpointConfig {
+            keep: 2,
+            ..CheckpointConfig::default()
+        };
+        let cp = Checkpointer::open(dir.to_str().unwrap(), "abc123", config).unwrap();
+        for i in 0..4 {
+            sh(&dir, &format!("echo {i} > a.txt"));
+            cp.snapshot("approve").unwrap().unwrap();
+        }
+        let ns: Vec<u32> = list(&dir, "abc123").unwrap().iter().map(|c| c.n).collect();
+        assert_eq!(ns, [3, 4]);
+        assert!(Checkpointer::open(dir.to_str().unwrap(), "../x", config).is_none());
+        assert!(Checkpointer::open(
+            dir.to_str().unwrap(),
+            "abc123",
+            CheckpointConfig {
+                enabled: false,
+                ..config
+            }
+        )
+        .is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,6 +1,10 @@
 //! CLI argument parsing module
 
-use anyhow::{anyhow, Result};
+use crate::completion::ResultMode;
+use crate::jsonl_output::OutputFormat;
+use crate::resource_limits::{parse_bytes, parse_cpus, ResourceLimits};
+use crate::usage::{Budget, BudgetAction};
+use anyhow::{anyhow, Context, Result};
 use clap::{ArgAction, Parser};
 use std::env;
 
@@ -60,6 +64,8 @@ pub const SUBCOMMANDS: &[&str] = &[
     "callback",
     "reap",
     "gc",
+    "patterns",
+    "rollback",
     "dsh-legacy",
     "help",
 ];
@@ -226,6 +232,9 @@ pub struct CliArgs {
     pub cli: String,
     pub cli_args: Vec<String>,
     pub prompt: Option<String>,
+    /// Prompts fed one at a time on each return to the prompt: every `-p`
+    /// after the first, then the `--prompt-file` entries.
+    pub prompt_queue: Vec<String>,
     pub timeout_ms: Option<u64>,
     pub idle_action: Option<String>,
     pub robust: bool,
@@ -240,6 +249,26 @@ pub struct CliArgs {
     pub force_tty: bool,
     /// Force plain rendered text output even when stdout is a TTY.
     pub no_tty: bool,
+    /// Plain output: only render the final screen on exit, don't stream.
+    pub no_stream: bool,
+    /// `--output jsonl`: JSON records on stdout (see jsonl_output.rs).
+    pub output: OutputFormat,
+    /// Record PTY traffic to `<cwd>/.agent-yes/<pid>.cast` (asciicast v2).
+    pub record: bool,
+    /// Replay a `.cast` recording through the pattern engine instead of
+    /// spawning the CLI.
+    pub replay: Option<String>,
+    /// Usage limit for this agent; crossing it triggers `budget_action`.
+    pub budget: Option<Budget>,
+    pub budget_action: BudgetAction,
+    /// What to do once the task is done: save the result, also exit, or nothing.
+    pub result_mode: ResultMode,
+    /// Kernel resource limits for the agent, over the CLI config's `limits:`.
+    pub limits: ResourceLimits,
+    /// Run the CLI in a namespace sandbox (see sandbox.rs).
+    pub sandbox: bool,
+    /// `--failover`: replaces the CLI config's `failover.to` (empty: none).
+    pub failover: Option<Vec<String>>,
     /// Swarm mode: None = disabled, Some(value) = enabled with optional config
     /// Value can be: topic name, room code (XXX-XXX), ay:// URL, or multiaddr
     pub swarm: Option<String>,
@@ -262,9 +291,13 @@ struct Args {
     #[arg(long, default_value = "claude")]
     cli: String,
 
-    /// Initial prompt text
-    #[arg(short, long)]
-    prompt: Option<String>,
+    /// Initial prompt text; repeat to queue more, fed one per return to the prompt
+    #[arg(short, long, action = ArgAction::Append)]
+    prompt: Vec<String>,
+
+    /// Queue prompts from a file: separated by `---` lines, else one per line
+    #[arg(long = "prompt-file", value_name = "FILE")]
+    prompt_file: Option<String>,
 
     /// Exit on idle (e.g., "60s", "1m", "5m")
     #[arg(short, long)]
@@ -322,6 +355,58 @@ struct Args {
     #[arg(long = "no-tty", default_value = "false")]
     no_tty: bool,
 
+    /// With plain text output, print only the final screen on exit instead of streaming settled lines
+    #[arg(long = "no-stream", default_value = "false")]
+    no_stream: bool,
+
+    /// stdout format: text (the TUI, or rendered text when not a TTY) or jsonl (JSON records, logs to stderr)
+    #[arg(long, default_value = "text")]
+    output: String,
+
+    /// Record the session (output and input, timestamped) to .agent-yes/<pid>.cast
+    #[arg(long, default_value = "false")]
+    record: bool,
+
+    /// Replay a .cast recording through the pattern engine and print what it would type
+    #[arg(long, value_name = "FILE")]
+    replay: Option<String>,
+
+    /// Usage budget for this agent: tokens ("500k", "2M") or dollars ("$5", needs a cost counter)
+    #[arg(long, value_name = "LIMIT")]
+    budget: Option<String>,
+
+    /// When the budget is crossed: wrap-up (type a wrap-up prompt), pause (auto-yes off) or stop
+    #[arg(long = "budget-action", default_value = "wrap-up")]
+    budget_action: String,
+
+    /// When the task is done: save (mark completed, write .agent-yes/<pid>.result.md), exit (save, then exit) or off
+    #[arg(long = "result-mode", default_value = "save")]
+    result_mode: String,
+
+    /// Memory limit for the agent and everything it starts ("4G"): cgroup memory.max, else RLIMIT_AS
+    #[arg(long = "memory-max", value_name = "SIZE")]
+    memory_max: Option<String>,
+
+    /// CPU limit for the agent in CPUs ("2", "0.5"): cgroup cpu.max (needs a delegated cgroup)
+    #[arg(long = "cpu-max", value_name = "CPUS")]
+    cpu_max: Option<String>,
+
+    /// Process limit for the agent: cgroup pids.max, else RLIMIT_NPROC
+    #[arg(long = "pids-max", value_name = "N")]
+    pids_max: Option<u64>,
+
+    /// Open-file limit for the agent (RLIMIT_NOFILE)
+    #[arg(long, value_name = "N")]
+    nofile: Option<u64>,
+
+    /// Run the agent in Linux user/mount namespaces that only see the cwd and the CLI config's `sandbox:` paths
+    #[arg(long)]
+    sandbox: bool,
+
+    /// CLIs to hand the task to when this one hits a long usage limit ("codex,qwen"; "" for none)
+    #[arg(long, value_name = "CLIS")]
+    failover: Option<String>,
+
     /// Enable swarm mode for multi-agent P2P networking
     ///
     /// Value formats:
@@ -400,8 +485,16 @@ fn resolve_args(args: Args, exe_name: &str) -> Result<CliArgs> {
     let timeout_str = args.timeout.or(args.idle_timeout).or(args.exit_on_idle);
     let timeout_ms = timeout_str.map(|s| parse_duration(&s)).transpose()?;
 
-    // Parse prompt from remaining args (after --)
-    let (cli_args, prompt) = extract_prompt_from_args(remaining_args, args.prompt);
+    // Parse prompt from remaining args (after --). The first -p is the launch
+    // prompt; the rest queue behind it, followed by the --prompt-file entries.
+    let mut prompts = args.prompt.into_iter();
+    let (cli_args, prompt) = extract_prompt_from_args(remaining_args, prompts.next());
+    let mut prompt_queue: Vec<String> = prompts.collect();
+    if let Some(path) = &args.prompt_file {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read --prompt-file {}", path))?;
+        prompt_queue.extend(crate::prompt_queue::parse_prompt_file(&text));
+    }
 
     // Handle swarm mode: new --swarm flag takes precedence over deprecated flags
     let swarm = if args.swarm.is_some() {
@@ -417,6 +510,7 @@ fn resolve_args(args: Args, exe_name: &str) -> Result<CliArgs> {
         cli,
         cli_args,
         prompt,
+        prompt_queue,
         timeout_ms,
         idle_action: args.idle_action,
         robust: args.robust,
@@ -429,6 +523,21 @@ fn resolve_args(args: Args, exe_name: &str) -> Result<CliArgs> {
         skip_permissions: args.yes,
         force_tty: args.force_tty,
         no_tty: args.no_tty,
+        no_stream: args.no_stream,
+        output: OutputFormat::parse(&args.output)?,
+        record: args.record,
+        replay: args.replay,
+        budget: args.budget.map(|s| Budget::parse(&s)).transpose()?,
+        budget_action: BudgetAction::parse(&args.budget_action)?,
+        result_mode: ResultMode::parse(&args.result_mode)?,
+        limits: ResourceLimits {
+            memory_max: args.memory_max.map(|s| parse_bytes(&s)).transpose()?,
+            cpu_max: args.cpu_max.map(|s| parse_cpus(&s)).transpose()?,
+            pids_max: args.pids_max,
+            nofile: args.nofile,
+        },
+        sandbox: args.sandbox,
+        failover: args.failover.map(|s| crate::failover::parse_chain(&s)),
         swarm,
         experimental_swarm: args.experimental_swarm,
         swarm_listen: args.swarm_listen,
@@ -776,7 +885,8 @@ mod tests {
     fn default_args() -> Args {
         Args {
             cli: "claude".into(),
-            prompt: None,
+            prompt: vec![],
+            prompt_file: None,
             timeout: None,
             exit_on_idle: None,
             idle_timeout: None,
@@ -791,6 +901,19 @@ mod tests {
             yes: false,
             force_tty: false,
             no_tty: false,
+            no_stream: false,
+            output: "text".into(),
+            record: false,
+            replay: None,
+            budget: None,
+            budget_action: "wrap-up".into(),
+            result_mode: "save".into(),
+            memory_max: None,
+            cpu_max: None,
+            pids_max: None,
+            nofile: None,
+            sandbox: false,
+            failover: None,
             swarm: None,
             experimental_swarm: false,
             swarm_listen: None,
@@ -873,7 +996,7 @@ mod tests {
     #[test]
     fn test_resolve_args_with_prompt() {
         let mut args = default_args();
-        args.prompt = Some("hello world".into());
+        args.prompt = vec!["hello world".into()];
         let result = resolve_args(args, "agent-yes").unwrap();
         assert_eq!(result.prompt, Some("hello world".into()));
     }
@@ -948,6 +1071,163 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_args_record_and_replay() {
+        let args = Args::try_parse_from(["agent-yes", "--record", "--replay", "s.cast"]).unwrap();
+        let result = resolve_args(args, "claude-yes").unwrap();
+        assert!(result.record);
+        assert_eq!(result.replay.as_deref(), Some("s.cast"));
+        assert!(result.prompt.is_none());
+    }
+
+    #[test]
+    fn test_resolve_args_budget() {
+        let result = resolve_args(default_args(), "agent-yes").unwrap();
+        assert_eq!(result.budget, None);
+        assert_eq!(result.budget_action, BudgetAction::WrapUp);
+
+        let args = Args::try_parse_from(["agent-yes", "--budget", "2M", "--budget-action", "stop"])
+            .unwrap();
+        let result = resolve_args(args, "claude-yes").unwrap();
+        assert_eq!(result.budget, Some(Budget::Tokens(2_000_000)));
+        assert_eq!(result.budget_action, BudgetAction::Stop);
+
+        let mut args = default_args();
+        args.budget_action = "later".into();
+        assert!(resolve_args(args, "agent-yes").is_err());
+    }
+
+    #[test]
+    fn test_resolve_args_result_mode() {
+        let result = resolve_args(default_args(), "agent-yes").unwrap();
+        assert_eq!(result.result_mode, ResultMode::Save);
+
+        let args = Args::try_parse_from(["agent-yes", "--result-mode", "exit"]).unwrap();
+        let result = resolve_args(args, "claude-yes").unwrap();
+        assert_eq!(result.result_mode, ResultMode::Exit);
+
+        let mut args = default_args();
+        args.result_mode = "later".into();
+        assert!(resolve_args(args, "agent-yes").is_err());
+    }
+
+    #[test]
+    fn test_resolve_args_limits() {
+        let result = resolve_args(default_args(), "agent-yes").unwrap();
+        assert!(result.limits.is_empty());
+
+        let args = Args::try_parse_from([
+            "agent-yes",
+            "--memory-max",
+            "4G",
+            "--cpu-max",
+            "1.5",
+            "--pids-max",
+            "256",
+        ])
+        .unwrap();
+        let result = resolve_args(args, "claude-yes").unwrap();
+        assert_eq!(result.limits.memory_max, Some(4 << 30));
+        assert_eq!(result.limits.cpu_max, Some(1.5));
+        assert_eq!(result.limits.pids_max, Some(256));
+        assert_eq!(result.limits.nofile, None);
+
+        let mut args = default_args();
+        args.memory_max = Some("lots".into());
+        assert!(resolve_args(args, "agent-yes").is_err());
+    }
+
+    #[test]
+    fn test_resolve_args_prompt_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("prompts.txt");
+        std::fs::write(&file, "write docs\n---\nship it\n").unwrap();
+        let args = Args::try_parse_from([
+            "agent-yes",
+            "-p",
+            "fix the bug",
+            "-p",
+            "add a test",
+            "--prompt-file",
+            file.to_str().unwrap(),
+        ])
+        .unwrap();
+        let result = resolve_args(args, "claude-yes").unwrap();
+        assert_eq!(result.prompt.as_deref(), Some("fix the bug"));
+        assert_eq!(
+            result.prompt_queue,
+            vec!["add a test", "write docs", "ship it"]
+        );
+
+        let mut args = default_args();
+        args.prompt_file = Some(dir.path().join("missing").to_string_lossy().into());
+        assert!(resolve_args(args, "agent-yes").is_err());
+    }
+
+    #[test]
+    fn test_failover_successor_keeps_the_flags() {
+        let args = Args::try_parse_from([
+            "agent-yes",
+            "--yes",
+            "--auto",
+            "no",
+            "--timeout",
+            "90s",
+            "-a",
+            "check TODO.md",
+            "--budget",
+            "$2.5",
+            "--budget-action",
+            "stop",
+            "--result-mode",
+            "exit",
+            "--memory-max",
+            "4G",
+            "--cpu-max",
+            "0.5",
+            "--nofile",
+            "512",
+            "--record",
+            "--sandbox",
+            "--output",
+            "jsonl",
+            "--continue",
+            "--",
+            "--model",
+            "opus",
+        ])
+        .unwrap();
+        let args = resolve_args(args, "claude-yes").unwrap();
+        let h = crate::failover::Handover {
+            to: "codex".into(),
+            rest: vec!["qwen".into()],
+            wait_secs: 3600,
+        };
+        let queued = ["-v flag-like prompt".to_string(), "ship it".to_string()];
+        let argv = crate::failover::successor_args(&h, &args, "<ay-handoff …>", &queued);
+        let next = resolve_args(
+            Args::try_parse_from(std::iter::once("agent-yes".to_string()).chain(argv)).unwrap(),
+            "agent-yes",
+        )
+        .unwrap();
+
+        assert_eq!(next.cli, "codex");
+        assert_eq!(next.prompt.as_deref(), Some("<ay-handoff …>"));
+        assert_eq!(next.prompt_queue, queued);
+        assert_eq!(next.failover, Some(vec!["qwen".to_string()]));
+        assert_eq!(next.timeout_ms, Some(90_000));
+        assert_eq!(next.idle_action.as_deref(), Some("check TODO.md"));
+        assert_eq!(next.budget, args.budget);
+        assert_eq!(next.budget_action, BudgetAction::Stop);
+        assert_eq!(next.result_mode, ResultMode::Exit);
+        assert_eq!(next.limits, args.limits);
+        assert_eq!(next.output, OutputFormat::Jsonl);
+        assert!(next.skip_permissions && !next.auto_yes && next.record && next.sandbox);
+        // The old CLI's own args and session don't carry over.
+        assert!(next.cli_args.is_empty());
+        assert!(!next.continue_session);
+    }
+
     #[test]
     fn test_resolve_args_invalid_timeout() {
         let mut args = default_args();
@@ -0,0 +1,97 @@
+//! Monotonic clock seam for the pattern engine.
+//!
+//! Live sessions read the real `Instant::now()`. Offline replay of a recorded
+//! session (see recording.rs) swaps in a fake clock that only moves when the
+//! replay driver advances it to the next event's timestamp, so idle waits,
+//! Enter retries and backoff timers fire at the same virtual moments they did
+//! in the recording — and a replay of an hour-long session runs in
+//! milliseconds. Its wall time starts at the recording's own timestamp, so a
+//! "resets 3pm" banner is slept out for the same length on every replay.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Default)]
+pub struct Clock {
+    fake: Option<Arc<FakeTime>>,
+}
+
+struct FakeTime {
+    origin: Instant,
+    /// Unix ms at `origin`.
+    wall_origin_ms: i64,
+    offset_ms: AtomicU64,
+}
+
+impl Clock {
+    /// A clock frozen at its creation instant until `advance_to` moves it.
+    /// Its wall time starts at `wall_start` (unix seconds), or the real time
+    /// without one.
+    pub fn fake(wall_start: Option<u64>) -> Self {
+        let wall_origin_ms = match wall_start {
+            Some(secs) => secs as i64 * 1000,
+            None => chrono::Utc::now().timestamp_millis(),
+        };
+        Self {
+            fake: Some(Arc::new(FakeTime {
+                origin: Instant::now(),
+                wall_origin_ms,
+                offset_ms: AtomicU64::new(0),
+            })),
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        match &self.fake {
+            Some(f) => f.origin + Duration::from_millis(f.offset_ms.load(Ordering::SeqCst)),
+            None => Instant::now(),
+        }
+    }
+
+    /// The local wall-clock time on this clock — for reading a time of day
+    /// off the screen ("resets 3pm").
+    pub fn wall_now(&self) -> chrono::DateTime<chrono::Local> {
+        match &self.fake {
+            Some(f) => {
+                let ms = f.wall_origin_ms + f.offset_ms.load(Ordering::SeqCst) as i64;
+                chrono::DateTime::from_timestamp_millis(ms)
+                    .unwrap_or_default()
+                    .with_timezone(&chrono::Local)
+            }
+            None => chrono::Local::now(),
+        }
+    }
+
+    /// Time since `earlier` on this clock (zero if `earlier` is in its future).
+    pub fn since(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+
+    /// Move a fake clock to `ms` after its origin. Never goes backwards; a
+    /// no-op on the real clock.
+    pub fn advance_to(&self, ms: u64) {
+        if let Some(f) = &self.fake {
+            f.offset_ms.fetch_max(ms, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_clock_moves_only_forward_on_demand() {
+        let clock = Clock::fake(Some(1_700_000_000));
+        let t0 = clock.now();
+        assert_eq!(clock.now(), t0);
+        clock.advance_to(1_500);
+        assert_eq!(clock.since(t0), Duration::from_millis(1_500));
+        assert_eq!(clock.wall_now().timestamp_millis(), 1_700_000_001_500);
+        // Clones share the same timeline; going backwards is ignored.
+        let other = clock.clone();
+        other.advance_to(100);
+        assert_eq!(clock.since(t0), Duration::from_millis(1_500));
+    }
+}
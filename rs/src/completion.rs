@@ -0,0 +1,161 @@
+//! Task completion (`done:` / `result:` in the CLI config) and `--result-mode`.
+//!
+//! An agent is done with its task when, after working on a prompt, it is back
+//! at a quiet prompt with nothing left in its prompt queue. A `done` pattern
+//! (claude's `✻ Worked for 2m 3s` line) confirms that within seconds; without
+//! one the prompt has to stay quiet for longer. The agent's pid record then
+//! reads `completed` — distinct from an idle agent that never got a task —
+//! until it starts working again.
+//!
+//! On completion the last assistant message is cut out of the rendered
+//! scrollback and written to `<cwd>/.agent-yes/<pid>.result.md`, which
+//! `ay result` falls back to when the agent never stored a result envelope
+//! itself. `result.message` marks the line that opens a message (claude:
+//! `● `); the message runs until the first `result.end` line — the input box
+//! and footer below it. CLIs without a `message` pattern only get the status.
+//!
+//! `--result-mode exit` also exits the CLI (exit reason `completed`), for
+//! one-shot runs that should end once the answer is in; `off` disables the
+//! whole detection.
+
+use crate::config_loader::{compile_regex_list, ResultOverride};
+use crate::log_files::project_log_dir;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::path::PathBuf;
+
+/// Compiled `result:` section.
+#[derive(Debug, Clone, Default)]
+pub struct ResultCapture {
+    pub message: Option<Regex>,
+    pub end: Vec<Regex>,
+}
+
+pub fn compile_result_capture(raw: Option<ResultOverride>) -> Result<ResultCapture> {
+    let Some(raw) = raw else {
+        return Ok(ResultCapture::default());
+    };
+    Ok(ResultCapture {
+        message: compile_regex_list(raw.message.map(|m| vec![m]))?.pop(),
+        end: compile_regex_list(raw.end)?,
+    })
+}
+
+impl ResultCapture {
+    /// The last assistant message in `scrollback`, marker stripped and its
+    /// continuation lines dedented. None when no message line is found or the
+    /// message is empty.
+    pub fn extract(&self, scrollback: &str) -> Option<String> {
+        let message = self.message.as_ref()?;
+        let lines: Vec<&str> = scrollback.lines().collect();
+        let (start, marker) = lines
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, line)| Some((i, message.find(line)?)))?;
+        let indent = lines[start][..marker.end()].chars().count();
+        let mut body = vec![&lines[start][marker.end()..]];
+        for line in &lines[start + 1..] {
+            if self.end.iter().any(|rx| rx.is_match(line)) {
+                break;
+            }
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            body.push(&line[spaces.min(indent)..]);
+        }
+        let text = body
+            .iter()
+            .map(|l| l.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+/// What happens once the task is done (`--result-mode`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResultMode {
+    /// Mark the agent completed and write `<pid>.result.md`.
+    #[default]
+    Save,
+    /// Same, then exit the CLI.
+    Exit,
+    /// No completion detection.
+    Off,
+}
+
+impl ResultMode {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "save" => Ok(ResultMode::Save),
+            "exit" => Ok(ResultMode::Exit),
+            "off" => Ok(ResultMode::Off),
+            other => Err(anyhow!(
+                "Unknown result mode '{}' (expected save, exit or off)",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResultMode::Save => "save",
+            ResultMode::Exit => "exit",
+            ResultMode::Off => "off",
+        }
+    }
+}
+
+pub fn result_path(pid: u32, cwd: &str) -> Option<PathBuf> {
+    project_log_dir(cwd).map(|dir| dir.join(format!("{}.result.md", pid)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture() -> ResultCapture {
+        compile_result_capture(Some(
+            serde_yaml::from_str(
+                r#"
+message: '^● '
+end: ['^\s*[✻✶] \w+ for \d', '^─{3,}']
+"#,
+            )
+            .unwrap(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_extract_takes_the_last_message() {
+        let scrollback = "\
+> fix the build
+● I'll look at the error first.
+● Bash(cargo build)
+  ⎿  Finished
+● The build is fixed: the missing import
+  was added to main.rs.
+
+    fn main() {}
+
+✻ Worked for 1m 2s
+────────────
+>
+────────────
+  ? for shortcuts";
+        assert_eq!(
+            capture().extract(scrollback).as_deref(),
+            Some("The build is fixed: the missing import\nwas added to main.rs.\n\n  fn main() {}")
+        );
+        assert_eq!(capture().extract("no messages here\n───────"), None);
+        assert_eq!(ResultCapture::default().extract(scrollback), None);
+    }
+
+    #[test]
+    fn test_parse_result_mode() {
+        assert_eq!(ResultMode::parse("exit").unwrap(), ResultMode::Exit);
+        assert_eq!(ResultMode::default(), ResultMode::Save);
+        assert!(ResultMode::parse("later").is_err());
+    }
+}
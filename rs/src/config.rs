@@ -1,9 +1,22 @@
 //! CLI tool configuration module
 
+use crate::action_rules::{compile_action_rules, ActionRule};
+use crate::approval_policy::{compile_approval_policy, ApprovalPolicy};
+use crate::checkpoint::{compile_checkpoint, CheckpointConfig};
+use crate::completion::{compile_result_capture, ResultCapture};
 use crate::config_loader::{
     compile_regex_list, load_cascading_config, CliConfigOverride, ConfigFile,
     InstallConfigOverride, RegexSource,
 };
+use crate::failover::{compile_failover, Failover};
+use crate::hooks::{compile_hooks, Hooks};
+use crate::paste::{compile_paste_fallback, PasteFallback};
+use crate::redact::{compile_redactor, Redactor};
+use crate::resource_limits::{compile_resource_limits, ResourceLimits};
+use crate::restart_policy::{compile_restart_policy, RestartPolicy};
+use crate::sandbox::{compile_sandbox, Sandbox};
+use crate::shutdown::{compile_shutdown_ladder, ShutdownLadder};
+use crate::usage::{compile_usage_counters, UsageCounters};
 use anyhow::{anyhow, Context, Result};
 use regex::Regex;
 use std::collections::HashMap;
@@ -73,6 +86,12 @@ pub struct CliConfig {
     pub yes_args: Vec<String>,
     /// Use cursor-based rendering (no newlines)
     pub no_eol: bool,
+    /// How multi-line injected text is typed without bracketed paste. See
+    /// paste.rs.
+    pub paste_fallback: PasteFallback,
+    /// The input line on the cursor's row; capture 1 is the typed text. See
+    /// input_arbiter.rs.
+    pub input_line: Vec<Regex>,
     /// No-output watchdog timeout (seconds). While a `working` spinner is on
     /// screen, a live CLI repaints its timer ~every second, so zero visible
     /// output for this long means the API stream silently stalled (the stream
@@ -95,6 +114,40 @@ pub struct CliConfig {
     /// Liveness window in ms: if we send stdin and the agent produces no PTY
     /// output within this window, mark it `unresponsive`. 0 = disabled.
     pub unresponsive_timeout_ms: u64,
+    /// Multi-step action rules (screen match → keys / text / waits). Checked
+    /// before `typing_respond` and `enter`, once per screen. See action_rules.rs.
+    pub actions: Vec<ActionRule>,
+    /// Approval policy consulted before auto-approving a permission prompt
+    /// that `enter` matched. Disabled (plain auto-yes) when it has no
+    /// extractors. See approval_policy.rs.
+    pub approval: ApprovalPolicy,
+    /// On-screen usage counters booked into the per-agent ledger. Disabled
+    /// when it has no extractors. See usage.rs.
+    pub usage: UsageCounters,
+    /// Screen markers of a finished turn; they shorten the quiet wait before
+    /// the agent is marked completed. See completion.rs.
+    pub done: Vec<Regex>,
+    /// Where the last assistant message sits in the scrollback, for
+    /// `<pid>.result.md`. See completion.rs.
+    pub result: ResultCapture,
+    /// Commands run on lifecycle transitions, top-level `hooks:` included.
+    /// See hooks.rs.
+    pub hooks: Hooks,
+    /// Per-step timeouts of the graceful shutdown ladder. See shutdown.rs.
+    pub shutdown: ShutdownLadder,
+    /// How `--robust` classes exits and paces restarts. See restart_policy.rs.
+    pub restart: RestartPolicy,
+    /// Kernel resource limits applied at spawn. See resource_limits.rs.
+    pub limits: ResourceLimits,
+    /// Namespace sandbox for the spawn, if enabled. See sandbox.rs.
+    pub sandbox: Sandbox,
+    /// Git checkpoints before auto-approved actions. See checkpoint.rs.
+    pub checkpoint: CheckpointConfig,
+    /// Where to hand the task on a long usage-limit block. See failover.rs.
+    pub failover: Failover,
+    /// Secret redaction for the logs, from the top-level `redact:`. See
+    /// redact.rs.
+    pub redact: Redactor,
 }
 
 /// Built-in no-output watchdog timeout when a CLI doesn't override it. Generous
@@ -153,18 +206,32 @@ fn load_builtin_config_file() -> Result<ConfigFile> {
 }
 
 fn build_cli_configs(config: ConfigFile) -> Result<HashMap<String, CliConfig>> {
+    let global_hooks = config.hooks.unwrap_or_default();
+    let redact = compile_redactor(config.redact).context("Invalid redact patterns")?;
     config
         .clis
         .into_iter()
-        .map(|(name, raw)| {
+        .map(|(name, mut raw)| {
+            // Top-level hooks apply to every CLI; its own win per event.
+            let mut hooks = global_hooks.clone();
+            hooks.extend(raw.hooks.take().unwrap_or_default());
+            raw.hooks = Some(hooks);
             build_cli_config(raw)
                 .with_context(|| format!("Failed to build CLI config for '{}'", name))
-                .map(|cfg| (name, cfg))
+                .map(|cfg| {
+                    (
+                        name,
+                        CliConfig {
+                            redact: redact.clone(),
+                            ..cfg
+                        },
+                    )
+                })
         })
         .collect()
 }
 
-fn build_cli_config(raw: CliConfigOverride) -> Result<CliConfig> {
+pub(crate) fn build_cli_config(raw: CliConfigOverride) -> Result<CliConfig> {
     Ok(CliConfig {
         prompt_arg: raw.prompt_arg.unwrap_or_else(|| "last-arg".to_string()),
         binary: raw.binary,
@@ -189,10 +256,25 @@ fn build_cli_config(raw: CliConfigOverride) -> Result<CliConfig> {
         default_args: raw.default_args.unwrap_or_default(),
         yes_args: raw.yes_args.unwrap_or_default(),
         no_eol: raw.no_eol.unwrap_or(false),
+        paste_fallback: compile_paste_fallback(raw.paste_fallback)?,
+        input_line: compile_regex_list(raw.input_line)?,
         stall_timeout_secs: raw.stall_timeout_secs.unwrap_or(DEFAULT_STALL_TIMEOUT_SECS),
         wedge_timeout_secs: raw.wedge_timeout_secs.unwrap_or(0),
         needs_input: compile_regex_list(raw.needs_input)?,
         unresponsive_timeout_ms: raw.unresponsive_timeout_ms.unwrap_or(0),
+        actions: compile_action_rules(raw.actions)?,
+        approval: compile_approval_policy(raw.approval).context("Invalid approval policy")?,
+        usage: compile_usage_counters(raw.usage).context("Invalid usage counters")?,
+        done: compile_regex_list(raw.done)?,
+        result: compile_result_capture(raw.result).context("Invalid result capture")?,
+        hooks: compile_hooks(raw.hooks).context("Invalid hooks")?,
+        shutdown: compile_shutdown_ladder(raw.shutdown),
+        restart: compile_restart_policy(raw.restart).context("Invalid restart policy")?,
+        limits: compile_resource_limits(raw.limits).context("Invalid resource limits")?,
+        sandbox: compile_sandbox(raw.sandbox).context("Invalid sandbox")?,
+        checkpoint: compile_checkpoint(raw.checkpoint),
+        failover: compile_failover(raw.failover),
+        redact: Redactor::default(),
     })
 }
 
@@ -251,6 +333,13 @@ mod tests {
         assert!(!config.enter.is_empty());
         // `-y` maps to claude's own permission-skip flag.
         assert_eq!(config.yes_args, vec!["--dangerously-skip-permissions"]);
+        assert_eq!(config.paste_fallback, PasteFallback::Backslash);
+        let input = &config.input_line[0].captures("> fix the te").unwrap()[1];
+        assert_eq!(input, "fix the te");
+        assert_eq!(
+            get_cli_config("bash").unwrap().paste_fallback,
+            PasteFallback::Raw
+        );
     }
 
     #[test]
@@ -440,6 +529,27 @@ mod tests {
             .auto_retry
             .iter()
             .any(|rx| rx.is_match("bump the session limit for QA runs")));
+        // Usage-limit banners hand their reset time to the quota sleep.
+        let reset = |screen| crate::reset_time::captured_reset(&config.auto_retry, screen);
+        assert_eq!(
+            reset("5-hour limit reached ∙ resets 3am (Asia/Tokyo)").map(str::trim),
+            Some("3am")
+        );
+        assert_eq!(
+            reset("Claude usage limit reached. Your limit will reset at 3pm (UTC)."),
+            Some("3pm ")
+        );
+        assert_eq!(
+            reset("Claude AI usage limit reached|1760594400"),
+            Some("1760594400")
+        );
+        assert_eq!(
+            reset(
+                "You've hit your usage limit. Upgrade to Pro or try again in 2 hours 14 minutes."
+            ),
+            Some("2 hours 14 minutes")
+        );
+        assert_eq!(reset("Claude usage limit reached"), None);
         assert!(!config.typing_respond.is_empty());
         assert!(config.typing_respond.contains_key("1\n"));
         assert_eq!(config.restore_args, vec!["--continue"]);
@@ -689,6 +799,69 @@ clis:
         assert!(config.update_available[0].is_match("custom update"));
     }
 
+    #[test]
+    fn test_merged_config_compiles_action_rules() {
+        let config = merged_config_from_yaml(
+            "claude",
+            r#"
+clis:
+  claude:
+    actions:
+      - match: ['Select all that apply']
+        steps:
+          - keys: [down, space, enter]
+          - waitFor: 'Submit'
+          - keys: [enter]
+"#,
+        );
+        assert_eq!(config.actions.len(), 1);
+        assert!(config.actions[0].matches("Select all that apply"));
+        assert_eq!(config.actions[0].steps.len(), 3);
+        // Builtin configs ship no actions.
+        assert!(get_cli_config("claude").unwrap().actions.is_empty());
+    }
+
+    #[test]
+    fn test_merged_config_layers_cli_hooks_over_global_hooks() {
+        use crate::hooks::HookKind;
+        let yaml = r#"
+hooks:
+  exit: ./global-exit.sh
+  stall: ./global-stall.sh
+clis:
+  claude:
+    hooks:
+      stall: { command: ./claude-stall.sh, typeBack: true }
+"#;
+        let claude = merged_config_from_yaml("claude", yaml);
+        let exit = claude.hooks.get(HookKind::Exit).unwrap();
+        assert_eq!(exit.command, "./global-exit.sh");
+        let stall = claude.hooks.get(HookKind::Stall).unwrap();
+        assert_eq!(stall.command, "./claude-stall.sh");
+        assert!(stall.type_back);
+        let codex = merged_config_from_yaml("codex", yaml);
+        assert_eq!(
+            codex.hooks.get(HookKind::Stall).unwrap().command,
+            "./global-stall.sh"
+        );
+        // Builtin configs ship no hooks.
+        assert!(get_cli_config("claude")
+            .unwrap()
+            .hooks
+            .get(HookKind::Exit)
+            .is_none());
+    }
+
+    #[test]
+    fn test_claude_usage_counters_read_the_spinner_line() {
+        let usage = get_cli_config("claude").unwrap().usage;
+        assert!(usage.is_enabled());
+        let screen = "✶ Verifying calendar meetings… (6m 30s · ↓ 19.5k tokens)\n· esc to interrupt";
+        assert_eq!(usage.read(screen), (Some(19500.0), None));
+        assert_eq!(usage.read("Token usage is fine"), (None, None));
+        assert!(!get_cli_config("codex").unwrap().usage.is_enabled());
+    }
+
     #[test]
     fn test_all_supported_clis() {
         let clis = vec![
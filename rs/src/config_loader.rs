@@ -89,6 +89,15 @@ pub struct CliConfigOverride {
     /// No EOL mode
     #[serde(default, alias = "noEOL")]
     pub no_eol: Option<bool>,
+    /// How multi-line injected text is typed when the CLI hasn't turned on
+    /// bracketed paste: `join`, `backslash` or `raw`. See paste.rs.
+    #[serde(default)]
+    pub paste_fallback: Option<String>,
+    /// The CLI's input line, matched against the cursor's row; capture 1 is
+    /// what's typed into it. Held injections wait while the human's line is
+    /// non-empty. See input_arbiter.rs.
+    #[serde(default)]
+    pub input_line: Option<Vec<RegexSource>>,
     /// Typing responses (pattern -> response)
     #[serde(default)]
     pub typing_respond: Option<HashMap<String, Vec<RegexSource>>>,
@@ -122,6 +131,512 @@ pub struct CliConfigOverride {
     /// disables the check — appropriate for CLIs that don't animate a spinner.
     #[serde(default)]
     pub unresponsive_timeout_ms: Option<u64>,
+    /// Declarative multi-step action rules: screen match → ordered keys / text
+    /// / waits. See action_rules.rs.
+    #[serde(default)]
+    pub actions: Option<Vec<ActionRuleOverride>>,
+    /// Approval policy for permission prompts: extract the proposed command /
+    /// path and allow, deny or hold it for a human. See approval_policy.rs.
+    #[serde(default)]
+    pub approval: Option<ApprovalOverride>,
+    /// On-screen usage counters (tokens / cost) booked into the per-agent
+    /// ledger. See usage.rs.
+    #[serde(default)]
+    pub usage: Option<UsageOverride>,
+    /// Screen markers of a finished turn. Completion is detected without them
+    /// (ready again after working, then quiet); a match just confirms it
+    /// sooner. See completion.rs.
+    #[serde(default)]
+    pub done: Option<Vec<RegexSource>>,
+    /// How to cut the last assistant message out of the scrollback for
+    /// `<pid>.result.md`. See completion.rs.
+    #[serde(default)]
+    pub result: Option<ResultOverride>,
+    /// Lifecycle hooks: lifecycle event (`ready`, `exit`, ...) → command.
+    /// Layered over the top-level `hooks:` per event. See hooks.rs.
+    #[serde(default)]
+    pub hooks: Option<HashMap<String, HookOverride>>,
+    /// Shutdown ladder timeouts: exit command → Ctrl-C → SIGTERM → SIGKILL.
+    /// See shutdown.rs.
+    #[serde(default)]
+    pub shutdown: Option<ShutdownOverride>,
+    /// `--robust` restart policy: crash-loop window, backoff and exit classes.
+    /// See restart_policy.rs.
+    #[serde(default)]
+    pub restart: Option<RestartOverride>,
+    /// Kernel resource limits for the agent's processes (cgroup v2, else
+    /// rlimits). See resource_limits.rs.
+    #[serde(default)]
+    pub limits: Option<LimitsOverride>,
+    /// Filesystem allowlist and network for `--sandbox`. See sandbox.rs.
+    #[serde(default)]
+    pub sandbox: Option<SandboxOverride>,
+    /// Git snapshots of the working tree before each auto-approved action.
+    /// See checkpoint.rs.
+    #[serde(default)]
+    pub checkpoint: Option<CheckpointOverride>,
+    /// CLIs to hand the task to on a long usage-limit block. See failover.rs.
+    #[serde(default)]
+    pub failover: Option<FailoverOverride>,
+}
+
+/// The `shutdown:` section: how long each step of the ladder waits for the
+/// CLI to exit before escalating. 0 skips the step. Merged field by field.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShutdownOverride {
+    #[serde(default)]
+    pub exit_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub ctrl_c_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub term_timeout_secs: Option<u64>,
+}
+
+impl ShutdownOverride {
+    fn merge(&mut self, other: ShutdownOverride) {
+        let ShutdownOverride {
+            exit_timeout_secs,
+            ctrl_c_timeout_secs,
+            term_timeout_secs,
+        } = other;
+        if exit_timeout_secs.is_some() {
+            self.exit_timeout_secs = exit_timeout_secs;
+        }
+        if ctrl_c_timeout_secs.is_some() {
+            self.ctrl_c_timeout_secs = ctrl_c_timeout_secs;
+        }
+        if term_timeout_secs.is_some() {
+            self.term_timeout_secs = term_timeout_secs;
+        }
+    }
+}
+
+/// The `restart:` section. Merged field by field; a list replaces the one
+/// below it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartOverride {
+    /// Restarts allowed within `windowSecs` before the agent is parked as
+    /// `crashloop`. 0 never parks it.
+    #[serde(default)]
+    pub max_restarts: Option<u32>,
+    #[serde(default)]
+    pub window_secs: Option<u64>,
+    /// First restart delay, doubled for each restart still in the window
+    #[serde(default)]
+    pub backoff_initial_ms: Option<u64>,
+    #[serde(default)]
+    pub backoff_max_ms: Option<u64>,
+    /// Exit codes never worth a restart (e.g. 127: command not found)
+    #[serde(default)]
+    pub fatal_exit_codes: Option<Vec<i32>>,
+    /// Exit codes that mean the CLI finished on purpose (0 always does)
+    #[serde(default)]
+    pub clean_exit_codes: Option<Vec<i32>>,
+    /// Screen patterns at exit, checked before the exit code
+    #[serde(default)]
+    pub fatal: Option<Vec<RegexSource>>,
+    #[serde(default)]
+    pub retryable: Option<Vec<RegexSource>>,
+    #[serde(default)]
+    pub clean: Option<Vec<RegexSource>>,
+}
+
+impl RestartOverride {
+    fn merge(&mut self, other: RestartOverride) {
+        let RestartOverride {
+            max_restarts,
+            window_secs,
+            backoff_initial_ms,
+            backoff_max_ms,
+            fatal_exit_codes,
+            clean_exit_codes,
+            fatal,
+            retryable,
+            clean,
+        } = other;
+        if max_restarts.is_some() {
+            self.max_restarts = max_restarts;
+        }
+        if window_secs.is_some() {
+            self.window_secs = window_secs;
+        }
+        if backoff_initial_ms.is_some() {
+            self.backoff_initial_ms = backoff_initial_ms;
+        }
+        if backoff_max_ms.is_some() {
+            self.backoff_max_ms = backoff_max_ms;
+        }
+        if fatal_exit_codes.is_some() {
+            self.fatal_exit_codes = fatal_exit_codes;
+        }
+        if clean_exit_codes.is_some() {
+            self.clean_exit_codes = clean_exit_codes;
+        }
+        if fatal.is_some() {
+            self.fatal = fatal;
+        }
+        if retryable.is_some() {
+            self.retryable = retryable;
+        }
+        if clean.is_some() {
+            self.clean = clean;
+        }
+    }
+}
+
+/// A size or count: a plain number, or text such as `"2G"` / `"0.5"`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum LimitValue {
+    Number(u64),
+    Text(String),
+}
+
+/// The `limits:` section. Merged field by field.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitsOverride {
+    /// Bytes, or with a K/M/G/T suffix
+    #[serde(default)]
+    pub memory_max: Option<LimitValue>,
+    /// CPUs' worth of time, fractions allowed (`"0.5"`)
+    #[serde(default)]
+    pub cpu_max: Option<LimitValue>,
+    #[serde(default)]
+    pub pids_max: Option<u64>,
+    /// Open-file rlimit (RLIMIT_NOFILE)
+    #[serde(default)]
+    pub nofile: Option<u64>,
+}
+
+impl LimitsOverride {
+    fn merge(&mut self, other: LimitsOverride) {
+        let LimitsOverride {
+            memory_max,
+            cpu_max,
+            pids_max,
+            nofile,
+        } = other;
+        if memory_max.is_some() {
+            self.memory_max = memory_max;
+        }
+        if cpu_max.is_some() {
+            self.cpu_max = cpu_max;
+        }
+        if pids_max.is_some() {
+            self.pids_max = pids_max;
+        }
+        if nofile.is_some() {
+            self.nofile = nofile;
+        }
+    }
+}
+
+/// The `sandbox:` section. Merged field by field; a path list replaces the
+/// one below it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxOverride {
+    /// Sandbox every run of this CLI, as if `--sandbox` were passed
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Visible read-only, on top of the toolchain defaults (`~/` allowed)
+    #[serde(default)]
+    pub read_only: Option<Vec<String>>,
+    /// Visible read-write besides the cwd (`~/` allowed)
+    #[serde(default)]
+    pub read_write: Option<Vec<String>>,
+    /// False: own network namespace, loopback only
+    #[serde(default)]
+    pub network: Option<bool>,
+}
+
+impl SandboxOverride {
+    fn merge(&mut self, other: SandboxOverride) {
+        let SandboxOverride {
+            enabled,
+            read_only,
+            read_write,
+            network,
+        } = other;
+        if enabled.is_some() {
+            self.enabled = enabled;
+        }
+        if read_only.is_some() {
+            self.read_only = read_only;
+        }
+        if read_write.is_some() {
+            self.read_write = read_write;
+        }
+        if network.is_some() {
+            self.network = network;
+        }
+    }
+}
+
+/// The `checkpoint:` section. Merged field by field.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointOverride {
+    /// False: no checkpoints for this CLI
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Checkpoints kept per agent, oldest dropped first (0 keeps all)
+    #[serde(default)]
+    pub keep: Option<u32>,
+    /// Longest a snapshot may hold up the approval it precedes
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+impl CheckpointOverride {
+    fn merge(&mut self, other: CheckpointOverride) {
+        let CheckpointOverride {
+            enabled,
+            keep,
+            timeout_secs,
+        } = other;
+        if enabled.is_some() {
+            self.enabled = enabled;
+        }
+        if keep.is_some() {
+            self.keep = keep;
+        }
+        if timeout_secs.is_some() {
+            self.timeout_secs = timeout_secs;
+        }
+    }
+}
+
+/// The `failover:` section. Merged field by field; `to` replaces the list
+/// below it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FailoverOverride {
+    /// CLIs to hand over to, in order
+    #[serde(default)]
+    pub to: Option<Vec<String>>,
+    /// Shortest usage-limit wait that triggers a handoff
+    #[serde(default)]
+    pub min_wait_secs: Option<u64>,
+}
+
+impl FailoverOverride {
+    fn merge(&mut self, other: FailoverOverride) {
+        let FailoverOverride { to, min_wait_secs } = other;
+        if to.is_some() {
+            self.to = to;
+        }
+        if min_wait_secs.is_some() {
+            self.min_wait_secs = min_wait_secs;
+        }
+    }
+}
+
+/// The top-level `redact:` section. Merged field by field; `patterns`
+/// replaces the list below it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactOverride {
+    /// False turns redaction off, built-in detectors included
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Extra secret patterns; a capture group redacts only the group
+    #[serde(default)]
+    pub patterns: Option<Vec<RegexSource>>,
+}
+
+impl RedactOverride {
+    fn merge(&mut self, other: RedactOverride) {
+        let RedactOverride { enabled, patterns } = other;
+        if enabled.is_some() {
+            self.enabled = enabled;
+        }
+        if patterns.is_some() {
+            self.patterns = patterns;
+        }
+    }
+}
+
+/// One `hooks:` entry: a bare command, or the command with its options.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum HookOverride {
+    Command(String),
+    Structured {
+        command: String,
+        /// Kill the hook after this many seconds (default 10)
+        #[serde(default, rename = "timeoutSecs")]
+        timeout_secs: Option<u64>,
+        /// Queue the hook's stdout as a prompt for the agent
+        #[serde(default, rename = "typeBack")]
+        type_back: Option<bool>,
+    },
+}
+
+/// The `result:` section. Merged field by field.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultOverride {
+    /// A line that opens an assistant message (claude: `^● `)
+    #[serde(default)]
+    pub message: Option<RegexSource>,
+    /// Lines that end it: the chrome below the last message (input box, footer)
+    #[serde(default)]
+    pub end: Option<Vec<RegexSource>>,
+}
+
+impl ResultOverride {
+    fn merge(&mut self, other: ResultOverride) {
+        let ResultOverride { message, end } = other;
+        if message.is_some() {
+            self.message = message;
+        }
+        if end.is_some() {
+            self.end = end;
+        }
+    }
+}
+
+/// The `usage:` section. Merged field by field.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageOverride {
+    /// Extractors for the token counter (capture group 1, e.g. `19.5k`)
+    #[serde(default)]
+    pub tokens: Option<Vec<RegexSource>>,
+    /// Extractors for a cost counter in USD (capture group 1, e.g. `0.42`)
+    #[serde(default)]
+    pub cost: Option<Vec<RegexSource>>,
+}
+
+impl UsageOverride {
+    fn merge(&mut self, other: UsageOverride) {
+        let UsageOverride { tokens, cost } = other;
+        if tokens.is_some() {
+            self.tokens = tokens;
+        }
+        if cost.is_some() {
+            self.cost = cost;
+        }
+    }
+}
+
+/// The `approval:` section. Merged field by field, so a user config can add
+/// `rules` without restating the shipped extractors.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalOverride {
+    /// Extractors for a proposed shell command (capture group 1)
+    #[serde(default)]
+    pub command: Option<Vec<RegexSource>>,
+    /// Extractors for a proposed file path (capture group 1)
+    #[serde(default)]
+    pub path: Option<Vec<RegexSource>>,
+    /// Ordered rules; the first match decides
+    #[serde(default)]
+    pub rules: Option<Vec<ApprovalRuleOverride>>,
+    /// Verdict when no rule matches: allow | deny | ask
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Verdict for an unmatched path outside the agent's cwd
+    #[serde(default)]
+    pub outside_cwd: Option<String>,
+    /// Keys that pick "No" on a denied prompt
+    #[serde(default)]
+    pub deny_keys: Option<Vec<String>>,
+}
+
+impl ApprovalOverride {
+    fn merge(&mut self, other: ApprovalOverride) {
+        let ApprovalOverride {
+            command,
+            path,
+            rules,
+            default,
+            outside_cwd,
+            deny_keys,
+        } = other;
+        if command.is_some() {
+            self.command = command;
+        }
+        if path.is_some() {
+            self.path = path;
+        }
+        if rules.is_some() {
+            self.rules = rules;
+        }
+        if default.is_some() {
+            self.default = default;
+        }
+        if outside_cwd.is_some() {
+            self.outside_cwd = outside_cwd;
+        }
+        if deny_keys.is_some() {
+            self.deny_keys = deny_keys;
+        }
+    }
+}
+
+/// One `approval.rules` entry: `verdict` applies when `command` matches a
+/// proposed command or `path` matches a proposed file path.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRuleOverride {
+    /// Label for the audit log
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub command: Option<RegexSource>,
+    #[serde(default)]
+    pub path: Option<RegexSource>,
+    /// allow | deny | ask
+    pub verdict: String,
+}
+
+/// One `actions:` entry. When any `match` pattern is on screen and no
+/// `exclude` pattern is, the `steps` run in order.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionRuleOverride {
+    /// Label for logs
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Trigger patterns (any)
+    #[serde(rename = "match")]
+    pub patterns: Vec<RegexSource>,
+    /// Guard patterns: the rule does not fire while any of these match
+    #[serde(default)]
+    pub exclude: Option<Vec<RegexSource>>,
+    /// Ordered steps
+    pub steps: Vec<ActionStepOverride>,
+    /// How long a `waitFor` step may wait before the run is abandoned
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// A single action step, written as a one-key map:
+/// `keys: [down, enter]`, `text: "..."`, `wait: 300` (ms), `waitFor: <regex>`.
+/// Untagged rather than an externally tagged enum: serde_yaml 0.9 reads those
+/// only from `!tag` syntax, and the same shape must parse from JSON configs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ActionStepOverride {
+    Keys {
+        keys: Vec<String>,
+    },
+    Text {
+        text: String,
+    },
+    Wait {
+        wait: u64,
+    },
+    WaitFor {
+        #[serde(rename = "waitFor")]
+        wait_for: RegexSource,
+    },
 }
 
 /// Install configuration override
@@ -217,6 +732,8 @@ impl CliConfigOverride {
             enter_exclude,
             prompt_arg,
             no_eol,
+            paste_fallback,
+            input_line,
             typing_respond,
             restore_args,
             restart_without_continue_arg,
@@ -225,6 +742,18 @@ impl CliConfigOverride {
             wedge_timeout_secs,
             needs_input,
             unresponsive_timeout_ms,
+            actions,
+            approval,
+            usage,
+            done,
+            result,
+            hooks,
+            shutdown,
+            restart,
+            limits,
+            sandbox,
+            checkpoint,
+            failover,
         } = other;
 
         if let Some(install) = install {
@@ -294,6 +823,12 @@ impl CliConfigOverride {
         if no_eol.is_some() {
             self.no_eol = no_eol;
         }
+        if paste_fallback.is_some() {
+            self.paste_fallback = paste_fallback;
+        }
+        if input_line.is_some() {
+            self.input_line = input_line;
+        }
         if let Some(typing_respond) = typing_respond {
             if let Some(existing_typing_respond) = self.typing_respond.as_mut() {
                 for (message, patterns) in typing_respond {
@@ -324,6 +859,78 @@ impl CliConfigOverride {
         if unresponsive_timeout_ms.is_some() {
             self.unresponsive_timeout_ms = unresponsive_timeout_ms;
         }
+        if actions.is_some() {
+            self.actions = actions;
+        }
+        if let Some(approval) = approval {
+            if let Some(existing_approval) = self.approval.as_mut() {
+                existing_approval.merge(approval);
+            } else {
+                self.approval = Some(approval);
+            }
+        }
+        if let Some(usage) = usage {
+            if let Some(existing_usage) = self.usage.as_mut() {
+                existing_usage.merge(usage);
+            } else {
+                self.usage = Some(usage);
+            }
+        }
+        if done.is_some() {
+            self.done = done;
+        }
+        if let Some(result) = result {
+            if let Some(existing_result) = self.result.as_mut() {
+                existing_result.merge(result);
+            } else {
+                self.result = Some(result);
+            }
+        }
+        if let Some(hooks) = hooks {
+            self.hooks.get_or_insert_with(HashMap::new).extend(hooks);
+        }
+        if let Some(shutdown) = shutdown {
+            if let Some(existing_shutdown) = self.shutdown.as_mut() {
+                existing_shutdown.merge(shutdown);
+            } else {
+                self.shutdown = Some(shutdown);
+            }
+        }
+        if let Some(restart) = restart {
+            if let Some(existing_restart) = self.restart.as_mut() {
+                existing_restart.merge(restart);
+            } else {
+                self.restart = Some(restart);
+            }
+        }
+        if let Some(limits) = limits {
+            if let Some(existing_limits) = self.limits.as_mut() {
+                existing_limits.merge(limits);
+            } else {
+                self.limits = Some(limits);
+            }
+        }
+        if let Some(sandbox) = sandbox {
+            if let Some(existing_sandbox) = self.sandbox.as_mut() {
+                existing_sandbox.merge(sandbox);
+            } else {
+                self.sandbox = Some(sandbox);
+            }
+        }
+        if let Some(checkpoint) = checkpoint {
+            if let Some(existing_checkpoint) = self.checkpoint.as_mut() {
+                existing_checkpoint.merge(checkpoint);
+            } else {
+                self.checkpoint = Some(checkpoint);
+            }
+        }
+        if let Some(failover) = failover {
+            if let Some(existing_failover) = self.failover.as_mut() {
+                existing_failover.merge(failover);
+            } else {
+                self.failover = Some(failover);
+            }
+        }
     }
 }
 
@@ -337,6 +944,12 @@ pub struct ConfigFile {
     /// Logs directory override
     #[serde(default)]
     pub logs_dir: Option<String>,
+    /// Lifecycle hooks for every CLI; a CLI's own `hooks:` wins per event
+    #[serde(default)]
+    pub hooks: Option<HashMap<String, HookOverride>>,
+    /// Secret redaction for logs, tails and webhooks. See redact.rs.
+    #[serde(default)]
+    pub redact: Option<RedactOverride>,
     /// CLI-specific overrides
     #[serde(default)]
     pub clis: HashMap<String, CliConfigOverride>,
@@ -351,6 +964,16 @@ impl ConfigFile {
         if other.logs_dir.is_some() {
             self.logs_dir = other.logs_dir;
         }
+        if let Some(hooks) = other.hooks {
+            self.hooks.get_or_insert_with(HashMap::new).extend(hooks);
+        }
+        if let Some(redact) = other.redact {
+            if let Some(existing_redact) = self.redact.as_mut() {
+                existing_redact.merge(redact);
+            } else {
+                self.redact = Some(redact);
+            }
+        }
         for (cli_name, cli_config) in other.clis {
             if let Some(existing) = self.clis.get_mut(&cli_name) {
                 existing.merge(cli_config);
@@ -435,11 +1058,9 @@ pub fn load_cascading_config() -> ConfigFile {
     merged
 }
 
-/// Get all possible config file paths (for debugging/user info).
-///
-/// Reserved for a `--show-config` / diagnostic surface that hasn't shipped yet;
-/// keeping the helper exported avoids breaking it when that surface lands.
-#[allow(dead_code)]
+/// Get all possible config file paths (for debugging/user info), in the order
+/// `load_cascading_config` reads them. `ay patterns debug` lists the ones that
+/// exist.
 pub fn get_config_paths() -> Vec<PathBuf> {
     let mut paths = Vec::new();
 
@@ -479,6 +1100,18 @@ mod tests {
         RegexSource::Pattern(value.to_string())
     }
 
+    fn action_rule(trigger: &str) -> ActionRuleOverride {
+        ActionRuleOverride {
+            name: None,
+            patterns: vec![pattern(trigger)],
+            exclude: None,
+            steps: vec![ActionStepOverride::Keys {
+                keys: vec!["enter".into()],
+            }],
+            timeout_ms: None,
+        }
+    }
+
     fn structured(pattern: &str, flags: &str) -> RegexSource {
         RegexSource::Structured {
             pattern: pattern.to_string(),
@@ -611,6 +1244,11 @@ logsDir: /custom/logs
         let mut base = ConfigFile {
             config_dir: Some("/base".to_string()),
             logs_dir: Some("/base/logs".to_string()),
+            hooks: None,
+            redact: Some(RedactOverride {
+                enabled: Some(true),
+                patterns: Some(vec![RegexSource::Pattern("base-secret".into())]),
+            }),
             clis: HashMap::new(),
         };
         base.clis.insert(
@@ -624,6 +1262,11 @@ logsDir: /custom/logs
         let override_config = ConfigFile {
             config_dir: Some("/override".to_string()),
             logs_dir: None,
+            hooks: None,
+            redact: Some(RedactOverride {
+                enabled: Some(false),
+                patterns: None,
+            }),
             clis: {
                 let mut clis = HashMap::new();
                 clis.insert(
@@ -641,6 +1284,13 @@ logsDir: /custom/logs
 
         assert_eq!(base.config_dir, Some("/override".to_string()));
         assert_eq!(base.logs_dir, Some("/base/logs".to_string()));
+        assert_eq!(
+            base.redact,
+            Some(RedactOverride {
+                enabled: Some(false),
+                patterns: Some(vec![RegexSource::Pattern("base-secret".into())]),
+            })
+        );
         let claude = base.clis.get("claude").unwrap();
         assert_eq!(claude.binary, Some("claude-bin".to_string()));
         assert_eq!(claude.default_args, Some(vec!["--verbose".to_string()]));
@@ -651,11 +1301,15 @@ logsDir: /custom/logs
         let mut base = ConfigFile {
             config_dir: None,
             logs_dir: Some("/old/logs".to_string()),
+            hooks: None,
+            redact: None,
             clis: HashMap::new(),
         };
         base.merge(ConfigFile {
             config_dir: None,
             logs_dir: Some("/new/logs".to_string()),
+            hooks: None,
+            redact: None,
             clis: HashMap::new(),
         });
         assert_eq!(base.logs_dir, Some("/new/logs".to_string()));
@@ -692,6 +1346,8 @@ logsDir: /custom/logs
                 enter_exclude: Some(vec![pattern("old-enter-exclude")]),
                 prompt_arg: Some("old-prompt".into()),
                 no_eol: Some(false),
+                paste_fallback: None,
+                input_line: None,
                 typing_respond: Some(HashMap::from([("1".into(), vec![pattern("old-pattern")])])),
                 restore_args: Some(vec!["old-restore".into()]),
                 restart_without_continue_arg: Some(vec![pattern("old-restart")]),
@@ -700,6 +1356,58 @@ logsDir: /custom/logs
                 wedge_timeout_secs: Some(1111),
                 needs_input: Some(vec![pattern("old-needs-input")]),
                 unresponsive_timeout_ms: Some(1000),
+                actions: Some(vec![action_rule("old-action")]),
+                approval: Some(ApprovalOverride {
+                    command: Some(vec![pattern("old-command")]),
+                    default: Some("ask".into()),
+                    ..Default::default()
+                }),
+                usage: Some(UsageOverride {
+                    tokens: Some(vec![pattern("old-tokens")]),
+                    cost: None,
+                }),
+                done: Some(vec![pattern("old-done")]),
+                result: Some(ResultOverride {
+                    message: Some(pattern("old-message")),
+                    end: Some(vec![pattern("old-end")]),
+                }),
+                hooks: Some(HashMap::from([
+                    (
+                        "ready".into(),
+                        HookOverride::Command("old-ready-hook".into()),
+                    ),
+                    ("exit".into(), HookOverride::Command("old-exit-hook".into())),
+                ])),
+                shutdown: Some(ShutdownOverride {
+                    exit_timeout_secs: Some(10),
+                    ctrl_c_timeout_secs: Some(5),
+                    term_timeout_secs: None,
+                }),
+                restart: Some(RestartOverride {
+                    max_restarts: Some(5),
+                    window_secs: Some(600),
+                    fatal_exit_codes: Some(vec![127]),
+                    fatal: Some(vec![pattern("old-restart-fatal")]),
+                    ..Default::default()
+                }),
+                limits: Some(LimitsOverride {
+                    memory_max: Some(LimitValue::Text("4G".into())),
+                    pids_max: Some(512),
+                    ..Default::default()
+                }),
+                sandbox: Some(SandboxOverride {
+                    read_only: Some(vec!["/old-ro".into()]),
+                    read_write: Some(vec!["~/.old".into()]),
+                    ..Default::default()
+                }),
+                checkpoint: Some(CheckpointOverride {
+                    keep: Some(10),
+                    ..Default::default()
+                }),
+                failover: Some(FailoverOverride {
+                    to: Some(vec!["codex".into()]),
+                    min_wait_secs: Some(600),
+                }),
             },
         );
 
@@ -734,6 +1442,8 @@ logsDir: /custom/logs
                 enter_exclude: Some(vec![pattern("new-enter-exclude")]),
                 prompt_arg: Some("new-prompt".into()),
                 no_eol: Some(true),
+                paste_fallback: None,
+                input_line: None,
                 restore_args: Some(vec!["new-restore".into()]),
                 typing_respond: Some(tr),
                 restart_without_continue_arg: Some(vec![pattern("new-restart")]),
@@ -742,12 +1452,65 @@ logsDir: /custom/logs
                 wedge_timeout_secs: Some(2222),
                 needs_input: Some(vec![pattern("new-needs-input")]),
                 unresponsive_timeout_ms: Some(2000),
+                actions: Some(vec![action_rule("new-action")]),
+                approval: Some(ApprovalOverride {
+                    default: Some("deny".into()),
+                    ..Default::default()
+                }),
+                usage: Some(UsageOverride {
+                    tokens: None,
+                    cost: Some(vec![pattern("new-cost")]),
+                }),
+                done: Some(vec![pattern("new-done")]),
+                result: Some(ResultOverride {
+                    message: None,
+                    end: Some(vec![pattern("new-end")]),
+                }),
+                hooks: Some(HashMap::from([(
+                    "exit".into(),
+                    HookOverride::Structured {
+                        command: "new-exit-hook".into(),
+                        timeout_secs: Some(5),
+                        type_back: None,
+                    },
+                )])),
+                shutdown: Some(ShutdownOverride {
+                    exit_timeout_secs: Some(30),
+                    ctrl_c_timeout_secs: None,
+                    term_timeout_secs: Some(2),
+                }),
+                restart: Some(RestartOverride {
+                    max_restarts: Some(2),
+                    fatal_exit_codes: Some(vec![126]),
+                    retryable: Some(vec![pattern("new-restart-retryable")]),
+                    ..Default::default()
+                }),
+                limits: Some(LimitsOverride {
+                    memory_max: Some(LimitValue::Number(1 << 30)),
+                    nofile: Some(4096),
+                    ..Default::default()
+                }),
+                sandbox: Some(SandboxOverride {
+                    read_write: Some(vec!["~/.new".into()]),
+                    network: Some(false),
+                    ..Default::default()
+                }),
+                checkpoint: Some(CheckpointOverride {
+                    enabled: Some(false),
+                    ..Default::default()
+                }),
+                failover: Some(FailoverOverride {
+                    to: Some(vec!["qwen".into()]),
+                    ..Default::default()
+                }),
             },
         );
 
         base.merge(ConfigFile {
             config_dir: None,
             logs_dir: None,
+            hooks: None,
+            redact: None,
             clis: override_clis,
         });
 
@@ -797,6 +1560,93 @@ logsDir: /custom/logs
         assert_eq!(t.wedge_timeout_secs, Some(2222));
         assert_eq!(t.needs_input, Some(vec![pattern("new-needs-input")]));
         assert_eq!(t.unresponsive_timeout_ms, Some(2000));
+        assert_eq!(t.actions, Some(vec![action_rule("new-action")]));
+        // approval merges field by field: shipped extractors survive a user
+        // config that only changes the verdicts.
+        assert_eq!(
+            t.approval,
+            Some(ApprovalOverride {
+                command: Some(vec![pattern("old-command")]),
+                default: Some("deny".into()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            t.usage,
+            Some(UsageOverride {
+                tokens: Some(vec![pattern("old-tokens")]),
+                cost: Some(vec![pattern("new-cost")]),
+            })
+        );
+        assert_eq!(t.done, Some(vec![pattern("new-done")]));
+        assert_eq!(
+            t.result,
+            Some(ResultOverride {
+                message: Some(pattern("old-message")),
+                end: Some(vec![pattern("new-end")]),
+            })
+        );
+        assert_eq!(
+            t.shutdown,
+            Some(ShutdownOverride {
+                exit_timeout_secs: Some(30),
+                ctrl_c_timeout_secs: Some(5),
+                term_timeout_secs: Some(2),
+            })
+        );
+        assert_eq!(
+            t.restart,
+            Some(RestartOverride {
+                max_restarts: Some(2),
+                window_secs: Some(600),
+                fatal_exit_codes: Some(vec![126]),
+                fatal: Some(vec![pattern("old-restart-fatal")]),
+                retryable: Some(vec![pattern("new-restart-retryable")]),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            t.limits,
+            Some(LimitsOverride {
+                memory_max: Some(LimitValue::Number(1 << 30)),
+                cpu_max: None,
+                pids_max: Some(512),
+                nofile: Some(4096),
+            })
+        );
+        assert_eq!(
+            t.sandbox,
+            Some(SandboxOverride {
+                enabled: None,
+                read_only: Some(vec!["/old-ro".into()]),
+                read_write: Some(vec!["~/.new".into()]),
+                network: Some(false),
+            })
+        );
+        assert_eq!(
+            t.checkpoint,
+            Some(CheckpointOverride {
+                enabled: Some(false),
+                keep: Some(10),
+                timeout_secs: None,
+            })
+        );
+        assert_eq!(
+            t.failover,
+            Some(FailoverOverride {
+                to: Some(vec!["qwen".into()]),
+                min_wait_secs: Some(600),
+            })
+        );
+        let hooks = t.hooks.as_ref().unwrap();
+        assert_eq!(
+            hooks["ready"],
+            HookOverride::Command("old-ready-hook".into())
+        );
+        assert!(matches!(
+            &hooks["exit"],
+            HookOverride::Structured { command, .. } if command == "new-exit-hook"
+        ));
         assert!(t.typing_respond.as_ref().unwrap().contains_key("y"));
         assert!(t.typing_respond.as_ref().unwrap().contains_key("1"));
     }
@@ -816,6 +1666,8 @@ logsDir: /custom/logs
         base.merge(ConfigFile {
             config_dir: None,
             logs_dir: None,
+            hooks: None,
+            redact: None,
             clis: override_clis,
         });
 
@@ -909,6 +1761,43 @@ logsDir: /custom/logs
         assert_eq!(codex.no_eol, Some(true));
     }
 
+    #[test]
+    fn test_parse_action_rules_yaml() {
+        let yaml = r#"
+clis:
+  claude:
+    actions:
+      - name: tick-and-confirm
+        match:
+          - pattern: '❯ 1\. Keep'
+            flags: m
+        exclude: ['read-only']
+        steps:
+          - keys: [down, space, enter]
+          - wait: 250
+          - waitFor: 'Describe'
+          - text: "ok"
+"#;
+        let parsed: ConfigFile = serde_yaml::from_str(yaml).unwrap();
+        let rule = &parsed.clis["claude"].actions.as_ref().unwrap()[0];
+        assert_eq!(rule.name.as_deref(), Some("tick-and-confirm"));
+        assert_eq!(rule.patterns, vec![structured("❯ 1\\. Keep", "m")]);
+        assert_eq!(rule.exclude, Some(vec![pattern("read-only")]));
+        assert_eq!(
+            rule.steps,
+            vec![
+                ActionStepOverride::Keys {
+                    keys: vec!["down".into(), "space".into(), "enter".into()]
+                },
+                ActionStepOverride::Wait { wait: 250 },
+                ActionStepOverride::WaitFor {
+                    wait_for: pattern("Describe")
+                },
+                ActionStepOverride::Text { text: "ok".into() },
+            ]
+        );
+    }
+
     #[test]
     fn test_full_json_config_with_all_fields() {
         let dir = tempdir().unwrap();
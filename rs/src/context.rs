@@ -1,26 +1,53 @@
 //! Agent context and main orchestrator
 
+use crate::action_rules::{ActionPoll, ActionRun};
+use crate::approval_policy::Verdict;
+use crate::checkpoint::Checkpointer;
+use crate::clock::Clock;
 use crate::codex_sessions;
+use crate::completion::{result_path, ResultMode};
 use crate::config::CliConfig;
+use crate::events::{Event, EventLog};
+use crate::failover::Handover;
+use crate::hooks::{HookKind, HookPayload, HookRunner, HOOK_SCREEN_TAIL_LINES};
 use crate::idle_waiter::IdleWaiter;
+use crate::input_arbiter::{Injection, InputArbiter, Payload, ReviewAnswer, Source, REVIEW_KEY};
+use crate::jsonl_output::OutputFormat;
 use crate::log_files::LogWriter;
-use crate::messaging::{send_ctrl_c, send_esc, send_text, MessageContext};
+use crate::messaging::{send_bytes, send_ctrl_c, send_esc, send_prompt, send_text, MessageContext};
+use crate::meta_commands::MetaCommand;
+use crate::paste::PasteFrameScanner;
+use crate::prompt_queue::{PromptQueue, QueueFrameScanner};
 use crate::pty_spawner::{get_terminal_size, PtyContext};
 use crate::ready_manager::ReadyManager;
+use crate::recording::{self, Capture, Cast, CastEvent, CastKind, CastWriter};
+use crate::redact::StreamRedactor;
+use crate::restart_policy::STALL_EXIT_CODE;
+use crate::shutdown::ShutdownStep;
+use crate::status_line::StatusLine;
+use crate::title_history::TitleHistory;
+use crate::transcript::TranscriptLog;
+use crate::usage::{Budget, BudgetAction, Ledger, WRAP_UP_PROMPT};
 use crate::utils::sleep_ms;
 use crate::vterm::VTermProxy;
 use anyhow::Result;
 use crossterm::terminal;
 use std::io::Write;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::io::AsyncReadExt;
 use tokio::sync::{mpsc, watch};
 use tracing::{debug, error, info, warn};
 
 const HEARTBEAT_INTERVAL_MS: u64 = 50; // Check frequently for Enter timing and patterns
-                                       // Min gap between "user is typing" activity-file writes. Small enough that the
-                                       // badge/backoff sees typing within a fraction of a second, large enough that a
-                                       // fast keystroke burst doesn't hammer the filesystem.
+/// How long the raw log may hold back a possibly-unfinished secret.
+const REDACT_HOLD_MS: u64 = 200;
+/// How long a FIFO frame may wait for the rest of itself once the FIFO is
+/// quiet.
+const FRAME_HOLD_MS: u64 = 500;
+// Min gap between "user is typing" activity-file writes. Small enough that the
+// badge/backoff sees typing within a fraction of a second, large enough that a
+// fast keystroke burst doesn't hammer the filesystem.
 const STDIN_ACTIVITY_THROTTLE_MS: u64 = 250;
 const FORCE_READY_TIMEOUT_MS: u64 = 10000;
 const ENTER_IDLE_WAIT_MS: u64 = 50; // Wait for 50ms idle before sending Enter (reduced from 1000 due to cursor control sequences)
@@ -46,6 +73,24 @@ const RETRY_MAX_DELAY_SECS: u64 = 256; // cap per-retry backoff: 8,16,32,…,256
 /// active typing, not a real excuse to delay recovery.
 const RETRY_MIN_IDLE_MS: u64 = 5_000;
 
+/// Quiet time required at the prompt before the next queued prompt is typed
+/// (see prompt_queue.rs) — longer than the retry debounce: a CLI can sit on a
+/// ready-looking screen for a few seconds between tool calls.
+const QUEUE_MIN_IDLE_MS: u64 = 10_000;
+/// How often the queue re-renders the screen to look for that moment.
+const QUEUE_CHECK_INTERVAL_MS: u64 = 1_000;
+
+/// Gap between two held injections written back to back — the ~200ms `ay
+/// send` leaves between a body and its Enter.
+const INJECTION_GAP_MS: u64 = 200;
+
+/// Quiet time at the prompt before a worked-on task counts as done (see
+/// completion.rs): short when a `done` marker is on screen, otherwise longer
+/// than the queue's wait, so a ready-looking pause between tool calls never
+/// reads as the end of the task.
+const COMPLETION_DONE_IDLE_MS: u64 = 2_000;
+const COMPLETION_IDLE_MS: u64 = 15_000;
+
 // Rapid-Ctrl-C panic gesture: a human escape hatch for an agent wedged on a
 // silent stall that ignores forwarded Ctrl-C. Pressing Ctrl-C this many times
 // within the window is read as "get me out": the first completed gesture
@@ -55,6 +100,13 @@ const PANIC_CTRL_C_COUNT: usize = 5;
 const PANIC_CTRL_C_WINDOW_SECS: u64 = 2;
 const RETRY_GIVE_UP_SECS: u64 = 8 * 3600; // stop after 8h (claude's usage window is ~5h)
 
+/// Jitter added on top of a parsed usage-limit reset time (see reset_time.rs),
+/// so a fleet of agents sharing one quota doesn't stampede the API the second
+/// it reopens — and a reset printed to the minute has really passed. Spread by
+/// pid: deterministic per agent, different across agents.
+const QUOTA_JITTER_MIN_SECS: u64 = 30;
+const QUOTA_JITTER_SPAN_SECS: u64 = 90;
+
 /// What the no-output watchdog should do this tick. Pure decision so it can be
 /// unit-tested without a live PTY/vterm.
 #[derive(Debug, PartialEq, Eq)]
@@ -143,6 +195,11 @@ fn retry_backoff_secs(streak: u32) -> u64 {
         .min(RETRY_MAX_DELAY_SECS)
 }
 
+/// Seconds to sleep for a usage-limit reset `secs` away — see QUOTA_JITTER_*.
+fn quota_wait_secs(secs: u64, pid: u32) -> u64 {
+    secs + QUOTA_JITTER_MIN_SECS + u64::from(pid) % QUOTA_JITTER_SPAN_SECS
+}
+
 /// Whether a scheduled auto-retry may actually fire: the agent must be sitting
 /// idle at a ready prompt (not mid-work) AND the terminal must have been quiet
 /// for at least `min_idle_ms` — see RETRY_MIN_IDLE_MS.
@@ -262,11 +319,16 @@ pub struct AgentContext {
     vterm: VTermProxy,
     start_time: Instant,
 
-    // Hash of vterm screen contents at the last typing_respond/enter match.
+    // Hash of vterm screen contents at the last action/typing_respond/enter match.
     // Suppresses re-trigger of one-shot patterns until the screen actually
     // changes (vterm contents() persists, unlike the old append-only buffer).
     last_action_screen_hash: Option<u64>,
 
+    // Multi-step action rule in flight (see action_rules.rs). While set it owns
+    // the keyboard: one-shot typing_respond/enter matching is skipped until the
+    // run finishes or times out.
+    action_run: Option<ActionRun>,
+
     // Hash of vterm screen contents at the last full pattern check. Used to
     // short-circuit check_patterns() entirely when nothing on screen changed
     // — heartbeat_check() can call check_patterns() every 50ms for no_eol
@@ -288,6 +350,10 @@ pub struct AgentContext {
     // Paraphrased reason captured when the error banner was matched — folded
     // into the typed retry message so the agent knows why it is being nudged.
     auto_retry_reason: Option<&'static str>,
+    // True while the scheduled retry is a sleep until a parsed usage-limit
+    // reset rather than a backoff step: the give-up window doesn't tick and
+    // the registry shows `waiting_for_quota`.
+    auto_retry_quota_wait: bool,
 
     // Idle screen scanner - re-checks enter patterns after prolonged idle
     last_idle_scan_at: Option<Instant>,
@@ -297,11 +363,25 @@ pub struct AgentContext {
 
     // Per-session log file writer
     log_writer: LogWriter,
+    // Redacts secrets on their way into the raw log (see redact.rs), and
+    // separately into the `--record` cast: each holds back its own tail.
+    log_redactor: StreamRedactor,
+    cast_redactor: StreamRedactor,
+    // Structured event stream next to the raw log (see events.rs).
+    events: EventLog,
+    // Conversation turns parsed from the scrollback (see transcript.rs).
+    transcript: TranscriptLog,
+    // Last (ready, working) classification of the screen, for `state` events.
+    screen_state: Option<(bool, bool)>,
+    // Subject of the permission prompt the approval policy is holding for a
+    // human (`ask`). Repaints of the same held prompt are audited once.
+    held_approval: Option<String>,
 
     // Working directory (for codex session storage)
     cwd: String,
 
-    // Stdin line accumulator for /auto command detection
+    // Stdin line accumulator for `/ay` meta-command detection (see
+    // meta_commands.rs)
     stdin_line_buffer: String,
 
     // Stop scanning for codex session ID after first one is found
@@ -316,6 +396,7 @@ pub struct AgentContext {
     // PTY passthrough and emit plain rendered text on exit instead.
     render_plain: bool,
     non_tty_renderer: crate::non_tty_renderer::NonTtyRenderer,
+    output: OutputFormat,
 
     // No-output watchdog (silent-stream-stall recovery). `stall_esc_sent_at` is
     // set when we Esc-cancel a suspected stall and cleared the moment output
@@ -343,8 +424,14 @@ pub struct AgentContext {
     // at most every TITLE_WRITE_MIN_MS).
     title_scanner: crate::title_scanner::TitleScanner,
     latest_title: Option<String>,
+    // Every phase the title went through (see title_history.rs); unthrottled,
+    // it only appends when the title's text changes.
+    title_history: TitleHistory,
     written_title: Option<String>,
     title_written_at: Option<Instant>,
+    // A desktop notification (OSC 9/777) is on the pid record as the `notify`
+    // badge; cleared once the agent is working again.
+    notification_shown: bool,
 
     // Liveness tracking. `last_stdin_at` is stamped whenever we send a
     // high-signal poke (user/FIFO input, auto-Enter, auto-retry, typing
@@ -371,6 +458,84 @@ pub struct AgentContext {
     // is first ready, then the session stays live. None for every other CLI
     // (they receive the prompt via argv instead). See run_with_fifo.
     initial_input: Option<String>,
+
+    // Time source for every timer above. Real for live sessions; a fake clock
+    // advanced event by event when replaying a recording (see replay()).
+    clock: Clock,
+    // Replaying a recording: the engine runs exactly as live, but nothing
+    // outside this struct is touched — no registry, webhook, inbox or codex
+    // session writes.
+    offline: bool,
+    // `--record`: asciicast of PTY output here, and of PTY input via the tee
+    // installed on the writer in run_with_fifo. See recording.rs.
+    recorder: Option<CastWriter>,
+
+    // Token / cost ledger fed from the on-screen usage counters, booked at
+    // the end of every turn (see usage.rs). `budget` is `--budget` with its
+    // action; it fires once per run. `budget_stop` is raised by the `stop`
+    // action and picked up by the main loop like an idle timeout.
+    ledger: Ledger,
+    budget: Option<(Budget, BudgetAction)>,
+    budget_fired: bool,
+    pub budget_stop: bool,
+
+    // Prompt queue (`-p` ×N, `--prompt-file`, `ay send --queue`): popped one
+    // entry per return to a quiet prompt, persisted across `--robust`
+    // restarts. The scanner strips queue frames out of the FIFO input;
+    // `fifo_read_at` is when the FIFO last handed over a chunk while the
+    // frame scanners may be holding part of one.
+    prompt_queue: PromptQueue,
+    queue_scanner: QueueFrameScanner,
+    fifo_read_at: Option<Instant>,
+    // Paste frames (`ay send` of a multi-line body) typed as one prompt; see
+    // paste.rs.
+    paste_scanner: PasteFrameScanner,
+    // Automated input waits here while the human is typing (see
+    // input_arbiter.rs). `input_review` is set between Ctrl+] and the key
+    // that answers it; `input_flush` writes everything held regardless.
+    arbiter: InputArbiter,
+    input_review: bool,
+    input_flush: bool,
+    injected_at: Option<Instant>,
+    queue_checked_at: Option<Instant>,
+    // `/ay` meta-commands sent over the FIFO as command frames, and where
+    // their replies go (status_line.rs; `status_tx` is the stdout writer).
+    // `detached` stops drawing to the terminal until the human's next key.
+    command_scanner: QueueFrameScanner,
+    status_line: StatusLine,
+    status_tx: Option<mpsc::Sender<String>>,
+    detached: bool,
+
+    // Task completion (see completion.rs). `seen_working` arms it once the CLI
+    // has worked on something; `completed` holds until it works again.
+    // `done_exit` is raised under `--result-mode exit` and picked up by the
+    // main loop like `budget_stop`. `result` is the last captured result.
+    result_mode: ResultMode,
+    seen_working: bool,
+    completed: bool,
+    completion_checked_at: Option<Instant>,
+    pub done_exit: bool,
+    pub result: Option<String>,
+
+    // Cross-CLI failover (see failover.rs): set when auto-retry meets a usage
+    // limit long enough to hand over; the main loop then stops the CLI like
+    // `budget_stop` and main.rs starts the successor.
+    pub failover: Option<Handover>,
+
+    // Lifecycle hooks (see hooks.rs). `needs_input_shown` edge-triggers the
+    // `needs_input` hook; `agent_id` is looked up in the registry on first use.
+    hook_runner: HookRunner,
+    needs_input_shown: bool,
+    agent_id: Option<String>,
+
+    // Git checkpoints before auto-approved actions (see checkpoint.rs): None
+    // until the first one is due, then the checkpointer if the cwd is a repo.
+    checkpointer: Option<Option<Arc<Checkpointer>>>,
+
+    // Graceful shutdown (see shutdown.rs). `stop_requested` is raised by
+    // SIGTERM; `shutdown_step` is the ladder step the CLI exited on.
+    pub stop_requested: bool,
+    pub shutdown_step: Option<&'static str>,
 }
 
 impl AgentContext {
@@ -387,6 +552,13 @@ impl AgentContext {
         render_plain: bool,
         initial_input: Option<String>,
     ) -> Self {
+        let ledger = Ledger::new(pid, &cwd, &cli);
+        let prompt_queue = PromptQueue::open(pid, &cwd);
+        let log_redactor = StreamRedactor::new(cli_config.redact.clone());
+        let cast_redactor = StreamRedactor::new(cli_config.redact.clone());
+        let title_history = TitleHistory::new(pid, &cwd);
+        let transcript =
+            TranscriptLog::new(pid, &cwd, cli_config.binary.as_deref().unwrap_or(&cli));
         Self {
             cli,
             cli_config,
@@ -411,6 +583,7 @@ impl AgentContext {
             auto_retry_started_at: None,
             auto_retry_next_at: None,
             auto_retry_reason: None,
+            auto_retry_quota_wait: false,
             stall_esc_sent_at: None,
             stall_force_restart: false,
             ctrl_c_times: Vec::new(),
@@ -418,17 +591,26 @@ impl AgentContext {
             last_idle_scan_at: None,
             stdout_drop_count: 0,
             log_writer: LogWriter::new(pid, &cwd),
+            log_redactor,
+            cast_redactor,
+            events: EventLog::new(pid, &cwd),
+            transcript,
+            screen_state: None,
+            held_approval: None,
             cwd,
             stdin_line_buffer: String::new(),
             codex_session_found: false,
             render_plain,
             non_tty_renderer: crate::non_tty_renderer::NonTtyRenderer::new(),
+            output: OutputFormat::Text,
             last_action_screen_hash: None,
+            action_run: None,
             last_checked_screen_hash: None,
             used_alt_screen: false,
             pid,
             title_scanner: crate::title_scanner::TitleScanner::new(),
             latest_title: None,
+            title_history,
             written_title: None,
             title_written_at: None,
             last_stdin_at: None,
@@ -437,9 +619,122 @@ impl AgentContext {
             watchdog_stalled: false,
             unresponsive: false,
             initial_input,
+            clock: Clock::default(),
+            offline: false,
+            recorder: None,
+            ledger,
+            budget: None,
+            budget_fired: false,
+            budget_stop: false,
+            prompt_queue,
+            queue_scanner: QueueFrameScanner::default(),
+            fifo_read_at: None,
+            paste_scanner: PasteFrameScanner::default(),
+            arbiter: InputArbiter::default(),
+            input_review: false,
+            input_flush: false,
+            injected_at: None,
+            queue_checked_at: None,
+            command_scanner: QueueFrameScanner::new(crate::fifo::COMMAND_FRAME_START),
+            status_line: StatusLine::default(),
+            status_tx: None,
+            detached: false,
+            result_mode: ResultMode::default(),
+            seen_working: false,
+            completed: false,
+            completion_checked_at: None,
+            done_exit: false,
+            result: None,
+            failover: None,
+            hook_runner: HookRunner::default(),
+            needs_input_shown: false,
+            notification_shown: false,
+            agent_id: None,
+            checkpointer: None,
+            stop_requested: false,
+            shutdown_step: None,
+        }
+    }
+
+    /// A context for replaying a recording: fake clock, no log file, no
+    /// stdout, no side effects outside the struct. Auto-yes is on so the
+    /// replay shows what the engine would type.
+    pub fn for_replay(
+        cli: String,
+        cli_config: CliConfig,
+        term_rows: u16,
+        term_cols: u16,
+        recorded_at: Option<u64>,
+    ) -> Self {
+        // An empty cwd opens no project log (see log_files::project_log_dir).
+        let mut ctx = Self::new(
+            cli,
+            cli_config,
+            false,
+            false,
+            true,
+            String::new(),
+            0,
+            term_rows,
+            term_cols,
+            true,
+            None,
+        );
+        let clock = Clock::fake(recorded_at);
+        ctx.start_time = clock.now();
+        ctx.last_output_at = clock.now();
+        ctx.idle_waiter = IdleWaiter::with_clock(clock.clone());
+        ctx.clock = clock;
+        ctx.offline = true;
+        ctx
+    }
+
+    /// Record this session's PTY traffic (`--record`). The same writer is
+    /// reused across `--robust` restarts so one file covers the whole run.
+    pub fn set_recorder(&mut self, recorder: CastWriter) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Enforce `--budget` on this run.
+    pub fn set_budget(&mut self, budget: Budget, action: BudgetAction) {
+        self.budget = Some((budget, action));
+    }
+
+    /// Plain (non-TTY) output streams settled lines mid-session instead of
+    /// only rendering the screen on exit, as text or `--output jsonl` records
+    /// (which also carry the event stream). See non_tty_renderer.rs.
+    pub fn set_plain_stream(&mut self, format: OutputFormat) {
+        self.non_tty_renderer = crate::non_tty_renderer::NonTtyRenderer::streaming(format);
+        self.output = format;
+    }
+
+    /// What happens once the task is done (`--result-mode`).
+    pub fn set_result_mode(&mut self, mode: ResultMode) {
+        self.result_mode = mode;
+    }
+
+    /// Mirror the usage booked by earlier runs (`--robust` restarts) onto a
+    /// freshly registered pid record.
+    pub fn publish_usage(&self) {
+        let total = self.ledger.total();
+        if !self.offline && !total.is_empty() {
+            crate::pid_store::PidStore::new().update_usage(self.pid, total);
+        }
+    }
+
+    /// Mirror the prompt queue's progress onto a freshly registered pid record.
+    pub fn publish_queue(&self) {
+        if !self.offline && self.prompt_queue.progress().is_some() {
+            crate::pid_store::PidStore::new().update_queue(self.pid, self.prompt_queue.progress());
         }
     }
 
+    /// The last `lines` rows of the rendered screen — after a run, what the
+    /// CLI left behind for the restart policy to class.
+    pub fn screen_tail(&self, lines: usize) -> String {
+        self.vterm.tail(lines)
+    }
+
     /// Path to the raw log file for this session (for PID store registration)
     pub fn raw_log_path(&self) -> Option<String> {
         self.log_writer
@@ -448,13 +743,28 @@ impl AgentContext {
             .map(|p| p.to_string_lossy().to_string())
     }
 
+    /// Append the conversation turns that reached the scrollback since the
+    /// last update to `<pid>.transcript.jsonl`.
+    fn update_transcript(&mut self) {
+        if !self.transcript.is_enabled() {
+            return;
+        }
+        let scrollback = self.vterm.dump_scrollback();
+        self.transcript
+            .update(&self.cli_config.redact.redact(&scrollback));
+    }
+
     /// On exit, render the full scrollback to `<pid>.log` and remove the raw
     /// byte log (which exists only for live tailing). Returns the rendered log
     /// path when it replaced the raw log, so the caller can repoint the pid
     /// index at it. No-op (returns None, keeping the raw log) when the session
     /// used the alternate screen — whose content the scrollback can't
-    /// reconstruct — or when the render is empty.
+    /// reconstruct — or when the render is empty. Brings the transcript up to
+    /// date and closes the title history first.
     pub fn finalize_log(&mut self) -> Option<String> {
+        self.flush_redactors();
+        self.update_transcript();
+        self.title_history.end();
         if self.used_alt_screen {
             return None;
         }
@@ -463,7 +773,11 @@ impl AgentContext {
         let base = raw_str.strip_suffix(".raw.log")?;
         let rendered_path = std::path::PathBuf::from(format!("{base}.log"));
 
-        let rendered = self.vterm.dump_scrollback();
+        let rendered = self
+            .cli_config
+            .redact
+            .redact(&self.vterm.dump_scrollback())
+            .into_owned();
         if rendered.trim().is_empty() {
             return None;
         }
@@ -505,7 +819,10 @@ impl AgentContext {
         idle_action: Option<&str>,
         fifo_path: Option<std::path::PathBuf>,
     ) -> Result<i32> {
-        let writer = pty.get_writer();
+        let writer = match &self.recorder {
+            Some(cast) => recording::tee_writer(pty.get_writer(), cast.clone()),
+            None => pty.get_writer(),
+        };
 
         // Shell "typed" prompt: once the shell prompt is first ready, type the
         // initial command and leave the session interactive. This is how
@@ -542,6 +859,12 @@ impl AgentContext {
         // Bounded at ~10MB (1250 × 8KB chunks) — if stdout is stuck, old output
         // is dropped. The agent's operation matters more than display completeness.
         let (stdout_tx, mut stdout_rx) = mpsc::channel::<String>(1250);
+        if self.output == OutputFormat::Jsonl {
+            self.events.mirror(Some(stdout_tx.clone()));
+        }
+        if !self.render_plain {
+            self.status_tx = Some(stdout_tx.clone());
+        }
         let stdout_handle = tokio::spawn(async move {
             use tokio::io::AsyncWriteExt;
             let mut stdout = tokio::io::stdout();
@@ -556,8 +879,10 @@ impl AgentContext {
 
         // Channel for stdin data — both the user's stdin AND the FIFO reader
         // converge here, so /auto detection, Ctrl+C handling, and PTY forwarding
-        // work the same regardless of input origin.
-        let (stdin_tx, mut stdin_rx) = mpsc::channel::<Vec<u8>>(100);
+        // work the same regardless of input origin. Each chunk is tagged
+        // `from_fifo` so the input arbiter can tell the human's keys apart.
+        let (stdin_tx, mut stdin_rx) = mpsc::channel::<(bool, Vec<u8>)>(100);
+        let human_tty = std::io::IsTerminal::is_terminal(&std::io::stdin());
 
         // Spawn stdin reader task
         let stdin_handle = tokio::spawn({
@@ -590,7 +915,7 @@ impl AgentContext {
                                     last_touch = Some(now);
                                 }
                             }
-                            if stdin_tx.send(buf[..n].to_vec()).await.is_err() {
+                            if stdin_tx.send((false, buf[..n].to_vec())).await.is_err() {
                                 break;
                             }
                         }
@@ -609,7 +934,16 @@ impl AgentContext {
         let fifo_handle: Option<std::thread::JoinHandle<()>> = if let Some(ref path) = fifo_path {
             #[cfg(any(unix, windows))]
             {
-                match crate::fifo::spawn_fifo_reader(path.clone(), stdin_tx.clone()) {
+                let (fifo_tx, mut fifo_rx) = mpsc::channel::<Vec<u8>>(100);
+                let stdin_tx = stdin_tx.clone();
+                tokio::spawn(async move {
+                    while let Some(data) = fifo_rx.recv().await {
+                        if stdin_tx.send((true, data)).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                match crate::fifo::spawn_fifo_reader(path.clone(), fifo_tx) {
                     Ok(h) => Some(h),
                     Err(e) => {
                         warn!("Failed to open FIFO for reading at {:?}: {}", path, e);
@@ -771,23 +1105,72 @@ impl AgentContext {
             })
         };
 
+        // SIGTERM (`ay stop --method=ladder`, `/api/kill`) climbs the shutdown
+        // ladder instead of leaving the CLI to die with us. So does SIGHUP (the
+        // terminal closed) — unless `/ay detach` let go of the terminal first.
+        // The flag tells SIGHUP apart.
+        let (term_tx, mut term_rx) = mpsc::channel::<bool>(1);
+        #[cfg(unix)]
+        let sigterm_handle = tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let (mut term, mut hup) = match (
+                signal(SignalKind::terminate()),
+                signal(SignalKind::hangup()),
+            ) {
+                (Ok(t), Ok(h)) => (t, h),
+                (Err(e), _) | (_, Err(e)) => {
+                    warn!("Failed to register SIGTERM/SIGHUP handlers: {}", e);
+                    return;
+                }
+            };
+            loop {
+                let hangup = tokio::select! {
+                    Some(()) = term.recv() => false,
+                    Some(()) = hup.recv() => true,
+                    else => break,
+                };
+                if term_tx.send(hangup).await.is_err() {
+                    break;
+                }
+            }
+        });
+        #[cfg(not(unix))]
+        drop(term_tx);
+
         loop {
             tokio::select! {
                 // Heartbeat for pattern detection
                 _ = heartbeat.tick() => {
                     self.heartbeat_check(&mut msg_ctx).await?;
+                    // A screen that went quiet still settles.
+                    self.stream_plain(&stdout_tx);
 
                     // No-output watchdog escalated: Esc didn't unstick a stalled
                     // stream. Exit non-zero (not fatal/abort) so a --robust parent
                     // restarts the CLI with its --continue restore args.
                     if self.stall_force_restart {
                         warn!("Stall watchdog: exiting run to trigger restart");
-                        exit_code = 75; // EX_TEMPFAIL
+                        exit_code = STALL_EXIT_CODE; // EX_TEMPFAIL
+                        break;
+                    }
+
+                    // `--budget-action stop` / `--result-mode exit`: leave like
+                    // an idle timeout.
+                    if self.budget_stop || self.done_exit || self.failover.is_some() {
+                        if self.budget_stop {
+                            info!("Budget exhausted, exiting");
+                        } else if let Some(h) = &self.failover {
+                            info!("Usage limit: handing over to {}", h.to);
+                        } else {
+                            info!("Task completed, exiting");
+                        }
+                        self.shutdown_ladder(pty, &mut msg_ctx, &stdout_tx).await?;
+                        exit_code = 0;
                         break;
                     }
 
                     // Force ready after timeout
-                    if !force_ready_sent && self.start_time.elapsed().as_millis() > FORCE_READY_TIMEOUT_MS as u128 {
+                    if !force_ready_sent && self.clock.since(self.start_time).as_millis() > FORCE_READY_TIMEOUT_MS as u128 {
                         if !self.stdin_ready.is_ready().await {
                             debug!("Force ready after timeout");
                             self.stdin_ready.ready().await;
@@ -797,6 +1180,18 @@ impl AgentContext {
                     }
                 }
 
+                Some(hangup) = term_rx.recv() => {
+                    if hangup && self.detached {
+                        info!("Terminal closed while detached; the agent keeps running");
+                        continue;
+                    }
+                    info!("{} received, shutting down", if hangup { "SIGHUP" } else { "SIGTERM" });
+                    self.stop_requested = true;
+                    self.shutdown_ladder(pty, &mut msg_ctx, &stdout_tx).await?;
+                    exit_code = if hangup { 129 } else { 143 }; // 128 + signal
+                    break;
+                }
+
                 // Terminal resize: propagate SIGWINCH to child PTY
                 Ok(()) = resize_rx.changed() => {
                     let (cols, rows) = *resize_rx.borrow_and_update();
@@ -805,11 +1200,49 @@ impl AgentContext {
                         warn!("PTY resize to {}x{} failed: {}", cols, rows, e);
                     }
                     self.vterm.resize(rows, cols);
+                    if let Some(cast) = &self.recorder {
+                        cast.resize(cols, rows);
+                    }
                     crate::pty_spawner::write_current_ptysize(std::process::id(), cols, rows);
                 }
 
                 // Stdin data
-                Some(data) = stdin_rx.recv() => {
+                Some((from_fifo, data)) = stdin_rx.recv() => {
+                    // Frames only come over the FIFO; the human's keys never
+                    // land in a frame the FIFO left unfinished.
+                    let data = if from_fifo {
+                        self.fifo_read_at = Some(self.clock.now());
+                        self.take_frames(&data).await
+                    } else {
+                        data
+                    };
+                    if data.is_empty() {
+                        continue;
+                    }
+                    if from_fifo {
+                        // `ay send` waits its turn behind the human's line.
+                        let now = self.clock.now();
+                        if self.arbiter.must_hold(now, self.input_line_empty()) {
+                            self.hold_input(Source::Fifo, Payload::Keys(data), now);
+                            continue;
+                        }
+                    } else {
+                        if self.detached {
+                            self.reattach();
+                            continue;
+                        }
+                        if self.input_review {
+                            self.answer_input_review(&data);
+                            continue;
+                        }
+                        if data == [REVIEW_KEY] && !self.arbiter.pending().is_empty() {
+                            self.show_input_review();
+                            continue;
+                        }
+                        if human_tty {
+                            self.arbiter.human_input(&data, self.clock.now());
+                        }
+                    }
                     // Check for Ctrl+C
                     if data.contains(&0x03) {
                         // Only abort if stdin not ready (still loading)
@@ -826,7 +1259,7 @@ impl AgentContext {
                             // Ctrl-C. Count every 0x03 in the chunk: a fast burst
                             // can arrive coalesced in a single read, and one
                             // timestamp per chunk would never reach the threshold.
-                            let now = Instant::now();
+                            let now = self.clock.now();
                             let presses = data.iter().filter(|&&b| b == 0x03).count().max(1);
                             for _ in 0..presses {
                                 self.ctrl_c_times.push(now);
@@ -859,6 +1292,7 @@ impl AgentContext {
                                     send_esc(&writer)?;
                                     self.panic_esc_sent_at = Some(now);
                                     self.ctrl_c_times.clear();
+                                    self.events.append(&Event::Panic { action: "esc" });
                                 }
                                 PanicAction::ForceKill => {
                                     warn!(
@@ -868,6 +1302,7 @@ impl AgentContext {
                                     // Picked up next heartbeat → exit 75 → restart.
                                     self.stall_force_restart = true;
                                     self.ctrl_c_times.clear();
+                                    self.events.append(&Event::Panic { action: "force_restart" });
                                 }
                             }
                         }
@@ -876,21 +1311,27 @@ impl AgentContext {
                     else if data.contains(&0x19) {
                         self.toggle_auto_yes().await;
                     }
-                    // Text input: accumulate line buffer for /auto detection
+                    // Text input: accumulate line buffer for `/ay` meta-commands
                     else if let Ok(text) = String::from_utf8(data.clone()) {
                         self.stdin_line_buffer.push_str(&text);
                         let has_enter = text.contains('\r') || text.contains('\n');
-                        let is_auto_cmd = has_enter && self.stdin_line_buffer.trim() == "/auto";
+                        let meta_line = (has_enter
+                            && crate::fifo::is_meta_command(&self.stdin_line_buffer))
+                        .then(|| self.stdin_line_buffer.clone());
                         if has_enter {
                             self.stdin_line_buffer.clear();
                         }
 
-                        if is_auto_cmd {
-                            self.toggle_auto_yes().await;
-                            // Send Ctrl+U to clear the typed /auto from the shell line
-                            let mut w = writer.lock().map_err(|e| anyhow::anyhow!("Lock: {}", e))?;
-                            w.write_all(b"\x15")?;
-                            w.flush()?;
+                        if let Some(line) = meta_line {
+                            // Send Ctrl+U to clear the typed command from the
+                            // CLI's line instead of submitting it
+                            {
+                                let mut w = writer.lock().map_err(|e| anyhow::anyhow!("Lock: {}", e))?;
+                                w.write_all(b"\x15")?;
+                                w.flush()?;
+                            }
+                            let from = if from_fifo { "fifo" } else { "terminal" };
+                            self.run_meta_command(&line, from).await;
                             continue;
                         }
 
@@ -938,7 +1379,7 @@ impl AgentContext {
                     if let Some(timeout) = timeout_ms {
                         let idle = self.idle_waiter.idle_time_ms();
                         // Log idle time every 2 seconds for debugging
-                        if self.start_time.elapsed().as_secs() % 2 == 0 {
+                        if self.clock.since(self.start_time).as_secs() % 2 == 0 {
                             debug!("Idle time: {}ms / {}ms timeout", idle, timeout);
                         }
                         if idle > timeout {
@@ -952,16 +1393,11 @@ impl AgentContext {
                             if !is_working {
                                 if let Some(action) = idle_action {
                                     info!("Idle timeout reached, performing idle action: {}", action);
-                                    send_text(&msg_ctx, action).await?;
-                                    send_text(&msg_ctx, "\n").await?;
-                                    self.idle_waiter.ping();
-                                    self.mark_stdin_sent();
+                                    self.inject(&msg_ctx, Source::Nudge, Payload::Prompt(action.to_string()))
+                                        .await?;
                                 } else {
                                     info!("Idle timeout reached ({}ms > {}ms), exiting", idle, timeout);
-                                    for cmd in &self.cli_config.exit_command {
-                                        send_text(&msg_ctx, cmd).await?;
-                                        send_text(&msg_ctx, "\n").await?;
-                                    }
+                                    self.shutdown_ladder(pty, &mut msg_ctx, &stdout_tx).await?;
                                     exit_code = 0;
                                     break;
                                 }
@@ -972,6 +1408,9 @@ impl AgentContext {
             }
         }
 
+        // A run that ends mid-turn (crash, stop) still books what it used.
+        self.commit_usage();
+
         // Restore terminal mode
         let _ = terminal::disable_raw_mode();
         // disable_raw_mode only ORs crossterm's own bits back — put the input
@@ -983,6 +1422,8 @@ impl AgentContext {
 
         // Cancel stdin reader and stdout writer
         stdin_handle.abort();
+        #[cfg(unix)]
+        sigterm_handle.abort();
         // FIFO reader thread will exit on its own when the channel closes
         // (we already dropped our extra sender clone). Just join briefly.
         if let Some(h) = fifo_handle {
@@ -992,15 +1433,20 @@ impl AgentContext {
             let _ = h;
         }
         // Drop sender to signal stdout writer to finish, then wait briefly
+        self.events.mirror(None);
+        self.status_tx = None;
         drop(stdout_tx);
         let _ = tokio::time::timeout(Duration::from_millis(500), stdout_handle).await;
 
-        // Plain (non-TTY) mode: nothing was forwarded during the run, so emit
-        // the final rendered screen now as clean plain text. Writing here
-        // (after the raw stdout writer has drained) keeps it from interleaving
-        // with anything else.
+        // Plain (non-TTY) mode: no raw bytes were forwarded during the run, so
+        // emit the final rendered screen now as clean plain text — or, when
+        // streaming, what of it wasn't printed yet. Writing here (after the
+        // stdout writer has drained) keeps it from interleaving with anything
+        // else.
         if self.render_plain {
-            let rendered = self.non_tty_renderer.finalize(&self.vterm);
+            let rendered = self
+                .non_tty_renderer
+                .finish(&mut self.vterm, self.clock.now());
             if !rendered.is_empty() {
                 use std::io::Write as _;
                 let mut out = std::io::stdout();
@@ -1017,6 +1463,136 @@ impl AgentContext {
         Ok(exit_code)
     }
 
+    /// Climb the shutdown ladder (see shutdown.rs) until the CLI exits, still
+    /// draining and logging its output so the session summary it prints on
+    /// the way out is kept. Records the step it exited on in `shutdown_step`.
+    async fn shutdown_ladder(
+        &mut self,
+        pty: &mut PtyContext,
+        msg_ctx: &mut MessageContext,
+        stdout_tx: &mpsc::Sender<String>,
+    ) -> Result<()> {
+        let steps = self
+            .cli_config
+            .shutdown
+            .steps(!self.cli_config.exit_command.is_empty());
+        for (step, wait) in steps {
+            if matches!(pty.try_wait(), Ok(Some(_))) {
+                break;
+            }
+            info!("Shutdown: {} (waiting {}s)", step.name(), wait.as_secs());
+            match step {
+                ShutdownStep::ExitCommand => {
+                    for cmd in self.cli_config.exit_command.clone() {
+                        send_text(msg_ctx, &cmd).await?;
+                        send_text(msg_ctx, "\n").await?;
+                    }
+                }
+                ShutdownStep::CtrlC => {
+                    send_ctrl_c(&msg_ctx.writer)?;
+                    sleep_ms(200).await;
+                    send_ctrl_c(&msg_ctx.writer)?;
+                }
+                ShutdownStep::Sigterm => {
+                    #[cfg(unix)]
+                    pty.signal_group(libc::SIGTERM);
+                }
+                ShutdownStep::Sigkill => {
+                    let _ = pty.kill();
+                }
+            }
+            self.shutdown_step = Some(step.name());
+            let started = Instant::now();
+            while started.elapsed() < wait {
+                while let Some(output) = pty.try_recv() {
+                    self.handle_output(&output, msg_ctx, stdout_tx).await?;
+                }
+                if matches!(pty.try_wait(), Ok(Some(_))) {
+                    info!("Shutdown: CLI exited after {}", step.name());
+                    return Ok(());
+                }
+                sleep_ms(50).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replay a recording through the pattern engine on this context's fake
+    /// clock (see `for_replay`). Output and resize events are applied at their
+    /// recorded times with the 50ms heartbeat interleaved, exactly as the live
+    /// loop would see them; recorded input is NOT fed back — it is the
+    /// reference the result is compared against. Returns what the engine typed,
+    /// as `"i"` events stamped with virtual time.
+    pub async fn replay(&mut self, cast: &Cast) -> Result<Vec<CastEvent>> {
+        let capture = Capture::default();
+        let mut msg_ctx = MessageContext::new(
+            capture.writer(),
+            self.idle_waiter.clone(),
+            self.stdin_ready.clone(),
+            self.next_stdout.clone(),
+        );
+        // render_plain is set, so nothing is ever sent to stdout.
+        let (stdout_tx, _stdout_rx) = mpsc::channel::<String>(1);
+        let end_ms = cast.events.last().map_or(0, |e| e.at_ms()) + recording::REPLAY_TAIL_MS;
+        let mut events = cast.events.iter().peekable();
+        let mut beat_ms = 0u64;
+        let mut typed = Vec::new();
+        loop {
+            let now_ms = match events.next_if(|e| e.at_ms() <= beat_ms) {
+                Some(event) => {
+                    self.clock.advance_to(event.at_ms());
+                    match event.kind {
+                        CastKind::Output => {
+                            self.handle_output(&event.data, &mut msg_ctx, &stdout_tx)
+                                .await?
+                        }
+                        CastKind::Resize => {
+                            if let Some((cols, rows)) = recording::parse_resize(&event.data) {
+                                self.vterm.resize(rows, cols);
+                            }
+                        }
+                        CastKind::Input => {}
+                    }
+                    event.at_ms()
+                }
+                None if beat_ms > end_ms => break,
+                None => {
+                    self.clock.advance_to(beat_ms);
+                    self.heartbeat_check(&mut msg_ctx).await?;
+                    beat_ms += HEARTBEAT_INTERVAL_MS;
+                    beat_ms - HEARTBEAT_INTERVAL_MS
+                }
+            };
+            let bytes = capture.take();
+            if !bytes.is_empty() {
+                typed.push(CastEvent {
+                    time: now_ms as f64 / 1000.0,
+                    kind: CastKind::Input,
+                    data: String::from_utf8_lossy(&bytes).into_owned(),
+                });
+            }
+            if self.stall_force_restart {
+                warn!("Replay: the stall watchdog would force a restart here");
+                break;
+            }
+        }
+        Ok(typed)
+    }
+
+    /// Plain mode: send whatever settled on screen to stdout (nothing unless
+    /// streaming; see non_tty_renderer.rs).
+    fn stream_plain(&mut self, stdout_tx: &mpsc::Sender<String>) {
+        if !self.render_plain {
+            return;
+        }
+        let text = self
+            .non_tty_renderer
+            .poll(&mut self.vterm, self.clock.now());
+        if !text.is_empty() {
+            let _ = stdout_tx.try_send(text);
+        }
+    }
+
     /// Handle PTY output
     async fn handle_output(
         &mut self,
@@ -1031,24 +1607,28 @@ impl AgentContext {
         // in is_stalled, both bias toward "alive" (under-detect rather than cry
         // wolf), which is the intended conservative behaviour. See
         // check_responsiveness.
-        self.last_output_at = Instant::now();
+        self.last_output_at = self.clock.now();
 
         // Track the child's terminal title (OSC 0/2) — the flush to the pid
         // store is throttled separately in maybe_flush_title().
         if let Some(title) = self.title_scanner.feed(output) {
+            self.title_history.record(&title);
             self.latest_title = Some(title);
         }
+        for n in self.title_scanner.take_notifications() {
+            self.on_notification(n);
+        }
         self.maybe_flush_title();
 
         // Forward raw PTY bytes to stdout only in TTY passthrough mode. In
         // plain (non-TTY) mode we suppress the raw stream and emit rendered
         // text on exit instead — see `non_tty_renderer` and the final flush
-        // at the end of `run_with_fifo`.
+        // at the end of `run_with_fifo`. Nor while detached (`/ay detach`).
         //
         // Send to background stdout writer (never blocks main loop).
         // If the channel is full (~10MB buffered), drop the output —
         // agent operation is more important than display completeness.
-        if !self.render_plain {
+        if !self.render_plain && !self.detached {
             match stdout_tx.try_send(output.to_string()) {
                 Ok(_) => {}
                 Err(mpsc::error::TrySendError::Full(_)) => {
@@ -1067,8 +1647,16 @@ impl AgentContext {
             }
         }
 
-        // Write to raw log file
-        self.log_writer.write(output);
+        // Write to raw log file, secrets redacted. A token that may continue in
+        // the next chunk is held back until it does, or until the heartbeat
+        // sees the stream go quiet.
+        let redacted = self.log_redactor.feed(output);
+        self.log_writer.write(&redacted);
+        // The recording too; a held-back tail goes out (and is timed) with
+        // the chunk that completes it.
+        if let Some(cast) = &self.recorder {
+            cast.output(&self.cast_redactor.feed(output));
+        }
 
         // Update buffers
         self.output_buffer.push_str(output);
@@ -1084,6 +1672,7 @@ impl AgentContext {
         // normal screen on exit.
         if self.render_plain {
             self.non_tty_renderer.observe(&self.vterm);
+            self.stream_plain(stdout_tx);
         }
 
         // Write back any terminal query responses (DSR, DA) to child process.
@@ -1101,7 +1690,7 @@ impl AgentContext {
         }
 
         // Extract and store codex session ID (once per session)
-        if crate::cli::is_codex_family(&self.cli) && !self.codex_session_found {
+        if crate::cli::is_codex_family(&self.cli) && !self.codex_session_found && !self.offline {
             if let Some(session_id) = codex_sessions::extract_session_id(output) {
                 codex_sessions::store_session(&self.cwd, &session_id);
                 self.codex_session_found = true;
@@ -1157,7 +1746,9 @@ impl AgentContext {
         let screen = self.vterm.contents();
         let working = self.cli_config.working.iter().any(|p| p.is_match(&screen));
         let idle_secs = self.idle_waiter.idle_time_ms() / 1000;
-        let esc_elapsed = self.stall_esc_sent_at.map(|t| t.elapsed().as_secs());
+        let esc_elapsed = self
+            .stall_esc_sent_at
+            .map(|t| self.clock.since(t).as_secs());
         // Wedge detector (see is_wedged): a frozen state that repaints no
         // `working` marker. The extra ready/needs-input screen scans only run
         // once the cheap conditions (enabled, spinner absent, long silence)
@@ -1218,7 +1809,11 @@ impl AgentContext {
                 // the watchdog needs idle to keep growing to escalate if Esc
                 // fails to recover the stream.
                 send_esc(&msg_ctx.writer)?;
-                self.stall_esc_sent_at = Some(Instant::now());
+                self.stall_esc_sent_at = Some(self.clock.now());
+                self.events.append(&Event::StallEsc {
+                    cause: if wedged { "wedge" } else { "stall" },
+                    idle_secs,
+                });
             }
             StallAction::Wait => {}
             StallAction::ForceRestart => {
@@ -1228,16 +1823,39 @@ impl AgentContext {
                     cause, STALL_ESC_GRACE_SECS
                 );
                 self.stall_force_restart = true;
+                self.events.append(&Event::StallForceRestart {
+                    cause: if wedged { "wedge" } else { "stall" },
+                });
             }
         }
         self.update_unresponsive();
         Ok(())
     }
 
+    /// Write out what the log and cast redactors held back.
+    fn flush_redactors(&mut self) {
+        if self.log_redactor.has_held() {
+            let held = self.log_redactor.flush();
+            self.log_writer.write(&held);
+        }
+        if self.cast_redactor.has_held() {
+            let held = self.cast_redactor.flush();
+            if let Some(cast) = &self.recorder {
+                cast.output(&held);
+            }
+        }
+    }
+
     async fn heartbeat_check(&mut self, msg_ctx: &mut MessageContext) -> Result<()> {
         // Terminal query responses (DSR, DA) are now handled automatically
         // by VTermProxy in handle_output() via vt100 callbacks.
 
+        // The stream went quiet: nothing is coming to complete the token the
+        // redactors held back, so let it through.
+        if self.clock.since(self.last_output_at) >= Duration::from_millis(REDACT_HOLD_MS) {
+            self.flush_redactors();
+        }
+
         // No-output watchdog: catch a silently-stalled stream the printed-error
         // auto-retry below can never see (a stall prints nothing).
         self.stall_watchdog_check(msg_ctx).await?;
@@ -1247,16 +1865,21 @@ impl AgentContext {
         // idle on an error banner producing no new output. Arming/reset happens in
         // check_patterns(); here we only fire the scheduled send.
         if let Some(next_at) = self.auto_retry_next_at {
-            let now = Instant::now();
+            let now = self.clock.now();
             // Give up after the outage window (usage limit resets ~5h; allow 8h).
-            if self
-                .auto_retry_started_at
-                .is_some_and(|s| s.elapsed().as_secs() >= RETRY_GIVE_UP_SECS)
+            // A sleep until a known reset is not an outage — it can't give up.
+            if !self.auto_retry_quota_wait
+                && self
+                    .auto_retry_started_at
+                    .is_some_and(|s| self.clock.since(s).as_secs() >= RETRY_GIVE_UP_SECS)
             {
                 warn!(
                     "Auto-retry: giving up after {}h with no recovery",
                     RETRY_GIVE_UP_SECS / 3600
                 );
+                self.events.append(&Event::AutoRetryGaveUp {
+                    attempts: self.auto_retry_streak,
+                });
                 self.auto_retry_next_at = None;
                 self.auto_retry_started_at = None;
                 self.auto_retry_streak = 0;
@@ -1270,9 +1893,17 @@ impl AgentContext {
                 let working = self.cli_config.working.iter().any(|p| p.is_match(&screen));
                 let ready = self.cli_config.ready.iter().any(|p| p.is_match(&screen));
                 let idle_ms = self.idle_waiter.idle_time_ms();
-                if !should_fire_retry(working, ready, idle_ms, RETRY_MIN_IDLE_MS) {
+                if !should_fire_retry(working, ready, idle_ms, RETRY_MIN_IDLE_MS)
+                    || self.arbiter.must_hold(now, self.input_line_empty())
+                {
                     self.auto_retry_next_at = Some(now + Duration::from_millis(500));
                 } else {
+                    if self.auto_retry_quota_wait {
+                        // The quota is back: resume with a fresh give-up window,
+                        // so a reset more than 8h out doesn't expire the streak.
+                        self.set_quota_wait(None);
+                        self.auto_retry_started_at = Some(now);
+                    }
                     self.auto_retry_streak = self.auto_retry_streak.saturating_add(1);
                     // Self-schedule the next retry with escalated backoff. Leaving
                     // this None and re-arming from check_patterns would tight-loop
@@ -1282,20 +1913,59 @@ impl AgentContext {
                     let reason = self.auto_retry_reason.unwrap_or(RETRY_REASON_FALLBACK);
                     let since = self
                         .auto_retry_started_at
-                        .map(|t| t.elapsed().as_secs())
+                        .map(|t| self.clock.since(t).as_secs())
                         .unwrap_or(0);
                     let line = build_retry_message(self.auto_retry_streak, reason, since, next);
                     warn!(
                         "Auto-retry: typing retry nudge (attempt {}, reason: {})",
                         self.auto_retry_streak, reason
                     );
-                    self.do_send_retry(msg_ctx, &line)?;
+                    self.do_send_retry(msg_ctx, &line).await?;
                     self.record_auto_retry_inbox(reason, self.auto_retry_streak, next);
+                    self.events.append(&Event::AutoRetrySent {
+                        attempt: self.auto_retry_streak,
+                        reason,
+                        next_backoff_secs: next,
+                    });
                     self.auto_retry_next_at = Some(now + Duration::from_secs(next));
                 }
             }
         }
 
+        // A frame whose end never came (or a lone ESC that looked like the
+        // start of one) goes through as plain input once the FIFO is quiet.
+        if self
+            .fifo_read_at
+            .is_some_and(|t| self.clock.since(t) >= Duration::from_millis(FRAME_HOLD_MS))
+        {
+            self.fifo_read_at = None;
+            let held = self.take_held_frames();
+            if !held.is_empty() {
+                self.inject(msg_ctx, Source::Fifo, Payload::Keys(held))
+                    .await?;
+            }
+        }
+
+        // Held automated input goes out once the human pauses.
+        self.release_input(msg_ctx).await?;
+
+        if let Some(bytes) = self.status_line.clear_due(&self.vterm, self.clock.now()) {
+            self.write_terminal(bytes);
+        }
+
+        // Output of `typeBack` hooks joins the prompt queue.
+        for text in self.hook_runner.take_output() {
+            self.enqueue_prompt(text);
+        }
+        self.drive_prompt_queue(msg_ctx).await?;
+        self.check_completion();
+
+        // Drive a running action rule: its timed `wait` / `waitFor` steps must
+        // advance even when the screen is quiet.
+        if self.action_run.is_some() {
+            self.drive_action(msg_ctx).await?;
+        }
+
         // Check patterns on heartbeat (for no-EOL CLIs)
         if self.cli_config.no_eol {
             self.check_patterns(msg_ctx).await?;
@@ -1307,11 +1977,13 @@ impl AgentContext {
             let idle_ms = self.idle_waiter.idle_time_ms();
             if idle_ms >= IDLE_SCAN_INTERVAL_MS {
                 let should_scan = match self.last_idle_scan_at {
-                    Some(last) => last.elapsed().as_millis() as u64 >= IDLE_SCAN_INTERVAL_MS,
+                    Some(last) => {
+                        self.clock.since(last).as_millis() as u64 >= IDLE_SCAN_INTERVAL_MS
+                    }
                     None => true,
                 };
                 if should_scan {
-                    self.last_idle_scan_at = Some(Instant::now());
+                    self.last_idle_scan_at = Some(self.clock.now());
                     let buffer = self.vterm.contents();
                     let buffer_hash = hash_str(&buffer);
                     // Skip if screen still equals the last handled action.
@@ -1319,26 +1991,26 @@ impl AgentContext {
                     // prompts can be re-handled after intervening output.
                     if self.last_action_screen_hash != Some(buffer_hash) {
                         self.last_action_screen_hash = None;
-                        for pattern in &self.cli_config.enter {
-                            if pattern.is_match(&buffer) {
-                                debug!("Idle scan: enter pattern matched after {}ms idle", idle_ms);
+                        if self.cli_config.enter.iter().any(|p| p.is_match(&buffer)) {
+                            debug!("Idle scan: enter pattern matched after {}ms idle", idle_ms);
+                            if self.approve_prompt(msg_ctx, &buffer).await? {
                                 self.pending_enter = true;
-                                self.pending_enter_detected_at = Some(Instant::now());
+                                self.pending_enter_detected_at = Some(self.clock.now());
                                 self.enter_sent_at = None;
                                 self.enter_retry_count = 0;
-                                self.last_action_screen_hash = Some(buffer_hash);
-                                break;
                             }
+                            self.last_action_screen_hash = Some(buffer_hash);
                         }
                     }
                 }
             }
         }
 
-        // Handle pending Enter with idle wait and retry logic
-        if self.pending_enter {
+        // Handle pending Enter with idle wait and retry logic. An Enter the
+        // arbiter is holding counts once it goes out (see enter_written).
+        if self.pending_enter && !self.arbiter.has_pending(Source::Enter) {
             let idle_time = self.idle_waiter.idle_time_ms();
-            let now = Instant::now();
+            let now = self.clock.now();
             debug!(
                 "Pending enter: idle_time={}ms, enter_sent={}",
                 idle_time,
@@ -1349,9 +2021,7 @@ impl AgentContext {
             if self.enter_sent_at.is_none() {
                 if idle_time >= ENTER_IDLE_WAIT_MS {
                     debug!("Sending Enter after {}ms idle", idle_time);
-                    self.do_send_enter(msg_ctx)?;
-                    self.enter_sent_at = Some(now);
-                    self.next_stdout.unready().await;
+                    self.do_send_enter(msg_ctx).await?;
                 }
             } else if let Some(sent_at) = self.enter_sent_at {
                 // Check if we received output after sending Enter
@@ -1371,22 +2041,14 @@ impl AgentContext {
                             "Retry 1: Sending Enter again after {}ms",
                             elapsed_since_send
                         );
-                        self.do_send_enter(msg_ctx)?;
-                        self.enter_retry_count = 1;
-                        self.enter_sent_at = Some(now);
+                        self.do_send_enter(msg_ctx).await?;
                     } else if self.enter_retry_count == 1 && elapsed_since_send >= ENTER_RETRY_2_MS
                     {
                         debug!(
                             "Retry 2: Sending Enter again after {}ms",
                             elapsed_since_send
                         );
-                        self.do_send_enter(msg_ctx)?;
-                        self.enter_retry_count = 2;
-                        // After second retry, just keep waiting
-                        self.pending_enter = false;
-                        self.pending_enter_detected_at = None;
-                        self.enter_sent_at = None;
-                        self.enter_retry_count = 0;
+                        self.do_send_enter(msg_ctx).await?;
                     }
                 }
             }
@@ -1396,34 +2058,51 @@ impl AgentContext {
     }
 
     /// Actually send the Enter key
-    fn do_send_enter(&mut self, msg_ctx: &MessageContext) -> Result<()> {
-        {
-            let mut writer = msg_ctx
-                .writer
-                .lock()
-                .map_err(|e| anyhow::anyhow!("Lock: {}", e))?;
-            writer.write_all(b"\r")?;
-            writer.flush()?;
+    async fn do_send_enter(&mut self, msg_ctx: &MessageContext) -> Result<()> {
+        self.inject(msg_ctx, Source::Enter, Payload::Keys(b"\r".to_vec()))
+            .await?;
+        if !self.arbiter.has_pending(Source::Enter) {
+            self.enter_written().await;
         }
-        self.idle_waiter.ping();
-        self.mark_stdin_sent();
         Ok(())
     }
 
-    /// Type "retry" + Enter — the auto-retry response to a recoverable API error.
-    fn do_send_retry(&mut self, msg_ctx: &MessageContext, line: &str) -> Result<()> {
-        {
-            let mut writer = msg_ctx
-                .writer
-                .lock()
-                .map_err(|e| anyhow::anyhow!("Lock: {}", e))?;
-            writer.write_all(line.as_bytes())?;
-            writer.write_all(b"\r")?;
-            writer.flush()?;
+    /// An auto-Enter reached the CLI, now or on release from a hold: record
+    /// it and move the pending-Enter ladder on — the first press waits for a
+    /// response, a retry restarts the wait, the second retry ends it.
+    async fn enter_written(&mut self) {
+        let retry = match self.enter_sent_at {
+            Some(_) => self.enter_retry_count + 1,
+            None => 0,
+        };
+        self.events.append(&Event::EnterSent { retry });
+        if !self.pending_enter {
+            return;
         }
-        self.idle_waiter.ping();
-        self.mark_stdin_sent();
-        Ok(())
+        match retry {
+            0 => {
+                self.enter_sent_at = Some(self.clock.now());
+                self.next_stdout.unready().await;
+            }
+            1 => {
+                self.enter_retry_count = 1;
+                self.enter_sent_at = Some(self.clock.now());
+            }
+            _ => {
+                // After second retry, just keep waiting
+                self.pending_enter = false;
+                self.pending_enter_detected_at = None;
+                self.enter_sent_at = None;
+                self.enter_retry_count = 0;
+            }
+        }
+    }
+
+    /// Type "retry" + Enter — the auto-retry response to a recoverable API error.
+    async fn do_send_retry(&mut self, msg_ctx: &MessageContext, line: &str) -> Result<()> {
+        let keys = format!("{}\r", line).into_bytes();
+        self.inject(msg_ctx, Source::Retry, Payload::Keys(keys))
+            .await
     }
 
     /// Best-effort structured record of the nudge into this agent's own
@@ -1433,14 +2112,14 @@ impl AgentContext {
     /// compaction is left to the TS appenders (these entries are rare —
     /// bounded by the backoff ladder and the 8h give-up window).
     fn record_auto_retry_inbox(&self, reason: &str, attempt: u32, next_backoff_secs: u64) {
+        if self.offline {
+            return;
+        }
         let dir = std::path::Path::new(&self.cwd).join(".agent-yes");
         if std::fs::create_dir_all(&dir).is_err() {
             return;
         }
-        let at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.as_millis() as u64)
-            .unwrap_or(0);
+        let at = self.clock.wall_now().timestamp_millis() as u64;
         let record = serde_json::json!({
             "at": at,
             "from": serde_json::Value::Null,
@@ -1468,7 +2147,7 @@ impl AgentContext {
     /// output) the liveness check waits for. Reset by output advancing
     /// `last_output_at` past this instant. See check_responsiveness.
     fn mark_stdin_sent(&mut self) {
-        self.last_stdin_at = Some(Instant::now());
+        self.last_stdin_at = Some(self.clock.now());
     }
 
     /// Poke-based liveness detector: the agent looks stuck when we sent a poke
@@ -1492,13 +2171,18 @@ impl AgentContext {
             return;
         }
         if let Some(at) = self.title_written_at {
-            if at.elapsed().as_millis() < TITLE_WRITE_MIN_MS as u128 {
+            if self.clock.since(at).as_millis() < TITLE_WRITE_MIN_MS as u128 {
                 return;
             }
         }
-        crate::pid_store::PidStore::new().update_title(self.pid, &latest);
+        if !self.offline {
+            crate::pid_store::PidStore::new().update_title(self.pid, &latest);
+        }
+        self.events.append(&Event::Title {
+            title: latest.clone(),
+        });
         self.written_title = Some(latest);
-        self.title_written_at = Some(Instant::now());
+        self.title_written_at = Some(self.clock.now());
     }
 
     fn check_responsiveness(&mut self) {
@@ -1509,53 +2193,904 @@ impl AgentContext {
         self.poke_unresponsive = is_stalled(
             self.last_stdin_at,
             self.last_output_at,
-            Instant::now(),
+            self.clock.now(),
             Duration::from_millis(timeout_ms),
         );
         self.update_unresponsive();
     }
 
-    /// Publish the unified `unresponsive` liveness flag — the agent is stuck when
-    /// EITHER detector trips: the post-stdin responsiveness check
-    /// (`poke_unresponsive`) or the no-output stall watchdog (`watchdog_stalled`,
-    /// the same frozen-spinner condition that drives Esc/force-restart recovery).
-    /// Edge-triggered: writes the pid_store flag + fires the webhook only on a
-    /// true change, so a stuck agent notifies once and recovery notifies once,
-    /// regardless of which detector caused the transition.
-    fn update_unresponsive(&mut self) {
-        let stuck = is_stuck(self.poke_unresponsive, self.watchdog_stalled);
-        if stuck == self.unresponsive {
+    fn enqueue_prompt(&mut self, prompt: String) {
+        self.prompt_queue.push(prompt);
+        let total = self.prompt_queue.progress().map_or(0, |p| p.total);
+        info!("Prompt queued ({} in queue)", total);
+        self.events.append(&Event::PromptQueued { total });
+        self.publish_queue();
+    }
+
+    /// Type the next queued prompt once the CLI is parked at its prompt: past
+    /// its first ready, not working, on a ready screen that has been quiet for
+    /// QUEUE_MIN_IDLE_MS. Holds off while auto-yes is paused, an auto-retry is
+    /// in flight, or the budget has fired.
+    async fn drive_prompt_queue(&mut self, msg_ctx: &mut MessageContext) -> Result<()> {
+        if !self.prompt_queue.has_pending()
+            || !self.auto_yes_enabled
+            || self.auto_retry_started_at.is_some()
+            || self.budget_fired
+            || self.arbiter.has_pending(Source::Queue)
+            || self.idle_waiter.idle_time_ms() < QUEUE_MIN_IDLE_MS
+        {
+            return Ok(());
+        }
+        let now = self.clock.now();
+        if self
+            .queue_checked_at
+            .is_some_and(|t| now.duration_since(t) < Duration::from_millis(QUEUE_CHECK_INTERVAL_MS))
+        {
+            return Ok(());
+        }
+        self.queue_checked_at = Some(now);
+        if !self.stdin_ready.is_ready().await {
+            return Ok(());
+        }
+        let screen = self.vterm.contents();
+        let working = self.cli_config.working.iter().any(|p| p.is_match(&screen));
+        let ready = self.cli_config.ready.iter().any(|p| p.is_match(&screen));
+        let idle_ms = self.idle_waiter.idle_time_ms();
+        if !should_fire_retry(working, ready, idle_ms, QUEUE_MIN_IDLE_MS) {
+            return Ok(());
+        }
+        let Some(prompt) = self.prompt_queue.pop() else {
+            return Ok(());
+        };
+        let progress = self.prompt_queue.progress();
+        let (sent, total) = progress.map_or((0, 0), |p| (p.sent, p.total));
+        info!("Prompt queue: sending {} of {}", sent, total);
+        self.checkpoint(format!("queue {}/{}", sent, total)).await;
+        self.inject(msg_ctx, Source::Queue, Payload::Prompt(prompt))
+            .await?;
+        self.events.append(&Event::QueuePromptSent { sent, total });
+        self.publish_queue();
+        Ok(())
+    }
+
+    /// Mark the task done once the agent has worked, is back at a quiet prompt
+    /// and has nothing left to do: write the last assistant message to
+    /// `<pid>.result.md` and flip the pid record to `completed`.
+    fn check_completion(&mut self) {
+        if self.result_mode == ResultMode::Off
+            || !self.seen_working
+            || self.completed
+            || self.prompt_queue.has_pending()
+            || self.auto_retry_started_at.is_some()
+            || self.idle_waiter.idle_time_ms() < COMPLETION_DONE_IDLE_MS
+        {
             return;
         }
-        self.unresponsive = stuck;
-        crate::pid_store::PidStore::new().set_unresponsive(self.pid, stuck);
-        if stuck {
-            warn!("Agent unresponsive: no PTY output while expecting it");
-            crate::webhook::notify("UNRESPONSIVE", "no output — agent looks stuck", &self.cwd);
+        let now = self.clock.now();
+        if self
+            .completion_checked_at
+            .is_some_and(|t| now.duration_since(t) < Duration::from_millis(QUEUE_CHECK_INTERVAL_MS))
+        {
+            return;
+        }
+        self.completion_checked_at = Some(now);
+        let screen = self.vterm.contents();
+        let working = self.cli_config.working.iter().any(|p| p.is_match(&screen));
+        let ready = self.cli_config.ready.iter().any(|p| p.is_match(&screen));
+        let done = self.cli_config.done.iter().any(|p| p.is_match(&screen));
+        let min_idle = if done {
+            COMPLETION_DONE_IDLE_MS
         } else {
-            info!("Agent responsive again after a stall");
-            crate::webhook::notify("RUNNING", "responsive again", &self.cwd);
+            COMPLETION_IDLE_MS
+        };
+        if !should_fire_retry(working, ready, self.idle_waiter.idle_time_ms(), min_idle) {
+            return;
+        }
+        self.completed = true;
+        let result = self
+            .cli_config
+            .result
+            .extract(&self.vterm.dump_scrollback());
+        let result_file = match (&result, result_path(self.pid, &self.cwd)) {
+            (Some(text), Some(path)) if !self.offline => {
+                match std::fs::write(&path, format!("{text}\n")) {
+                    Ok(()) => Some(path),
+                    Err(e) => {
+                        warn!("Failed to write result {:?}: {}", path, e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        info!(
+            "Task completed{}",
+            result_file
+                .as_ref()
+                .map(|p| format!(", result in {}", p.display()))
+                .unwrap_or_default()
+        );
+        self.events.append(&Event::Completed {
+            result_chars: result.as_ref().map_or(0, |t| t.chars().count()),
+        });
+        if result.is_some() {
+            self.result = result.clone();
+        }
+        self.fire_hook(HookKind::Completed, if done { "done" } else { "quiet" });
+        if !self.offline {
+            crate::pid_store::PidStore::new().set_live_status(self.pid, "completed");
+            let summary = result.as_deref().unwrap_or("").lines().next().unwrap_or("");
+            crate::webhook::notify("COMPLETED", summary, &self.cwd);
+        }
+        if self.result_mode == ResultMode::Exit {
+            self.done_exit = true;
         }
     }
 
-    async fn toggle_auto_yes(&mut self) {
-        self.auto_yes_enabled = !self.auto_yes_enabled;
-        if self.auto_yes_enabled {
-            eprintln!("\r\n[auto-yes: ON]\r");
-        } else {
-            eprintln!("\r\n[auto-yes: OFF]\r");
-            self.stdin_ready.ready().await;
+    /// The CLI raised a desktop notification: record it, badge the agent and
+    /// pass it on to the webhook and the `notification` hook.
+    fn on_notification(&mut self, n: crate::title_scanner::Notification) {
+        let redact = &self.cli_config.redact;
+        let n = crate::title_scanner::Notification {
+            title: n.title.map(|t| redact.redact(&t).into_owned()),
+            body: redact.redact(&n.body).into_owned(),
+        };
+        let text = n.text();
+        self.events.append(&Event::Notification {
+            title: n.title,
+            body: n.body,
+        });
+        if !self.offline {
+            crate::pid_store::PidStore::new().set_notification(self.pid, Some(&text));
+            crate::webhook::notify("NOTIFICATION", &text, &self.cwd);
+            self.notification_shown = true;
         }
+        self.fire_hook(HookKind::Notification, &text);
     }
 
-    /// Check patterns and respond accordingly
-    async fn check_patterns(&mut self, msg_ctx: &mut MessageContext) -> Result<()> {
-        // Use vterm rendered screen for pattern matching (correctly handles cursor movement, clearing, etc.)
-        let buffer = self.vterm.contents();
+    /// Whether the CLI's input line (`inputLine`, on the cursor's row) is
+    /// empty on screen; None for a CLI without `inputLine`.
+    fn input_line_empty(&self) -> Option<bool> {
+        if self.cli_config.input_line.is_empty() {
+            return None;
+        }
+        let (row, _) = self.vterm.cursor_position();
+        let rows = self.vterm.visible_rows();
+        let line = rows.get(usize::from(row)).map_or("", String::as_str);
+        Some(!self.cli_config.input_line.iter().any(|p| {
+            p.captures(line)
+                .and_then(|c| c.get(1))
+                .is_some_and(|m| !m.as_str().trim().is_empty())
+        }))
+    }
 
-        // Short-circuit if screen contents are byte-identical to the last
-        // check. Sticky state (is_fatal, ready, pending_enter) cannot transition
-        // without a screen change, and one-shot patterns are already suppressed
+    /// Type automated input now, or hold it while the human is at the
+    /// keyboard (see input_arbiter.rs).
+    async fn inject(
+        &mut self,
+        msg_ctx: &MessageContext,
+        source: Source,
+        payload: Payload,
+    ) -> Result<()> {
+        let now = self.clock.now();
+        if self.arbiter.must_hold(now, self.input_line_empty()) {
+            self.hold_input(source, payload, now);
+            return Ok(());
+        }
+        self.write_injection(msg_ctx, &payload).await
+    }
+
+    fn hold_input(&mut self, source: Source, payload: Payload, now: Instant) {
+        if self.arbiter.pending().is_empty() {
+            self.show_status(&format!("input held: {} — Ctrl+] to review", source.name()));
+        }
+        self.arbiter.hold(source, payload, now);
+        self.events.append(&Event::InputHeld {
+            source: source.name(),
+        });
+    }
+
+    async fn write_injection(&mut self, msg_ctx: &MessageContext, payload: &Payload) -> Result<()> {
+        match payload {
+            Payload::Keys(bytes) => send_bytes(msg_ctx, bytes).await?,
+            Payload::Prompt(text) => self.send_prompt(msg_ctx, text).await?,
+        }
+        self.idle_waiter.ping();
+        self.mark_stdin_sent();
+        self.injected_at = Some(self.clock.now());
+        Ok(())
+    }
+
+    /// Write the next held injection that is due, one per INJECTION_GAP_MS so
+    /// a body and its Enter keep their spacing.
+    async fn release_input(&mut self, msg_ctx: &MessageContext) -> Result<()> {
+        if self.arbiter.pending().is_empty() {
+            self.input_flush = false;
+            return Ok(());
+        }
+        let now = self.clock.now();
+        if self
+            .injected_at
+            .is_some_and(|t| now.duration_since(t) < Duration::from_millis(INJECTION_GAP_MS))
+        {
+            return Ok(());
+        }
+        let flushed = self.input_flush;
+        let next = if flushed {
+            self.arbiter.take_next()
+        } else {
+            self.arbiter.next_due(now, self.input_line_empty())
+        };
+        let Some(injection) = next else {
+            return Ok(());
+        };
+        // The flush covers what was held when it was asked for, not what
+        // gets held after.
+        if self.arbiter.pending().is_empty() {
+            self.input_flush = false;
+        }
+        let held_ms = now.duration_since(injection.queued_at).as_millis() as u64;
+        if injection.source.answers_screen() && !self.still_answers(&injection) {
+            info!(
+                "Held {} input dropped: its prompt is gone",
+                injection.source.name()
+            );
+            self.events.append(&Event::InputStale {
+                source: injection.source.name(),
+                held_ms,
+            });
+            return Ok(());
+        }
+        self.events.append(&Event::InputReleased {
+            source: injection.source.name(),
+            held_ms,
+            flushed,
+        });
+        self.write_injection(msg_ctx, &injection.payload).await?;
+        if injection.source == Source::Enter {
+            self.enter_written().await;
+        }
+        Ok(())
+    }
+
+    /// Whether a held answer still fits the screen: its prompt is still up
+    /// (see `Source::answers_screen`).
+    fn still_answers(&self, injection: &Injection) -> bool {
+        let screen = self.vterm.contents();
+        match (injection.source, &injection.payload) {
+            (Source::Respond, Payload::Keys(keys)) => {
+                self.cli_config
+                    .typing_respond
+                    .iter()
+                    .any(|(response, patterns)| {
+                        response.as_bytes() == keys.as_slice()
+                            && patterns.iter().any(|p| p.is_match(&screen))
+                    })
+            }
+            (Source::Enter, _) => {
+                self.cli_config.enter.iter().any(|p| p.is_match(&screen))
+                    && !self
+                        .cli_config
+                        .enter_exclude
+                        .iter()
+                        .any(|p| p.is_match(&screen))
+            }
+            (Source::Deny, _) => self
+                .cli_config
+                .approval
+                .decide(&screen, &self.cwd)
+                .is_some_and(|d| d.verdict == Verdict::Deny),
+            _ => true,
+        }
+    }
+
+    /// Ctrl+] with input held: list it and wait for the answer.
+    fn show_input_review(&mut self) {
+        let line = self.arbiter.review_line();
+        self.show_status(&line);
+        self.input_review = true;
+    }
+
+    fn answer_input_review(&mut self, key: &[u8]) {
+        self.input_review = false;
+        match ReviewAnswer::from_key(key) {
+            ReviewAnswer::Flush => {
+                self.input_flush = true;
+                self.show_status("held input: sending");
+            }
+            ReviewAnswer::Drop => {
+                let count = self.arbiter.drop_all();
+                self.events.append(&Event::InputDropped { count });
+                self.show_status(&format!("held input: dropped {}", count));
+            }
+            ReviewAnswer::Keep => self.show_status("held input: still holding"),
+        }
+    }
+
+    /// Run a `/ay` meta-command line (see meta_commands.rs), typed at the
+    /// terminal or framed over the FIFO (`from`), and show the reply.
+    async fn run_meta_command(&mut self, line: &str, from: &'static str) {
+        let Some(parsed) = crate::meta_commands::parse(line) else {
+            return;
+        };
+        info!("Meta-command from {}: {}", from, line.trim());
+        let reply = match parsed {
+            Ok(command) => self.meta_command(command).await,
+            Err(reply) => reply,
+        };
+        self.events.append(&Event::Command {
+            command: line.trim().to_string(),
+            from,
+            reply: reply.clone(),
+        });
+        self.show_status(&reply);
+    }
+
+    async fn meta_command(&mut self, command: MetaCommand) -> String {
+        match command {
+            MetaCommand::ToggleAutoYes => self.set_auto_yes(!self.auto_yes_enabled).await,
+            MetaCommand::Pause => self.set_auto_yes(false).await,
+            MetaCommand::Resume => self.set_auto_yes(true).await,
+            MetaCommand::Status => self.status_summary(),
+            MetaCommand::Queue => match self.prompt_queue.progress() {
+                Some(p) => format!("queue: {} of {} sent", p.sent, p.total),
+                None => "queue: empty".to_string(),
+            },
+            MetaCommand::QueueAdd(prompt) if prompt.is_empty() => {
+                "usage: /ay queue add <prompt>".to_string()
+            }
+            MetaCommand::QueueAdd(prompt) => {
+                self.enqueue_prompt(prompt);
+                let total = self.prompt_queue.progress().map_or(0, |p| p.total);
+                format!("queued ({} in queue)", total)
+            }
+            MetaCommand::Budget => {
+                let used = self.ledger.total().describe();
+                match self.budget {
+                    Some((budget, action)) => format!(
+                        "budget: {} of {} used, then {}{}",
+                        used,
+                        budget.describe(),
+                        action.as_str(),
+                        if self.budget_fired { " (crossed)" } else { "" }
+                    ),
+                    None => format!("budget: {} used, no --budget set", used),
+                }
+            }
+            MetaCommand::RetryNow => {
+                if self.auto_retry_next_at.is_none() {
+                    return "retry: no auto-retry pending".to_string();
+                }
+                self.auto_retry_next_at = Some(self.clock.now());
+                "retry: sending once the prompt is quiet".to_string()
+            }
+            MetaCommand::Checkpoint => {
+                if self.offline || !self.cli_config.checkpoint.enabled {
+                    return "checkpoint: checkpoints are off for this CLI".to_string();
+                }
+                match self.checkpoint("manual".to_string()).await {
+                    Some(n) => format!("checkpoint {} taken", n),
+                    None => "checkpoint: none taken (nothing changed, or see the log)".to_string(),
+                }
+            }
+            MetaCommand::Detach => self.detach(),
+            MetaCommand::Help => crate::meta_commands::HELP.to_string(),
+        }
+    }
+
+    /// One line for `/ay status`.
+    fn status_summary(&self) -> String {
+        let mut parts = vec![format!(
+            "auto-yes {}",
+            if self.auto_yes_enabled { "ON" } else { "OFF" }
+        )];
+        if let Some(p) = self.prompt_queue.progress() {
+            parts.push(format!("queue {}/{}", p.sent, p.total));
+        }
+        let held = self.arbiter.pending().len();
+        if held > 0 {
+            parts.push(format!("{} held", held));
+        }
+        if let Some(at) = self.auto_retry_next_at {
+            let secs = at.saturating_duration_since(self.clock.now()).as_secs();
+            parts.push(format!(
+                "retry #{} in {}s",
+                self.auto_retry_streak.saturating_add(1),
+                secs
+            ));
+        }
+        let total = self.ledger.total();
+        if !total.is_empty() {
+            parts.push(total.describe());
+        }
+        if self.budget_fired {
+            parts.push("budget crossed".to_string());
+        }
+        parts.join(" · ")
+    }
+
+    /// Act on the frames in a FIFO chunk and return what is left for the
+    /// CLI. Queue and command frames (`ay send --queue`, `ay send "/ay …"`)
+    /// are for us; a paste frame comes back as the paste to type.
+    async fn take_frames(&mut self, data: &[u8]) -> Vec<u8> {
+        let (data, queued) = self.queue_scanner.feed(data);
+        for prompt in queued {
+            self.enqueue_prompt(prompt);
+        }
+        let (data, commands) = self.command_scanner.feed(&data);
+        for line in commands {
+            self.run_meta_command(&line, "fifo").await;
+        }
+        self.paste_scanner.feed(
+            &data,
+            self.vterm.bracketed_paste(),
+            self.cli_config.paste_fallback,
+        )
+    }
+
+    /// What the frame scanners still hold, oldest first: what reached a
+    /// later scanner already got past the earlier ones.
+    fn take_held_frames(&mut self) -> Vec<u8> {
+        let mut held = self.paste_scanner.take_held();
+        held.extend(self.command_scanner.take_held());
+        held.extend(self.queue_scanner.take_held());
+        held
+    }
+
+    /// Show one of the wrapper's own messages: on the status line in
+    /// passthrough mode, as a stderr line in plain mode. Dropped while
+    /// detached — the log has it.
+    fn show_status(&mut self, text: &str) {
+        if self.detached {
+            return;
+        }
+        if self.render_plain {
+            eprintln!("[{}]", text);
+            return;
+        }
+        let bytes = self.status_line.show(&self.vterm, text, self.clock.now());
+        self.write_terminal(bytes);
+    }
+
+    /// Write to the terminal through the stdout writer, in order with the
+    /// child's output.
+    fn write_terminal(&self, bytes: Vec<u8>) {
+        if let Some(tx) = &self.status_tx {
+            let _ = tx.try_send(String::from_utf8_lossy(&bytes).into_owned());
+        }
+    }
+
+    /// `/ay detach`: stop drawing to this terminal and survive it closing
+    /// (SIGHUP). The agent runs on for the FIFO, `ay attach` and the web
+    /// console; the human's next key here reattaches.
+    fn detach(&mut self) -> String {
+        if self.render_plain {
+            return "detach: only a terminal session can detach".to_string();
+        }
+        self.write_terminal(
+            format!(
+                "\r\n\x1b[0m[detached: the agent keeps running, even if this terminal \
+                 closes — press any key to reattach, or `ay attach {}`]\r\n",
+                self.pid
+            )
+            .into_bytes(),
+        );
+        self.detached = true;
+        "detached".to_string()
+    }
+
+    /// Repaint the screen as the child left it and resume passthrough.
+    fn reattach(&mut self) {
+        self.detached = false;
+        self.write_terminal(self.vterm.state_formatted());
+        self.show_status("reattached");
+    }
+
+    /// Type `text` as one prompt and submit it, pasted if it spans lines.
+    async fn send_prompt(&self, msg_ctx: &MessageContext, text: &str) -> Result<()> {
+        send_prompt(
+            msg_ctx,
+            text,
+            self.vterm.bracketed_paste(),
+            self.cli_config.paste_fallback,
+        )
+        .await
+    }
+
+    /// The agent picked up work again after completing: back to `active`.
+    fn reopen_completed(&mut self) {
+        self.completed = false;
+        if !self.offline {
+            crate::pid_store::PidStore::new().set_live_status(self.pid, "active");
+        }
+    }
+
+    /// Run the `kind` hook, if one is configured, in the background.
+    fn fire_hook(&mut self, kind: HookKind, reason: &str) {
+        if self.offline {
+            return;
+        }
+        let Some(hook) = self.cli_config.hooks.get(kind).cloned() else {
+            return;
+        };
+        let payload = self.hook_payload(kind, reason);
+        self.hook_runner.spawn(&hook, &payload);
+    }
+
+    /// Run the `exit` hook, if one is configured, and wait for it (up to its
+    /// timeout). `reason` is the pid record's `exit_reason`.
+    pub fn run_exit_hook(&mut self, reason: &str) {
+        if self.offline {
+            return;
+        }
+        let Some(hook) = self.cli_config.hooks.get(HookKind::Exit).cloned() else {
+            return;
+        };
+        let payload = self.hook_payload(HookKind::Exit, reason);
+        self.hook_runner.run_blocking(&hook, &payload);
+    }
+
+    /// Arm a failover for a usage limit that resets in `secs`, when the CLI
+    /// config has a chain and one of its CLIs is installed. Auto-retry still
+    /// arms as usual: it is what runs if nothing can take over.
+    fn maybe_fail_over(&mut self, secs: u64) {
+        let failover = &self.cli_config.failover;
+        if self.offline || self.failover.is_some() || !failover.applies(secs) {
+            return;
+        }
+        match failover.next(&self.cli, crate::failover::installed) {
+            Some((to, rest)) => {
+                warn!("Usage limit resets in {}s: failing over to {}", secs, to);
+                self.events.append(&Event::Failover {
+                    to: to.clone(),
+                    wait_secs: secs,
+                });
+                self.failover = Some(Handover {
+                    to,
+                    rest,
+                    wait_secs: secs,
+                });
+            }
+            None => warn!(
+                "Usage limit resets in {}s but no failover CLI ({}) is installed; waiting it out",
+                secs,
+                failover.to.join(", ")
+            ),
+        }
+    }
+
+    /// The CLI's latest terminal title, if it set one.
+    pub fn title(&self) -> Option<&str> {
+        self.latest_title.as_deref()
+    }
+
+    /// This agent's registry id, looked up on first use.
+    pub fn agent_id(&mut self) -> Option<String> {
+        if self.agent_id.is_none() {
+            self.agent_id = crate::pid_store::PidStore::new()
+                .read_all()
+                .ok()
+                .and_then(|records| records.into_iter().find(|r| r.pid == self.pid))
+                .and_then(|r| r.agent_id);
+        }
+        self.agent_id.clone()
+    }
+
+    fn hook_payload(&mut self, kind: HookKind, reason: &str) -> HookPayload {
+        HookPayload {
+            hook: kind.name(),
+            pid: self.pid,
+            agent_id: self.agent_id(),
+            cwd: self.cwd.clone(),
+            cli: self.cli.clone(),
+            title: self.latest_title.clone(),
+            reason: reason.to_string(),
+            screen_tail: self.vterm.tail(HOOK_SCREEN_TAIL_LINES),
+        }
+    }
+
+    /// Enter (`Some(unix ms it ends)`) or leave the usage-limit quota wait,
+    /// mirroring it into the registry as `waiting_for_quota`.
+    fn set_quota_wait(&mut self, until: Option<u64>) {
+        if !self.auto_retry_quota_wait && until.is_none() {
+            return;
+        }
+        self.auto_retry_quota_wait = until.is_some();
+        if self.offline {
+            return;
+        }
+        crate::pid_store::PidStore::new().set_waiting_for_quota(self.pid, until);
+    }
+
+    /// Publish the unified `unresponsive` liveness flag — the agent is stuck when
+    /// EITHER detector trips: the post-stdin responsiveness check
+    /// (`poke_unresponsive`) or the no-output stall watchdog (`watchdog_stalled`,
+    /// the same frozen-spinner condition that drives Esc/force-restart recovery).
+    /// Edge-triggered: writes the pid_store flag + fires the webhook only on a
+    /// true change, so a stuck agent notifies once and recovery notifies once,
+    /// regardless of which detector caused the transition.
+    fn update_unresponsive(&mut self) {
+        let stuck = is_stuck(self.poke_unresponsive, self.watchdog_stalled);
+        if stuck == self.unresponsive {
+            return;
+        }
+        self.unresponsive = stuck;
+        self.events.append(&Event::Unresponsive {
+            unresponsive: stuck,
+            poke: self.poke_unresponsive,
+            watchdog: self.watchdog_stalled,
+        });
+        if stuck {
+            let reason = if self.watchdog_stalled {
+                "watchdog"
+            } else {
+                "poke"
+            };
+            self.fire_hook(HookKind::Stall, reason);
+        }
+        if self.offline {
+            return;
+        }
+        crate::pid_store::PidStore::new().set_unresponsive(self.pid, stuck);
+        if stuck {
+            warn!("Agent unresponsive: no PTY output while expecting it");
+            crate::webhook::notify("UNRESPONSIVE", "no output — agent looks stuck", &self.cwd);
+        } else {
+            info!("Agent responsive again after a stall");
+            crate::webhook::notify("RUNNING", "responsive again", &self.cwd);
+        }
+    }
+
+    /// Advance the running action rule as far as it can go right now: send
+    /// every consecutive keys/text step, then stop at the first wait that
+    /// hasn't elapsed. Abandoned when auto-yes is switched off mid-run or a
+    /// `waitFor` times out.
+    async fn drive_action(&mut self, msg_ctx: &mut MessageContext) -> Result<()> {
+        let Some(mut run) = self.action_run.take() else {
+            return Ok(());
+        };
+        if !self.auto_yes_enabled {
+            debug!("Action rule abandoned: auto-yes turned off");
+            return Ok(());
+        }
+        let screen = self.vterm.contents();
+        loop {
+            // A step held for the human: the rest waits behind it, and a
+            // wait after it counts from when its keys go out.
+            if self.arbiter.has_pending(Source::Action) {
+                run.restart_step(self.clock.now());
+                self.action_run = Some(run);
+                return Ok(());
+            }
+            let rule = &self.cli_config.actions[run.rule];
+            match run.poll(rule, &screen, self.clock.now()) {
+                ActionPoll::Send(bytes) => {
+                    debug!("Action rule '{}' sending {:?}", rule.name, bytes);
+                    self.inject(msg_ctx, Source::Action, Payload::Keys(bytes.into_bytes()))
+                        .await?;
+                }
+                ActionPoll::Pending => {
+                    self.action_run = Some(run);
+                    return Ok(());
+                }
+                ActionPoll::Done => {
+                    debug!("Action rule '{}' finished", rule.name);
+                    return Ok(());
+                }
+                ActionPoll::TimedOut => {
+                    warn!(
+                        "Action rule '{}' timed out waiting for its screen after {}ms",
+                        rule.name,
+                        rule.timeout.as_millis()
+                    );
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Run a prompt the `enter` patterns matched through the approval policy
+    /// (approval_policy.rs) and report whether Enter may be pressed — always
+    /// when no policy is configured or the screen shows no proposal the
+    /// extractors recognise. A `deny` answers "No" right here; an `ask` leaves
+    /// the prompt up for a human. Each decision is audited beside the
+    /// agent's permission stamp and emitted on the event stream.
+    async fn approve_prompt(&mut self, msg_ctx: &mut MessageContext, screen: &str) -> Result<bool> {
+        let Some(decision) = self.cli_config.approval.decide(screen, &self.cwd) else {
+            self.checkpoint("approve".to_string()).await;
+            return Ok(true);
+        };
+        let subject = &decision.proposal.subject;
+        let repeat = decision.verdict == Verdict::Ask
+            && self.held_approval.as_deref() == Some(subject.as_str());
+        if !repeat {
+            info!(
+                "Approval policy: {} {} {:?} (rule: {})",
+                decision.verdict.as_str(),
+                decision.proposal.kind.as_str(),
+                subject,
+                decision.rule
+            );
+            if !self.offline {
+                if let Some(dir) = crate::log_files::project_log_dir(&self.cwd) {
+                    crate::agent_permissions::record_approval(
+                        &dir,
+                        self.pid,
+                        &crate::agent_permissions::ApprovalRecord {
+                            at: self.clock.wall_now().timestamp_millis() as u64,
+                            verdict: decision.verdict.as_str().to_string(),
+                            rule: decision.rule.clone(),
+                            kind: decision.proposal.kind.as_str().to_string(),
+                            subject: subject.clone(),
+                        },
+                    );
+                }
+            }
+            self.events.append(&Event::Approval {
+                verdict: decision.verdict.as_str(),
+                rule: decision.rule.clone(),
+                kind: decision.proposal.kind.as_str(),
+                subject: subject.clone(),
+            });
+        }
+        self.held_approval = None;
+        match decision.verdict {
+            Verdict::Allow => {
+                let reason = format!("approve: {} {}", decision.proposal.kind.as_str(), subject);
+                self.checkpoint(reason).await;
+                Ok(true)
+            }
+            Verdict::Deny => {
+                let keys = self.cli_config.approval.deny_keys.clone().into_bytes();
+                self.inject(msg_ctx, Source::Deny, Payload::Keys(keys))
+                    .await?;
+                Ok(false)
+            }
+            Verdict::Ask => {
+                self.held_approval = Some(decision.proposal.subject);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Snapshot the working tree before an auto-approved action (see
+    /// checkpoint.rs). Never holds up the action: a checkpoint that can't be
+    /// taken is logged and skipped. Returns the checkpoint's number, None
+    /// when none was taken (disabled, failed, or nothing changed).
+    async fn checkpoint(&mut self, reason: String) -> Option<u32> {
+        if self.offline || !self.cli_config.checkpoint.enabled {
+            return None;
+        }
+        if self.checkpointer.is_none() {
+            let agent = crate::checkpoint::agent_key(self.agent_id().as_deref(), self.pid);
+            let cwd = self.cwd.clone();
+            let config = self.cli_config.checkpoint;
+            let opened =
+                tokio::task::spawn_blocking(move || Checkpointer::open(&cwd, &agent, config))
+                    .await
+                    .ok()
+                    .flatten();
+            self.checkpointer = Some(opened.map(Arc::new));
+        }
+        let Some(Some(checkpointer)) = self.checkpointer.clone() else {
+            return None;
+        };
+        match tokio::task::spawn_blocking(move || checkpointer.snapshot(&reason)).await {
+            Ok(Ok(Some(checkpoint))) => {
+                info!(
+                    "Checkpoint {} ({}): {}",
+                    checkpoint.n, checkpoint.commit, checkpoint.reason
+                );
+                self.events.append(&Event::Checkpoint {
+                    n: checkpoint.n,
+                    commit: checkpoint.commit,
+                    reason: checkpoint.reason,
+                });
+                return Some(checkpoint.n);
+            }
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) if e.is::<crate::checkpoint::TimedOut>() => {
+                // Every later snapshot would stall its approval just as long.
+                warn!(
+                    "Checkpoint skipped: {:#} (over {}s); checkpoints are off for the rest of \
+                     this run, raise checkpoint.timeoutSecs to keep them",
+                    e,
+                    self.cli_config.checkpoint.timeout.as_secs()
+                );
+                self.checkpointer = Some(None);
+            }
+            Ok(Err(e)) => warn!("Checkpoint failed: {:#}", e),
+            Err(e) => warn!("Checkpoint failed: {}", e),
+        }
+        None
+    }
+
+    /// Book the turn's usage and mirror the new total to the pid store.
+    fn commit_usage(&mut self) {
+        let Some(total) = self.ledger.commit() else {
+            return;
+        };
+        if !self.offline {
+            crate::pid_store::PidStore::new().update_usage(self.pid, total);
+        }
+    }
+
+    /// Fire the `--budget` action once the ledger total crosses the limit.
+    /// `wrap-up` waits for a quiet prompt so the message lands as a new turn
+    /// instead of being typed into a running one.
+    async fn check_budget(&mut self, msg_ctx: &mut MessageContext, at_prompt: bool) -> Result<()> {
+        let Some((budget, action)) = self.budget else {
+            return Ok(());
+        };
+        let total = self.ledger.total();
+        if self.budget_fired || !budget.is_exceeded_by(&total) {
+            return Ok(());
+        }
+        if action == BudgetAction::WrapUp && !at_prompt {
+            return Ok(());
+        }
+        self.budget_fired = true;
+        warn!(
+            "Budget of {} crossed ({} tokens used): {}",
+            budget.describe(),
+            total.tokens,
+            action.as_str()
+        );
+        self.events.append(&Event::BudgetExceeded {
+            tokens: total.tokens,
+            cost_usd: total.cost_usd,
+            budget: budget.describe(),
+            action: action.as_str(),
+        });
+        if !self.offline {
+            crate::webhook::notify(
+                "BUDGET",
+                &format!("{} used, {}", budget.describe(), action.as_str()),
+                &self.cwd,
+            );
+        }
+        match action {
+            BudgetAction::WrapUp => {
+                self.inject(
+                    msg_ctx,
+                    Source::Nudge,
+                    Payload::Prompt(WRAP_UP_PROMPT.into()),
+                )
+                .await?;
+            }
+            BudgetAction::Pause => {
+                if self.auto_yes_enabled {
+                    self.toggle_auto_yes().await;
+                }
+            }
+            BudgetAction::Stop => self.budget_stop = true,
+        }
+        Ok(())
+    }
+
+    async fn toggle_auto_yes(&mut self) {
+        let reply = self.set_auto_yes(!self.auto_yes_enabled).await;
+        self.show_status(&reply);
+    }
+
+    async fn set_auto_yes(&mut self, on: bool) -> String {
+        self.auto_yes_enabled = on;
+        if on {
+            "auto-yes: ON".to_string()
+        } else {
+            self.stdin_ready.ready().await;
+            "auto-yes: OFF".to_string()
+        }
+    }
+
+    /// Check patterns and respond accordingly
+    async fn check_patterns(&mut self, msg_ctx: &mut MessageContext) -> Result<()> {
+        // Use vterm rendered screen for pattern matching (correctly handles cursor movement, clearing, etc.)
+        let buffer = self.vterm.contents();
+
+        // Short-circuit if screen contents are byte-identical to the last
+        // check. Sticky state (is_fatal, ready, pending_enter) cannot transition
+        // without a screen change, and one-shot patterns are already suppressed
         // via last_action_screen_hash, so re-running all regexes is pure waste.
         let buffer_hash = hash_str(&buffer);
         if self.last_checked_screen_hash == Some(buffer_hash) {
@@ -1563,6 +3098,56 @@ impl AgentContext {
         }
         self.last_checked_screen_hash = Some(buffer_hash);
 
+        let ready_now = self.cli_config.ready.iter().any(|p| p.is_match(&buffer));
+        let working_now = self.cli_config.working.iter().any(|p| p.is_match(&buffer));
+        if self.screen_state != Some((ready_now, working_now)) {
+            let was_at_prompt = self.screen_state.is_some_and(|(r, w)| r && !w);
+            self.screen_state = Some((ready_now, working_now));
+            self.events.append(&Event::State {
+                ready: ready_now,
+                working: working_now,
+            });
+            if ready_now && !working_now && !was_at_prompt {
+                self.fire_hook(HookKind::Ready, "ready");
+                self.update_transcript();
+            }
+        }
+
+        // The needs-input menu is only scanned for when a hook wants it.
+        if self.cli_config.hooks.get(HookKind::NeedsInput).is_some() {
+            let needs_input_now = self
+                .cli_config
+                .needs_input
+                .iter()
+                .any(|p| p.is_match(&buffer));
+            if needs_input_now && !self.needs_input_shown {
+                self.fire_hook(HookKind::NeedsInput, "menu");
+            }
+            self.needs_input_shown = needs_input_now;
+        }
+
+        if working_now {
+            self.seen_working = true;
+            if self.notification_shown {
+                self.notification_shown = false;
+                crate::pid_store::PidStore::new().set_notification(self.pid, None);
+            }
+            if self.completed {
+                self.reopen_completed();
+            }
+        }
+
+        // Usage ledger: book the counters on this screen; the turn closes once
+        // the agent is back at a quiet prompt.
+        if self.cli_config.usage.is_enabled() {
+            let at_prompt = ready_now && !working_now;
+            self.ledger.observe(&self.cli_config.usage, &buffer);
+            if at_prompt {
+                self.commit_usage();
+            }
+            self.check_budget(msg_ctx, at_prompt).await?;
+        }
+
         // Auto-retry on recoverable API errors (overload / rate-limit / usage-
         // limit). Evaluated BEFORE fatal so these don't kill the session. We only
         // arm/reset the backoff state here; the actual (back-off-timed) "retry"
@@ -1573,7 +3158,6 @@ impl AgentContext {
                 .auto_retry
                 .iter()
                 .any(|p| p.is_match(&buffer));
-            let ready_now = self.cli_config.ready.iter().any(|p| p.is_match(&buffer));
             if err && ready_now {
                 // Remember WHY (paraphrased — see classify_retry_reason) so the
                 // typed message can explain itself. Refresh on every match: the
@@ -1583,24 +3167,62 @@ impl AgentContext {
                 // the next retry unless one is already counting down.
                 if self.auto_retry_next_at.is_none() {
                     if self.auto_retry_started_at.is_none() {
-                        self.auto_retry_started_at = Some(Instant::now());
+                        self.auto_retry_started_at = Some(self.clock.now());
                     }
-                    let delay = retry_backoff_secs(self.auto_retry_streak);
-                    self.auto_retry_next_at = Some(Instant::now() + Duration::from_secs(delay));
-                    warn!(
-                        "Auto-retry armed: recoverable error detected, retrying in {}s (attempt {})",
-                        delay,
-                        self.auto_retry_streak + 1
-                    );
+                    // A banner that names its reset time ("resets 3pm", "try
+                    // again in 2h") is slept out in one go; anything else
+                    // climbs the backoff ladder.
+                    let reset =
+                        crate::reset_time::captured_reset(&self.cli_config.auto_retry, &buffer)
+                            .and_then(|text| {
+                                crate::reset_time::secs_until_reset(text, &self.clock.wall_now())
+                            });
+                    if let Some(secs) = reset {
+                        self.maybe_fail_over(secs);
+                    }
+                    let delay = match reset {
+                        Some(secs) => quota_wait_secs(secs, self.pid),
+                        None => retry_backoff_secs(self.auto_retry_streak),
+                    };
+                    self.auto_retry_next_at = Some(self.clock.now() + Duration::from_secs(delay));
+                    let next_at = self.clock.wall_now().timestamp_millis() as u64 + delay * 1000;
+                    if reset.is_some() {
+                        warn!(
+                            "Auto-retry armed: usage limit resets in {}s, sleeping {}s (attempt {})",
+                            reset.unwrap_or(0),
+                            delay,
+                            self.auto_retry_streak + 1
+                        );
+                        self.set_quota_wait(Some(next_at));
+                    } else {
+                        warn!(
+                            "Auto-retry armed: recoverable error detected, retrying in {}s (attempt {})",
+                            delay,
+                            self.auto_retry_streak + 1
+                        );
+                    }
+                    let reason = self.auto_retry_reason.unwrap_or(RETRY_REASON_FALLBACK);
+                    self.events.append(&Event::AutoRetryArmed {
+                        attempt: self.auto_retry_streak + 1,
+                        reason,
+                        delay_secs: delay,
+                        next_at,
+                        waiting_for_quota: reset.is_some(),
+                    });
+                    self.fire_hook(HookKind::Retry, reason);
                 }
             } else if ready_now && !err && self.auto_retry_started_at.is_some() {
                 // Back at a clean prompt with no error → recovered (whether from our
                 // retry or the CLI's own). Reset the backoff ladder and cancel any
                 // pending retry.
                 debug!("Auto-retry: recovered, resetting backoff ladder");
+                self.events.append(&Event::AutoRetryRecovered {
+                    attempts: self.auto_retry_streak,
+                });
                 self.auto_retry_streak = 0;
                 self.auto_retry_started_at = None;
                 self.auto_retry_next_at = None;
+                self.set_quota_wait(None);
             }
         }
 
@@ -1643,6 +3265,12 @@ impl AgentContext {
             return Ok(());
         }
 
+        // A multi-step action in flight owns the keyboard until it finishes;
+        // this new screen may be the one its `waitFor` step is holding for.
+        if self.action_run.is_some() {
+            return self.drive_action(msg_ctx).await;
+        }
+
         // One-shot pattern suppression: if the screen hasn't changed since
         // the last typing_respond/enter match, skip those checks. Without
         // this, vterm.contents() persists matched prompts and would
@@ -1655,18 +3283,42 @@ impl AgentContext {
         // any intervening output), one-shot patterns can fire again.
         self.last_action_screen_hash = None;
 
+        // Check action rules — more specific than typing_respond/enter (a rule
+        // is usually written for a menu `enter` would also match), so first.
+        if let Some(idx) = self
+            .cli_config
+            .actions
+            .iter()
+            .position(|rule| rule.matches(&buffer))
+        {
+            debug!(
+                "Action rule '{}' matched",
+                self.cli_config.actions[idx].name
+            );
+            self.action_run = Some(ActionRun::new(idx, self.clock.now()));
+            self.output_buffer.clear();
+            self.last_action_screen_hash = Some(buffer_hash);
+            return self.drive_action(msg_ctx).await;
+        }
+
         // Check typing response patterns
-        for (response, patterns) in &self.cli_config.typing_respond {
-            for pattern in patterns {
-                if pattern.is_match(&buffer) {
-                    debug!("Typing response pattern matched, sending: {:?}", response);
-                    send_text(msg_ctx, response).await?;
-                    self.mark_stdin_sent();
-                    self.output_buffer.clear();
-                    self.last_action_screen_hash = Some(buffer_hash);
-                    return Ok(());
-                }
-            }
+        let response = self
+            .cli_config
+            .typing_respond
+            .iter()
+            .find(|(_, patterns)| patterns.iter().any(|p| p.is_match(&buffer)))
+            .map(|(response, _)| response.clone());
+        if let Some(response) = response {
+            debug!("Typing response pattern matched, sending: {:?}", response);
+            self.inject(
+                msg_ctx,
+                Source::Respond,
+                Payload::Keys(response.into_bytes()),
+            )
+            .await?;
+            self.output_buffer.clear();
+            self.last_action_screen_hash = Some(buffer_hash);
+            return Ok(());
         }
 
         // Check enter patterns
@@ -1675,24 +3327,26 @@ impl AgentContext {
             .enter_exclude
             .iter()
             .any(|pattern| pattern.is_match(&buffer));
-        for pattern in &self.cli_config.enter {
-            if pattern.is_match(&buffer) {
-                if enter_excluded {
-                    debug!("Enter pattern matched but excluded");
-                    return Ok(());
-                }
-                if !self.pending_enter {
+        if self.cli_config.enter.iter().any(|p| p.is_match(&buffer)) {
+            if enter_excluded {
+                debug!("Enter pattern matched but excluded");
+                return Ok(());
+            }
+            if !self.pending_enter {
+                if self.approve_prompt(msg_ctx, &buffer).await? {
                     debug!("Enter pattern matched, scheduling Enter after idle");
                     self.pending_enter = true;
-                    self.pending_enter_detected_at = Some(Instant::now());
+                    self.pending_enter_detected_at = Some(self.clock.now());
                     self.enter_sent_at = None;
                     self.enter_retry_count = 0;
-                    self.output_buffer.clear();
-                    self.last_action_screen_hash = Some(buffer_hash);
                 }
-                return Ok(());
+                self.output_buffer.clear();
+                self.last_action_screen_hash = Some(buffer_hash);
             }
+            return Ok(());
         }
+        // No prompt on screen: a held one was answered or withdrawn.
+        self.held_approval = None;
 
         Ok(())
     }
@@ -1745,6 +3399,289 @@ fn find_char_boundary(s: &str, at: usize) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::input_arbiter::HUMAN_TYPING_HOLD_MS;
+    use crate::status_line::STATUS_LINE_MS;
+    use regex::Regex;
+
+    #[tokio::test]
+    async fn test_replay_types_enter_on_a_recorded_prompt() {
+        let cast = recording::parse_cast(
+            "{\"version\": 2, \"width\": 80, \"height\": 24}\n\
+             [0.2, \"o\", \"Allow this edit?\\r\\n❯ 1. Yes\\r\\n  2. No\"]\n\
+             [0.9, \"i\", \"\\r\"]\n",
+        )
+        .unwrap();
+        let config = crate::config::get_cli_config("claude").unwrap();
+        let mut ctx = AgentContext::for_replay(
+            "claude".into(),
+            config,
+            cast.height,
+            cast.width,
+            cast.timestamp,
+        );
+        let typed = ctx.replay(&cast).await.unwrap();
+        // Nothing arrives after the Enter, so both retries fire too — on the
+        // fake clock, at their exact offsets from the prompt.
+        let times: Vec<u64> = typed.iter().map(|e| (e.time * 1000.0) as u64).collect();
+        assert!(typed.iter().all(|e| e.data == "\r"), "{typed:?}");
+        assert_eq!(times.len(), 3, "{typed:?}");
+        assert!((250..=300).contains(&times[0]), "{times:?}");
+        assert_eq!(times[1] - times[0], ENTER_RETRY_1_MS);
+        assert!(times[2] - times[1] >= ENTER_RETRY_2_MS);
+    }
+
+    #[tokio::test]
+    async fn test_recording_redacts_a_secret_split_across_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.cast");
+        let config = crate::config::get_cli_config("claude").unwrap();
+        let mut ctx = AgentContext::for_replay("claude".into(), config, 24, 80, None);
+        ctx.set_recorder(CastWriter::create(&path, 80, 24, "claude").unwrap());
+        let mut msg_ctx = MessageContext::new(
+            Capture::default().writer(),
+            ctx.idle_waiter.clone(),
+            ctx.stdin_ready.clone(),
+            ctx.next_stdout.clone(),
+        );
+        let (stdout_tx, _stdout_rx) = mpsc::channel::<String>(1);
+        for chunk in ["token ghp_abcdefghij", "klmnopqrstuvwxyz0123456789", " ok"] {
+            ctx.handle_output(chunk, &mut msg_ctx, &stdout_tx)
+                .await
+                .unwrap();
+        }
+        ctx.finalize_log();
+        let cast = std::fs::read_to_string(&path).unwrap();
+        assert!(!cast.contains("ghp_abc"), "{cast}");
+        assert!(cast.contains("[REDACTED:github-token]"), "{cast}");
+    }
+
+    #[tokio::test]
+    async fn test_review_keys_keep_flush_and_drop_held_input() {
+        let config = crate::config::get_cli_config("claude").unwrap();
+        let mut ctx = AgentContext::for_replay("claude".into(), config, 24, 80, None);
+        let capture = Capture::default();
+        let msg_ctx = MessageContext::new(
+            capture.writer(),
+            ctx.idle_waiter.clone(),
+            ctx.stdin_ready.clone(),
+            ctx.next_stdout.clone(),
+        );
+        ctx.arbiter.human_input(b"half a li", ctx.clock.now());
+        // Not UTF-8: relayed FIFO bytes go out exactly as they came.
+        ctx.inject(&msg_ctx, Source::Fifo, Payload::Keys(b"caf\xe9\r".to_vec()))
+            .await
+            .unwrap();
+        assert!(capture.take().is_empty());
+
+        ctx.show_input_review();
+        assert!(ctx.input_review);
+        ctx.answer_input_review(b"x");
+        assert!(!ctx.input_review);
+        ctx.release_input(&msg_ctx).await.unwrap();
+        assert!(capture.take().is_empty());
+
+        ctx.answer_input_review(b"f");
+        ctx.release_input(&msg_ctx).await.unwrap();
+        assert_eq!(capture.take(), b"caf\xe9\r");
+
+        // Held after the flush: held like anything else, until dropped.
+        ctx.clock.advance_to(INJECTION_GAP_MS);
+        ctx.inject(&msg_ctx, Source::Queue, Payload::Prompt("next".into()))
+            .await
+            .unwrap();
+        ctx.release_input(&msg_ctx).await.unwrap();
+        assert_eq!(ctx.arbiter.pending().len(), 1);
+        ctx.answer_input_review(b"d");
+        assert!(ctx.arbiter.pending().is_empty());
+        assert!(capture.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_held_enter_counts_once_it_goes_out() {
+        let config = crate::config::get_cli_config("claude").unwrap();
+        let mut ctx = AgentContext::for_replay("claude".into(), config, 24, 80, None);
+        let (tx, mut rx) = mpsc::channel::<String>(16);
+        ctx.events.mirror(Some(tx));
+        let capture = Capture::default();
+        let msg_ctx = MessageContext::new(
+            capture.writer(),
+            ctx.idle_waiter.clone(),
+            ctx.stdin_ready.clone(),
+            ctx.next_stdout.clone(),
+        );
+        ctx.vterm
+            .process(" Do you want to proceed?\r\n ❯ 1. Yes\r\n   2. No".as_bytes());
+        ctx.pending_enter = true;
+
+        // Held while the human types: nothing sent, nothing booked.
+        ctx.arbiter.human_input(b"x", ctx.clock.now());
+        ctx.do_send_enter(&msg_ctx).await.unwrap();
+        assert!(capture.take().is_empty());
+        assert!(ctx.enter_sent_at.is_none());
+        let events = drain(&mut rx);
+        assert!(!events.contains("enter_sent"), "{events}");
+
+        ctx.clock.advance_to(HUMAN_TYPING_HOLD_MS + 1);
+        ctx.release_input(&msg_ctx).await.unwrap();
+        assert_eq!(capture.take(), b"\r");
+        assert!(ctx.enter_sent_at.is_some());
+        assert_eq!(ctx.enter_retry_count, 0);
+        let events = drain(&mut rx);
+        assert!(
+            events.contains(r#""type":"enter_sent","retry":0"#),
+            "{events}"
+        );
+
+        // A retry written straight away moves the ladder on.
+        ctx.clock
+            .advance_to(HUMAN_TYPING_HOLD_MS + 1 + INJECTION_GAP_MS);
+        ctx.do_send_enter(&msg_ctx).await.unwrap();
+        assert_eq!(capture.take(), b"\r");
+        assert_eq!(ctx.enter_retry_count, 1);
+        assert!(drain(&mut rx).contains(r#""retry":1"#));
+    }
+
+    #[tokio::test]
+    async fn test_held_answer_is_dropped_once_its_prompt_is_gone() {
+        let mut config = crate::config::get_cli_config("claude").unwrap();
+        config.typing_respond = [(
+            "y\r".to_string(),
+            vec![Regex::new(r"Overwrite\? \(y/n\)").unwrap()],
+        )]
+        .into();
+        let mut ctx = AgentContext::for_replay("claude".into(), config, 24, 80, None);
+        let capture = Capture::default();
+        let mut msg_ctx = MessageContext::new(
+            capture.writer(),
+            ctx.idle_waiter.clone(),
+            ctx.stdin_ready.clone(),
+            ctx.next_stdout.clone(),
+        );
+        let (stdout_tx, _stdout_rx) = mpsc::channel::<String>(1);
+        let idle = HUMAN_TYPING_HOLD_MS + 1;
+
+        // Answered while the human types, and written once they stop.
+        ctx.arbiter.human_input(b"x", ctx.clock.now());
+        ctx.handle_output("Overwrite? (y/n)", &mut msg_ctx, &stdout_tx)
+            .await
+            .unwrap();
+        assert!(ctx.arbiter.has_pending(Source::Respond));
+        ctx.clock.advance_to(idle);
+        ctx.release_input(&msg_ctx).await.unwrap();
+        assert_eq!(capture.take(), b"y\r");
+
+        // Answered while the human types, but the prompt went meanwhile.
+        ctx.handle_output("\x1b[2J\x1b[Hok\r\n", &mut msg_ctx, &stdout_tx)
+            .await
+            .unwrap();
+        ctx.arbiter.human_input(b"x", ctx.clock.now());
+        ctx.handle_output("Overwrite? (y/n)", &mut msg_ctx, &stdout_tx)
+            .await
+            .unwrap();
+        assert!(ctx.arbiter.has_pending(Source::Respond));
+        ctx.handle_output("\x1b[2J\x1b[Hcancelled\r\n", &mut msg_ctx, &stdout_tx)
+            .await
+            .unwrap();
+        ctx.clock.advance_to(2 * idle + INJECTION_GAP_MS);
+        ctx.release_input(&msg_ctx).await.unwrap();
+        assert!(!ctx.arbiter.has_pending(Source::Respond));
+        assert!(capture.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fifo_command_frames_run_and_never_reach_the_cli() {
+        use crate::fifo::{COMMAND_FRAME_START, QUEUE_FRAME_END};
+        let config = crate::config::get_cli_config("claude").unwrap();
+        let mut ctx = AgentContext::for_replay("claude".into(), config, 24, 80, None);
+        assert!(ctx.auto_yes_enabled);
+        let frame = format!("typed{COMMAND_FRAME_START}/ay pause{QUEUE_FRAME_END}more");
+        let (a, b) = frame.as_bytes().split_at(frame.find("pause").unwrap());
+        // A frame split across reads is held back, not leaked to the CLI.
+        assert_eq!(ctx.take_frames(a).await, b"typed");
+        assert!(ctx.auto_yes_enabled);
+        assert_eq!(ctx.take_frames(b).await, b"more");
+        assert!(!ctx.auto_yes_enabled);
+        // A line that doesn't parse gets a reply, nothing more.
+        let frame = format!("{COMMAND_FRAME_START}/ay frobnicate{QUEUE_FRAME_END}");
+        assert!(ctx.take_frames(frame.as_bytes()).await.is_empty());
+        assert!(!ctx.auto_yes_enabled);
+        // So is one split inside its start marker.
+        let frame = format!("x{COMMAND_FRAME_START}/ay resume{QUEUE_FRAME_END}");
+        let (a, b) = frame.as_bytes().split_at(5);
+        assert_eq!(ctx.take_frames(a).await, b"x");
+        assert!(ctx.take_frames(b).await.is_empty());
+        assert!(ctx.auto_yes_enabled);
+    }
+
+    /// A context drawing to a terminal, with what it writes there.
+    fn terminal_ctx() -> (AgentContext, mpsc::Receiver<String>, mpsc::Sender<String>) {
+        let config = crate::config::get_cli_config("claude").unwrap();
+        let mut ctx = AgentContext::for_replay("claude".into(), config, 4, 40, None);
+        let (tx, rx) = mpsc::channel::<String>(16);
+        ctx.render_plain = false;
+        ctx.status_tx = Some(tx.clone());
+        (ctx, rx, tx)
+    }
+
+    fn drain(rx: &mut mpsc::Receiver<String>) -> String {
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_status_line_comes_down_on_the_heartbeat() {
+        let (mut ctx, mut rx, _tx) = terminal_ctx();
+        let mut msg_ctx = MessageContext::new(
+            Capture::default().writer(),
+            ctx.idle_waiter.clone(),
+            ctx.stdin_ready.clone(),
+            ctx.next_stdout.clone(),
+        );
+        ctx.vterm.process(b"hello");
+        ctx.show_status("auto-yes: OFF");
+        assert!(drain(&mut rx).contains("auto-yes: OFF"));
+        ctx.clock.advance_to(STATUS_LINE_MS - 1);
+        ctx.heartbeat_check(&mut msg_ctx).await.unwrap();
+        assert_eq!(drain(&mut rx), "");
+        ctx.clock.advance_to(STATUS_LINE_MS);
+        ctx.heartbeat_check(&mut msg_ctx).await.unwrap();
+        // The bottom row erased and repainted from the child's screen.
+        assert!(drain(&mut rx).starts_with("\x1b[4;1H\x1b[0m\x1b[2K"));
+        ctx.clock.advance_to(2 * STATUS_LINE_MS);
+        ctx.heartbeat_check(&mut msg_ctx).await.unwrap();
+        assert_eq!(drain(&mut rx), "");
+    }
+
+    #[tokio::test]
+    async fn test_detach_goes_quiet_until_reattached() {
+        let (mut ctx, mut rx, stdout_tx) = terminal_ctx();
+        let mut msg_ctx = MessageContext::new(
+            Capture::default().writer(),
+            ctx.idle_waiter.clone(),
+            ctx.stdin_ready.clone(),
+            ctx.next_stdout.clone(),
+        );
+        ctx.run_meta_command("/ay detach", "fifo").await;
+        assert!(ctx.detached);
+        assert!(drain(&mut rx).contains("press any key to reattach"));
+
+        // Nothing reaches the terminal: not the child, not our messages.
+        ctx.handle_output("working on it", &mut msg_ctx, &stdout_tx)
+            .await
+            .unwrap();
+        ctx.show_status("input held: fifo");
+        assert_eq!(drain(&mut rx), "");
+
+        ctx.reattach();
+        assert!(!ctx.detached);
+        let repaint = drain(&mut rx);
+        assert!(repaint.contains("working on it"), "{repaint:?}");
+        assert!(repaint.contains("reattached"), "{repaint:?}");
+
+        // Plain output has no terminal to detach from.
+        ctx.render_plain = true;
+        ctx.run_meta_command("/ay detach", "fifo").await;
+        assert!(!ctx.detached);
+    }
 
     #[test]
     fn test_is_wedged_trips_only_in_the_no_marker_state() {
@@ -1763,6 +3700,19 @@ mod tests {
         assert!(!is_wedged(1800, false, false, true, 9999));
     }
 
+    #[test]
+    fn test_quota_wait_secs_adds_bounded_per_pid_jitter() {
+        assert_eq!(quota_wait_secs(3600, 0), 3600 + QUOTA_JITTER_MIN_SECS);
+        for pid in [1, 89, 90, 4242, u32::MAX] {
+            let jitter = quota_wait_secs(3600, pid) - 3600;
+            assert!(
+                (QUOTA_JITTER_MIN_SECS..QUOTA_JITTER_MIN_SECS + QUOTA_JITTER_SPAN_SECS)
+                    .contains(&jitter)
+            );
+        }
+        assert_ne!(quota_wait_secs(3600, 1), quota_wait_secs(3600, 2));
+    }
+
     #[test]
     fn test_retry_backoff_secs_doubles_then_caps() {
         // 8, 16, 32, 64, 128, 256 …
@@ -0,0 +1,293 @@
+//! Structured per-agent event stream: `<cwd>/.agent-yes/<pid>.events.jsonl`.
+//!
+//! The pid index only carries the current status, title and `unresponsive`
+//! flag, and the webhook a one-line string. The engine's decisions — why it
+//! pressed Enter, when the next auto-retry fires, which detector tripped the
+//! stall recovery — are appended here as one JSON object per line, next to the
+//! raw log, so dashboards and post-mortems can reconstruct the "why". `ayrs`
+//! serves the file as `/api/events/<kw>?since=<offset>`.
+//!
+//! Every line is `{"at": <unix ms>, "type": "<snake_case>", ...fields}`. The
+//! file is append-only and shared by every `--robust` restart of the same
+//! wrapper pid; writes are best-effort like `LogWriter`.
+
+use crate::log_files::project_log_dir;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The rendered screen's ready / working classification changed.
+    State {
+        ready: bool,
+        working: bool,
+    },
+    /// Auto-Enter pressed on a matched prompt; `retry` 0 is the first press.
+    EnterSent {
+        retry: u8,
+    },
+    /// A recoverable error banner scheduled the next retry nudge.
+    AutoRetryArmed {
+        attempt: u32,
+        reason: &'static str,
+        delay_secs: u64,
+        /// Wall-clock unix ms the nudge is due (it waits for a quiet prompt).
+        next_at: u64,
+        /// The delay is a sleep until a parsed usage-limit reset, not backoff.
+        waiting_for_quota: bool,
+    },
+    AutoRetrySent {
+        attempt: u32,
+        reason: &'static str,
+        next_backoff_secs: u64,
+    },
+    /// Back at a clean prompt: the streak ended after `attempts` nudges.
+    AutoRetryRecovered {
+        attempts: u32,
+    },
+    AutoRetryGaveUp {
+        attempts: u32,
+    },
+    /// The no-output watchdog sent Esc. `cause` is `stall` (frozen spinner) or
+    /// `wedge` (no prompt, no spinner, no menu).
+    StallEsc {
+        cause: &'static str,
+        idle_secs: u64,
+    },
+    StallForceRestart {
+        cause: &'static str,
+    },
+    /// Rapid-Ctrl-C gesture: `esc` or `force_restart`.
+    Panic {
+        action: &'static str,
+    },
+    Title {
+        title: String,
+    },
+    /// The CLI raised a desktop notification (OSC 9 / OSC 777).
+    Notification {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        body: String,
+    },
+    /// The approval policy decided a permission prompt (see approval_policy.rs).
+    Approval {
+        verdict: &'static str,
+        rule: String,
+        kind: &'static str,
+        subject: String,
+    },
+    /// The working tree was snapshotted before an auto-approved action (see
+    /// checkpoint.rs).
+    Checkpoint {
+        n: u32,
+        commit: String,
+        reason: String,
+    },
+    /// A usage limit resets in `wait_secs`; the task is being handed to `to`
+    /// (see failover.rs).
+    Failover {
+        to: String,
+        wait_secs: u64,
+    },
+    /// The `--budget` limit was crossed; `action` is what was done about it.
+    BudgetExceeded {
+        tokens: u64,
+        cost_usd: f64,
+        budget: String,
+        action: &'static str,
+    },
+    /// A prompt was appended to the prompt queue (`ay send --queue`).
+    PromptQueued {
+        total: usize,
+    },
+    /// Automated input was held back while the human was typing (see
+    /// input_arbiter.rs).
+    InputHeld {
+        source: &'static str,
+    },
+    /// Held input was written: the human paused with an empty line, or they
+    /// flushed it (`flushed`).
+    InputReleased {
+        source: &'static str,
+        held_ms: u64,
+        flushed: bool,
+    },
+    /// The human dropped the held input.
+    InputDropped {
+        count: usize,
+    },
+    /// A held answer to an on-screen prompt was dropped on release: the
+    /// prompt had gone.
+    InputStale {
+        source: &'static str,
+        held_ms: u64,
+    },
+    /// A `/ay` meta-command ran, typed at the terminal or sent over the FIFO
+    /// (`from`). See meta_commands.rs.
+    Command {
+        command: String,
+        from: &'static str,
+        reply: String,
+    },
+    /// The next queued prompt was typed: number `sent` of `total`.
+    QueuePromptSent {
+        sent: usize,
+        total: usize,
+    },
+    /// The task is done: the agent is back at a quiet prompt after working
+    /// and nothing is queued. `result_chars` is the captured result's length
+    /// (0 when none was found).
+    Completed {
+        result_chars: usize,
+    },
+    /// The unified stuck flag flipped; the two detector sub-states say why.
+    Unresponsive {
+        unresponsive: bool,
+        poke: bool,
+        watchdog: bool,
+    },
+}
+
+#[derive(Serialize)]
+struct Line<'a, T> {
+    at: u64,
+    #[serde(flatten)]
+    event: &'a T,
+}
+
+/// `event` as one `{"at": <unix ms>, "type": ...}` line, newline included.
+pub fn json_line<T: Serialize>(event: &T) -> Option<String> {
+    let at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let json = serde_json::to_string(&Line { at, event }).ok()?;
+    Some(format!("{}\n", json))
+}
+
+/// Appends events for one agent. Disabled (every append a no-op) when there is
+/// no project log dir — an empty cwd, as in `--replay`. Under `--output jsonl`
+/// every line is also mirrored to stdout (see jsonl_output.rs).
+pub struct EventLog {
+    file: Option<fs::File>,
+    pub path: Option<PathBuf>,
+    stdout: Option<mpsc::Sender<String>>,
+}
+
+impl EventLog {
+    pub fn new(pid: u32, cwd: &str) -> Self {
+        let Some(dir) = project_log_dir(cwd) else {
+            return Self::disabled();
+        };
+        let path = dir.join(format!("{}.events.jsonl", pid));
+        let _ = fs::create_dir_all(&dir);
+        match fs::OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(f) => Self {
+                file: Some(f),
+                path: Some(path),
+                stdout: None,
+            },
+            Err(e) => {
+                warn!("Failed to open event log {:?}: {}", path, e);
+                Self::disabled()
+            }
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            file: None,
+            path: None,
+            stdout: None,
+        }
+    }
+
+    /// Mirror every line to the stdout writer (None stops it, so the writer
+    /// can finish).
+    pub fn mirror(&mut self, stdout: Option<mpsc::Sender<String>>) {
+        self.stdout = stdout;
+    }
+
+    pub fn append(&mut self, event: &Event) {
+        if self.file.is_none() && self.stdout.is_none() {
+            return;
+        }
+        let Some(line) = json_line(event) else {
+            return;
+        };
+        if let Some(tx) = &self.stdout {
+            let _ = tx.try_send(line.clone());
+        }
+        let Some(f) = self.file.as_mut() else { return };
+        // One write per line so a concurrent reader never sees a torn event.
+        if let Err(e) = f.write_all(line.as_bytes()) {
+            warn!("Event log {:?} stopped: {}", self.path, e);
+            self.file = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log_appends_typed_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_string_lossy().to_string();
+        let mut log = EventLog::new(42, &cwd);
+        log.append(&Event::State {
+            ready: true,
+            working: false,
+        });
+        log.append(&Event::StallEsc {
+            cause: "wedge",
+            idle_secs: 300,
+        });
+        // A restart reopens the same file and keeps appending.
+        EventLog::new(42, &cwd).append(&Event::EnterSent { retry: 1 });
+
+        let text = fs::read_to_string(dir.path().join(".agent-yes/42.events.jsonl")).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["type"], "state");
+        assert_eq!(lines[0]["ready"], true);
+        assert!(lines[0]["at"].as_u64().unwrap() > 0);
+        assert_eq!(lines[1]["type"], "stall_esc");
+        assert_eq!(lines[1]["cause"], "wedge");
+        assert_eq!(lines[2]["type"], "enter_sent");
+        assert_eq!(lines[2]["retry"], 1);
+    }
+
+    #[test]
+    fn test_event_log_mirrors_to_stdout() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut log = EventLog::disabled();
+        log.mirror(Some(tx));
+        log.append(&Event::EnterSent { retry: 0 });
+        let line = rx.try_recv().unwrap();
+        assert!(
+            line.starts_with("{\"at\":")
+                && line.ends_with("\"type\":\"enter_sent\",\"retry\":0}\n")
+        );
+        log.mirror(None);
+        log.append(&Event::EnterSent { retry: 1 });
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_event_log_disabled_without_cwd() {
+        let mut log = EventLog::new(42, "");
+        assert!(log.path.is_none());
+        log.append(&Event::Title { title: "x".into() });
+    }
+}
@@ -0,0 +1,366 @@
+//! Cross-CLI failover on a long usage-limit block (`failover:` in the CLI
+//! config, or `--failover`).
+//!
+//! Auto-retry sleeps out a usage limit that names its reset time. When that
+//! wait is at least `minWaitSecs`, an agent with a failover chain hands its
+//! task to the next CLI instead: it stops its own CLI, records
+//! `exit_reason: failover:<cli>`, and runs `agent-yes --cli <next>` in the same
+//! cwd and terminal with a handoff prompt (see [`build_handoff_msg`]): the
+//! original task, the last rendered screen and the terminal title.
+//!
+//! The successor is registered with `parent_pid` = this wrapper and
+//! `handoff_from` = this agent's id, so the fleet view shows the chain as one
+//! task. It gets the rest of the chain as its `--failover`, so
+//! `to: [codex, qwen]` on claude goes claude → codex → qwen and stops there.
+//! CLIs that aren't installed are skipped. The successor runs under this
+//! agent's flags and takes over the prompts still queued here. This wrapper
+//! waits for it, passing on the SIGTERM (`ay stop`) or SIGHUP it gets.
+//!
+//! ```yaml
+//! clis:
+//!   claude:
+//!     failover:
+//!       to: [codex, qwen]
+//!       minWaitSecs: 1800
+//! ```
+
+use crate::cli::CliArgs;
+use crate::config_loader::FailoverOverride;
+use crate::jsonl_output::OutputFormat;
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tracing::info;
+
+const DEFAULT_MIN_WAIT_SECS: u64 = 900;
+
+/// Rows of the final screen carried in the handoff.
+pub const HANDOFF_SCREEN_LINES: usize = 40;
+
+/// Env var naming the agent a successor took over from; read once at
+/// registration (pid_store.rs) and stripped from the CLI's env.
+pub const HANDOFF_FROM_ENV: &str = "AGENT_YES_HANDOFF_FROM";
+
+/// Compiled `failover:` section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failover {
+    /// CLIs to hand over to, in order.
+    pub to: Vec<String>,
+    /// Shortest usage-limit wait that triggers a handoff.
+    pub min_wait: Duration,
+}
+
+impl Default for Failover {
+    fn default() -> Self {
+        compile_failover(None)
+    }
+}
+
+pub fn compile_failover(raw: Option<FailoverOverride>) -> Failover {
+    let raw = raw.unwrap_or_default();
+    Failover {
+        to: raw.to.unwrap_or_default(),
+        min_wait: Duration::from_secs(raw.min_wait_secs.unwrap_or(DEFAULT_MIN_WAIT_SECS)),
+    }
+}
+
+impl Failover {
+    /// Whether a usage limit that resets in `wait_secs` should be handed over.
+    pub fn applies(&self, wait_secs: u64) -> bool {
+        !self.to.is_empty() && wait_secs >= self.min_wait.as_secs()
+    }
+
+    /// The first CLI of the chain that passes `usable` (other than `current`),
+    /// with the rest of the chain after it.
+    pub fn next(
+        &self,
+        current: &str,
+        usable: impl Fn(&str) -> bool,
+    ) -> Option<(String, Vec<String>)> {
+        let i = self
+            .to
+            .iter()
+            .position(|cli| cli != current && usable(cli))?;
+        Some((self.to[i].clone(), self.to[i + 1..].to_vec()))
+    }
+}
+
+/// Whether `cli` is a configured CLI whose binary is installed.
+pub fn installed(cli: &str) -> bool {
+    crate::config::get_runtime_cli_config(cli)
+        .map(|c| crate::installer::binary_exists(c.binary.as_deref().unwrap_or(cli)))
+        .unwrap_or(false)
+}
+
+/// A handoff decided by the run loop: picked up by main.rs once the CLI has
+/// been shut down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handover {
+    pub to: String,
+    /// Chain left for the successor's own `--failover`.
+    pub rest: Vec<String>,
+    /// Seconds until the usage limit resets.
+    pub wait_secs: u64,
+}
+
+/// Parse `--failover codex,qwen` (empty: no failover).
+pub fn parse_chain(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The agent being handed over from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Predecessor {
+    pub cli: String,
+    pub agent_id: String,
+    /// Human-readable wait, e.g. "2h 10m".
+    pub wait: String,
+    pub title: Option<String>,
+}
+
+/// The successor's initial prompt. Same nonce-delimited shape as
+/// `<ay-init-msg>` (init_msg.rs): the nonce is minted after the task and the
+/// screen exist, so neither can forge a boundary.
+pub fn build_handoff_msg(
+    task: Option<&str>,
+    from: &Predecessor,
+    screen: &str,
+    nonce: &str,
+) -> String {
+    let task = task.map(str::trim).filter(|t| !t.is_empty()).unwrap_or(
+        "(no initial prompt — the task is whatever the screen below shows it working on)",
+    );
+    // The title is the CLI's to set (OSC 0/2); quoted, so it stays one value.
+    let title = serde_json::to_string(from.title.as_deref().unwrap_or("")).unwrap_or_default();
+    format!(
+        "<ay-handoff {nonce} from {cli} {id} — {cli} is rate-limited for {wait}>\n\
+         <ay-task {nonce}>\n\
+         {task}\n\
+         </ay-task {nonce}>\n\
+         <ay-last-screen {nonce} title={title}>\n\
+         {screen}\n\
+         </ay-last-screen {nonce}>\n\
+         \n\
+         You are taking over the task above from {cli} (agent {id}), which hit its usage\n\
+         limit and has exited. Its work so far is in this working directory, uncommitted\n\
+         changes included. The screen above is where it stopped. Check the working tree,\n\
+         then continue the task from there — do not start over.\n\
+         </ay-handoff {nonce}>",
+        cli = from.cli,
+        id = from.agent_id,
+        wait = from.wait,
+        screen = screen.trim_end(),
+    )
+}
+
+/// The successor's argv: `prompt` first, then every prompt still `queued`
+/// here as a further `-p` (they land in its own queue), under this agent's
+/// flags. Left out: the CLI args after `--` and `--continue`, which belonged
+/// to the old CLI, and `--queue`, whose lock this wrapper still holds.
+pub fn successor_args(
+    h: &Handover,
+    args: &CliArgs,
+    prompt: &str,
+    queued: &[String],
+) -> Vec<String> {
+    // `--flag=value`, so a value starting with `-` can't pass for a flag.
+    let mut argv = vec![format!("--cli={}", h.to)];
+    for p in std::iter::once(prompt).chain(queued.iter().map(String::as_str)) {
+        argv.push(format!("--prompt={p}"));
+    }
+    let mut flag = |name: &str, value: Option<String>| {
+        argv.push(match value {
+            Some(v) => format!("{name}={v}"),
+            None => name.to_string(),
+        });
+    };
+    flag("--failover", Some(h.rest.join(",")));
+    flag("--robust", Some(args.robust.to_string()));
+    if let Some(ms) = args.timeout_ms {
+        flag("--timeout", Some(format!("{ms}ms")));
+    }
+    if let Some(action) = &args.idle_action {
+        flag("--idle-action", Some(action.clone()));
+    }
+    if !args.auto_yes {
+        flag("--auto", Some("no".into()));
+    }
+    if let Some(budget) = &args.budget {
+        flag("--budget", Some(budget.to_arg()));
+    }
+    flag("--budget-action", Some(args.budget_action.as_str().into()));
+    flag("--result-mode", Some(args.result_mode.as_str().into()));
+    let limits = &args.limits;
+    if let Some(bytes) = limits.memory_max {
+        flag("--memory-max", Some(bytes.to_string()));
+    }
+    if let Some(cpus) = limits.cpu_max {
+        flag("--cpu-max", Some(cpus.to_string()));
+    }
+    if let Some(n) = limits.pids_max {
+        flag("--pids-max", Some(n.to_string()));
+    }
+    if let Some(n) = limits.nofile {
+        flag("--nofile", Some(n.to_string()));
+    }
+    if args.output == OutputFormat::Jsonl {
+        flag("--output", Some("jsonl".into()));
+    }
+    for (on, name) in [
+        (args.skip_permissions, "--yes"),
+        (args.sandbox, "--sandbox"),
+        (args.verbose, "--verbose"),
+        (args.record, "--record"),
+        (args.install, "--install"),
+        (args.use_skills, "--use-skills"),
+        (args.force_tty, "--force-tty"),
+        (args.no_tty, "--no-tty"),
+        (args.no_stream, "--no-stream"),
+    ] {
+        if on {
+            flag(name, None);
+        }
+    }
+    argv
+}
+
+/// Run the successor in this terminal and return its exit code. A child, not
+/// an exec: it registers its own pid record, with this wrapper's pid as its
+/// parent. See [`successor_args`] for what it is started with.
+pub async fn run_successor(
+    h: &Handover,
+    args: &CliArgs,
+    prompt: &str,
+    queued: &[String],
+    from_id: &str,
+) -> Result<i32> {
+    let exe = std::env::current_exe().context("Failed to locate agent-yes for failover")?;
+    let mut cmd = tokio::process::Command::new(exe);
+    cmd.args(successor_args(h, args, prompt, queued))
+        .env("AGENT_YES_PID", std::process::id().to_string())
+        .env(HANDOFF_FROM_ENV, from_id);
+    info!("Failover: starting {} (rest of chain: {:?})", h.to, h.rest);
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to start failover agent {}", h.to))?;
+    let status = wait_forwarding_signals(&mut child)
+        .await
+        .with_context(|| format!("Failed to wait for failover agent {}", h.to))?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Wait for the successor. The run left its SIGTERM/SIGHUP handlers
+/// installed, so without passing them on an `ay stop` aimed at this wrapper
+/// would be swallowed and the successor run on.
+#[cfg(unix)]
+async fn wait_forwarding_signals(
+    child: &mut tokio::process::Child,
+) -> std::io::Result<std::process::ExitStatus> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let (Ok(mut term), Ok(mut hup)) = (
+        signal(SignalKind::terminate()),
+        signal(SignalKind::hangup()),
+    ) else {
+        return child.wait().await;
+    };
+    loop {
+        let sig = tokio::select! {
+            status = child.wait() => return status,
+            _ = term.recv() => libc::SIGTERM,
+            _ = hup.recv() => libc::SIGHUP,
+        };
+        if let Some(pid) = child.id() {
+            info!("Failover: passing signal {} on to pid {}", sig, pid);
+            unsafe { libc::kill(pid as i32, sig) };
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_forwarding_signals(
+    child: &mut tokio::process::Child,
+) -> std::io::Result<std::process::ExitStatus> {
+    child.wait().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applies_and_next() {
+        let f = compile_failover(Some(FailoverOverride {
+            to: Some(vec!["codex".into(), "qwen".into()]),
+            min_wait_secs: Some(600),
+        }));
+        assert!(!f.applies(599));
+        assert!(f.applies(600));
+        assert!(!Failover::default().applies(86_400));
+
+        let all = |_: &str| true;
+        assert_eq!(
+            f.next("claude", all),
+            Some(("codex".into(), vec!["qwen".into()]))
+        );
+        // codex isn't installed: straight to qwen, nothing after it.
+        assert_eq!(
+            f.next("claude", |c| c != "codex"),
+            Some(("qwen".into(), vec![]))
+        );
+        // Never to itself.
+        assert_eq!(f.next("codex", all), Some(("qwen".into(), vec![])));
+        assert_eq!(f.next("claude", |_| false), None);
+    }
+
+    #[test]
+    fn test_parse_chain() {
+        assert_eq!(parse_chain("codex, qwen"), ["codex", "qwen"]);
+        assert!(parse_chain("").is_empty());
+    }
+
+    #[test]
+    fn test_handoff_msg() {
+        let from = Predecessor {
+            cli: "claude".into(),
+            agent_id: "abc123def456".into(),
+            wait: "2h 10m".into(),
+            title: Some("Fix the \"flaky\" test".into()),
+        };
+        let msg = build_handoff_msg(
+            Some("fix the flaky test"),
+            &from,
+            "> ran tests\n\n",
+            "0badf00d",
+        );
+        assert!(msg.starts_with(
+            "<ay-handoff 0badf00d from claude abc123def456 — claude is rate-limited for 2h 10m>\n\
+             <ay-task 0badf00d>\nfix the flaky test\n</ay-task 0badf00d>\n\
+             <ay-last-screen 0badf00d title=\"Fix the \\\"flaky\\\" test\">\n> ran tests\n</ay-last-screen 0badf00d>\n"
+        ));
+        assert!(msg.ends_with("</ay-handoff 0badf00d>"));
+        let msg = build_handoff_msg(None, &from, "", "0badf00d");
+        assert!(msg.contains("(no initial prompt"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_waiting_on_the_successor_passes_sigterm_on() {
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", "trap 'exit 7' TERM; while :; do sleep 0.05; done"])
+            .spawn()
+            .unwrap();
+        tokio::spawn(async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            unsafe { libc::kill(libc::getpid(), libc::SIGTERM) };
+        });
+        let status =
+            tokio::time::timeout(Duration::from_secs(10), wait_forwarding_signals(&mut child))
+                .await
+                .expect("successor never got the SIGTERM")
+                .unwrap();
+        assert_eq!(status.code(), Some(7));
+    }
+}
@@ -29,6 +29,34 @@ use std::ffi::CString;
 use std::path::{Path, PathBuf};
 use tracing::warn;
 
+/// Opens a prompt-queue frame in the FIFO stream: `ESC _ ay-queue; <prompt>
+/// ESC \`. An APC string no terminal key ever produces, so the run loop can
+/// pull it out of the input and append the prompt to the agent's queue
+/// instead of typing it. See prompt_queue.rs.
+pub const QUEUE_FRAME_START: &str = "\x1b_ay-queue;";
+/// Closes a prompt-queue or paste frame (the ST string terminator).
+pub const QUEUE_FRAME_END: &str = "\x1b\\";
+/// Opens a paste frame: `ESC _ ay-paste; <text> ESC \`. `ay send` wraps a
+/// multi-line body in one so the run loop can type it as a single prompt
+/// (bracketed paste, or the CLI's fallback). See paste.rs.
+pub const PASTE_FRAME_START: &str = "\x1b_ay-paste;";
+/// Opens a meta-command frame: `ESC _ ay-cmd; /ay <command> ESC \`. The
+/// run loop executes the command itself; the CLI never sees it. See
+/// meta_commands.rs.
+pub const COMMAND_FRAME_START: &str = "\x1b_ay-cmd;";
+/// The frame kinds above that this wrapper takes out of its FIFO input,
+/// advertised as `frames` in its pid record. A sender frames a write only for
+/// a wrapper that advertises them — the TS wrapper and older Rust ones would
+/// type the frame into the CLI.
+pub const FRAMES_VERSION: u32 = 1;
+
+/// Whether `text` is one of the wrapper's own meta-commands (`/ay …`, or the
+/// older `/auto`) rather than something to type into the CLI.
+pub fn is_meta_command(text: &str) -> bool {
+    let text = text.trim();
+    text == "/auto" || text == "/ay" || text.starts_with("/ay ")
+}
+
 /// Resolve the FIFO path for a given pid. On Unix this is a filesystem path
 /// under `$AGENT_YES_HOME/fifo/` or `$HOME/.agent-yes/fifo/`; on Windows it's the Win32 named-pipe
 /// namespace string (`\\.\pipe\agent-yes-<pid>`), which `net.connect` /
@@ -0,0 +1,356 @@
+//! Lifecycle hooks (`hooks:` in the config, top-level and per CLI).
+//!
+//! The webhook only knows how to GET one formatted string. A hook runs any
+//! command on a lifecycle transition the wrapper already detects:
+//!
+//! - `ready`: the CLI is back at its prompt (ready, not working)
+//! - `needs_input`: a `needsInput` menu came up
+//! - `retry`: an auto-retry was armed (reason: overload, rate limit, ...)
+//! - `stall`: the agent went unresponsive (reason: `poke` / `watchdog`)
+//! - `completed`: the task is done (see completion.rs)
+//! - `exit`: the CLI exited (reason: the pid record's `exit_reason`)
+//! - `notification`: the CLI raised a desktop notification (reason: its text)
+//!
+//! The command runs through the platform shell with a JSON payload on stdin
+//! (`{"hook", "pid", "agent_id", "cwd", "cli", "title", "reason",
+//! "screen_tail"}`) and is killed after `timeoutSecs` (default 10). With
+//! `typeBack: true` whatever it prints on a clean exit is queued as a prompt
+//! for the agent (prompt_queue.rs), typed once it is back at a quiet prompt.
+//!
+//! ```yaml
+//! hooks:
+//!   exit: ./scripts/on-agent-exit.sh
+//! clis:
+//!   claude:
+//!     hooks:
+//!       ready: { command: ./next-task.sh, typeBack: true }
+//! ```
+//!
+//! Hooks run on a background thread and never block the PTY loop, except
+//! `exit`, which runs to completion (or its timeout) before the wrapper moves
+//! on, so a short-lived wrapper doesn't take it down with it.
+
+use crate::config_loader::HookOverride;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 10;
+
+/// Lines of the rendered screen handed to a hook as `screen_tail`.
+pub const HOOK_SCREEN_TAIL_LINES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HookKind {
+    Ready,
+    NeedsInput,
+    Retry,
+    Stall,
+    Completed,
+    Exit,
+    Notification,
+}
+
+impl HookKind {
+    pub const ALL: [HookKind; 7] = [
+        HookKind::Ready,
+        HookKind::NeedsInput,
+        HookKind::Retry,
+        HookKind::Stall,
+        HookKind::Completed,
+        HookKind::Exit,
+        HookKind::Notification,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HookKind::Ready => "ready",
+            HookKind::NeedsInput => "needs_input",
+            HookKind::Retry => "retry",
+            HookKind::Stall => "stall",
+            HookKind::Completed => "completed",
+            HookKind::Exit => "exit",
+            HookKind::Notification => "notification",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hook {
+    pub command: String,
+    pub timeout: Duration,
+    pub type_back: bool,
+}
+
+/// Compiled `hooks:` section.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    hooks: HashMap<HookKind, Hook>,
+}
+
+pub fn compile_hooks(raw: Option<HashMap<String, HookOverride>>) -> Result<Hooks> {
+    let mut hooks = HashMap::new();
+    for (name, raw) in raw.unwrap_or_default() {
+        let kind = HookKind::ALL
+            .into_iter()
+            .find(|k| k.name() == name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown hook '{}' (expected ready, needs_input, retry, stall, completed, exit or notification)",
+                    name
+                )
+            })?;
+        let (command, timeout_secs, type_back) = match raw {
+            HookOverride::Command(command) => (command, None, None),
+            HookOverride::Structured {
+                command,
+                timeout_secs,
+                type_back,
+            } => (command, timeout_secs, type_back),
+        };
+        if command.trim().is_empty() {
+            return Err(anyhow!("Hook '{}' has an empty command", name));
+        }
+        hooks.insert(
+            kind,
+            Hook {
+                command,
+                timeout: Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS)),
+                type_back: type_back.unwrap_or(false),
+            },
+        );
+    }
+    Ok(Hooks { hooks })
+}
+
+impl Hooks {
+    pub fn get(&self, kind: HookKind) -> Option<&Hook> {
+        self.hooks.get(&kind)
+    }
+}
+
+/// What a hook reads on stdin.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookPayload {
+    pub hook: &'static str,
+    pub pid: u32,
+    pub agent_id: Option<String>,
+    pub cwd: String,
+    pub cli: String,
+    pub title: Option<String>,
+    pub reason: String,
+    pub screen_tail: String,
+}
+
+/// Runs hooks for one agent and collects the output of `typeBack` hooks,
+/// which the context drains into its prompt queue.
+pub struct HookRunner {
+    tx: mpsc::Sender<String>,
+    rx: mpsc::Receiver<String>,
+}
+
+impl Default for HookRunner {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self { tx, rx }
+    }
+}
+
+impl HookRunner {
+    /// Fire-and-forget: run `hook` on a background thread.
+    pub fn spawn(&self, hook: &Hook, payload: &HookPayload) {
+        let hook = hook.clone();
+        let payload = payload.clone();
+        let tx = self.tx.clone();
+        std::thread::spawn(move || {
+            if let Some(output) = run_hook(&hook, &payload) {
+                if hook.type_back {
+                    let _ = tx.send(output);
+                }
+            }
+        });
+    }
+
+    /// Run `hook` on this thread, up to its timeout. Nothing is typed back.
+    pub fn run_blocking(&self, hook: &Hook, payload: &HookPayload) {
+        run_hook(hook, payload);
+    }
+
+    /// Output of finished `typeBack` hooks since the last call.
+    pub fn take_output(&self) -> Vec<String> {
+        self.rx.try_iter().collect()
+    }
+}
+
+/// Run one hook to completion or its timeout. Returns its trimmed stdout when
+/// it exited 0 and printed something.
+fn run_hook(hook: &Hook, payload: &HookPayload) -> Option<String> {
+    let json = serde_json::to_string(payload).ok()?;
+    debug!("Hook {}: {}", payload.hook, hook.command);
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut c = Command::new("powershell");
+        c.args(["-NoProfile", "-Command", &hook.command]);
+        c
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        use std::os::unix::process::CommandExt;
+        let mut c = Command::new("sh");
+        // Its own process group, so a timeout takes down whatever it started.
+        c.arg("-c").arg(&hook.command).process_group(0);
+        c
+    };
+    let mut child = match cmd
+        .current_dir(&payload.cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Hook {} failed to start: {}", payload.hook, e);
+            return None;
+        }
+    };
+    // A hook that ignores stdin may exit before reading it: a broken pipe here
+    // is not an error.
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(json.as_bytes());
+    }
+    // Drain stdout on its own thread so a chatty hook can't fill the pipe and
+    // hang while we wait on it.
+    let reader = child.stdout.take().map(|mut out| {
+        std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = out.read_to_string(&mut buf);
+            buf
+        })
+    });
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if started.elapsed() >= hook.timeout => {
+                warn!(
+                    "Hook {} timed out after {}s, killing it",
+                    payload.hook,
+                    hook.timeout.as_secs()
+                );
+                #[cfg(unix)]
+                unsafe {
+                    libc::kill(-(child.id() as i32), libc::SIGKILL);
+                }
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => {
+                warn!("Hook {} wait failed: {}", payload.hook, e);
+                break None;
+            }
+        }
+    };
+    match status {
+        Some(s) if s.success() => {
+            let output = reader.and_then(|r| r.join().ok()).unwrap_or_default();
+            let output = output.trim();
+            (!output.is_empty()).then(|| output.to_string())
+        }
+        Some(s) => {
+            warn!("Hook {} exited with {}", payload.hook, s);
+            None
+        }
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(cwd: &str) -> HookPayload {
+        HookPayload {
+            hook: "ready",
+            pid: 42,
+            agent_id: Some("agt_1".into()),
+            cwd: cwd.into(),
+            cli: "claude".into(),
+            title: None,
+            reason: "ready".into(),
+            screen_tail: "> ".into(),
+        }
+    }
+
+    fn hook(command: &str, timeout_secs: u64) -> Hook {
+        Hook {
+            command: command.into(),
+            timeout: Duration::from_secs(timeout_secs),
+            type_back: true,
+        }
+    }
+
+    #[test]
+    fn test_compile_hooks() {
+        let raw: HashMap<String, HookOverride> = serde_yaml::from_str(
+            r#"
+exit: ./on-exit.sh
+ready: { command: ./next.sh, timeoutSecs: 3, typeBack: true }
+"#,
+        )
+        .unwrap();
+        let hooks = compile_hooks(Some(raw)).unwrap();
+        assert_eq!(hooks.get(HookKind::Exit).unwrap().command, "./on-exit.sh");
+        assert!(!hooks.get(HookKind::Exit).unwrap().type_back);
+        let ready = hooks.get(HookKind::Ready).unwrap();
+        assert_eq!(ready.timeout, Duration::from_secs(3));
+        assert!(ready.type_back);
+        assert!(hooks.get(HookKind::Stall).is_none());
+
+        let bad: HashMap<String, HookOverride> = serde_yaml::from_str("idle: x").unwrap();
+        assert!(compile_hooks(Some(bad)).is_err());
+        let none = compile_hooks(None).unwrap();
+        assert!(HookKind::ALL.iter().all(|k| none.get(*k).is_none()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_hook_reads_payload_and_returns_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_string_lossy().to_string();
+        let out = run_hook(
+            &hook("cat > payload.json; echo next task", 5),
+            &payload(&cwd),
+        );
+        assert_eq!(out.as_deref(), Some("next task"));
+        let written: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join("payload.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(written["pid"], 42);
+        assert_eq!(written["agent_id"], "agt_1");
+        assert_eq!(written["hook"], "ready");
+
+        // A failing hook's output is never typed back.
+        assert_eq!(
+            run_hook(&hook("echo oops; exit 3", 5), &payload(&cwd)),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_hook_kills_on_timeout() {
+        let started = Instant::now();
+        let out = run_hook(&hook("sleep 30; echo late", 1), &payload("/tmp"));
+        assert_eq!(out, None);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
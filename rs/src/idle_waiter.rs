@@ -5,6 +5,7 @@
 //! retry policies) are expected to land soon, so the helpers stay public.
 #![allow(dead_code)]
 
+use crate::clock::Clock;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -15,28 +16,39 @@ use tokio::time::sleep;
 pub struct IdleWaiter {
     last_activity: Arc<AtomicU64>,
     start_time: Instant,
+    clock: Clock,
 }
 
 impl IdleWaiter {
     /// Create a new IdleWaiter
     pub fn new() -> Self {
-        let start = Instant::now();
+        Self::with_clock(Clock::default())
+    }
+
+    /// Create an IdleWaiter that measures idle time on `clock` (a fake clock
+    /// when replaying a recording).
+    pub fn with_clock(clock: Clock) -> Self {
         Self {
             last_activity: Arc::new(AtomicU64::new(0)),
-            start_time: start,
+            start_time: clock.now(),
+            clock,
         }
     }
 
+    fn elapsed_ms(&self) -> u64 {
+        self.clock.since(self.start_time).as_millis() as u64
+    }
+
     /// Record activity (reset idle timer)
     pub fn ping(&self) {
-        let elapsed = self.start_time.elapsed().as_millis() as u64;
+        let elapsed = self.elapsed_ms();
         self.last_activity.store(elapsed, Ordering::SeqCst);
     }
 
     /// Get time since last activity in milliseconds
     pub fn idle_time_ms(&self) -> u64 {
         let last = self.last_activity.load(Ordering::SeqCst);
-        let now = self.start_time.elapsed().as_millis() as u64;
+        let now = self.elapsed_ms();
         now.saturating_sub(last)
     }
 
@@ -55,10 +67,10 @@ impl IdleWaiter {
 
     /// Wait until idle or timeout
     pub async fn wait_timeout(&self, idle_ms: u64, timeout_ms: u64) -> bool {
-        let deadline = self.start_time.elapsed().as_millis() as u64 + timeout_ms;
+        let deadline = self.elapsed_ms() + timeout_ms;
 
         loop {
-            let now = self.start_time.elapsed().as_millis() as u64;
+            let now = self.elapsed_ms();
             if now >= deadline {
                 return false;
             }
@@ -162,6 +174,17 @@ mod tests {
         assert!(waiter.idle_time_ms() < 10);
     }
 
+    #[test]
+    fn test_idle_time_follows_fake_clock() {
+        let clock = Clock::fake(None);
+        let waiter = IdleWaiter::with_clock(clock.clone());
+        waiter.ping();
+        clock.advance_to(2_000);
+        assert_eq!(waiter.idle_time_ms(), 2_000);
+        waiter.ping();
+        assert_eq!(waiter.idle_time_ms(), 0);
+    }
+
     #[tokio::test]
     async fn test_clone() {
         let waiter = IdleWaiter::new();
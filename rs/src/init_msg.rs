@@ -141,9 +141,16 @@ mod tests {
             started_at: 1,
             wrapper_pid: wrapper,
             parent_pid: None,
+            handoff_from: None,
             agent_id: agent_id.map(String::from),
             title: None,
+            notification: None,
             permissions: None,
+            usage: None,
+            waiting_for_quota: None,
+            queue: None,
+            restarts: Vec::new(),
+            frames: None,
         }
     }
 
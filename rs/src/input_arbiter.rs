@@ -0,0 +1,326 @@
+//! Input arbitration between the human at the keyboard and automated
+//! injections.
+//!
+//! Everything the wrapper types on its own — `ay send` bytes off the FIFO,
+//! approval answers, action rules, auto-Enter, auto-retry nudges, queued
+//! prompts, idle/wrap-up nudges — goes through one queue here. An injection
+//! is written at once unless the human is busy: a keystroke within
+//! `HUMAN_TYPING_HOLD_MS`, or a half-typed line of theirs still sitting in the
+//! CLI's input line (`inputLine` in the config; without it, the keystrokes
+//! alone tell). Held injections go out, highest priority first, once the
+//! human pauses and their line is empty — however long that takes; nothing is
+//! ever typed into a line they are still writing. Answers to an on-screen
+//! prompt are dropped instead if the prompt is gone by then.
+//!
+//! `Ctrl+]` (while anything is held) lists the pending injections; the next
+//! key flushes them now (`f`), drops them (`d`) or keeps holding (anything
+//! else).
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long after the human's last keystroke injections stay held.
+pub const HUMAN_TYPING_HOLD_MS: u64 = 1_500;
+/// Ctrl+] — review held injections.
+pub const REVIEW_KEY: u8 = 0x1d;
+
+/// Where an injection came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// `ay send` / `/api/send` over the FIFO.
+    Fifo,
+    /// The approval policy's `denyKeys` for a denied proposal.
+    Deny,
+    /// A step of an action rule.
+    Action,
+    /// A `typingRespond` answer to an on-screen question.
+    Respond,
+    /// Enter on a prompt the `enter` patterns matched.
+    Enter,
+    /// The auto-retry nudge after a recoverable error.
+    Retry,
+    /// The idle action or the budget wrap-up prompt.
+    Nudge,
+    /// The next prompt of the prompt queue.
+    Queue,
+}
+
+impl Source {
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Fifo => "fifo",
+            Source::Deny => "deny",
+            Source::Action => "action",
+            Source::Respond => "respond",
+            Source::Enter => "enter",
+            Source::Retry => "retry",
+            Source::Nudge => "nudge",
+            Source::Queue => "queue",
+        }
+    }
+
+    /// Release order, lowest first. Someone's explicit `ay send` goes first;
+    /// then answers to the prompt on screen now, a refusal before anything
+    /// that would go ahead; then the wrapper's own new input, recovery before
+    /// new work.
+    pub fn priority(self) -> u8 {
+        match self {
+            Source::Fifo => 0,
+            Source::Deny => 1,
+            Source::Action => 2,
+            Source::Respond => 3,
+            Source::Enter => 4,
+            Source::Retry => 5,
+            Source::Nudge => 6,
+            Source::Queue => 7,
+        }
+    }
+
+    /// Whether the injection answers the screen it was decided on, and so
+    /// is stale once that screen is gone.
+    pub fn answers_screen(self) -> bool {
+        matches!(self, Source::Deny | Source::Respond | Source::Enter)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    /// Written as-is.
+    Keys(Vec<u8>),
+    /// Typed as one prompt and submitted (see messaging::send_prompt).
+    Prompt(String),
+}
+
+impl Payload {
+    /// One line for the review list.
+    pub fn preview(&self) -> String {
+        let text = match self {
+            Payload::Keys(b) => String::from_utf8_lossy(b).into_owned(),
+            Payload::Prompt(p) => p.clone(),
+        };
+        let text: String = text
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        let text = text.trim();
+        match text.char_indices().nth(60) {
+            Some((at, _)) => format!("{}…", &text[..at]),
+            None => text.to_string(),
+        }
+    }
+}
+
+/// The key pressed after `Ctrl+]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewAnswer {
+    Flush,
+    Drop,
+    Keep,
+}
+
+impl ReviewAnswer {
+    pub fn from_key(key: &[u8]) -> Self {
+        match key {
+            b"f" | b"F" => ReviewAnswer::Flush,
+            b"d" | b"D" => ReviewAnswer::Drop,
+            _ => ReviewAnswer::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Injection {
+    pub source: Source,
+    pub payload: Payload,
+    pub queued_at: Instant,
+}
+
+#[derive(Debug, Default)]
+pub struct InputArbiter {
+    pending: VecDeque<Injection>,
+    last_human_at: Option<Instant>,
+    /// Rough length of the line the human is typing: printable keys add,
+    /// Backspace takes away, Enter / Ctrl+U / Ctrl+C clear it.
+    human_line_len: usize,
+}
+
+impl InputArbiter {
+    /// Note a chunk of human keystrokes.
+    pub fn human_input(&mut self, data: &[u8], now: Instant) {
+        self.last_human_at = Some(now);
+        // Escape sequences (arrows, function keys) don't change the length.
+        if data.first() == Some(&0x1b) {
+            return;
+        }
+        for &b in data {
+            match b {
+                b'\r' | b'\n' | 0x15 | 0x03 => self.human_line_len = 0,
+                0x7f | 0x08 => self.human_line_len = self.human_line_len.saturating_sub(1),
+                // UTF-8 continuation bytes belong to the char already counted.
+                b if b >= 0x20 && (b & 0xc0) != 0x80 => self.human_line_len += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether the human is busy at the keyboard. `line_empty` is the CLI's
+    /// input line as seen on screen, None when the CLI has no `inputLine`.
+    pub fn human_busy(&self, now: Instant, line_empty: Option<bool>) -> bool {
+        let typing = self
+            .last_human_at
+            .is_some_and(|t| now.duration_since(t) < Duration::from_millis(HUMAN_TYPING_HOLD_MS));
+        typing || (self.human_line_len > 0 && line_empty != Some(true))
+    }
+
+    /// Whether a new injection must wait: the human is busy, or older ones
+    /// are still held (they keep their place).
+    pub fn must_hold(&self, now: Instant, line_empty: Option<bool>) -> bool {
+        !self.pending.is_empty() || self.human_busy(now, line_empty)
+    }
+
+    pub fn hold(&mut self, source: Source, payload: Payload, now: Instant) {
+        self.pending.push_back(Injection {
+            source,
+            payload,
+            queued_at: now,
+        });
+    }
+
+    /// The next injection to write, if any is due: the highest-priority one,
+    /// once the human is idle.
+    pub fn next_due(&mut self, now: Instant, line_empty: Option<bool>) -> Option<Injection> {
+        if self.human_busy(now, line_empty) {
+            return None;
+        }
+        self.take_next()
+    }
+
+    /// The highest-priority injection, oldest first within a priority.
+    pub fn take_next(&mut self) -> Option<Injection> {
+        let (at, _) = self
+            .pending
+            .iter()
+            .enumerate()
+            .min_by_key(|(i, inj)| (inj.source.priority(), *i))?;
+        self.pending.remove(at)
+    }
+
+    /// The held injections and the review keys, as one status line.
+    pub fn review_line(&self) -> String {
+        let held: Vec<String> = self
+            .pending
+            .iter()
+            .enumerate()
+            .map(|(i, inj)| {
+                format!(
+                    "{}. {}: {}",
+                    i + 1,
+                    inj.source.name(),
+                    inj.payload.preview()
+                )
+            })
+            .collect();
+        format!(
+            "held: {} — f send now · d drop · other key keep",
+            held.join(" · ")
+        )
+    }
+
+    pub fn pending(&self) -> &VecDeque<Injection> {
+        &self.pending
+    }
+
+    pub fn has_pending(&self, source: Source) -> bool {
+        self.pending.iter().any(|i| i.source == source)
+    }
+
+    /// Drop everything held; returns how many.
+    pub fn drop_all(&mut self) -> usize {
+        let n = self.pending.len();
+        self.pending.clear();
+        n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(s: &str) -> Payload {
+        Payload::Keys(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_holds_while_the_human_types() {
+        let t0 = Instant::now();
+        let mut a = InputArbiter::default();
+        assert!(!a.must_hold(t0, None));
+        a.human_input(b"fix the te", t0);
+        assert!(a.must_hold(t0, None));
+        a.hold(Source::Fifo, keys("hello"), t0);
+        // Paused, but their half-typed line is still there.
+        let later = t0 + Duration::from_millis(HUMAN_TYPING_HOLD_MS);
+        assert!(a.next_due(later, None).is_none());
+        assert!(a.next_due(later, Some(false)).is_none());
+        // The screen shows it was cleared.
+        assert_eq!(
+            a.next_due(later, Some(true)).map(|i| i.payload),
+            Some(keys("hello"))
+        );
+        // Backspacing the line away counts as empty too.
+        a.human_input(b"\x15ab\x7f\x7f", later);
+        assert!(!a.human_busy(later + Duration::from_secs(2), None));
+        a.human_input(b"\x1b[A", later);
+        assert!(!a.human_busy(later + Duration::from_secs(2), None));
+    }
+
+    #[test]
+    fn test_releases_by_priority_only_once_the_human_is_done() {
+        let t0 = Instant::now();
+        let mut a = InputArbiter::default();
+        a.human_input(b"x", t0);
+        a.hold(Source::Queue, Payload::Prompt("next task".into()), t0);
+        a.hold(Source::Enter, keys("\r"), t0);
+        a.hold(Source::Fifo, keys("one"), t0);
+        a.hold(Source::Fifo, keys("\r"), t0);
+        assert!(a.has_pending(Source::Queue));
+        // An hour on, still typing, or a line left half-typed: nothing goes.
+        let later = t0 + Duration::from_secs(3600);
+        a.human_input(b"y", later);
+        assert!(a.next_due(later, None).is_none());
+        let paused = later + Duration::from_millis(HUMAN_TYPING_HOLD_MS);
+        assert!(a.next_due(paused, Some(false)).is_none());
+        a.human_input(b"\r", paused);
+        let idle = paused + Duration::from_millis(HUMAN_TYPING_HOLD_MS);
+        assert_eq!(a.next_due(idle, None).unwrap().payload, keys("one"));
+        assert_eq!(a.next_due(idle, None).unwrap().payload, keys("\r"));
+        assert_eq!(a.next_due(idle, None).unwrap().source, Source::Enter);
+        assert_eq!(a.next_due(idle, None).unwrap().source, Source::Queue);
+        assert!(a.next_due(idle, None).is_none());
+        a.hold(Source::Respond, keys("y"), idle);
+        assert_eq!(a.drop_all(), 1);
+    }
+
+    #[test]
+    fn test_review_line_and_keys() {
+        let t0 = Instant::now();
+        let mut a = InputArbiter::default();
+        a.hold(Source::Fifo, keys("hello\r"), t0);
+        a.hold(Source::Queue, Payload::Prompt("next task".into()), t0);
+        assert_eq!(
+            a.review_line(),
+            "held: 1. fifo: hello · 2. queue: next task — f send now · d drop · other key keep"
+        );
+        assert_eq!(ReviewAnswer::from_key(b"f"), ReviewAnswer::Flush);
+        assert_eq!(ReviewAnswer::from_key(b"D"), ReviewAnswer::Drop);
+        assert_eq!(ReviewAnswer::from_key(b"\r"), ReviewAnswer::Keep);
+        assert_eq!(ReviewAnswer::from_key(b"fd"), ReviewAnswer::Keep);
+    }
+
+    #[test]
+    fn test_preview_is_one_short_line() {
+        assert_eq!(keys("a\r\nb").preview(), "a  b");
+        let long = Payload::Prompt("é".repeat(80)).preview();
+        assert_eq!(long.chars().count(), 61);
+    }
+}
@@ -0,0 +1,131 @@
+//! `--output jsonl`: newline-delimited JSON on stdout instead of TUI bytes or
+//! rendered text, so CI jobs and other programs can drive agent-yes without
+//! scraping the screen.
+//!
+//! Every line has the events.jsonl shape, `{"at": <unix ms>, "type": ...}`:
+//!
+//! - `start`, once: the CLI, the wrapper pid and the cwd;
+//! - every event the engine appends to `<pid>.events.jsonl` (see events.rs):
+//!   `state` transitions, the auto-actions (`enter_sent`, `auto_retry_sent`,
+//!   `approval`, ...), `title` changes, `completed`;
+//! - `line` for each settled screen line and `screen` for an alternate-screen
+//!   snapshot — the plain-text stream of non_tty_renderer.rs, one record each;
+//! - last, `result` with the captured result text (when there is one) and
+//!   `exit` with the exit code and reason — or, when the task is handed to a
+//!   failover CLI (see failover.rs), `handoff` naming it; the successor then
+//!   streams its own records, `start` to `exit`, on the same stdout.
+//!
+//! Logs go to stderr so stdout stays parseable.
+
+use crate::events::json_line;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::io::Write;
+
+/// `--output`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The TUI on a terminal, rendered text otherwise.
+    #[default]
+    Text,
+    Jsonl,
+}
+
+impl OutputFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            other => Err(anyhow!(
+                "Unknown output format '{}' (expected text or jsonl)",
+                other
+            )),
+        }
+    }
+}
+
+/// The records `--output jsonl` adds to the event stream.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record<'a> {
+    Start {
+        cli: &'a str,
+        pid: u32,
+        cwd: &'a str,
+    },
+    /// A settled screen line.
+    Line {
+        text: &'a str,
+    },
+    /// An alternate-screen snapshot.
+    Screen {
+        text: &'a str,
+    },
+    Result {
+        text: &'a str,
+    },
+    Exit {
+        code: i32,
+        reason: &'a str,
+    },
+    /// This agent's CLI stopped and `to` takes the task over.
+    Handoff {
+        to: &'a str,
+    },
+}
+
+/// `record` as one stdout line, with its newline.
+pub fn format(record: &Record) -> String {
+    json_line(record).unwrap_or_default()
+}
+
+/// Write `record` straight to stdout — for the records outside a run, when
+/// nothing else is writing there.
+pub fn print(record: &Record) {
+    let mut out = std::io::stdout().lock();
+    let _ = out.write_all(format(record).as_bytes());
+    let _ = out.flush();
+}
+
+/// The closing records: the result, if one was captured, then the exit.
+pub fn print_end(result: Option<&str>, code: i32, reason: &str) {
+    if let Some(text) = result {
+        print(&Record::Result { text });
+    }
+    print(&Record::Exit { code, reason });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(OutputFormat::parse("jsonl").unwrap(), OutputFormat::Jsonl);
+        assert_eq!(OutputFormat::parse("text").unwrap(), OutputFormat::Text);
+        assert!(OutputFormat::parse("json").is_err());
+    }
+
+    #[test]
+    fn test_records() {
+        let line = format(&Record::Exit {
+            code: 0,
+            reason: "completed",
+        });
+        assert!(line.ends_with("}\n"));
+        let v: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(v["type"], "exit");
+        assert_eq!(v["code"], 0);
+        assert_eq!(v["reason"], "completed");
+        assert!(v["at"].as_u64().unwrap() > 0);
+
+        let v: serde_json::Value =
+            serde_json::from_str(&format(&Record::Line { text: "● Done." })).unwrap();
+        assert_eq!(v["type"], "line");
+        assert_eq!(v["text"], "● Done.");
+
+        let v: serde_json::Value =
+            serde_json::from_str(&format(&Record::Handoff { to: "codex" })).unwrap();
+        assert_eq!((&v["type"], &v["to"]), (&"handoff".into(), &"codex".into()));
+    }
+}
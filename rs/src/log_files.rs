@@ -129,7 +129,12 @@ pub fn global_dir() -> Option<PathBuf> {
         .or_else(|| dirs::home_dir().map(|h| h.join(".agent-yes")))
 }
 
+/// None for an empty cwd (an offline replay, or a record with no cwd) — a bare
+/// relative `.agent-yes` would land in whatever directory the process runs in.
 pub fn project_log_dir(cwd: &str) -> Option<PathBuf> {
+    if cwd.is_empty() {
+        return None;
+    }
     Some(Path::new(cwd).join(".agent-yes"))
 }
 
@@ -172,6 +177,7 @@ mod tests {
         let dir = tempfile::tempdir().unwrap();
         let log_dir = project_log_dir(dir.path().to_str().unwrap()).unwrap();
         assert_eq!(log_dir, dir.path().join(".agent-yes"));
+        assert_eq!(project_log_dir(""), None);
     }
 
     #[test]
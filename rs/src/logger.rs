@@ -2,8 +2,9 @@
 
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-/// Initialize logging with optional verbose mode
-pub fn init(verbose: bool) {
+/// Initialize logging with optional verbose mode. `stderr` moves the logs off
+/// stdout (`--output jsonl`).
+pub fn init(verbose: bool, stderr: bool) {
     let filter = if verbose || std::env::var("VERBOSE").is_ok() {
         "debug"
     } else {
@@ -13,7 +14,8 @@ pub fn init(verbose: bool) {
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter));
 
     tracing_subscriber::registry()
-        .with(fmt::layer().with_target(false))
+        .with((!stderr).then(|| fmt::layer().with_target(false)))
+        .with(stderr.then(|| fmt::layer().with_target(false).with_writer(std::io::stderr)))
         .with(env_filter)
         .init();
 }
@@ -1,32 +1,58 @@
+mod action_rules;
 mod agent_permissions;
+mod approval_policy;
+mod checkpoint;
 mod cli;
+mod clock;
 mod codex_sessions;
+mod completion;
 mod config;
 mod config_loader;
 mod context;
+mod events;
+mod failover;
 mod fifo;
+mod hooks;
 mod identity;
 mod idle_waiter;
 mod init_msg;
+mod input_arbiter;
 mod installer;
+mod jsonl_output;
 mod log_files;
 mod logger;
 mod messaging;
+mod meta_commands;
 mod non_tty_renderer;
+mod paste;
+mod patterns_debug;
 mod pid_store;
+mod prompt_queue;
 mod pty_spawner;
 mod ready_manager;
 mod reaper;
+mod recording;
+mod redact;
+mod reset_time;
+mod resource_limits;
+mod restart_policy;
 mod running_lock;
+mod sandbox;
+mod shutdown;
+mod status_line;
 mod supported_clis;
 mod swarm;
+mod title_history;
 mod title_scanner;
+mod transcript;
+mod usage;
 mod utils;
 mod vterm;
 mod webhook;
 
 use anyhow::Result;
 use cli::CliArgs;
+use jsonl_output::OutputFormat;
 use tracing::{error, info};
 
 /// Detect how the Rust binary was installed.
@@ -153,12 +179,31 @@ fn warn_cwd_passthrough() {
     }
 }
 
+fn main() -> Result<()> {
+    // The sandbox helper (sandbox.rs) re-enters here as the PTY's child. It
+    // must run before the tokio runtime exists: unshare(CLONE_NEWUSER) fails
+    // in a process with more than one thread.
+    if let Some(code) = sandbox::maybe_exec() {
+        std::process::exit(code);
+    }
+    run_main()
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn run_main() -> Result<()> {
     // Delegate management subcommands (ls/send/restart/stop/serve/…) to the JS
     // launcher. This binary is only the agent runner; without this, a leading
     // subcommand word would be parsed by clap as prompt text and spawn an agent.
     // Must run before parse_args(). See cli::maybe_delegate_subcommand.
+    // `patterns` and `rollback` are the subcommands this runner implements
+    // itself (the vterm + pattern engine, the checkpoint plumbing); the JS
+    // launcher forwards them here.
+    if let Some(code) = patterns_debug::maybe_run_subcommand() {
+        std::process::exit(code);
+    }
+    if let Some(code) = checkpoint::maybe_run_subcommand() {
+        std::process::exit(code);
+    }
     if let Some(code) = cli::maybe_delegate_subcommand() {
         std::process::exit(code);
     }
@@ -173,7 +218,7 @@ async fn main() -> Result<()> {
     let args = cli::parse_args()?;
 
     // Initialize logging
-    logger::init(args.verbose);
+    logger::init(args.verbose, args.output == OutputFormat::Jsonl);
 
     let install_method = detect_install_method();
     info!(
@@ -207,19 +252,56 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Offline replay of a recording: no CLI is spawned.
+    if let Some(path) = args.replay.clone() {
+        let exit_code = run_replay(&args, &path).await?;
+        std::process::exit(exit_code);
+    }
+
     // Run the agent
     let exit_code = run_agent(args, &cwd).await?;
 
     std::process::exit(exit_code);
 }
 
+/// `--replay <file.cast>`: run a recording through the pattern engine of
+/// `args.cli` (cascaded config included) on a fake clock and print the input it
+/// would type next to the input the recording captured.
+async fn run_replay(args: &CliArgs, path: &str) -> Result<i32> {
+    let cli_config = crate::config::get_runtime_cli_config(&args.cli)?;
+    let cast = recording::read_cast(std::path::Path::new(path))?;
+    let mut ctx = crate::context::AgentContext::for_replay(
+        args.cli.clone(),
+        cli_config,
+        cast.height,
+        cast.width,
+        cast.timestamp,
+    );
+    ctx.auto_yes_enabled = args.auto_yes;
+    let typed = ctx.replay(&cast).await?;
+    print!("{}", recording::format_replay_report(&cast.events, &typed));
+    if ctx.is_fatal {
+        println!("fatal pattern matched");
+    }
+    Ok(0)
+}
+
 async fn run_agent(args: CliArgs, cwd: &str) -> Result<i32> {
     use crate::config::get_runtime_cli_config;
     use crate::context::AgentContext;
-    use crate::pid_store::PidStore;
+    use crate::pid_store::{PidStore, RestartEntry};
     use crate::pty_spawner::spawn_agent;
+    use crate::restart_policy::{ExitClass, RestartDecision, EXIT_SCREEN_TAIL_LINES};
 
-    let cli_config = get_runtime_cli_config(&args.cli)?;
+    let mut cli_config = get_runtime_cli_config(&args.cli)?;
+    // `--memory-max` & co. win over the config's `limits:`.
+    cli_config.limits = cli_config.limits.overlay(args.limits);
+    if args.sandbox {
+        cli_config.sandbox.enabled = true;
+    }
+    if let Some(to) = args.failover.clone() {
+        cli_config.failover.to = to;
+    }
 
     // Pre-flight: make sure the wrapped CLI is actually installed before we
     // enter the spawn/restart loop. A missing CLI otherwise produces an endless
@@ -249,10 +331,13 @@ async fn run_agent(args: CliArgs, cwd: &str) -> Result<i32> {
     // prompt is wrapped: `args.prompt` stays the raw task, so the registry (and
     // therefore `ay ls`) still shows what this agent was actually asked to do.
     // No-op for a top-level agent (no inherited AGENT_YES_PID). See init_msg.rs
-    // — the format is byte-identical to ts/initMsg.ts.
+    // — the format is byte-identical to ts/initMsg.ts. A failover successor's
+    // prompt is already a `<ay-handoff …>` (see failover.rs) and goes as is.
+    let handed_over = std::env::var_os(failover::HANDOFF_FROM_ENV).is_some();
     let delivered_prompt: Option<String> = args.prompt.as_ref().map(|raw| {
         let spawner = std::env::var("AGENT_YES_PID")
             .ok()
+            .filter(|_| !handed_over)
             .and_then(|v| v.trim().parse::<u32>().ok())
             .filter(|p| *p > 0)
             .and_then(|parent| {
@@ -348,10 +433,18 @@ async fn run_agent(args: CliArgs, cwd: &str) -> Result<i32> {
     // --no-tty / NO_COLOR / CI), emit plain rendered text instead of the raw
     // TUI byte stream. See docs/non-tty-output.md.
     let stdout_is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
-    let render_plain =
-        crate::non_tty_renderer::should_render_plain(args.force_tty, args.no_tty, stdout_is_tty);
-    if render_plain {
-        info!("stdout is not a TTY (or --no-tty): emitting plain rendered text on exit");
+    // `--output jsonl` takes the plain path too, with records for text.
+    let jsonl = args.output == OutputFormat::Jsonl;
+    let render_plain = jsonl
+        || crate::non_tty_renderer::should_render_plain(args.force_tty, args.no_tty, stdout_is_tty);
+    if jsonl {
+        jsonl_output::print(&jsonl_output::Record::Start {
+            cli: &args.cli,
+            pid,
+            cwd,
+        });
+    } else if render_plain {
+        info!("stdout is not a TTY (or --no-tty): emitting plain rendered text");
     }
 
     // Clean up stale PID records on startup
@@ -386,24 +479,38 @@ async fn run_agent(args: CliArgs, cwd: &str) -> Result<i32> {
     // reap_group (SIGKILL / OOM / force-restart). Cheap and runs on every start.
     reaper::sweep();
 
-    // Crash-restart loop guard: if the agent keeps exiting non-zero almost
-    // immediately, restarting is futile (misconfig, broken install, etc.).
-    // Track consecutive fast failures and give up after a few rather than
-    // spinning forever.
-    let mut fast_failures: u32 = 0;
-    const MAX_FAST_FAILURES: u32 = 3;
-    const FAST_FAILURE_WINDOW: std::time::Duration = std::time::Duration::from_secs(3);
+    // `--record`: one asciicast for the whole run, shared across restarts.
+    let recorder = if args.record {
+        let (cols, rows) = crate::pty_spawner::get_terminal_size();
+        let path = crate::log_files::project_log_dir(cwd)
+            .map(|d| d.join(format!("{}.cast", pid)))
+            .ok_or_else(|| anyhow::anyhow!("No project dir to record into"))?;
+        let cast = recording::CastWriter::create(&path, cols, rows, &args.cli)?;
+        info!("Recording session to {:?}", cast.path);
+        Some(cast)
+    } else {
+        None
+    };
+
+    // The prompt queue outlives restarts: started once here, reopened by each
+    // run's AgentContext from `<cwd>/.agent-yes/<pid>.queue.json`.
+    prompt_queue::PromptQueue::create(pid, cwd, args.prompt.as_deref(), args.prompt_queue.clone());
 
-    loop {
-        let iter_start = std::time::Instant::now();
+    // Every restart so far, republished into the pid record after each run
+    // re-registers it. The restart policy reads it to back off and to spot a
+    // crash loop. See restart_policy.rs.
+    let mut restarts: Vec<RestartEntry> = Vec::new();
+    // The last result any run captured, for `--output jsonl`'s closing records.
+    let mut result: Option<String> = None;
 
+    loop {
         // Spawn the agent process
         let mut ctx = spawn_agent(&args.cli, &cmd_args, &cli_config, cwd, args.verbose).await?;
 
         // Record (wrapper pid, agent pgid) so a later sweep reaps this agent's
         // process group if WE die before running reap_group (e.g. SIGKILL).
         if let Some(child_pid) = ctx.child.process_id() {
-            reaper::register(std::process::id(), child_pid as i32);
+            reaper::register(std::process::id(), child_pid as i32, ctx.cgroup.as_deref());
         }
 
         // Create agent context (also initialises log file)
@@ -421,6 +528,16 @@ async fn run_agent(args: CliArgs, cwd: &str) -> Result<i32> {
             render_plain,
             initial_input.clone(),
         );
+        if let Some(cast) = &recorder {
+            agent_ctx.set_recorder(cast.clone());
+        }
+        if let Some(budget) = args.budget {
+            agent_ctx.set_budget(budget, args.budget_action);
+        }
+        agent_ctx.set_result_mode(args.result_mode);
+        if jsonl || (render_plain && !args.no_stream) {
+            agent_ctx.set_plain_stream(args.output);
+        }
 
         // Create per-pid FIFO for `cy send <keyword> <msg>`. Best-effort —
         // failure (Windows, full disk, etc.) just means cy send won't work
@@ -443,12 +560,14 @@ async fn run_agent(args: CliArgs, cwd: &str) -> Result<i32> {
         // Stamp the permission posture alongside the record: `auto_continue` is
         // robust AND a CLI that declares restore_args — robust alone only
         // restarts, it resumes nothing. Mirrors ts/index.ts.
-        let permissions = agent_permissions::derive_permissions(
+        let mut permissions = agent_permissions::derive_permissions(
             &cmd_args,
             &cli_config.yes_args,
             args.robust,
             args.robust && !cli_config.restore_args.is_empty(),
         );
+        permissions.approval_policy = cli_config.approval.is_enabled();
+        permissions.sandbox = cli_config.sandbox.stamp(cwd);
         pid_store.register_full(
             pid,
             &args.cli,
@@ -458,6 +577,11 @@ async fn run_agent(args: CliArgs, cwd: &str) -> Result<i32> {
             fifo_str.as_deref(),
             Some(permissions),
         );
+        if !restarts.is_empty() {
+            pid_store.set_restarts(pid, &restarts);
+        }
+        agent_ctx.publish_usage();
+        agent_ctx.publish_queue();
         webhook::notify("RUNNING", args.prompt.as_deref().unwrap_or(""), cwd);
 
         // Run the main loop
@@ -485,26 +609,82 @@ async fn run_agent(args: CliArgs, cwd: &str) -> Result<i32> {
         // linger and mislead `ay ls`/`ay send` after this agent is gone.
         fifo::cleanup_stdin_activity(pid);
 
+        // Class the exit from what the CLI left on screen before the log is
+        // finalized. Deliberate stops never restart, whatever their exit code.
+        let restart_policy = &cli_config.restart;
+        let class = restart_policy.classify(
+            exit_code,
+            agent_ctx.is_fatal,
+            &agent_ctx.screen_tail(EXIT_SCREEN_TAIL_LINES),
+        );
+        let deliberate = agent_ctx.stop_requested
+            || agent_ctx.is_user_abort
+            || agent_ctx.budget_stop
+            || agent_ctx.done_exit
+            || agent_ctx.failover.is_some();
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let decision = if args.robust && !deliberate && !agent_ctx.should_restart_without_continue {
+            restart_policy.decide(class, &restarts, now_ms)
+        } else {
+            RestartDecision::Stop
+        };
+
+        // A failover hands the successor the task, the last screen and the
+        // title; built before finalize_log, while the screen is still there.
+        let handover = agent_ctx.failover.clone().map(|h| {
+            let screen = agent_ctx.screen_tail(failover::HANDOFF_SCREEN_LINES);
+            let screen = cli_config.redact.redact(&screen).into_owned();
+            let from = failover::Predecessor {
+                cli: args.cli.clone(),
+                agent_id: agent_ctx.agent_id().unwrap_or_else(|| pid.to_string()),
+                wait: context::fmt_dur_secs(h.wait_secs),
+                title: agent_ctx.title().map(String::from),
+            };
+            let msg = failover::build_handoff_msg(
+                args.prompt.as_deref(),
+                &from,
+                &screen,
+                &init_msg::mint_nonce(),
+            );
+            (h, from.agent_id, msg)
+        });
+
         // Render the full scrollback to <pid>.log and drop the now-redundant
         // raw byte log (kept only when the session used the alternate screen).
         // The returned path repoints the pid index from the raw log to it.
         let rendered_log = agent_ctx.finalize_log();
 
         // Update PID store and send EXIT webhook
-        let exit_reason = if agent_ctx.is_user_abort {
-            "user_abort"
+        // A SIGTERM stop records the shutdown-ladder step the CLI exited on.
+        let exit_reason = if agent_ctx.stop_requested {
+            crate::shutdown::exit_reason(agent_ctx.shutdown_step)
+        } else if agent_ctx.is_user_abort {
+            "user_abort".to_string()
         } else if agent_ctx.is_fatal {
-            "fatal"
-        } else if exit_code == 0 {
-            "completed"
+            "fatal".to_string()
+        } else if agent_ctx.budget_stop {
+            "budget".to_string()
+        } else if let Some(h) = &agent_ctx.failover {
+            format!("failover:{}", h.to)
+        } else if decision == RestartDecision::CrashLoop {
+            "crashloop".to_string()
+        } else if agent_ctx.done_exit || exit_code == 0 {
+            "completed".to_string()
         } else {
-            "crashed"
+            "crashed".to_string()
+        };
+        // A crash loop parks the record as `crashloop`, which `clean_stale`
+        // keeps around for a day so it gets noticed.
+        let status = if decision == RestartDecision::CrashLoop {
+            "crashloop"
+        } else {
+            "exited"
         };
         pid_store.update_status(
             pid,
-            "exited",
+            status,
             Some(exit_code),
-            Some(exit_reason),
+            Some(&exit_reason),
             rendered_log.as_deref(),
         );
         webhook::notify(
@@ -512,47 +692,100 @@ async fn run_agent(args: CliArgs, cwd: &str) -> Result<i32> {
             &format!("{} exitCode={}", exit_reason, exit_code),
             cwd,
         );
+        agent_ctx.run_exit_hook(&exit_reason);
+        if agent_ctx.result.is_some() {
+            result = agent_ctx.result.take();
+        }
 
         // Handle restart-without-continue (e.g., "No conversation found to continue")
         // Must be checked before normal crash restart to avoid re-adding --continue
-        if agent_ctx.should_restart_without_continue {
+        if agent_ctx.should_restart_without_continue && !agent_ctx.stop_requested {
             info!("Restarting without continue args...");
+            restarts.push(RestartEntry {
+                at: now_ms,
+                exit_code,
+                exit_reason: exit_reason.clone(),
+                class: ExitClass::Retryable.name().to_string(),
+                delay_ms: 0,
+            });
             // Remove restore args (--continue, --resume) from cmd_args
             cmd_args.retain(|a| !cli_config.restore_args.contains(a));
             continue;
         }
 
-        // Check if we should restart
-        if args.robust && exit_code != 0 && !agent_ctx.is_fatal && !agent_ctx.is_user_abort {
-            // Count consecutive fast failures; a run that survived past the
-            // window is a real session, so reset the counter on it.
-            if iter_start.elapsed() < FAST_FAILURE_WINDOW {
-                fast_failures += 1;
-            } else {
-                fast_failures = 0;
+        match decision {
+            RestartDecision::Restart(delay) => {
+                restarts.push(RestartEntry {
+                    at: now_ms,
+                    exit_code,
+                    exit_reason: exit_reason.clone(),
+                    class: class.name().to_string(),
+                    delay_ms: delay.as_millis() as u64,
+                });
+                pid_store.set_restarts(pid, &restarts);
+                info!(
+                    "Agent exited with code {} ({}), restarting in {}ms...",
+                    exit_code,
+                    class.name(),
+                    delay.as_millis()
+                );
+                if !sleep_restart_backoff(delay).await {
+                    info!("SIGTERM during restart backoff, not restarting");
+                    if jsonl {
+                        jsonl_output::print_end(result.as_deref(), exit_code, &exit_reason);
+                    }
+                    return Ok(exit_code);
+                }
+                // Add restore args for next iteration
+                if !cmd_args.iter().any(|a| cli_config.restore_args.contains(a)) {
+                    cmd_args.extend(cli_config.restore_args.iter().cloned());
+                }
+                continue;
             }
-            if fast_failures >= MAX_FAST_FAILURES {
+            RestartDecision::CrashLoop => {
                 error!(
-                    "Agent exited non-zero within {}s {} times in a row — giving up to avoid a \
-                     crash-restart loop (last exit code {}). Check the agent CLI and its config.",
-                    FAST_FAILURE_WINDOW.as_secs(),
-                    fast_failures,
+                    "Agent restarted {} times within {}s — parking it as crashloop instead of \
+                     restarting again (last exit code {}). Check the agent CLI and its config.",
+                    restart_policy.max_restarts,
+                    restart_policy.window.as_secs(),
                     exit_code
                 );
-                return Ok(exit_code);
-            }
-            info!("Agent crashed with code {}, restarting...", exit_code);
-            // Add restore args for next iteration
-            if !cmd_args.iter().any(|a| cli_config.restore_args.contains(a)) {
-                cmd_args.extend(cli_config.restore_args.iter().cloned());
             }
-            continue;
+            RestartDecision::Stop => {}
         }
 
+        // The successor's own records end the stream; ours would be a
+        // second result/exit in it.
+        if let Some((h, from_id, msg)) = handover {
+            if jsonl {
+                jsonl_output::print(&jsonl_output::Record::Handoff { to: &h.to });
+            }
+            let queued = prompt_queue::PromptQueue::open(pid, cwd).pending().to_vec();
+            return failover::run_successor(&h, &args, &msg, &queued, &from_id).await;
+        }
+        if jsonl {
+            jsonl_output::print_end(result.as_deref(), exit_code, &exit_reason);
+        }
         return Ok(exit_code);
     }
 }
 
+/// Sleep out a restart backoff. False when a SIGTERM (`ay stop`) arrived
+/// meanwhile: the SIGTERM handler installed by the run stays installed, so
+/// without this it would be swallowed and the agent restarted anyway.
+async fn sleep_restart_backoff(delay: std::time::Duration) -> bool {
+    #[cfg(unix)]
+    if let Ok(mut term) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+    {
+        return tokio::select! {
+            _ = tokio::time::sleep(delay) => true,
+            _ = term.recv() => false,
+        };
+    }
+    tokio::time::sleep(delay).await;
+    true
+}
+
 /// Run in swarm mode - P2P agent networking
 #[cfg(feature = "swarm")]
 async fn run_swarm_mode(args: CliArgs, cwd: &str) -> Result<i32> {
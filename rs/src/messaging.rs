@@ -8,6 +8,7 @@
 #![allow(dead_code)]
 
 use crate::idle_waiter::IdleWaiter;
+use crate::paste::{self, PasteFallback};
 use crate::ready_manager::ReadyManager;
 use anyhow::Result;
 use std::io::Write;
@@ -101,12 +102,17 @@ pub async fn send_enter(ctx: &mut MessageContext, _wait_ms: u64) -> Result<()> {
 /// Send raw text (no Enter)
 pub async fn send_text(ctx: &MessageContext, text: &str) -> Result<()> {
     debug!("Sending text: {}", text);
+    send_bytes(ctx, text.as_bytes()).await
+}
 
+/// Write `bytes` as they are — input relayed from elsewhere, which need not
+/// be UTF-8.
+pub async fn send_bytes(ctx: &MessageContext, bytes: &[u8]) -> Result<()> {
     let mut writer = ctx
         .writer
         .lock()
         .map_err(|e| anyhow::anyhow!("Lock error: {}", e))?;
-    writer.write_all(text.as_bytes())?;
+    writer.write_all(bytes)?;
     writer.flush()?;
 
     ctx.idle_waiter.ping();
@@ -114,6 +120,27 @@ pub async fn send_text(ctx: &MessageContext, text: &str) -> Result<()> {
     Ok(())
 }
 
+/// Pause between a pasted multi-line prompt and the Enter that submits it, so
+/// the CLI has taken in the paste before the key arrives.
+const PASTE_SUBMIT_DELAY_MS: u64 = 100;
+
+/// Type `text` as one prompt and submit it. Multi-line text is pasted (see
+/// paste.rs) and its Enter sent on its own; a single line is typed as before.
+pub async fn send_prompt(
+    ctx: &MessageContext,
+    text: &str,
+    bracketed: bool,
+    fallback: PasteFallback,
+) -> Result<()> {
+    if !paste::is_multiline(text) {
+        send_text(ctx, text).await?;
+        return send_text(ctx, "\n").await;
+    }
+    send_text(ctx, &paste::encode(text, bracketed, fallback)).await?;
+    tokio::time::sleep(std::time::Duration::from_millis(PASTE_SUBMIT_DELAY_MS)).await;
+    send_text(ctx, "\r").await
+}
+
 /// Send Esc (cancel in-flight request) WITHOUT pinging the idle timer.
 ///
 /// The no-output watchdog uses this: it must NOT reset idle, because it relies
@@ -200,6 +227,26 @@ mod tests {
         assert_eq!(*buf.lock().unwrap(), b"hello");
     }
 
+    #[tokio::test]
+    async fn test_send_prompt_pastes_multiline_text() {
+        let buf = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let writer: Arc<Mutex<Box<dyn Write + Send>>> =
+            Arc::new(Mutex::new(Box::new(BufWriter(buf.clone()))));
+        let ctx = MessageContext::new(
+            writer,
+            IdleWaiter::new(),
+            ReadyManager::new(),
+            ReadyManager::new(),
+        );
+        send_prompt(&ctx, "one", true, PasteFallback::Join)
+            .await
+            .unwrap();
+        send_prompt(&ctx, "a\nb", true, PasteFallback::Join)
+            .await
+            .unwrap();
+        assert_eq!(*buf.lock().unwrap(), b"one\n\x1b[200~a\rb\x1b[201~\r");
+    }
+
     #[tokio::test]
     async fn test_send_enter() {
         let buf = Arc::new(Mutex::new(Vec::<u8>::new()));
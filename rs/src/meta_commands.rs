@@ -0,0 +1,95 @@
+//! In-session meta-commands: `/ay <command>` lines the wrapper acts on itself
+//! instead of passing them to the CLI.
+//!
+//! Typed at the terminal, the line is caught on Enter and wiped from the
+//! CLI's input line with Ctrl+U, never submitted (`/auto` has always worked
+//! this way). Over the FIFO, `ay send <agent> "/ay status"` and `/api/send`
+//! wrap the line in a command frame — see `fifo::COMMAND_FRAME_START` — so
+//! scripts get the same vocabulary as the human at the keyboard. Replies go
+//! to the status line (status_line.rs) and, with the command, to the event
+//! log.
+
+/// One line of usage, the reply to `/ay` and `/ay help`.
+pub const HELP: &str =
+    "/ay pause | resume | status | queue [add <prompt>] | budget | retry now | checkpoint | detach";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetaCommand {
+    /// `/auto`: flip auto-yes.
+    ToggleAutoYes,
+    /// Auto-yes off: nothing is answered, queued or nudged until `resume`.
+    Pause,
+    Resume,
+    Status,
+    /// The prompt queue's progress.
+    Queue,
+    QueueAdd(String),
+    /// Usage so far against `--budget`.
+    Budget,
+    /// Fire a scheduled auto-retry now instead of waiting out its backoff.
+    RetryNow,
+    Checkpoint,
+    /// Stop drawing to this terminal; the agent keeps running if it closes.
+    Detach,
+    Help,
+}
+
+/// Parses a meta-command line. None when `line` isn't one (see
+/// `fifo::is_meta_command`); an error reply for a `/ay` line that doesn't
+/// parse.
+pub fn parse(line: &str) -> Option<Result<MetaCommand, String>> {
+    if !crate::fifo::is_meta_command(line) {
+        return None;
+    }
+    let line = line.trim();
+    if line == "/auto" {
+        return Some(Ok(MetaCommand::ToggleAutoYes));
+    }
+    let rest = line["/ay".len()..].trim();
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let command = match words.as_slice() {
+        [] | ["help"] => MetaCommand::Help,
+        ["pause"] => MetaCommand::Pause,
+        ["resume"] => MetaCommand::Resume,
+        ["status"] => MetaCommand::Status,
+        ["queue"] => MetaCommand::Queue,
+        ["queue", "add", ..] => {
+            let prompt = rest["queue".len()..].trim_start()["add".len()..].trim();
+            MetaCommand::QueueAdd(prompt.to_string())
+        }
+        ["budget"] => MetaCommand::Budget,
+        ["retry", "now"] => MetaCommand::RetryNow,
+        ["checkpoint"] => MetaCommand::Checkpoint,
+        ["detach"] => MetaCommand::Detach,
+        _ => return Some(Err(format!("unknown command '{}' — {}", rest, HELP))),
+    };
+    Some(Ok(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse("/ay status\r"), Some(Ok(MetaCommand::Status)));
+        assert_eq!(parse(" /ay  retry now "), Some(Ok(MetaCommand::RetryNow)));
+        assert_eq!(parse("/auto"), Some(Ok(MetaCommand::ToggleAutoYes)));
+        assert_eq!(parse("/ay"), Some(Ok(MetaCommand::Help)));
+        assert_eq!(
+            parse("/ay queue add  run the  tests\n"),
+            Some(Ok(MetaCommand::QueueAdd("run the  tests".into())))
+        );
+        assert_eq!(
+            parse("/ay queue add"),
+            Some(Ok(MetaCommand::QueueAdd(String::new())))
+        );
+        assert!(
+            matches!(parse("/ay retry"), Some(Err(e)) if e.starts_with("unknown command 'retry'"))
+        );
+        // Not ours: the CLI's own slash commands, and look-alikes.
+        assert_eq!(parse("/compact"), None);
+        assert_eq!(parse("/ayy"), None);
+        assert_eq!(parse("say /ay status"), None);
+    }
+}
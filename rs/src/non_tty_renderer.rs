@@ -10,13 +10,29 @@
 //! rendered text** — the semantic content the user would have seen on a TTY.
 //! See `docs/non-tty-output.md` for the full design.
 //!
-//! v1 is exit-only: we don't stream mid-session. The renderer observes the
-//! vterm after every chunk so it can capture the alt-screen contents before
-//! they vanish (alt-screen TUIs restore the normal screen on exit, which
-//! would otherwise leave us with a blank screen at flush time), then emits
-//! the final rendered screen once on process exit.
+//! The renderer observes the vterm after every chunk so it can capture the
+//! alt-screen contents before they vanish (alt-screen TUIs restore the normal
+//! screen on exit, which would otherwise leave us with a blank screen at flush
+//! time), then emits the final rendered screen once on process exit.
+//!
+//! Unless `--no-stream`, it also streams while the agent runs (see
+//! [`NonTtyRenderer::poll`]): on the normal screen, every line once it has
+//! settled — scrolled into the scrollback, or unchanged above the cursor for
+//! `SETTLE_MS` — so spinner and progress redraws never print; on the alternate
+//! screen, where nothing scrolls, a rendered snapshot every `SNAPSHOT_SECS`
+//! when it changed. The exit render then only adds what hasn't been printed.
+//! Under `--output jsonl` the same stream goes out as `line` / `screen`
+//! records (see jsonl_output.rs).
+
+use crate::jsonl_output::{self, OutputFormat, Record};
+use crate::vterm::{VTermProxy, SCROLLBACK_ROWS};
+use std::time::{Duration, Instant};
+
+/// How long a visible row must stay unchanged before it is streamed.
+pub const SETTLE_MS: u64 = 1000;
 
-use crate::vterm::VTermProxy;
+/// Interval between alternate-screen snapshots.
+pub const SNAPSHOT_SECS: u64 = 30;
 
 /// Decide whether stdout should receive plain rendered text instead of raw
 /// PTY passthrough.
@@ -56,6 +72,33 @@ pub struct NonTtyRenderer {
     /// after the agent leaves the alt screen (which clears the live screen),
     /// so a clean exit still surfaces the conversation.
     captured_alt: Option<String>,
+    /// Mid-session streaming state; None for exit-only.
+    stream: Option<Stream>,
+}
+
+/// What streaming has seen and printed so far.
+#[derive(Default)]
+struct Stream {
+    /// Scrollback length at the last poll.
+    scrollback: usize,
+    /// Normal-screen rows at the last poll, and when each last changed.
+    rows: Vec<String>,
+    changed_at: Vec<Instant>,
+    /// The top visible rows already printed, as printed. A row that no longer
+    /// matches (the screen was cleared or redrawn) is printed again once it
+    /// settles.
+    emitted: Vec<String>,
+    /// Whether the stream was on the alternate screen at the last poll.
+    in_alt: bool,
+    /// The last alternate-screen snapshot printed, and when the next is due.
+    snapshot: Option<String>,
+    snapshot_due: Option<Instant>,
+    /// Anything printed yet / the last line printed was blank (runs of blank
+    /// rows print as one).
+    printed: bool,
+    blank: bool,
+    /// Print `--output jsonl` records instead of text.
+    jsonl: bool,
 }
 
 impl NonTtyRenderer {
@@ -63,6 +106,18 @@ impl NonTtyRenderer {
         Self::default()
     }
 
+    /// A renderer that also streams mid-session (see [`Self::poll`]), as
+    /// text or as jsonl records.
+    pub fn streaming(format: OutputFormat) -> Self {
+        Self {
+            stream: Some(Stream {
+                jsonl: format == OutputFormat::Jsonl,
+                ..Stream::default()
+            }),
+            ..Self::default()
+        }
+    }
+
     /// Call after every `vterm.process(chunk)`. Snapshots the alt-screen
     /// contents while it is active so they survive the eventual restore.
     pub fn observe(&mut self, vterm: &VTermProxy) {
@@ -89,6 +144,187 @@ impl NonTtyRenderer {
             None => trim_screen(&current),
         }
     }
+
+    /// Text that settled since the last call, to print now; empty when
+    /// exit-only. Call after `observe` on every chunk, and periodically so a
+    /// quiet screen still gets its last lines out.
+    pub fn poll(&mut self, vterm: &mut VTermProxy, now: Instant) -> String {
+        let Some(s) = self.stream.as_mut() else {
+            return String::new();
+        };
+        let mut out = String::new();
+        let alt = vterm.alternate_screen();
+        if alt != s.in_alt {
+            if alt {
+                // The normal screen is gone from view: print what was left of
+                // it, then take the first snapshot after a full interval.
+                let rest = s
+                    .rows
+                    .iter()
+                    .rposition(|r| !r.trim().is_empty())
+                    .map_or(0, |last| last + 1);
+                for i in s.emitted.len()..rest {
+                    let row = s.rows[i].clone();
+                    s.line(&mut out, &row);
+                }
+                s.emitted = s.rows[..rest.max(s.emitted.len())].to_vec();
+                s.snapshot_due = Some(now + Duration::from_secs(SNAPSHOT_SECS));
+            } else if let Some(screen) = &self.captured_alt {
+                // Back from the alternate screen: its last state, once.
+                s.snapshot(&mut out, &trim_screen(screen));
+                s.snapshot = None;
+            }
+            s.in_alt = alt;
+        }
+        if alt {
+            if s.snapshot_due.is_some_and(|due| now >= due) {
+                s.snapshot(&mut out, &trim_screen(&vterm.contents()));
+                s.snapshot_due = Some(now + Duration::from_secs(SNAPSHOT_SECS));
+            }
+            return out;
+        }
+
+        let scrollback = vterm.scrollback_len();
+        let cur = vterm.visible_rows();
+        let cursor_row = vterm.cursor_position().0 as usize;
+        let scrolled = if scrollback > s.scrollback {
+            scrollback - s.scrollback
+        } else if scrollback == SCROLLBACK_ROWS && cur != s.rows {
+            // A full scrollback stops growing: find the scroll by content.
+            scroll_shift(&s.rows, &cur, cursor_row)
+        } else {
+            0
+        };
+
+        // Rows that scrolled off before they were printed.
+        if scrolled > s.emitted.len() {
+            let from = scrollback - scrolled.min(scrollback) + s.emitted.len();
+            for row in vterm.scrollback_rows(from) {
+                s.line(&mut out, &row);
+            }
+        }
+        for rows in [&mut s.emitted, &mut s.rows] {
+            rows.drain(..scrolled.min(rows.len()));
+        }
+        s.changed_at.drain(..scrolled.min(s.changed_at.len()));
+
+        // A printed row that no longer matches: the screen was redrawn from
+        // there down.
+        if let Some(i) = s.emitted.iter().zip(&cur).position(|(e, c)| e != c) {
+            s.emitted.truncate(i);
+        }
+        s.emitted.truncate(cur.len());
+        s.changed_at.resize(cur.len(), now);
+        for (i, row) in cur.iter().enumerate() {
+            if s.rows.get(i) != Some(row) {
+                s.changed_at[i] = now;
+            }
+        }
+
+        // Settled rows above the cursor, in order; the cursor row is still
+        // being written.
+        let settle = Duration::from_millis(SETTLE_MS);
+        while s.emitted.len() < cursor_row.min(cur.len())
+            && now.saturating_duration_since(s.changed_at[s.emitted.len()]) >= settle
+        {
+            let row = cur[s.emitted.len()].clone();
+            s.line(&mut out, &row);
+            s.emitted.push(row);
+        }
+
+        s.scrollback = scrollback;
+        s.rows = cur;
+        out
+    }
+
+    /// The text to print on exit. Exit-only: [`Self::finalize`]. Streaming:
+    /// whatever hasn't been printed yet — the rest of the normal screen, or
+    /// the alternate screen if it changed since its last snapshot.
+    pub fn finish(&mut self, vterm: &mut VTermProxy, now: Instant) -> String {
+        if self.stream.is_none() {
+            return self.finalize(vterm);
+        }
+        let mut out = self.poll(vterm, now);
+        let current = vterm.contents();
+        let alt_screen = if vterm.alternate_screen() {
+            Some(current)
+        } else if current.trim().is_empty() {
+            self.captured_alt.clone()
+        } else {
+            None
+        };
+        let Some(s) = self.stream.as_mut() else {
+            return out;
+        };
+        match alt_screen {
+            Some(screen) => s.snapshot(&mut out, &trim_screen(&screen)),
+            None => {
+                let rows = vterm.visible_rows();
+                let rest = rows[s.emitted.len().min(rows.len())..].join("\n");
+                for row in trim_screen(&rest).lines() {
+                    s.line(&mut out, row);
+                }
+            }
+        }
+        out
+    }
+}
+
+impl Stream {
+    /// Print one row, dropping leading blank rows and collapsing runs of them.
+    fn line(&mut self, out: &mut String, row: &str) {
+        let row = row.trim_end();
+        if row.is_empty() {
+            if self.blank || !self.printed {
+                return;
+            }
+            self.blank = true;
+        } else {
+            self.blank = false;
+            self.printed = true;
+        }
+        if self.jsonl {
+            out.push_str(&jsonl_output::format(&Record::Line { text: row }));
+        } else {
+            out.push_str(row);
+            out.push('\n');
+        }
+    }
+
+    /// Print an alternate-screen snapshot unless it is blank or unchanged. As
+    /// text, the first thing printed goes as is; later ones get a separator
+    /// line.
+    fn snapshot(&mut self, out: &mut String, screen: &str) {
+        if screen.is_empty() || self.snapshot.as_deref() == Some(screen) {
+            return;
+        }
+        if self.jsonl {
+            out.push_str(&jsonl_output::format(&Record::Screen { text: screen }));
+        } else {
+            if self.printed {
+                if !self.blank {
+                    out.push('\n');
+                }
+                out.push_str("--- screen ---\n");
+            }
+            out.push_str(screen);
+        }
+        self.snapshot = Some(screen.to_string());
+        self.printed = true;
+        self.blank = false;
+    }
+}
+
+/// Rows the screen scrolled by between `prev` and `cur`: the smallest shift
+/// that lines the rows above the cursor up with `prev`. Only needed once the
+/// scrollback is full and its length stops telling.
+fn scroll_shift(prev: &[String], cur: &[String], cursor_row: usize) -> usize {
+    (1..prev.len())
+        .find(|&d| {
+            let n = (prev.len() - d).min(cursor_row).min(cur.len());
+            n > 0 && prev[d..d + n] == cur[..n] && cur[..n].iter().any(|r| !r.trim().is_empty())
+        })
+        .unwrap_or(0)
 }
 
 /// Trim a rendered screen to readable plain text: right-trim every line,
@@ -287,6 +523,112 @@ mod tests {
         assert!(out.contains("AGENT FINAL ANSWER"), "got: {:?}", out);
     }
 
+    fn poll(r: &mut NonTtyRenderer, v: &mut VTermProxy, seq: &[u8], now: Instant) -> String {
+        v.process(seq);
+        r.observe(v);
+        r.poll(v, now)
+    }
+
+    #[test]
+    fn test_stream_prints_settled_lines_once() {
+        let t0 = Instant::now();
+        let settled = t0 + Duration::from_millis(SETTLE_MS);
+        let mut r = NonTtyRenderer::streaming(OutputFormat::Text);
+        let mut v = VTermProxy::new(4, 40);
+
+        // Rows above the cursor wait to settle; the cursor row never does.
+        assert_eq!(poll(&mut r, &mut v, b"one\r\ntwo\r\nthr", t0), "");
+        assert_eq!(r.poll(&mut v, settled), "one\ntwo\n");
+        assert_eq!(r.poll(&mut v, settled), "");
+
+        // Scrolling past the top prints what scrolled off unprinted, at once.
+        let out = poll(&mut r, &mut v, b"ee\r\n4\r\n5\r\n6\r\n7", settled);
+        assert_eq!(out, "three\n");
+        let later = settled + Duration::from_millis(SETTLE_MS);
+        assert_eq!(r.poll(&mut v, later), "4\n5\n6\n");
+        assert_eq!(r.finish(&mut v, later), "7\n");
+    }
+
+    #[test]
+    fn test_stream_skips_spinner_redraws() {
+        let t0 = Instant::now();
+        let mut r = NonTtyRenderer::streaming(OutputFormat::Text);
+        let mut v = VTermProxy::new(6, 40);
+        poll(&mut r, &mut v, b"reading files\r\n\x1b[s", t0);
+        for (i, frame) in ["|", "/", "-", "\\"].iter().enumerate() {
+            // Spinner row above the cursor, redrawn faster than it settles.
+            let seq = format!("\x1b[2;1H{frame} working\x1b[4;1H");
+            let now = t0 + Duration::from_millis(SETTLE_MS * (i as u64 + 1) / 2);
+            let out = poll(&mut r, &mut v, seq.as_bytes(), now);
+            assert!(!out.contains("working"), "{out:?}");
+        }
+    }
+
+    #[test]
+    fn test_stream_redraw_reprints() {
+        let t0 = Instant::now();
+        let settled = t0 + Duration::from_millis(SETTLE_MS);
+        let mut r = NonTtyRenderer::streaming(OutputFormat::Text);
+        let mut v = VTermProxy::new(4, 40);
+        poll(&mut r, &mut v, b"old\r\n", t0);
+        assert_eq!(r.poll(&mut v, settled), "old\n");
+        // Screen cleared and redrawn: the new content prints once settled.
+        poll(&mut r, &mut v, b"\x1b[2J\x1b[Hnew\r\n", settled);
+        let later = settled + Duration::from_millis(SETTLE_MS);
+        assert_eq!(r.poll(&mut v, later), "new\n");
+    }
+
+    #[test]
+    fn test_stream_alt_screen_snapshots() {
+        let t0 = Instant::now();
+        let snap = Duration::from_secs(SNAPSHOT_SECS);
+        let mut r = NonTtyRenderer::streaming(OutputFormat::Text);
+        let mut v = VTermProxy::new(6, 40);
+        poll(&mut r, &mut v, b"agent-yes v1\r\n", t0);
+        // Entering the alt screen prints what was left of the normal one.
+        let out = poll(
+            &mut r,
+            &mut v,
+            b"\x1b[?1049h\x1b[2J\x1b[H\xe2\x97\x8f hello",
+            t0,
+        );
+        assert_eq!(out, "agent-yes v1\n");
+        assert_eq!(r.poll(&mut v, t0 + snap / 2), "");
+        assert_eq!(
+            r.poll(&mut v, t0 + snap),
+            "\n--- screen ---\n\u{25cf} hello\n"
+        );
+        // Unchanged: nothing at the next interval.
+        assert_eq!(r.poll(&mut v, t0 + snap * 2), "");
+        let out = poll(&mut r, &mut v, b"\r\n\xe2\x97\x8f done", t0 + snap * 3);
+        assert_eq!(out, "\n--- screen ---\n\u{25cf} hello\n\u{25cf} done\n");
+        // Exit restores the normal screen; the last alt state was printed.
+        v.process(b"\x1b[?1049l");
+        r.observe(&v);
+        assert_eq!(r.finish(&mut v, t0 + snap * 3), "");
+    }
+
+    #[test]
+    fn test_stream_jsonl_records() {
+        let t0 = Instant::now();
+        let mut r = NonTtyRenderer::streaming(OutputFormat::Jsonl);
+        let mut v = VTermProxy::new(4, 40);
+        poll(&mut r, &mut v, b"one\r\n", t0);
+        let out = r.poll(&mut v, t0 + Duration::from_millis(SETTLE_MS));
+        let v: serde_json::Value = serde_json::from_str(out.trim_end()).unwrap();
+        assert_eq!(v["type"], "line");
+        assert_eq!(v["text"], "one");
+    }
+
+    #[test]
+    fn test_finish_exit_only_is_finalize() {
+        let mut r = NonTtyRenderer::new();
+        let mut v = vt(b"Hello from codex\r\n");
+        r.observe(&v);
+        assert_eq!(r.poll(&mut v, Instant::now()), "");
+        assert_eq!(r.finish(&mut v, Instant::now()), "Hello from codex\n");
+    }
+
     #[test]
     fn test_finalize_no_ansi_in_output() {
         let mut r = NonTtyRenderer::new();
@@ -0,0 +1,221 @@
+//! Typing injected multi-line text into the CLI as one prompt.
+//!
+//! Writing a multi-line message straight into the PTY reads to the CLI like
+//! someone typing it: every newline is an Enter, so the first line submits on
+//! its own and the rest trails in as further prompts. A terminal paste doesn't
+//! have that problem once the CLI has turned on bracketed paste (DECSET 2004):
+//! the text arrives between `ESC[200~` and `ESC[201~` and the CLI takes it as
+//! one block. So injected text is wrapped the same way whenever the child has
+//! the mode on (tracked by `VTermProxy::bracketed_paste`), and the submitting
+//! Enter is sent separately afterwards.
+//!
+//! A CLI without bracketed paste gets its `pasteFallback` instead:
+//!
+//! - `join` (default): line breaks become spaces
+//! - `backslash`: each line break is typed as `\` + Enter, the continuation
+//!   most line editors (claude, shells) understand
+//! - `raw`: written as-is, every line break an Enter (shells running a script)
+//!
+//! Single-line text is always typed unchanged.
+//!
+//! `ay send` marks a multi-line body with an APC frame — see
+//! `fifo::PASTE_FRAME_START` — which `PasteFrameScanner` replaces in the
+//! input stream with the encoded text.
+
+use crate::fifo::{PASTE_FRAME_START, QUEUE_FRAME_END};
+use crate::prompt_queue::{marker_tail, MAX_FRAME_BYTES};
+use anyhow::{anyhow, Result};
+
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PasteFallback {
+    #[default]
+    Join,
+    Backslash,
+    Raw,
+}
+
+pub fn compile_paste_fallback(raw: Option<String>) -> Result<PasteFallback> {
+    match raw.as_deref() {
+        None | Some("join") => Ok(PasteFallback::Join),
+        Some("backslash") => Ok(PasteFallback::Backslash),
+        Some("raw") => Ok(PasteFallback::Raw),
+        Some(other) => Err(anyhow!(
+            "Unknown pasteFallback '{}' (expected join, backslash or raw)",
+            other
+        )),
+    }
+}
+
+pub fn is_multiline(text: &str) -> bool {
+    text.trim_end_matches(['\r', '\n']).contains(['\r', '\n'])
+}
+
+/// The bytes that make `text` arrive as one prompt: bracketed when the CLI
+/// has bracketed paste on, else per `fallback`.
+pub fn encode(text: &str, bracketed: bool, fallback: PasteFallback) -> String {
+    if !is_multiline(text) || (!bracketed && fallback == PasteFallback::Raw) {
+        return text.to_string();
+    }
+    // Control characters (ESC above all — `ESC[201~` would end the paste
+    // early) have no business in a prompt; line breaks are normalized to \n.
+    let text: String = text
+        .trim_end_matches(['\r', '\n'])
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .filter(|&c| c == '\n' || c == '\t' || !c.is_control())
+        .collect();
+    if bracketed {
+        // A terminal pastes line breaks as CR.
+        return format!("{}{}{}", PASTE_START, text.replace('\n', "\r"), PASTE_END);
+    }
+    match fallback {
+        PasteFallback::Join => text
+            .split('\n')
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join(" "),
+        PasteFallback::Backslash => text.replace('\n', "\\\r"),
+        PasteFallback::Raw => text,
+    }
+}
+
+/// Replaces paste frames in the FIFO byte stream with the encoded text. Like
+/// `QueueFrameScanner`, an unfinished frame (or start marker) is held until
+/// the rest arrives in a later read, or handed back by `take_held`.
+#[derive(Debug, Default)]
+pub struct PasteFrameScanner {
+    partial: Option<Vec<u8>>,
+    tail: Vec<u8>,
+}
+
+impl PasteFrameScanner {
+    pub fn feed(&mut self, data: &[u8], bracketed: bool, fallback: PasteFallback) -> Vec<u8> {
+        let start = PASTE_FRAME_START.as_bytes();
+        let end = QUEUE_FRAME_END.as_bytes();
+        let mut out = Vec::new();
+        let mut rest = std::mem::take(&mut self.tail);
+        rest.extend_from_slice(data);
+        loop {
+            if let Some(mut buf) = self.partial.take() {
+                buf.append(&mut rest);
+                let Some(at) = find(&buf, end) else {
+                    if buf.len() > MAX_FRAME_BYTES {
+                        out.extend_from_slice(start);
+                        out.append(&mut buf);
+                    } else {
+                        self.partial = Some(buf);
+                    }
+                    break;
+                };
+                rest = buf.split_off(at + end.len());
+                buf.truncate(at);
+                let text = String::from_utf8_lossy(&buf);
+                out.extend_from_slice(encode(&text, bracketed, fallback).as_bytes());
+                continue;
+            }
+            match find(&rest, start) {
+                Some(at) => {
+                    out.extend_from_slice(&rest[..at]);
+                    rest.drain(..at + start.len());
+                    self.partial = Some(Vec::new());
+                }
+                None => {
+                    self.tail = rest.split_off(marker_tail(&rest, start));
+                    out.append(&mut rest);
+                    break;
+                }
+            }
+        }
+        out
+    }
+
+    /// What is held back, as plain input.
+    pub fn take_held(&mut self) -> Vec<u8> {
+        match self.partial.take() {
+            Some(buf) => [PASTE_FRAME_START.as_bytes(), &buf].concat(),
+            None => std::mem::take(&mut self.tail),
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_brackets_multiline_text() {
+        assert_eq!(
+            encode("fix it\nthen test\n", true, PasteFallback::Join),
+            "\x1b[200~fix it\rthen test\x1b[201~"
+        );
+        // Nothing inside can close the bracket early.
+        assert_eq!(
+            encode("a\x1b[201~\r\nb", true, PasteFallback::Join),
+            "\x1b[200~a[201~\rb\x1b[201~"
+        );
+        // Single-line text is typed as-is.
+        assert_eq!(encode("/compact", true, PasteFallback::Join), "/compact");
+        assert_eq!(encode("done\n", true, PasteFallback::Join), "done\n");
+    }
+
+    #[test]
+    fn test_encode_fallbacks() {
+        let text = "first line\n\n  second line\n";
+        assert_eq!(
+            encode(text, false, PasteFallback::Join),
+            "first line second line"
+        );
+        assert_eq!(
+            encode(text, false, PasteFallback::Backslash),
+            "first line\\\r\\\r  second line"
+        );
+        assert_eq!(encode(text, false, PasteFallback::Raw), text);
+        assert_eq!(
+            compile_paste_fallback(Some("backslash".into())).unwrap(),
+            PasteFallback::Backslash
+        );
+        assert_eq!(compile_paste_fallback(None).unwrap(), PasteFallback::Join);
+        assert!(compile_paste_fallback(Some("typed".into())).is_err());
+    }
+
+    #[test]
+    fn test_scanner_replaces_frames_across_reads() {
+        let mut s = PasteFrameScanner::default();
+        let frame = format!("{PASTE_FRAME_START}one\ntwo{QUEUE_FRAME_END}");
+        let (a, b) = frame.as_bytes().split_at(15);
+        let mut out = s.feed(b"x", true, PasteFallback::Join);
+        out.extend(s.feed(a, true, PasteFallback::Join));
+        assert_eq!(out, b"x");
+        out.extend(s.feed(&[b, b"\r"].concat(), true, PasteFallback::Join));
+        assert_eq!(out, b"x\x1b[200~one\rtwo\x1b[201~\r");
+        assert_eq!(
+            s.feed(frame.as_bytes(), false, PasteFallback::Join),
+            b"one two"
+        );
+
+        // Split inside the start marker, too.
+        for split in 1..PASTE_FRAME_START.len() {
+            let (a, b) = frame.as_bytes().split_at(split);
+            let mut out = s.feed(a, false, PasteFallback::Join);
+            assert!(out.is_empty(), "split {split}");
+            out.extend(s.feed(b, false, PasteFallback::Join));
+            assert_eq!(out, b"one two", "split {split}");
+        }
+
+        // A frame that never ends comes back as it was sent.
+        assert!(s
+            .feed(b"\x1b_ay-paste;half", true, PasteFallback::Join)
+            .is_empty());
+        assert_eq!(s.take_held(), b"\x1b_ay-paste;half");
+        assert!(s.take_held().is_empty());
+    }
+}
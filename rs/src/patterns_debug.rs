@@ -0,0 +1,633 @@
+//! `ay patterns debug <log|cast>` — explain which rule fired on a saved session.
+//!
+//! Native to the Rust runner because it owns the terminal emulator and the
+//! pattern engine: the log is rendered chunk by chunk through the same
+//! `VTermProxy`, and every rendered screen is checked against the same
+//! cascaded `CliConfig` the runtime compiles (`get_runtime_cli_config`, i.e.
+//! the builtin config merged with `load_cascading_config`), in the same order
+//! `AgentContext::check_patterns` uses.
+//!
+//! The timeline shows state patterns (ready, working, needsInput, autoRetry,
+//! enterExclude) as they start/stop matching, one-shot reactions (action
+//! rules, typingRespond, enter, fatal) each time they would fire, and why a
+//! matching one-shot did NOT fire — excluded by `enterExclude`, or swallowed by
+//! the screen-hash de-dupe because the chunk left the screen unchanged.
+//!
+//! A raw log has no chunk boundaries or timing, so it is fed one line at a
+//! time; a `.cast` recording (see recording.rs) replays its real chunks, with
+//! timestamps and the input that was typed.
+
+use crate::approval_policy::Verdict;
+use crate::config::CliConfig;
+use crate::recording::{self, CastKind};
+use crate::vterm::VTermProxy;
+use anyhow::{Context, Result};
+use clap::Parser;
+use regex::Regex;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+/// Longest slice of a raw log fed to the emulator in one go when no newline
+/// turns up (a full-screen TUI repaint can run for kilobytes without one).
+const RAW_CHUNK_MAX: usize = 4096;
+const LINE_PREVIEW_CHARS: usize = 80;
+
+#[derive(Parser, Debug)]
+#[command(name = "ay patterns debug")]
+#[command(about = "Replay a raw log or .cast recording and explain which patterns matched")]
+struct DebugArgs {
+    /// `.agent-yes/<pid>.raw.log`, a rendered `<pid>.log`, or a `.cast` recording
+    file: PathBuf,
+
+    /// CLI whose patterns to use (default: the recording's command, the pid's
+    /// registry entry, then claude)
+    #[arg(long)]
+    cli: Option<String>,
+
+    /// Terminal width for rendering a raw log (default: this terminal's)
+    #[arg(long)]
+    cols: Option<u16>,
+
+    /// Terminal height for rendering a raw log (default: this terminal's)
+    #[arg(long)]
+    rows: Option<u16>,
+}
+
+/// Run `ay patterns …` natively when argv asks for it. Returns the exit code,
+/// or None when this isn't a `patterns` invocation.
+pub fn maybe_run_subcommand() -> Option<i32> {
+    let raw: Vec<String> = std::env::args().collect();
+    if raw.get(1).map(String::as_str) != Some("patterns") {
+        return None;
+    }
+    if raw.get(2).map(String::as_str) != Some("debug") {
+        eprintln!("usage: ay patterns debug <file> [--cli <name>] [--cols N] [--rows N]");
+        return Some(2);
+    }
+    let args = DebugArgs::parse_from(
+        std::iter::once("ay patterns debug".to_string()).chain(raw[3..].iter().cloned()),
+    );
+    match run(args) {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("ay patterns debug: {:#}", e);
+            Some(1)
+        }
+    }
+}
+
+fn run(args: DebugArgs) -> Result<()> {
+    let bytes =
+        std::fs::read(&args.file).with_context(|| format!("Failed to read {:?}", args.file))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let cast = if looks_like_cast(&text) {
+        Some(recording::parse_cast(&text).with_context(|| format!("Invalid {:?}", args.file))?)
+    } else {
+        None
+    };
+
+    let cli = args
+        .cli
+        .or_else(|| cast.as_ref().and_then(|c| c.command.clone()))
+        .or_else(|| cli_from_registry(&args.file))
+        .unwrap_or_else(|| "claude".to_string());
+    let cli_config = crate::config::get_runtime_cli_config(&cli)?;
+
+    let (term_cols, term_rows) = crate::pty_spawner::get_terminal_size();
+    let (cols, rows) = match &cast {
+        Some(c) => (c.width, c.height),
+        None => (
+            args.cols.unwrap_or(term_cols),
+            args.rows.unwrap_or(term_rows),
+        ),
+    };
+    let chunks = match &cast {
+        Some(c) => cast_chunks(c),
+        None => raw_chunks(&text),
+    };
+
+    println!("{} — cli {}, {}x{}", args.file.display(), cli, cols, rows);
+    let sources: Vec<String> = crate::config_loader::get_config_paths()
+        .into_iter()
+        .filter(|p| p.exists())
+        .map(|p| p.display().to_string())
+        .collect();
+    if sources.is_empty() {
+        println!("config: builtin only");
+    } else {
+        println!("config: builtin + {}", sources.join(", "));
+    }
+    // The approval policy's outsideCwd check needs the agent's cwd: a log
+    // under `<cwd>/.agent-yes/` names it, anything else is read from here.
+    let cwd = args
+        .file
+        .parent()
+        .filter(|d| d.file_name().is_some_and(|n| n == ".agent-yes"))
+        .and_then(|d| d.parent())
+        .map(PathBuf::from)
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default();
+    for entry in explain(&cli_config, &chunks, rows, cols, &cwd.to_string_lossy()) {
+        println!("{}", entry);
+    }
+    Ok(())
+}
+
+fn looks_like_cast(text: &str) -> bool {
+    let first = text.lines().next().unwrap_or("").trim_start();
+    first.starts_with('{') && first.contains("\"version\"")
+}
+
+/// `<pid>.raw.log` / `<pid>.log` → that pid's CLI, if the registry still has it.
+fn cli_from_registry(path: &std::path::Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let pid: u32 = name.split('.').next()?.parse().ok()?;
+    crate::pid_store::PidStore::new()
+        .read_all()
+        .ok()?
+        .into_iter()
+        .find(|r| r.pid == pid)
+        .map(|r| r.cli)
+}
+
+/// One unit of input to the timeline: what the emulator is fed before the
+/// next pattern check, or a line the recording says was typed.
+#[derive(Debug, Clone)]
+struct Chunk {
+    /// Byte offset in the file just past this chunk (raw logs), or the event
+    /// index (recordings).
+    offset: usize,
+    time: Option<f64>,
+    kind: CastKind,
+    data: String,
+}
+
+fn raw_chunks(text: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let window_end = floor_char_boundary(text, (start + RAW_CHUNK_MAX).min(text.len()));
+        let end = text[start..window_end]
+            .find('\n')
+            .map(|i| start + i + 1)
+            .unwrap_or(window_end);
+        chunks.push(Chunk {
+            offset: end,
+            time: None,
+            kind: CastKind::Output,
+            data: text[start..end].to_string(),
+        });
+        start = end;
+    }
+    chunks
+}
+
+fn cast_chunks(cast: &recording::Cast) -> Vec<Chunk> {
+    cast.events
+        .iter()
+        .enumerate()
+        .map(|(i, e)| Chunk {
+            offset: i + 1,
+            time: Some(e.time),
+            kind: e.kind,
+            data: e.data.clone(),
+        })
+        .collect()
+}
+
+fn floor_char_boundary(s: &str, mut at: usize) -> usize {
+    while at > 0 && !s.is_char_boundary(at) {
+        at -= 1;
+    }
+    at
+}
+
+#[derive(Debug, PartialEq)]
+enum EntryKind {
+    /// A state pattern began matching.
+    Start,
+    /// A state pattern stopped matching.
+    Stop,
+    /// A one-shot reaction would fire; the payload says what it does.
+    Fire(String),
+    /// A one-shot matched but did not fire; the payload says why.
+    Suppressed(String),
+    /// Bytes the recording says were typed.
+    Typed(String),
+}
+
+#[derive(Debug)]
+struct Entry {
+    offset: usize,
+    time: Option<f64>,
+    kind: EntryKind,
+    category: &'static str,
+    pattern: String,
+    /// 1-based screen line of the match, and its text.
+    line: Option<(usize, String)>,
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let at = match self.time {
+            Some(t) => format!("{:>9.3}s", t),
+            None => format!("@{:>9}", self.offset),
+        };
+        let mark = match &self.kind {
+            EntryKind::Start => "+",
+            EntryKind::Stop => "-",
+            EntryKind::Fire(_) => "!",
+            EntryKind::Suppressed(_) => "x",
+            EntryKind::Typed(_) => "<",
+        };
+        write!(f, "{}  {} {:<14}", at, mark, self.category)?;
+        if !self.pattern.is_empty() {
+            write!(f, " /{}/", self.pattern)?;
+        }
+        if let Some((line, text)) = &self.line {
+            write!(f, "  L{} {:?}", line, text)?;
+        }
+        match &self.kind {
+            EntryKind::Fire(what) => write!(f, "  → {}", what),
+            EntryKind::Suppressed(why) => write!(f, "  suppressed: {}", why),
+            EntryKind::Typed(data) => write!(f, " {:?}", data),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Where `rx` first matches `screen`: 1-based line number and that line's text.
+fn locate(rx: &Regex, screen: &str) -> Option<(usize, String)> {
+    let m = rx.find(screen)?;
+    let line_no = screen[..m.start()].matches('\n').count() + 1;
+    let text: String = screen
+        .lines()
+        .nth(line_no - 1)
+        .unwrap_or("")
+        .trim()
+        .chars()
+        .take(LINE_PREVIEW_CHARS)
+        .collect();
+    Some((line_no, text))
+}
+
+fn first_match<'a>(patterns: &'a [Regex], screen: &str) -> Option<(&'a Regex, (usize, String))> {
+    patterns
+        .iter()
+        .find_map(|rx| locate(rx, screen).map(|loc| (rx, loc)))
+}
+
+fn screen_hash(s: &str) -> u64 {
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    s.hash(&mut h);
+    h.finish()
+}
+
+/// Walk `chunks` through a `rows`x`cols` emulator and build the timeline.
+/// Auto-yes is assumed on; the runtime's timers (idle-gated Enter, retries,
+/// backoff) are out of scope — `--replay` covers those. `cwd` feeds the
+/// approval policy's outside-the-cwd check.
+fn explain(config: &CliConfig, chunks: &[Chunk], rows: u16, cols: u16, cwd: &str) -> Vec<Entry> {
+    let mut vterm = VTermProxy::new(rows, cols);
+    let states: [(&'static str, &[Regex]); 5] = [
+        ("ready", &config.ready),
+        ("working", &config.working),
+        ("needsInput", &config.needs_input),
+        ("autoRetry", &config.auto_retry),
+        ("enterExclude", &config.enter_exclude),
+    ];
+    let mut typing: Vec<(&String, &Vec<Regex>)> = config.typing_respond.iter().collect();
+    typing.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut out = Vec::new();
+    let mut active: HashSet<(&'static str, String)> = HashSet::new();
+    let mut last_hash: Option<u64> = None;
+    let mut dedupe_reported = false;
+    let mut fatal = false;
+    // Pattern of the Enter scheduled on an earlier screen. While the same
+    // prompt keeps repainting it is one pending Enter (the runtime's
+    // `pending_enter`), not a fresh one per screen.
+    let mut enter_pending: Option<String> = None;
+
+    for chunk in chunks {
+        let entry = |kind, category, pattern: String, line| Entry {
+            offset: chunk.offset,
+            time: chunk.time,
+            kind,
+            category,
+            pattern,
+            line,
+        };
+        match chunk.kind {
+            CastKind::Input => {
+                out.push(entry(
+                    EntryKind::Typed(chunk.data.clone()),
+                    "input",
+                    String::new(),
+                    None,
+                ));
+                continue;
+            }
+            CastKind::Resize => {
+                if let Some((c, r)) = recording::parse_resize(&chunk.data) {
+                    vterm.resize(r, c);
+                }
+                continue;
+            }
+            CastKind::Output => vterm.process(chunk.data.as_bytes()),
+        }
+        let screen = vterm.contents();
+        let hash = screen_hash(&screen);
+
+        // One-shot reactions that match the current screen, in runtime order.
+        let action = config.actions.iter().find(|r| r.matches(&screen));
+        let typed = typing
+            .iter()
+            .find_map(|(resp, pats)| first_match(pats, &screen).map(|m| (*resp, m)));
+        let enter = first_match(&config.enter, &screen);
+
+        if last_hash == Some(hash) {
+            // check_patterns short-circuits an unchanged screen, so whatever
+            // one-shot is still on it does not fire again. Report once per run.
+            if !dedupe_reported {
+                let what = action
+                    .map(|r| ("action", r.name.clone(), None))
+                    .or_else(|| {
+                        typed.as_ref().map(|(_, (rx, loc))| {
+                            ("typingRespond", rx.to_string(), Some(loc.clone()))
+                        })
+                    })
+                    .or_else(|| {
+                        enter
+                            .as_ref()
+                            .map(|(rx, loc)| ("enter", rx.to_string(), Some(loc.clone())))
+                    });
+                if let Some((category, pattern, line)) = what {
+                    out.push(entry(
+                        EntryKind::Suppressed("screen unchanged (screen-hash de-dupe)".into()),
+                        category,
+                        pattern,
+                        line,
+                    ));
+                    dedupe_reported = true;
+                }
+            }
+            continue;
+        }
+        last_hash = Some(hash);
+        dedupe_reported = false;
+
+        for (category, patterns) in states {
+            for rx in patterns {
+                let key = (category, rx.to_string());
+                match (locate(rx, &screen), active.contains(&key)) {
+                    (Some(loc), false) => {
+                        out.push(entry(EntryKind::Start, category, key.1.clone(), Some(loc)));
+                        active.insert(key);
+                    }
+                    (None, true) => {
+                        out.push(entry(EntryKind::Stop, category, key.1.clone(), None));
+                        active.remove(&key);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if !fatal {
+            if let Some((rx, loc)) = first_match(&config.fatal, &screen) {
+                out.push(entry(
+                    EntryKind::Fire("session ends (fatal)".into()),
+                    "fatal",
+                    rx.to_string(),
+                    Some(loc),
+                ));
+                fatal = true;
+                continue;
+            }
+        }
+
+        if let Some(rule) = action {
+            out.push(entry(
+                EntryKind::Fire(format!("runs {} step(s)", rule.steps.len())),
+                "action",
+                rule.name.clone(),
+                None,
+            ));
+        } else if let Some((response, (rx, loc))) = typed {
+            out.push(entry(
+                EntryKind::Fire(format!("types {:?}", response)),
+                "typingRespond",
+                rx.to_string(),
+                Some(loc),
+            ));
+        } else if let Some((rx, loc)) = enter {
+            let pattern = rx.to_string();
+            // A permission prompt goes through the approval policy first; a
+            // new proposal under the same prompt pattern is a new decision.
+            let decision = config.approval.decide(&screen, cwd);
+            let key = match &decision {
+                Some(d) => format!("{}\n{}", pattern, d.proposal.subject),
+                None => pattern.clone(),
+            };
+            match first_match(&config.enter_exclude, &screen) {
+                Some((ex, _)) => {
+                    out.push(entry(
+                        EntryKind::Suppressed(format!("enterExclude /{}/", ex)),
+                        "enter",
+                        pattern,
+                        Some(loc),
+                    ));
+                    enter_pending = None;
+                }
+                None if enter_pending.as_deref() == Some(key.as_str()) => {}
+                None => {
+                    let kind = match decision {
+                        None => EntryKind::Fire("Enter (after idle)".into()),
+                        Some(d) => {
+                            let why = format!(
+                                "approval {} by {} on {:?}",
+                                d.verdict.as_str(),
+                                d.rule,
+                                d.proposal.subject
+                            );
+                            match d.verdict {
+                                Verdict::Allow => {
+                                    EntryKind::Fire(format!("Enter (after idle), {}", why))
+                                }
+                                Verdict::Deny => EntryKind::Fire(format!(
+                                    "{:?} (No), {}",
+                                    config.approval.deny_keys, why
+                                )),
+                                Verdict::Ask => EntryKind::Suppressed(why),
+                            }
+                        }
+                    };
+                    out.push(entry(kind, "enter", pattern, Some(loc)));
+                    enter_pending = Some(key);
+                }
+            }
+            continue;
+        }
+        enter_pending = None;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_loader::CliConfigOverride;
+
+    fn config_from_yaml(yaml: &str) -> CliConfig {
+        let raw: CliConfigOverride = serde_yaml::from_str(yaml).unwrap();
+        crate::config::build_cli_config(raw).unwrap()
+    }
+
+    fn kinds(entries: &[Entry]) -> Vec<(&'static str, &EntryKind)> {
+        entries.iter().map(|e| (e.category, &e.kind)).collect()
+    }
+
+    #[test]
+    fn test_raw_chunks_split_on_lines_and_cap() {
+        let text = format!("a\nbb\n{}", "x".repeat(RAW_CHUNK_MAX + 10));
+        let chunks = raw_chunks(&text);
+        assert_eq!(chunks[0].data, "a\n");
+        assert_eq!(chunks[1].data, "bb\n");
+        assert_eq!(chunks[1].offset, 5);
+        assert_eq!(chunks[2].data.len(), RAW_CHUNK_MAX);
+        assert_eq!(chunks.last().unwrap().offset, text.len());
+    }
+
+    #[test]
+    fn test_explain_reports_fire_exclude_and_dedupe() {
+        let config = config_from_yaml(
+            r#"
+ready: ['^> ']
+enter: ['❯ 1\. Yes']
+enterExclude: ['dangerous']
+"#,
+        );
+        let out = |data: &str| Chunk {
+            offset: 0,
+            time: None,
+            kind: CastKind::Output,
+            data: data.into(),
+        };
+        let chunks = vec![
+            out("> \r\n"),
+            out("❯ 1. Yes\r\n"),
+            // Prompt still up on a changed screen: the same pending Enter.
+            out("  2. No\r\n"),
+            // Cursor-only repaint: same screen → de-duped.
+            out("\x1b[1;1H"),
+            out("dangerous\r\n"),
+        ];
+        let entries = explain(&config, &chunks, 10, 40, "");
+        assert_eq!(
+            kinds(&entries),
+            vec![
+                ("ready", &EntryKind::Start),
+                ("enter", &EntryKind::Fire("Enter (after idle)".into())),
+                (
+                    "enter",
+                    &EntryKind::Suppressed("screen unchanged (screen-hash de-dupe)".into())
+                ),
+                // "dangerous" lands on line 1 (the cursor was homed), over "> ".
+                ("ready", &EntryKind::Stop),
+                ("enterExclude", &EntryKind::Start),
+                (
+                    "enter",
+                    &EntryKind::Suppressed("enterExclude /dangerous/".into())
+                ),
+            ]
+        );
+        // The enter match is located on the screen line that carries it.
+        assert_eq!(entries[1].line, Some((2, "❯ 1. Yes".into())));
+        assert!(entries[1].to_string().contains("L2 \"❯ 1. Yes\""));
+    }
+
+    #[test]
+    fn test_explain_typing_respond_wins_over_enter_and_fatal_stops() {
+        let config = config_from_yaml(
+            r#"
+enter: ['API key']
+typingRespond:
+  "1\n": ['use this API key\?']
+fatal: ['not logged in']
+"#,
+        );
+        let chunks = vec![
+            Chunk {
+                offset: 30,
+                time: Some(0.5),
+                kind: CastKind::Output,
+                data: "Do you want to use this API key?\r\n".into(),
+            },
+            Chunk {
+                offset: 31,
+                time: Some(0.6),
+                kind: CastKind::Input,
+                data: "1\n".into(),
+            },
+            Chunk {
+                offset: 60,
+                time: Some(1.0),
+                kind: CastKind::Output,
+                data: "not logged in\r\n".into(),
+            },
+        ];
+        let entries = explain(&config, &chunks, 10, 60, "");
+        assert_eq!(
+            kinds(&entries),
+            vec![
+                ("typingRespond", &EntryKind::Fire("types \"1\\n\"".into())),
+                ("input", &EntryKind::Typed("1\n".into())),
+                ("fatal", &EntryKind::Fire("session ends (fatal)".into())),
+            ]
+        );
+        assert!(entries[0].to_string().starts_with("    0.500s  !"));
+    }
+
+    #[test]
+    fn test_explain_shows_approval_decisions() {
+        let config = config_from_yaml(
+            r#"
+enter: ['❯ 1\. Yes']
+approval:
+  command: ['RUN: (.+)']
+  rules:
+    - { name: no-force-push, command: 'push --force', verdict: deny }
+  default: ask
+"#,
+        );
+        let out = |data: &str| Chunk {
+            offset: 0,
+            time: None,
+            kind: CastKind::Output,
+            data: data.into(),
+        };
+        let chunks = vec![
+            out("RUN: git push --force\r\n❯ 1. Yes\r\n"),
+            out("\x1b[2J\x1b[HRUN: make\r\n❯ 1. Yes\r\n"),
+        ];
+        let entries = explain(&config, &chunks, 10, 40, "/repo");
+        assert_eq!(
+            kinds(&entries),
+            vec![
+                (
+                    "enter",
+                    &EntryKind::Fire(
+                        "\"\\u{1b}\" (No), approval deny by no-force-push on \"git push --force\""
+                            .into()
+                    )
+                ),
+                (
+                    "enter",
+                    &EntryKind::Suppressed("approval ask by default on \"make\"".into())
+                ),
+            ]
+        );
+    }
+}
@@ -1,6 +1,7 @@
 //! JSONL-based process registry — tracks running agent-yes processes
 
 use crate::agent_permissions::AgentPermissions;
+use crate::usage::Usage;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -34,7 +35,7 @@ pub struct PidRecord {
     /// (Windows, or a build that disables it).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub fifo_file: Option<String>,
-    pub status: String, // "active" | "idle" | "exited"
+    pub status: String, // "active" | "idle" | "completed" | "exited" | "crashloop"
     /// True when the agent was sent stdin but produced no PTY output within
     /// its configured liveness window — i.e. it looks stuck. Orthogonal to
     /// `status` (which stays "active"); cleared on recovery and on exit.
@@ -52,6 +53,11 @@ pub struct PidRecord {
     /// child.parent_pid == parent.wrapper_pid.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub parent_pid: Option<u32>,
+    /// The agent this one took over from on a usage-limit failover (its
+    /// `agent_id`, inherited via AGENT_YES_HANDOFF_FROM). None otherwise. See
+    /// failover.rs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff_from: Option<String>,
     /// Stable identifier minted once at registration, so a share grant or an
     /// `ay <cmd> <id>` can reference this agent without depending on its
     /// ephemeral pid. Currently per-process; cross-restart re-binding is a
@@ -65,12 +71,61 @@ pub struct PidRecord {
     /// on rewrite) so compaction preserves it.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    /// The last desktop notification (OSC 9 / OSC 777) the child CLI raised,
+    /// until it starts working again — its own "needs attention" signal,
+    /// shown as the `notify` badge. Mirrors the TS `notification`. See
+    /// title_scanner.rs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification: Option<String>,
     /// The permission posture this agent was SPAWNED with (yolo flag + the
     /// wrapper's robust/auto-continue flags). Stamped at registration because
     /// neither the CLI argv nor the wrapper flags survive into the index
     /// otherwise. Mirrors the TS `permissions`. See agent_permissions.rs.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub permissions: Option<AgentPermissions>,
+    /// Tokens (and cost, when the CLI shows it) this agent has used so far,
+    /// across `--robust` restarts — the running total of its usage ledger, so
+    /// `ay ls --json` answers "who burned the quota". Mirrors the TS `usage`.
+    /// See usage.rs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Unix ms this agent is sleeping until: a usage-limit banner named its
+    /// reset time, so auto-retry waits for it instead of backing off blindly.
+    /// Orthogonal to `status`; cleared when the wait ends and on exit. Mirrors
+    /// the TS `waiting_for_quota`. See reset_time.rs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waiting_for_quota: Option<u64>,
+    /// Prompt-queue progress — `sent` of `total` prompts fed so far — so
+    /// `ay ls` shows "3 of 7". None when the agent has no queue. Mirrors the TS
+    /// `queue`. See prompt_queue.rs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueProgress>,
+    /// Every `--robust` restart so far, oldest first — when, what the CLI
+    /// exited with, how the restart policy classed it and how long it waited.
+    /// Outlives re-registration on restart. Mirrors the TS `restarts`. See
+    /// restart_policy.rs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub restarts: Vec<RestartEntry>,
+    /// The FIFO frames (`fifo::FRAMES_VERSION`) this agent's wrapper takes
+    /// out of its input. None for the TS wrapper and older Rust ones, which
+    /// get unframed writes. Mirrors the TS `frames`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestartEntry {
+    pub at: i64, // unix ms
+    pub exit_code: i32,
+    pub exit_reason: String,
+    pub class: String, // "retryable" | "fatal" | "clean"
+    pub delay_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueProgress {
+    pub sent: usize,
+    pub total: usize,
 }
 
 /// The agent id for this process: adopt a caller-injected `AGENT_YES_AGENT_ID`
@@ -163,9 +218,18 @@ impl PidStore {
             parent_pid: std::env::var("AGENT_YES_PID")
                 .ok()
                 .and_then(|s| s.parse::<u32>().ok()),
+            handoff_from: std::env::var("AGENT_YES_HANDOFF_FROM")
+                .ok()
+                .filter(|s| !s.is_empty()),
             agent_id: Some(new_agent_id()),
             title: None,
+            notification: None,
             permissions,
+            usage: None,
+            waiting_for_quota: None,
+            queue: None,
+            restarts: Vec::new(),
+            frames: Some(crate::fifo::FRAMES_VERSION),
         };
         // Hold the cross-runtime lock across the append so a concurrent rewrite
         // (another wrapper's clean_stale / a status update) can't clobber it.
@@ -195,8 +259,9 @@ impl PidStore {
                     r.exit_reason = exit_reason.map(|s| s.to_string());
                     // A terminal status makes liveness moot — never leave a dead
                     // record flagged unresponsive.
-                    if status == "exited" {
+                    if status == "exited" || status == "crashloop" {
                         r.unresponsive = false;
+                        r.waiting_for_quota = None;
                     }
                     // Only repoint the log when given one (raw -> rendered on
                     // clean exit); otherwise keep the raw path recorded at start.
@@ -238,6 +303,102 @@ impl PidStore {
         }
     }
 
+    /// Set (or clear, with None) the agent's pending notification.
+    pub fn set_notification(&self, pid: u32, text: Option<&str>) {
+        let _lock = acquire_lock(&self.path);
+        let result = (|| -> Result<()> {
+            let mut records = self.read_all()?;
+            let mut changed = false;
+            for r in &mut records {
+                if r.pid == pid && r.notification.as_deref() != text {
+                    r.notification = text.map(String::from);
+                    changed = true;
+                }
+            }
+            if changed {
+                self.write_all(&records)?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!("PidStore: failed to update notification: {}", e);
+        }
+    }
+
+    /// Publish the agent's running usage total. Called once per booked turn
+    /// (see usage.rs), so it needs no throttle of its own.
+    pub fn update_usage(&self, pid: u32, usage: Usage) {
+        let _lock = acquire_lock(&self.path);
+        let result = (|| -> Result<()> {
+            let mut records = self.read_all()?;
+            let mut changed = false;
+            for r in &mut records {
+                if r.pid == pid && r.usage != Some(usage) {
+                    r.usage = Some(usage);
+                    changed = true;
+                }
+            }
+            if changed {
+                self.write_all(&records)?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!("PidStore: failed to update usage: {}", e);
+        }
+    }
+
+    /// Publish the agent's prompt-queue progress (on each pop/push and on
+    /// registration). Writes only on a change.
+    pub fn update_queue(&self, pid: u32, queue: Option<QueueProgress>) {
+        let _lock = acquire_lock(&self.path);
+        let result = (|| -> Result<()> {
+            let mut records = self.read_all()?;
+            let mut changed = false;
+            for r in &mut records {
+                if r.pid == pid && r.queue != queue {
+                    r.queue = queue;
+                    changed = true;
+                }
+            }
+            if changed {
+                self.write_all(&records)?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!("PidStore: failed to update queue: {}", e);
+        }
+    }
+
+    /// Move a live agent between `active` and `completed` (its task is done and
+    /// it sits at its prompt). Never touches an `exited` (or `crashloop`)
+    /// record, and rewrites only on a change.
+    pub fn set_live_status(&self, pid: u32, status: &str) {
+        let _lock = acquire_lock(&self.path);
+        let result = (|| -> Result<()> {
+            let mut records = self.read_all()?;
+            let mut changed = false;
+            for r in &mut records {
+                if r.pid == pid
+                    && r.status != status
+                    && r.status != "exited"
+                    && r.status != "crashloop"
+                {
+                    r.status = status.to_string();
+                    changed = true;
+                }
+            }
+            if changed {
+                self.write_all(&records)?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!("PidStore: failed to update live status: {}", e);
+        }
+    }
+
     /// Set (or clear) the `unresponsive` flag for an agent. Edge-triggered by
     /// the supervisor — only called on a true transition — and it rewrites the
     /// registry only when the value actually changes, so the steady state costs
@@ -263,6 +424,52 @@ impl PidStore {
         }
     }
 
+    /// Publish the restart history. Each restart re-registers the agent with an
+    /// empty history, so the wrapper republishes the whole list after it.
+    pub fn set_restarts(&self, pid: u32, restarts: &[RestartEntry]) {
+        let _lock = acquire_lock(&self.path);
+        let result = (|| -> Result<()> {
+            let mut records = self.read_all()?;
+            let mut changed = false;
+            for r in &mut records {
+                if r.pid == pid && r.restarts != restarts {
+                    r.restarts = restarts.to_vec();
+                    changed = true;
+                }
+            }
+            if changed {
+                self.write_all(&records)?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!("PidStore: failed to set restarts: {}", e);
+        }
+    }
+
+    /// Set (or clear) the quota wait: `until` is the unix ms auto-retry resumes.
+    /// Writes only on a change, like `set_unresponsive`.
+    pub fn set_waiting_for_quota(&self, pid: u32, until: Option<u64>) {
+        let _lock = acquire_lock(&self.path);
+        let result = (|| -> Result<()> {
+            let mut records = self.read_all()?;
+            let mut changed = false;
+            for r in &mut records {
+                if r.pid == pid && r.waiting_for_quota != until {
+                    r.waiting_for_quota = until;
+                    changed = true;
+                }
+            }
+            if changed {
+                self.write_all(&records)?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!("PidStore: failed to set waiting_for_quota: {}", e);
+        }
+    }
+
     /// Re-register agents that are RUNNING but have no registry record.
     ///
     /// `clean_stale` used to evict live agents on a bad `kill(pid, 0)` reading
@@ -348,9 +555,16 @@ impl PidStore {
                     // it, and claiming otherwise would corrupt the agent tree.
                     wrapper_pid: None,
                     parent_pid: None,
+                    handoff_from: None,
                     agent_id: Some(new_agent_id()),
                     title: None,
+                    notification: None,
                     permissions: None,
+                    usage: None,
+                    waiting_for_quota: None,
+                    queue: None,
+                    restarts: Vec::new(),
+                    frames: None,
                 });
             }
         }
@@ -564,6 +778,8 @@ fn log_siblings(log_file: Option<&str>) -> Vec<PathBuf> {
         PathBuf::from(format!("{base}.log")),
         PathBuf::from(format!("{base}.lines.log")),
         PathBuf::from(format!("{base}.debug.log")),
+        PathBuf::from(format!("{base}.events.jsonl")),
+        PathBuf::from(format!("{base}.approvals.jsonl")),
     ]
 }
 
@@ -614,16 +830,28 @@ fn log_recently_written(r: &PidRecord, now_ms: i64) -> bool {
 /// So a still-growing raw log now vetoes eviction. An exited record is still
 /// dropped unconditionally: that status is written by the wrapper itself on the
 /// way out, so it is a statement of fact rather than an inference.
+///
+/// A `crashloop` record is just as dead, but it is the only trace of an agent
+/// the restart policy gave up on, so it stays listed for CRASHLOOP_RETENTION_MS
+/// after its last restart for someone to notice.
 fn keep_record(r: &PidRecord) -> bool {
+    let now_ms = chrono::Utc::now().timestamp_millis();
     if r.status == "exited" {
         return false;
     }
+    if r.status == "crashloop" {
+        let last = r.restarts.last().map_or(r.started_at, |e| e.at);
+        return now_ms.saturating_sub(last) < CRASHLOOP_RETENTION_MS;
+    }
     if is_process_alive(r.pid) {
         return true;
     }
-    log_recently_written(r, chrono::Utc::now().timestamp_millis())
+    log_recently_written(r, now_ms)
 }
 
+/// How long a parked `crashloop` record survives `clean_stale`.
+const CRASHLOOP_RETENTION_MS: i64 = 24 * 60 * 60 * 1000;
+
 pub fn is_process_alive(pid: u32) -> bool {
     #[cfg(unix)]
     unsafe {
@@ -692,6 +920,8 @@ mod tests {
         assert_eq!(records[0].prompt, Some("hello".into()));
         assert_eq!(records[0].cwd, "/tmp");
         assert_eq!(records[0].status, "active");
+        // `ay send` frames its writes to us.
+        assert_eq!(records[0].frames, Some(crate::fifo::FRAMES_VERSION));
     }
 
     #[test]
@@ -733,6 +963,18 @@ mod tests {
         assert_eq!(records[0].exit_reason, Some("done".into()));
     }
 
+    #[test]
+    fn test_set_live_status_never_revives_an_exited_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PidStore::with_path(dir.path().join("pids.jsonl"));
+        store.register(42, "claude", None, "/tmp", None);
+        store.set_live_status(42, "completed");
+        assert_eq!(store.read_all().unwrap()[0].status, "completed");
+        store.update_status(42, "exited", Some(0), Some("completed"), None);
+        store.set_live_status(42, "active");
+        assert_eq!(store.read_all().unwrap()[0].status, "exited");
+    }
+
     /// The regression this guard exists for: a LIVE agent whose pid probe says
     /// "dead" (EPERM, or a transient failure on a loaded box) used to be
     /// evicted permanently, because nothing re-registers an agent after spawn.
@@ -807,6 +1049,34 @@ mod tests {
         assert!(store.read_all().unwrap().is_empty());
     }
 
+    #[test]
+    fn test_crashloop_record_keeps_its_restart_history_through_clean_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PidStore::with_path(dir.path().join("pids.jsonl"));
+        let dead_pid = 999_999_999;
+        store.register(dead_pid, "claude", None, "/tmp", None);
+        let entry = |at| RestartEntry {
+            at,
+            exit_code: 1,
+            exit_reason: "crashed".into(),
+            class: "retryable".into(),
+            delay_ms: 1000,
+        };
+        let now = chrono::Utc::now().timestamp_millis();
+        store.set_restarts(dead_pid, &[entry(now - 2000), entry(now)]);
+        store.update_status(dead_pid, "crashloop", Some(1), Some("crashloop"), None);
+        store.clean_stale();
+        let records = store.read_all().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, "crashloop");
+        assert_eq!(records[0].restarts.len(), 2);
+
+        // Parked long enough: dropped like any dead record.
+        store.set_restarts(dead_pid, &[entry(now - CRASHLOOP_RETENTION_MS - 1)]);
+        store.clean_stale();
+        assert!(store.read_all().unwrap().is_empty());
+    }
+
     #[test]
     fn test_clean_stale_missing_log_falls_back_to_the_pid_probe() {
         // No log path, or a path that does not exist: no opinion, so the probe
@@ -960,9 +1230,16 @@ mod tests {
             started_at: 0,
             wrapper_pid: None,
             parent_pid: None,
+            handoff_from: None,
             agent_id: None,
             title: None,
+            notification: None,
             permissions: None,
+            usage: None,
+            waiting_for_quota: None,
+            queue: None,
+            restarts: Vec::new(),
+            frames: None,
         }];
         store.write_all(&records).unwrap();
         let loaded = store.read_all().unwrap();
@@ -978,9 +1255,11 @@ mod tests {
         let raw = PathBuf::from(format!("{}.raw.log", base.display()));
         let rendered = PathBuf::from(format!("{}.log", base.display()));
         let lines = PathBuf::from(format!("{}.lines.log", base.display()));
+        let events = PathBuf::from(format!("{}.events.jsonl", base.display()));
         std::fs::write(&raw, "raw").unwrap();
         std::fs::write(&rendered, "rendered").unwrap();
         std::fs::write(&lines, "lines").unwrap();
+        std::fs::write(&events, "{}").unwrap();
 
         let old_started_at = chrono::Utc::now().timestamp_millis()
             - (DEFAULT_LOG_RETENTION_DAYS + 1) * 24 * 60 * 60 * 1000;
@@ -999,9 +1278,16 @@ mod tests {
                 started_at: old_started_at,
                 wrapper_pid: None,
                 parent_pid: None,
+                handoff_from: None,
                 agent_id: None,
                 title: None,
+                notification: None,
                 permissions: None,
+                usage: None,
+                waiting_for_quota: None,
+                queue: None,
+                restarts: Vec::new(),
+                frames: None,
             }])
             .unwrap();
 
@@ -1010,6 +1296,7 @@ mod tests {
         assert!(!raw.exists());
         assert!(!rendered.exists());
         assert!(!lines.exists());
+        assert!(!events.exists());
     }
 
     #[test]
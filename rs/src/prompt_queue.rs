@@ -0,0 +1,373 @@
+//! Per-agent prompt queue: an ordered list of prompts fed one at a time, each
+//! time the CLI is back at its prompt and the screen has gone quiet.
+//!
+//! Seeded from repeated `-p` and `--prompt-file`; appended later over the FIFO
+//! (`ay send --queue`, `/api/send` with `queue: true`) as an APC frame — see
+//! `fifo::QUEUE_FRAME_START` — that the run loop strips out of the input
+//! stream instead of typing it.
+//!
+//! State lives in `<cwd>/.agent-yes/<pid>.queue.json` so a `--robust` restart
+//! (same wrapper pid) picks up at the same position, and its progress is
+//! mirrored onto the pid record as `queue: {sent, total}` for `ay ls`.
+
+use crate::fifo::{QUEUE_FRAME_END, QUEUE_FRAME_START};
+use crate::log_files::project_log_dir;
+use crate::pid_store::QueueProgress;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Line separating prompts in a `--prompt-file`.
+const PROMPT_FILE_SEPARATOR: &str = "---";
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct QueueState {
+    entries: Vec<String>,
+    /// Index of the next entry to send; everything before it was sent.
+    next: usize,
+    /// Whether `entries[0]` is the launch prompt (delivered via argv, not the
+    /// queue). It counts toward "3 of 7" but alone is no queue worth showing.
+    #[serde(default)]
+    initial: bool,
+}
+
+#[derive(Debug)]
+pub struct PromptQueue {
+    path: Option<PathBuf>,
+    state: QueueState,
+}
+
+impl PromptQueue {
+    /// Start this wrapper's queue, replacing whatever a previous process with
+    /// the same pid left behind. `initial` is the launch prompt (already sent).
+    pub fn create(pid: u32, cwd: &str, initial: Option<&str>, queued: Vec<String>) -> Self {
+        let mut entries: Vec<String> = initial.map(str::to_string).into_iter().collect();
+        entries.extend(queued);
+        let queue = Self {
+            path: queue_path(pid, cwd),
+            state: QueueState {
+                next: usize::from(initial.is_some()),
+                initial: initial.is_some(),
+                entries,
+            },
+        };
+        match &queue.path {
+            Some(path) if queue.progress().is_none() => {
+                let _ = fs::remove_file(path);
+            }
+            _ => queue.save(),
+        }
+        queue
+    }
+
+    /// Reopen the queue `create` started — on every (re)start of the CLI.
+    pub fn open(pid: u32, cwd: &str) -> Self {
+        let path = queue_path(pid, cwd);
+        let state = path
+            .as_deref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path, state }
+    }
+
+    pub fn push(&mut self, prompt: String) {
+        self.state.entries.push(prompt);
+        self.save();
+    }
+
+    /// Take the next prompt, advancing (and persisting) the position.
+    pub fn pop(&mut self) -> Option<String> {
+        let prompt = self.state.entries.get(self.state.next)?.clone();
+        self.state.next += 1;
+        self.save();
+        Some(prompt)
+    }
+
+    /// The prompts not sent yet, in order.
+    pub fn pending(&self) -> &[String] {
+        self.state
+            .entries
+            .get(self.state.next..)
+            .unwrap_or_default()
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.state.next < self.state.entries.len()
+    }
+
+    /// "`sent` of `total`", or None when nothing was ever queued.
+    pub fn progress(&self) -> Option<QueueProgress> {
+        let total = self.state.entries.len();
+        (total > usize::from(self.state.initial)).then_some(QueueProgress {
+            sent: self.state.next.min(total),
+            total,
+        })
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = write_state(path, &self.state) {
+            warn!("Failed to write prompt queue {:?}: {}", path, e);
+        }
+    }
+}
+
+fn queue_path(pid: u32, cwd: &str) -> Option<PathBuf> {
+    project_log_dir(cwd).map(|dir| dir.join(format!("{}.queue.json", pid)))
+}
+
+/// Write via a temp file + rename so a crash mid-write never leaves a
+/// truncated queue for the restart to choke on.
+fn write_state(path: &Path, state: &QueueState) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec(state)?)?;
+    fs::rename(&tmp, path)
+}
+
+/// Split a `--prompt-file` into prompts: entries separated by a `---` line,
+/// or — when the file has no separator — one prompt per non-empty line.
+pub fn parse_prompt_file(text: &str) -> Vec<String> {
+    let has_separator = text.lines().any(|l| l.trim() == PROMPT_FILE_SEPARATOR);
+    let mut prompts = Vec::new();
+    let mut current = Vec::new();
+    for line in text.lines() {
+        if !has_separator || line.trim() == PROMPT_FILE_SEPARATOR {
+            if has_separator {
+                prompts.push(current.join("\n"));
+                current.clear();
+            } else {
+                prompts.push(line.to_string());
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    prompts.push(current.join("\n"));
+    prompts
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// An unterminated frame longer than this is not a frame after all: it goes
+/// through as plain input.
+pub const MAX_FRAME_BYTES: usize = 1024 * 1024;
+
+/// Pulls queue frames out of the FIFO byte stream. A frame — its start marker
+/// included — can straddle two reads (the FIFO reader hands over 4 KiB at a
+/// time), so an unfinished one is held until the rest arrives. The run loop
+/// hands back what is still held once the FIFO goes quiet (`take_held`).
+///
+/// `new` scans for another frame kind with the same terminator — the run loop
+/// pulls meta-command frames (`fifo::COMMAND_FRAME_START`) out the same way.
+#[derive(Debug)]
+pub struct QueueFrameScanner {
+    start: &'static str,
+    partial: Option<Vec<u8>>,
+    // A trailing piece of `start`, waiting for the next read to finish it.
+    tail: Vec<u8>,
+}
+
+impl Default for QueueFrameScanner {
+    fn default() -> Self {
+        Self::new(QUEUE_FRAME_START)
+    }
+}
+
+impl QueueFrameScanner {
+    pub fn new(start: &'static str) -> Self {
+        Self {
+            start,
+            partial: None,
+            tail: Vec::new(),
+        }
+    }
+
+    /// Returns the bytes to forward as input and the frame bodies (the
+    /// prompts to enqueue).
+    pub fn feed(&mut self, data: &[u8]) -> (Vec<u8>, Vec<String>) {
+        let start = self.start.as_bytes();
+        let end = QUEUE_FRAME_END.as_bytes();
+        let mut passthrough = Vec::new();
+        let mut prompts = Vec::new();
+        let mut rest = std::mem::take(&mut self.tail);
+        rest.extend_from_slice(data);
+        loop {
+            if let Some(mut buf) = self.partial.take() {
+                buf.append(&mut rest);
+                let Some(at) = find(&buf, end) else {
+                    if buf.len() > MAX_FRAME_BYTES {
+                        passthrough.extend_from_slice(start);
+                        passthrough.append(&mut buf);
+                    } else {
+                        self.partial = Some(buf);
+                    }
+                    break;
+                };
+                rest = buf.split_off(at + end.len());
+                buf.truncate(at);
+                let prompt = String::from_utf8_lossy(&buf).trim().to_string();
+                if !prompt.is_empty() {
+                    prompts.push(prompt);
+                }
+                continue;
+            }
+            match find(&rest, start) {
+                Some(at) => {
+                    passthrough.extend_from_slice(&rest[..at]);
+                    rest.drain(..at + start.len());
+                    self.partial = Some(Vec::new());
+                }
+                None => {
+                    self.tail = rest.split_off(marker_tail(&rest, start));
+                    passthrough.append(&mut rest);
+                    break;
+                }
+            }
+        }
+        (passthrough, prompts)
+    }
+
+    /// What is held back, as plain input: an unfinished frame (marker
+    /// included) or piece of a marker.
+    pub fn take_held(&mut self) -> Vec<u8> {
+        match self.partial.take() {
+            Some(buf) => [self.start.as_bytes(), &buf].concat(),
+            None => std::mem::take(&mut self.tail),
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Where a trailing, unfinished `start` marker begins in `data` (its length
+/// when there is none).
+pub(crate) fn marker_tail(data: &[u8], start: &[u8]) -> usize {
+    (1..start.len().min(data.len() + 1))
+        .rev()
+        .find(|&n| data.ends_with(&start[..n]))
+        .map_or(data.len(), |n| data.len() - n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fifo::COMMAND_FRAME_START;
+
+    fn frame(text: &str) -> String {
+        format!("{QUEUE_FRAME_START}{text}{QUEUE_FRAME_END}")
+    }
+
+    #[test]
+    fn test_parse_prompt_file() {
+        assert_eq!(
+            parse_prompt_file("fix the build\n\nrun the tests\n"),
+            vec!["fix the build", "run the tests"]
+        );
+        assert_eq!(
+            parse_prompt_file("---\nfirst\nstill first\n---\n\n---\nsecond\n"),
+            vec!["first\nstill first", "second"]
+        );
+        assert!(parse_prompt_file("\n  \n").is_empty());
+    }
+
+    #[test]
+    fn test_queue_persists_position_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_str().unwrap();
+        let mut q = PromptQueue::create(7, cwd, Some("start"), vec!["a".into(), "b".into()]);
+        assert_eq!(q.progress(), Some(QueueProgress { sent: 1, total: 3 }));
+        assert_eq!(q.pop().as_deref(), Some("a"));
+
+        // A --robust restart reopens where the last run stopped.
+        let mut q = PromptQueue::open(7, cwd);
+        q.push("c".into());
+        assert_eq!(q.progress(), Some(QueueProgress { sent: 2, total: 4 }));
+        assert_eq!(q.pending(), ["b", "c"]);
+        assert_eq!(q.pop().as_deref(), Some("b"));
+        assert_eq!(q.pop().as_deref(), Some("c"));
+        assert_eq!(q.pop(), None);
+        assert!(!q.has_pending());
+
+        // A new wrapper with the same pid starts over.
+        let q = PromptQueue::create(7, cwd, Some("start"), vec![]);
+        assert_eq!(q.progress(), None);
+        assert!(!PromptQueue::open(7, cwd).has_pending());
+    }
+
+    #[test]
+    fn test_scanner_extracts_frames_split_across_reads() {
+        let mut scanner = QueueFrameScanner::default();
+        let (through, prompts) = scanner.feed(format!("ab{}cd", frame("one")).as_bytes());
+        assert_eq!(through, b"abcd");
+        assert_eq!(prompts, vec!["one"]);
+
+        let whole = frame("two words");
+        let (head, tail) = whole.split_at(whole.len() - 3);
+        let (through, prompts) = scanner.feed(head.as_bytes());
+        assert!(through.is_empty() && prompts.is_empty());
+        let (through, prompts) = scanner.feed(format!("{tail}x{}", frame("three")).as_bytes());
+        assert_eq!(through, b"x");
+        assert_eq!(prompts, vec!["two words", "three"]);
+
+        let (through, prompts) = scanner.feed(b"plain \x1b[A");
+        assert_eq!(through, b"plain \x1b[A");
+        assert!(prompts.is_empty());
+
+        // Other frame kinds pass through, for their own scanner.
+        let cmd = format!("{COMMAND_FRAME_START}/ay status{QUEUE_FRAME_END}");
+        let (through, _) = scanner.feed(cmd.as_bytes());
+        let (rest, commands) = QueueFrameScanner::new(COMMAND_FRAME_START).feed(&through);
+        assert!(rest.is_empty());
+        assert_eq!(commands, vec!["/ay status"]);
+    }
+
+    #[test]
+    fn test_scanner_joins_a_start_marker_split_across_reads() {
+        let whole = format!("ab{}", frame("one"));
+        for split in 1..whole.len() {
+            let mut scanner = QueueFrameScanner::default();
+            let (mut through, mut prompts) = scanner.feed(&whole.as_bytes()[..split]);
+            let (more, rest) = scanner.feed(&whole.as_bytes()[split..]);
+            through.extend(more);
+            prompts.extend(rest);
+            assert_eq!(through, b"ab", "split {split}");
+            assert_eq!(prompts, vec!["one"], "split {split}");
+        }
+    }
+
+    #[test]
+    fn test_scanner_gives_up_an_unterminated_frame() {
+        // A lone ESC might start a frame; the run loop asks for it back once
+        // the FIFO is quiet.
+        let mut scanner = QueueFrameScanner::default();
+        let (through, _) = scanner.feed(b"x\x1b");
+        assert_eq!(through, b"x");
+        assert_eq!(scanner.take_held(), b"\x1b");
+        let (through, _) = scanner.feed(format!("{QUEUE_FRAME_START}half").as_bytes());
+        assert!(through.is_empty());
+        assert_eq!(
+            scanner.take_held(),
+            format!("{QUEUE_FRAME_START}half").as_bytes()
+        );
+        assert!(scanner.take_held().is_empty());
+
+        // Past MAX_FRAME_BYTES it is plain input straight away.
+        let long = "y".repeat(MAX_FRAME_BYTES + 1);
+        let (through, prompts) = scanner.feed(format!("{QUEUE_FRAME_START}{long}").as_bytes());
+        assert_eq!(through.len(), QUEUE_FRAME_START.len() + long.len());
+        assert!(prompts.is_empty());
+        let (through, _) = scanner.feed(b"z");
+        assert_eq!(through, b"z");
+    }
+}
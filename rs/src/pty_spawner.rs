@@ -4,6 +4,7 @@ use crate::config::CliConfig;
 use anyhow::{anyhow, Result};
 use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
 use std::io::{Read, Write};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use tokio::sync::mpsc;
@@ -379,6 +380,8 @@ pub fn get_terminal_size() -> (u16, u16) {
 pub struct PtyContext {
     pub master: Box<dyn MasterPty + Send>,
     pub child: Box<dyn portable_pty::Child + Send + Sync>,
+    /// The agent's own cgroup, when resource limits put it in one.
+    pub cgroup: Option<PathBuf>,
     output_rx: mpsc::UnboundedReceiver<String>,
     writer: Arc<Mutex<Box<dyn Write + Send>>>,
 }
@@ -435,6 +438,16 @@ impl PtyContext {
     /// processes outside this agent's session.
     pub fn reap_group(&self) {
         #[cfg(unix)]
+        self.signal_group(libc::SIGKILL);
+        // Anything that left the group (setsid) is still in the cgroup.
+        if let Some(cgroup) = &self.cgroup {
+            crate::reaper::kill_cgroup(cgroup);
+        }
+    }
+
+    /// Send `sig` to the child's entire process group (see `reap_group`).
+    #[cfg(unix)]
+    pub fn signal_group(&self, sig: libc::c_int) {
         if let Some(pid) = self.child.process_id() {
             let pid = pid as i32;
             // Resolve the child's group; fall back to its pid (== pgid for a
@@ -443,7 +456,7 @@ impl PtyContext {
             let target = if pgid > 0 { pgid } else { pid };
             // Negative target == "every process in group `target`".
             unsafe {
-                libc::kill(-target, libc::SIGKILL);
+                libc::kill(-target, sig);
             }
         }
     }
@@ -513,13 +526,16 @@ pub async fn spawn_agent(
     // On Windows, use cmd.exe /c to resolve .cmd/.bat files via PATHEXT
     #[cfg(target_os = "windows")]
     let mut cmd = {
+        if config.sandbox.enabled {
+            return Err(anyhow!("--sandbox needs Linux namespaces"));
+        }
         let mut c = CommandBuilder::new("cmd.exe");
         c.arg("/c");
         c.arg(binary);
         c
     };
     #[cfg(not(target_os = "windows"))]
-    let mut cmd = CommandBuilder::new(binary);
+    let mut cmd: CommandBuilder = crate::sandbox::command(binary, &config.sandbox)?;
     for arg in args {
         cmd.arg(arg);
     }
@@ -538,6 +554,8 @@ pub async fn spawn_agent(
     // don't inherit it and register under the same id — which would make that id
     // ambiguous. Our own process env still carries it for new_agent_id().
     cmd.env_remove("AGENT_YES_AGENT_ID");
+    // Same for the failover link: it names the agent THIS one took over from.
+    cmd.env_remove(crate::failover::HANDOFF_FROM_ENV);
 
     // Strip the parent Claude Code session markers so the wrapped CLI is a CLEAN
     // top-level session. Without this, an `ay claude` launched from inside another
@@ -589,6 +607,12 @@ pub async fn spawn_agent(
     // Spawn the child
     let child = slave.spawn_command(cmd)?;
 
+    // Resource limits go on before the agent gets far: into its cgroup (and
+    // rlimits) right away, so whatever it starts from here on inherits them.
+    let cgroup = child
+        .process_id()
+        .and_then(|pid| crate::resource_limits::apply(pid, &config.limits));
+
     // Scheduler policy: deprioritize the agent CLI so it yields CPU to the
     // interactive `ay serve` daemon (nice 0) under host load. RAISING serve's
     // priority (negative nice) needs CAP_SYS_NICE — dropped in many containers —
@@ -661,6 +685,7 @@ pub async fn spawn_agent(
     Ok(PtyContext {
         master,
         child,
+        cgroup,
         output_rx,
         writer: Arc::new(Mutex::new(writer)),
     })
@@ -1074,6 +1099,7 @@ mod tests {
         let ctx = PtyContext {
             master: pair.master,
             child: pair.slave.spawn_command(child_cmd).expect("spawn"),
+            cgroup: None,
             output_rx,
             writer: std::sync::Arc::new(std::sync::Mutex::new(writer)),
         };
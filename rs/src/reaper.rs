@@ -11,16 +11,24 @@
 //! RECORDED pgid of a CONFIRMED-DEAD wrapper — never `ppid==1` — it is
 //! container-safe (where PID 1 is the init with many legitimate children) and
 //! never touches a process outside a dead agent's own group.
+//!
+//! An agent spawned with resource limits also records its cgroup
+//! (resource_limits.rs). A process can leave its group with `setsid`, but not
+//! its cgroup, so the cgroup is the more reliable handle: the sweep kills every
+//! process in it, then removes it.
 
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Serialize, Deserialize)]
 struct Entry {
     wpid: i32, // the agent-yes wrapper process
     pgid: i32, // the agent CLI's process group (it leads its own session)
+    /// The agent's own cgroup v2 directory, when it was given one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cgroup: Option<PathBuf>,
 }
 
 fn registry_path() -> PathBuf {
@@ -44,9 +52,10 @@ fn is_alive(_pid: i32) -> bool {
     true
 }
 
-/// Record this wrapper + its agent's process group so a later sweep can reap the
-/// group if the wrapper dies without cleaning up. Best-effort.
-pub fn register(wrapper_pid: u32, pgid: i32) {
+/// Record this wrapper + its agent's process group (and cgroup, if any) so a
+/// later sweep can reap them if the wrapper dies without cleaning up.
+/// Best-effort.
+pub fn register(wrapper_pid: u32, pgid: i32, cgroup: Option<&Path>) {
     if pgid <= 1 {
         return; // never persist a group we'd refuse to signal anyway
     }
@@ -57,6 +66,7 @@ pub fn register(wrapper_pid: u32, pgid: i32) {
     let Ok(line) = serde_json::to_string(&Entry {
         wpid: wrapper_pid as i32,
         pgid,
+        cgroup: cgroup.map(Path::to_path_buf),
     }) else {
         return;
     };
@@ -99,6 +109,9 @@ pub fn sweep() {
                 libc::kill(-entry.pgid, libc::SIGKILL);
             }
         }
+        if let Some(cgroup) = &entry.cgroup {
+            kill_cgroup(cgroup);
+        }
     }
     let tmp = path.with_extension("jsonl.tmp");
     if fs::write(&tmp, keep.join("\n")).is_ok() {
@@ -106,6 +119,38 @@ pub fn sweep() {
     }
 }
 
+/// SIGKILL every process in the cgroup at `dir` and remove it. Uses
+/// `cgroup.kill` (Linux 5.14+), else signals each pid in `cgroup.procs`. Only
+/// touches directories that look like a cgroup, so a stale or bogus registry
+/// entry can't make it remove anything else. Best-effort.
+pub fn kill_cgroup(dir: &Path) {
+    let procs = dir.join("cgroup.procs");
+    if !procs.exists() {
+        return;
+    }
+    if fs::write(dir.join("cgroup.kill"), "1").is_err() {
+        #[cfg(unix)]
+        for pid in fs::read_to_string(&procs)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|l| l.trim().parse::<i32>().ok())
+            .filter(|pid| *pid > 1)
+        {
+            unsafe {
+                libc::kill(pid, libc::SIGKILL);
+            }
+        }
+    }
+    // rmdir succeeds once the last member is gone; the kill takes a moment to
+    // land.
+    for _ in 0..50 {
+        if fs::remove_dir(dir).is_ok() || !dir.exists() {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,8 +177,8 @@ mod tests {
         // live entry's pgid is never signalled (wrapper alive); the dead entry's
         // pgid points at a nonexistent group so its kill is a harmless ESRCH
         // no-op — we only exercise the bookkeeping here, not real signalling.
-        register(std::process::id() as u32, 222_222);
-        register(999_999, 999_998);
+        register(std::process::id(), 222_222, None);
+        register(999_999, 999_998, None);
         sweep();
 
         let left = fs::read_to_string(registry_path()).unwrap();
@@ -146,4 +191,11 @@ mod tests {
             None => std::env::remove_var("AGENT_YES_HOME"),
         }
     }
+
+    #[test]
+    fn test_kill_cgroup_leaves_non_cgroup_dirs_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        kill_cgroup(dir.path());
+        assert!(dir.path().exists());
+    }
 }
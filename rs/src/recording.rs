@@ -0,0 +1,353 @@
+//! Session recording (asciicast v2) and the reader that feeds `--replay`.
+//!
+//! The raw log (log_files.rs) keeps PTY bytes without timing and is replaced by
+//! a rendered dump on exit, so it can't reproduce what the pattern engine saw
+//! *when*. `--record` additionally writes `<cwd>/.agent-yes/<pid>.cast`: a JSON
+//! header line, then one `[seconds, code, data]` line per event —
+//!   - `"o"`: a PTY output chunk, exactly as handed to the pattern engine;
+//!   - `"i"`: bytes written to the PTY — forwarded keystrokes, FIFO messages,
+//!     terminal query replies and everything the engine injected;
+//!   - `"r"`: a resize, data `COLSxROWS`.
+//!
+//! Any asciicast player (`asciinema play`) shows the output track. `--replay`
+//! runs it back through `AgentContext` on a fake clock (see
+//! `AgentContext::replay`), so a "this prompt got stuck" report can carry the
+//! recording and a pattern change can be checked against real sessions
+//! without spawning the CLI.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::warn;
+
+/// How far past the last recorded event a replay keeps ticking the heartbeat,
+/// so a prompt that arrived at the very end still gets its idle-gated Enter
+/// (and both Enter retries) in the replayed input track.
+pub const REPLAY_TAIL_MS: u64 = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastKind {
+    Output,
+    Input,
+    Resize,
+}
+
+impl CastKind {
+    fn code(self) -> &'static str {
+        match self {
+            CastKind::Output => "o",
+            CastKind::Input => "i",
+            CastKind::Resize => "r",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "o" => Some(CastKind::Output),
+            "i" => Some(CastKind::Input),
+            "r" => Some(CastKind::Resize),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastEvent {
+    /// Seconds since the recording started.
+    pub time: f64,
+    pub kind: CastKind,
+    pub data: String,
+}
+
+impl CastEvent {
+    pub fn at_ms(&self) -> u64 {
+        (self.time * 1000.0).round().max(0.0) as u64
+    }
+}
+
+/// A parsed recording: the terminal size from the header plus its events in
+/// file order.
+#[derive(Debug)]
+pub struct Cast {
+    pub width: u16,
+    pub height: u16,
+    /// The header's `command` — the CLI name when agent-yes recorded it.
+    pub command: Option<String>,
+    /// The header's `timestamp`: when the recording started, unix seconds.
+    pub timestamp: Option<u64>,
+    pub events: Vec<CastEvent>,
+}
+
+struct CastState {
+    file: Option<fs::File>,
+    started: Instant,
+}
+
+/// Appends events to a `.cast` file. Cheap to clone — the PTY writer tee and
+/// `AgentContext` share one. Best-effort like `LogWriter`: a failed write
+/// disables the recording rather than disturbing the session.
+#[derive(Clone)]
+pub struct CastWriter {
+    state: Arc<Mutex<CastState>>,
+    pub path: PathBuf,
+}
+
+impl CastWriter {
+    pub fn create(path: &Path, cols: u16, rows: u16, command: &str) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .with_context(|| format!("Failed to create recording {:?}", path))?;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let header = serde_json::json!({
+            "version": 2,
+            "width": cols,
+            "height": rows,
+            "timestamp": timestamp,
+            "command": command,
+        });
+        writeln!(file, "{}", header)?;
+        Ok(Self {
+            state: Arc::new(Mutex::new(CastState {
+                file: Some(file),
+                started: Instant::now(),
+            })),
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn output(&self, data: &str) {
+        self.event(CastKind::Output, data);
+    }
+
+    pub fn input(&self, data: &[u8]) {
+        self.event(CastKind::Input, &String::from_utf8_lossy(data));
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) {
+        self.event(CastKind::Resize, &format!("{}x{}", cols, rows));
+    }
+
+    fn event(&self, kind: CastKind, data: &str) {
+        if data.is_empty() {
+            return;
+        }
+        let Ok(mut g) = self.state.lock() else { return };
+        let time = g.started.elapsed().as_secs_f64();
+        let Some(f) = g.file.as_mut() else { return };
+        let line = serde_json::json!([(time * 1e6).round() / 1e6, kind.code(), data]);
+        if let Err(e) = writeln!(f, "{}", line) {
+            warn!("Recording to {:?} stopped: {}", self.path, e);
+            g.file = None;
+        }
+    }
+}
+
+/// PTY writer that records every byte it forwards as an `"i"` event.
+struct TeeWriter {
+    inner: Arc<Mutex<Box<dyn Write + Send>>>,
+    cast: CastWriter,
+}
+
+impl Write for TeeWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self
+            .inner
+            .lock()
+            .map_err(|e| std::io::Error::other(format!("Lock: {}", e)))?
+            .write(buf)?;
+        self.cast.input(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner
+            .lock()
+            .map_err(|e| std::io::Error::other(format!("Lock: {}", e)))?
+            .flush()
+    }
+}
+
+/// Wrap a PTY writer so everything written through it is also recorded.
+pub fn tee_writer(
+    inner: Arc<Mutex<Box<dyn Write + Send>>>,
+    cast: CastWriter,
+) -> Arc<Mutex<Box<dyn Write + Send>>> {
+    Arc::new(Mutex::new(Box::new(TeeWriter { inner, cast })))
+}
+
+/// In-memory PTY writer for replay: collects what the engine would have typed.
+#[derive(Clone, Default)]
+pub struct Capture {
+    buf: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Capture {
+    pub fn writer(&self) -> Arc<Mutex<Box<dyn Write + Send>>> {
+        Arc::new(Mutex::new(Box::new(self.clone())))
+    }
+
+    pub fn take(&self) -> Vec<u8> {
+        let mut g = self.buf.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::take(&mut *g)
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut g = self.buf.lock().unwrap_or_else(|e| e.into_inner());
+        g.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn read_cast(path: &Path) -> Result<Cast> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to read recording {:?}", path))?;
+    parse_cast(&text).with_context(|| format!("Invalid recording {:?}", path))
+}
+
+/// Parse asciicast v2 text. Event codes other than o/i/r (markers, etc.) are
+/// skipped so recordings from other tools still replay their output track.
+pub fn parse_cast(text: &str) -> Result<Cast> {
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    let header: serde_json::Value =
+        serde_json::from_str(lines.next().ok_or_else(|| anyhow!("empty file"))?)
+            .context("header is not JSON")?;
+    if header.get("version").and_then(|v| v.as_u64()) != Some(2) {
+        return Err(anyhow!("not an asciicast v2 recording"));
+    }
+    let dim = |key: &str| {
+        header
+            .get(key)
+            .and_then(|v| v.as_u64())
+            .map(|n| n.clamp(1, u16::MAX as u64) as u16)
+            .ok_or_else(|| anyhow!("header is missing `{}`", key))
+    };
+    let (width, height) = (dim("width")?, dim("height")?);
+    let mut events = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let (time, code, data): (f64, String, String) = serde_json::from_str(line)
+            .with_context(|| format!("event {} is not [time, code, data]", i + 1))?;
+        if let Some(kind) = CastKind::from_code(&code) {
+            events.push(CastEvent { time, kind, data });
+        }
+    }
+    let command = header
+        .get("command")
+        .and_then(|v| v.as_str())
+        .map(str::to_string);
+    let timestamp = header.get("timestamp").and_then(|v| v.as_u64());
+    Ok(Cast {
+        width,
+        height,
+        command,
+        timestamp,
+        events,
+    })
+}
+
+/// `"COLSxROWS"` → `(cols, rows)`.
+pub fn parse_resize(data: &str) -> Option<(u16, u16)> {
+    let (cols, rows) = data.split_once('x')?;
+    Some((cols.trim().parse().ok()?, rows.trim().parse().ok()?))
+}
+
+/// Side-by-side timeline of the input a recording captured and the input the
+/// engine produced on replay, ordered by time.
+pub fn format_replay_report(recorded: &[CastEvent], replayed: &[CastEvent]) -> String {
+    let mut rows: Vec<(f64, &str, &str)> = recorded
+        .iter()
+        .filter(|e| e.kind == CastKind::Input)
+        .map(|e| (e.time, "recorded", e.data.as_str()))
+        .chain(
+            replayed
+                .iter()
+                .map(|e| (e.time, "replayed", e.data.as_str())),
+        )
+        .collect();
+    rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut out = String::new();
+    for (time, track, data) in &rows {
+        out.push_str(&format!("{:>10.3}s  {}  {:?}\n", time, track, data));
+    }
+    let recorded_n = rows.iter().filter(|r| r.1 == "recorded").count();
+    out.push_str(&format!(
+        "{} recorded input event(s), {} replayed\n",
+        recorded_n,
+        rows.len() - recorded_n
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast_writer_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".agent-yes/1.cast");
+        let cast = CastWriter::create(&path, 120, 40, "claude").unwrap();
+        cast.output("\x1b[2J❯ Do you trust this folder?");
+        cast.resize(100, 30);
+        let writer = tee_writer(Capture::default().writer(), cast.clone());
+        writer.lock().unwrap().write_all(b"\r").unwrap();
+
+        let parsed = read_cast(&path).unwrap();
+        assert_eq!((parsed.width, parsed.height), (120, 40));
+        assert_eq!(parsed.command.as_deref(), Some("claude"));
+        let kinds: Vec<_> = parsed.events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![CastKind::Output, CastKind::Resize, CastKind::Input]
+        );
+        assert_eq!(parsed.events[0].data, "\x1b[2J❯ Do you trust this folder?");
+        assert_eq!(parse_resize(&parsed.events[1].data), Some((100, 30)));
+        assert_eq!(parsed.events[2].data, "\r");
+    }
+
+    #[test]
+    fn test_parse_cast_skips_unknown_codes_and_rejects_v1() {
+        let cast = parse_cast(
+            "{\"version\": 2, \"width\": 80, \"height\": 24}\n\
+             [0.5, \"o\", \"hi\"]\n\
+             [0.7, \"m\", \"marker\"]\n\
+             [1.25, \"i\", \"\\r\"]\n",
+        )
+        .unwrap();
+        assert_eq!(cast.events.len(), 2);
+        assert_eq!(cast.events[1].at_ms(), 1250);
+        assert!(parse_cast("{\"version\": 1, \"width\": 80, \"height\": 24}\n").is_err());
+        assert!(parse_cast("").is_err());
+    }
+
+    #[test]
+    fn test_format_replay_report_interleaves_tracks() {
+        let ev = |time, data: &str| CastEvent {
+            time,
+            kind: CastKind::Input,
+            data: data.into(),
+        };
+        let report = format_replay_report(&[ev(1.0, "\r")], &[ev(0.5, "\r"), ev(2.0, "\r")]);
+        let lines: Vec<_> = report.lines().collect();
+        assert!(lines[0].contains("replayed"));
+        assert!(lines[1].contains("recorded"));
+        assert_eq!(lines[3], "1 recorded input event(s), 2 replayed");
+    }
+}
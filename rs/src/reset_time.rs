@@ -0,0 +1,211 @@
+//! Usage-limit reset times: the `reset` capture of an `autoRetry` pattern.
+//!
+//! A usage-limit banner usually says when the limit lifts — "resets 3pm
+//! (Asia/Tokyo)", "try again in 2 hours 14 minutes". An `autoRetry` pattern
+//! with a named `(?P<reset>…)` group hands that text here, and instead of
+//! nudging along the exponential backoff ladder the agent sleeps until the
+//! reset (plus jitter) marked `waiting_for_quota`. Accepted forms:
+//!   - relative: `2h 15m`, `2 hours 14 minutes`, `in 45 min`, `1 day`;
+//!   - a clock time: `3pm`, `3:30 PM`, `15:00`, optionally dated `Oct 20, 3pm`;
+//!   - absolute: RFC 3339 `2026-10-16T15:00:00+09:00`, or unix seconds
+//!     (`Claude AI usage limit reached|1760594400`).
+//!
+//! A clock time is read in the machine's local zone — the zone a CLI prints
+//! in parentheses is its own rendering of that same local zone — and one that
+//! has already passed means the next day (or year, when dated).
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, TimeZone};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// A reset further out than this is a misparse, not a quota window.
+const MAX_RESET_SECS: u64 = 8 * 24 * 3600;
+
+static CLOCK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^(?:at\s+|on\s+)?(?:(?P<mon>jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.?\s+(?P<day>\d{1,2})(?:st|nd|rd|th)?,?\s+(?:at\s+)?)?(?P<h>\d{1,2})(?::(?P<m>\d{2}))?\s*(?:(?P<ampm>[ap])\.?m\.?)?$",
+    )
+    .unwrap()
+});
+
+static RELATIVE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(\d+)\s*(days?|d|hours?|hrs?|h|minutes?|mins?|m|seconds?|secs?|s)\b").unwrap()
+});
+
+/// The `reset` capture of the first pattern that matches the screen and has one.
+pub fn captured_reset<'a>(patterns: &[Regex], screen: &'a str) -> Option<&'a str> {
+    patterns
+        .iter()
+        .find_map(|rx| Some(rx.captures(screen)?.name("reset")?.as_str()))
+}
+
+/// Seconds from `now` until the reset `text` describes. None when it can't be
+/// parsed, is not in the future, or is implausibly far out.
+pub fn secs_until_reset<Tz: TimeZone>(text: &str, now: &DateTime<Tz>) -> Option<u64> {
+    // "3pm (Asia/Tokyo)." → "3pm"
+    let text = text.split('(').next().unwrap_or("");
+    let text = text.trim().trim_end_matches(['.', ',', '·']).trim();
+    if text.is_empty() {
+        return None;
+    }
+    let secs = if let Ok(at) = DateTime::parse_from_rfc3339(text) {
+        at.timestamp() - now.timestamp()
+    } else if text.len() == 10 && text.bytes().all(|b| b.is_ascii_digit()) {
+        text.parse::<i64>().ok()? - now.timestamp()
+    } else if let Some(secs) = clock_time(text, now) {
+        secs
+    } else {
+        relative(text)?
+    };
+    u64::try_from(secs)
+        .ok()
+        .filter(|s| *s > 0 && *s <= MAX_RESET_SECS)
+}
+
+fn clock_time<Tz: TimeZone>(text: &str, now: &DateTime<Tz>) -> Option<i64> {
+    let caps = CLOCK.captures(text)?;
+    let minute: u32 = caps
+        .name("m")
+        .map_or(Some(0), |m| m.as_str().parse().ok())?;
+    let hour: u32 = caps["h"].parse().ok()?;
+    let hour = match caps.name("ampm").map(|m| m.as_str().to_ascii_lowercase()) {
+        Some(ampm) if (1..=12).contains(&hour) => hour % 12 + if ampm == "p" { 12 } else { 0 },
+        Some(_) => return None,
+        // A bare "3" is no time of day; "15:00" is.
+        None if caps.name("m").is_none() => return None,
+        None => hour,
+    };
+    let time = chrono::NaiveTime::from_hms_opt(hour, minute, 0)?;
+    let local_now = now.naive_local();
+    let target = match (caps.name("mon"), caps.name("day")) {
+        (Some(mon), Some(day)) => {
+            let month = month_number(mon.as_str())?;
+            let day: u32 = day.as_str().parse().ok()?;
+            let this_year = NaiveDate::from_ymd_opt(local_now.year(), month, day)?.and_time(time);
+            if this_year > local_now {
+                this_year
+            } else {
+                NaiveDate::from_ymd_opt(local_now.year() + 1, month, day)?.and_time(time)
+            }
+        }
+        _ => {
+            let today = local_now.date().and_time(time);
+            if today > local_now {
+                today
+            } else {
+                today + ChronoDuration::days(1)
+            }
+        }
+    };
+    let target = now.timezone().from_local_datetime(&target).earliest()?;
+    Some(target.timestamp() - now.timestamp())
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let prefix = name.get(..3)?.to_ascii_lowercase();
+    MONTHS
+        .iter()
+        .position(|m| *m == prefix)
+        .map(|i| i as u32 + 1)
+}
+
+/// "2 hours 14 minutes", "in 45m", "1h, 5m" — every word must be a
+/// number+unit pair or filler, so prose with a stray number is rejected.
+fn relative(text: &str) -> Option<i64> {
+    let mut secs = 0i64;
+    let mut found = false;
+    for caps in RELATIVE.captures_iter(text) {
+        let n: i64 = caps[1].parse().ok()?;
+        let unit = caps[2].to_ascii_lowercase();
+        secs += n * match unit.chars().next()? {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            _ => 1,
+        };
+        found = true;
+    }
+    let rest = RELATIVE.replace_all(text, "");
+    let filler = rest
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .all(|w| {
+            matches!(
+                w.to_ascii_lowercase().as_str(),
+                "in" | "and" | "about" | "~"
+            )
+        });
+    (found && filler).then_some(secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    /// 2026-10-16 10:30 in Tokyo.
+    fn now() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2026-10-16T10:30:00+09:00").unwrap()
+    }
+
+    #[test]
+    fn test_relative_resets() {
+        assert_eq!(secs_until_reset("2 hours 14 minutes", &now()), Some(8040));
+        assert_eq!(secs_until_reset("in 45m", &now()), Some(2700));
+        assert_eq!(secs_until_reset("1h, 5 mins.", &now()), Some(3900));
+        assert_eq!(secs_until_reset("1 day", &now()), Some(86_400));
+        assert_eq!(secs_until_reset("a while", &now()), None);
+        assert_eq!(secs_until_reset("3 files changed", &now()), None);
+    }
+
+    #[test]
+    fn test_clock_resets() {
+        assert_eq!(
+            secs_until_reset("3pm (Asia/Tokyo)", &now()),
+            Some(4 * 3600 + 1800)
+        );
+        assert_eq!(secs_until_reset("11:15 AM", &now()), Some(2700));
+        assert_eq!(secs_until_reset("13:00", &now()), Some(9000));
+        // Already past today → tomorrow.
+        assert_eq!(secs_until_reset("9am", &now()), Some(22 * 3600 + 1800));
+        assert_eq!(secs_until_reset("12am", &now()), Some(13 * 3600 + 1800));
+        assert_eq!(
+            secs_until_reset("Oct 17, 3pm", &now()),
+            Some(28 * 3600 + 1800)
+        );
+        // "3m" is three minutes, not 3 o'clock; a bare hour is no time at all.
+        assert_eq!(secs_until_reset("3m", &now()), Some(180));
+        assert_eq!(secs_until_reset("3", &now()), None);
+        assert_eq!(secs_until_reset("13pm", &now()), None);
+    }
+
+    #[test]
+    fn test_absolute_and_bounds() {
+        assert_eq!(
+            secs_until_reset("2026-10-16T11:00:00+09:00", &now()),
+            Some(1800)
+        );
+        assert_eq!(secs_until_reset("2026-10-16T10:00:00+09:00", &now()), None);
+        let epoch = now().timestamp() + 600;
+        assert_eq!(secs_until_reset(&epoch.to_string(), &now()), Some(600));
+        assert_eq!(secs_until_reset("30 days", &now()), None);
+        assert_eq!(secs_until_reset("", &now()), None);
+    }
+
+    #[test]
+    fn test_captured_reset_takes_the_named_group() {
+        let patterns = vec![
+            Regex::new("Overloaded").unwrap(),
+            Regex::new(r"limit reached(?:[^\n]{0,20}?resets (?P<reset>[^\n(]+))?").unwrap(),
+        ];
+        assert_eq!(
+            captured_reset(&patterns, "5-hour limit reached ∙ resets 3pm (Asia/Tokyo)"),
+            Some("3pm ")
+        );
+        assert_eq!(captured_reset(&patterns, "5-hour limit reached"), None);
+        assert_eq!(captured_reset(&patterns, "Overloaded"), None);
+    }
+}
@@ -0,0 +1,351 @@
+//! Kernel resource limits per agent (`limits:` in the CLI config, or the
+//! `--memory-max` / `--cpu-max` / `--pids-max` / `--nofile` flags).
+//!
+//! Renicing (`pty_spawner::deprioritize_pid`) only makes an agent yield the
+//! CPU; a runaway build it starts can still eat every core, all memory or the
+//! process table (docs/kernel-resource-limits.md). With limits set, the agent
+//! gets its own cgroup v2, `ay-<wrapper pid>`, under the wrapper's cgroup (or
+//! `$AGENT_YES_CGROUP`), with `memory.max`, `cpu.max` and `pids.max` written
+//! before the agent is moved in. The open-file limit is always an rlimit;
+//! cgroups have no such knob.
+//!
+//! The wrapper's cgroup must be delegated to us: writable, with the
+//! controllers available. When the wrapper is alone in it (`systemd-run --user
+//! --scope -p Delegate=yes ay ...`) it moves itself into an `ay-wrapper` leaf
+//! first, since cgroup v2 only enables controllers for children of a cgroup
+//! with no processes of its own. Without a delegated cgroup, the limits fall
+//! back to rlimits on the agent (inherited by everything it starts):
+//! `memory.max` → RLIMIT_AS (counts virtual memory, so leave headroom) and
+//! `pids.max` → RLIMIT_NPROC (counts all of the user's processes). `cpu.max`
+//! has no rlimit equivalent and is dropped with a warning.
+//!
+//! The cgroup is recorded with the reaper (reaper.rs), which kills it whole —
+//! unlike a process group, nothing can leave it.
+
+use crate::config_loader::{LimitValue, LimitsOverride};
+use anyhow::{anyhow, Context, Result};
+use std::path::PathBuf;
+use tracing::warn;
+
+/// cgroup v2 `cpu.max` period, in µs (the kernel default).
+const CPU_PERIOD_US: u64 = 100_000;
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(target_os = "linux", not(target_env = "gnu")))]
+type Resource = libc::c_int;
+
+/// Compiled `limits:` section (and flags).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// Bytes.
+    pub memory_max: Option<u64>,
+    /// CPUs' worth of time per period; 0.5 = half a core.
+    pub cpu_max: Option<f64>,
+    pub pids_max: Option<u64>,
+    pub nofile: Option<u64>,
+}
+
+pub fn compile_resource_limits(raw: Option<LimitsOverride>) -> Result<ResourceLimits> {
+    let raw = raw.unwrap_or_default();
+    Ok(ResourceLimits {
+        memory_max: raw
+            .memory_max
+            .map(|v| match v {
+                LimitValue::Number(n) => Ok(n),
+                LimitValue::Text(s) => parse_bytes(&s),
+            })
+            .transpose()
+            .context("Invalid memoryMax")?,
+        cpu_max: raw
+            .cpu_max
+            .map(|v| match v {
+                LimitValue::Number(n) => parse_cpus(&n.to_string()),
+                LimitValue::Text(s) => parse_cpus(&s),
+            })
+            .transpose()
+            .context("Invalid cpuMax")?,
+        pids_max: raw.pids_max,
+        nofile: raw.nofile,
+    })
+}
+
+/// `"512M"`, `"2G"`, `"1.5GiB"` or plain bytes. Suffixes are binary (K = 1024).
+pub fn parse_bytes(s: &str) -> Result<u64> {
+    let t = s.trim();
+    let lower = t.to_ascii_lowercase();
+    let digits = lower.trim_end_matches(['i', 'b']);
+    let (number, scale) = match digits.chars().last() {
+        Some('k') => (&digits[..digits.len() - 1], 1u64 << 10),
+        Some('m') => (&digits[..digits.len() - 1], 1 << 20),
+        Some('g') => (&digits[..digits.len() - 1], 1 << 30),
+        Some('t') => (&digits[..digits.len() - 1], 1 << 40),
+        _ => (digits, 1),
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid size '{}' (expected e.g. 512M, 2G)", t))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(anyhow!("Invalid size '{}': must be positive", t));
+    }
+    Ok((value * scale as f64) as u64)
+}
+
+/// `"2"`, `"0.5"`: how many CPUs' worth of time the agent may use.
+pub fn parse_cpus(s: &str) -> Result<f64> {
+    let value: f64 = s
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid CPU limit '{}' (expected e.g. 2 or 0.5)", s.trim()))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(anyhow!(
+            "Invalid CPU limit '{}': must be positive",
+            s.trim()
+        ));
+    }
+    Ok(value)
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        *self == ResourceLimits::default()
+    }
+
+    /// `self`, with every limit `other` sets taking precedence (flags over the
+    /// config).
+    pub fn overlay(self, other: ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            memory_max: other.memory_max.or(self.memory_max),
+            cpu_max: other.cpu_max.or(self.cpu_max),
+            pids_max: other.pids_max.or(self.pids_max),
+            nofile: other.nofile.or(self.nofile),
+        }
+    }
+
+    /// The cgroup v2 interface files to write, with their values.
+    #[cfg(any(target_os = "linux", test))]
+    fn cgroup_files(&self) -> Vec<(&'static str, String)> {
+        let mut files = Vec::new();
+        if let Some(bytes) = self.memory_max {
+            files.push(("memory.max", bytes.to_string()));
+        }
+        if let Some(cpus) = self.cpu_max {
+            let quota = ((cpus * CPU_PERIOD_US as f64) as u64).max(1_000);
+            files.push(("cpu.max", format!("{} {}", quota, CPU_PERIOD_US)));
+        }
+        if let Some(pids) = self.pids_max {
+            files.push(("pids.max", pids.to_string()));
+        }
+        files
+    }
+}
+
+/// Apply `limits` to the freshly spawned agent `pid`. Returns the cgroup it
+/// was placed in, if it got one. Never fails the spawn: what can't be applied
+/// is logged.
+pub fn apply(pid: u32, limits: &ResourceLimits) -> Option<PathBuf> {
+    if limits.is_empty() {
+        return None;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(nofile) = limits.nofile {
+            set_rlimit(pid, libc::RLIMIT_NOFILE, nofile, "nofile");
+        }
+        if limits.cgroup_files().is_empty() {
+            return None;
+        }
+        match place_in_cgroup(pid, limits) {
+            Ok(dir) => return Some(dir),
+            Err(e) => warn!("No delegated cgroup for the agent ({:#}), using rlimits", e),
+        }
+        if let Some(bytes) = limits.memory_max {
+            set_rlimit(pid, libc::RLIMIT_AS, bytes, "memory");
+        }
+        if let Some(pids) = limits.pids_max {
+            set_rlimit(pid, libc::RLIMIT_NPROC, pids, "pids");
+        }
+        if limits.cpu_max.is_some() {
+            warn!("cpu limit needs a delegated cgroup; not applied");
+        }
+        None
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        warn!("Resource limits are only supported on Linux; not applied");
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_rlimit(pid: u32, resource: Resource, value: u64, name: &str) {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    let rc = unsafe { libc::prlimit(pid as libc::pid_t, resource, &limit, std::ptr::null_mut()) };
+    if rc != 0 {
+        warn!(
+            "Failed to set the {} rlimit on {}: {}",
+            name,
+            pid,
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+/// The cgroup v2 path in a `/proc/<pid>/cgroup` file (the `0::` line).
+#[cfg(any(target_os = "linux", test))]
+fn parse_proc_cgroup(content: &str) -> Option<&str> {
+    content.lines().find_map(|l| l.strip_prefix("0::"))
+}
+
+/// Where agent cgroups go: `$AGENT_YES_CGROUP`, else the wrapper's own cgroup.
+#[cfg(target_os = "linux")]
+fn cgroup_base() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("AGENT_YES_CGROUP") {
+        return Ok(PathBuf::from(dir));
+    }
+    let content = std::fs::read_to_string("/proc/self/cgroup")?;
+    let path = parse_proc_cgroup(&content).ok_or_else(|| anyhow!("not on cgroup v2"))?;
+    Ok(PathBuf::from("/sys/fs/cgroup").join(path.trim().trim_start_matches('/')))
+}
+
+#[cfg(target_os = "linux")]
+fn place_in_cgroup(pid: u32, limits: &ResourceLimits) -> Result<PathBuf> {
+    use std::fs;
+    let base = cgroup_base()?;
+    let files = limits.cgroup_files();
+    let controllers: Vec<&str> = files
+        .iter()
+        .map(|(file, _)| file.split('.').next().unwrap_or(file))
+        .collect();
+    let available = fs::read_to_string(base.join("cgroup.controllers"))
+        .with_context(|| format!("{} is not a cgroup", base.display()))?;
+    if let Some(missing) = controllers
+        .iter()
+        .find(|c| !available.split_whitespace().any(|a| a == **c))
+    {
+        return Err(anyhow!(
+            "{} controller not available in {}",
+            missing,
+            base.display()
+        ));
+    }
+    // Controllers only reach children of a cgroup without processes (the root
+    // excepted). If the wrapper is the only one here, step into a leaf.
+    let own = std::process::id().to_string();
+    let procs = fs::read_to_string(base.join("cgroup.procs")).unwrap_or_default();
+    if procs.split_whitespace().eq([own.as_str()]) {
+        let leaf = base.join("ay-wrapper");
+        fs::create_dir_all(&leaf)?;
+        fs::write(leaf.join("cgroup.procs"), &own)
+            .context("moving the wrapper into its leaf cgroup")?;
+    }
+    let enable: Vec<String> = controllers.iter().map(|c| format!("+{}", c)).collect();
+    fs::write(base.join("cgroup.subtree_control"), enable.join(" "))
+        .with_context(|| format!("enabling controllers in {}", base.display()))?;
+
+    let dir = base.join(format!("ay-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let result = (|| -> Result<()> {
+        for (file, value) in &files {
+            fs::write(dir.join(file), value).with_context(|| format!("writing {}", file))?;
+        }
+        fs::write(dir.join("cgroup.procs"), pid.to_string()).context("moving the agent in")?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_dir(&dir);
+        return Err(e);
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("1024").unwrap(), 1024);
+        assert_eq!(parse_bytes("512M").unwrap(), 512 << 20);
+        assert_eq!(parse_bytes("2g").unwrap(), 2 << 30);
+        assert_eq!(parse_bytes("1.5GiB").unwrap(), 3 << 29);
+        assert!(parse_bytes("lots").is_err());
+        assert!(parse_bytes("0").is_err());
+    }
+
+    #[test]
+    fn test_compile_and_overlay() {
+        let config = compile_resource_limits(Some(LimitsOverride {
+            memory_max: Some(LimitValue::Text("4G".into())),
+            cpu_max: Some(LimitValue::Number(2)),
+            pids_max: Some(256),
+            nofile: None,
+        }))
+        .unwrap();
+        assert_eq!(
+            config.cgroup_files(),
+            [
+                ("memory.max", (4u64 << 30).to_string()),
+                ("cpu.max", "200000 100000".to_string()),
+                ("pids.max", "256".to_string()),
+            ]
+        );
+        let flags = ResourceLimits {
+            cpu_max: Some(0.5),
+            nofile: Some(1024),
+            ..Default::default()
+        };
+        let merged = config.overlay(flags);
+        assert_eq!(merged.cpu_max, Some(0.5));
+        assert_eq!(merged.memory_max, Some(4 << 30));
+        assert_eq!(merged.nofile, Some(1024));
+        assert!(compile_resource_limits(None).unwrap().is_empty());
+        assert!(compile_resource_limits(Some(LimitsOverride {
+            cpu_max: Some(LimitValue::Text("all".into())),
+            ..Default::default()
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_proc_cgroup() {
+        assert_eq!(
+            parse_proc_cgroup("0::/user.slice/user-1000.slice/session-2.scope\n"),
+            Some("/user.slice/user-1000.slice/session-2.scope")
+        );
+        // cgroup v1 hierarchies only.
+        assert_eq!(parse_proc_cgroup("12:pids:/\n11:memory:/\n"), None);
+    }
+
+    /// The rlimit fallback lands on the agent, not the wrapper.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_set_rlimit_on_child() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        set_rlimit(child.id(), libc::RLIMIT_NOFILE, 64, "nofile");
+        let mut got = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        let rc = unsafe {
+            libc::prlimit(
+                child.id() as libc::pid_t,
+                libc::RLIMIT_NOFILE,
+                std::ptr::null(),
+                &mut got,
+            )
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        assert_eq!(rc, 0);
+        assert_eq!(got.rlim_cur, 64);
+    }
+}
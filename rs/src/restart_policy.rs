@@ -0,0 +1,258 @@
+//! Restart policy for `--robust` (`restart:` in the CLI config).
+//!
+//! `--robust` used to restart any non-zero exit, forever, right away. Now each
+//! exit is classed first:
+//!
+//! - `fatal`: a `fatal` screen pattern fired during the run, a `restart.fatal`
+//!   pattern is on the screen at exit, or the exit code is in
+//!   `fatalExitCodes` (default 126/127: the CLI can't even start) — restarting
+//!   won't help, so don't
+//! - `clean`: a `restart.clean` pattern, exit 0 or a `cleanExitCodes` code —
+//!   the CLI meant to stop
+//! - `retryable`: a `restart.retryable` pattern, the stall watchdog's exit 75,
+//!   or any other exit code
+//!
+//! Screen patterns are checked before exit codes, in that order.
+//!
+//! Only `retryable` exits restart, after an exponential backoff (`backoffInitialMs`
+//! doubled for every restart still inside `windowSecs`, capped at
+//! `backoffMaxMs`). More than `maxRestarts` restarts inside the window is a
+//! crash loop: the agent is parked as `crashloop` in the pid registry instead
+//! of restarting again. Every restart is kept in the record's `restarts`.
+//!
+//! ```yaml
+//! clis:
+//!   claude:
+//!     restart:
+//!       maxRestarts: 3
+//!       windowSecs: 300
+//!       fatal: ['Invalid API key']
+//! ```
+
+use crate::config_loader::{compile_regex_list, RestartOverride};
+use crate::pid_store::RestartEntry;
+use anyhow::Result;
+use regex::Regex;
+use std::time::Duration;
+
+const DEFAULT_MAX_RESTARTS: u32 = 5;
+const DEFAULT_WINDOW_SECS: u64 = 600;
+const DEFAULT_BACKOFF_INITIAL_MS: u64 = 1_000;
+const DEFAULT_BACKOFF_MAX_MS: u64 = 60_000;
+const DEFAULT_FATAL_EXIT_CODES: [i32; 2] = [126, 127];
+
+/// Rows of the final screen matched against the `restart` patterns.
+pub const EXIT_SCREEN_TAIL_LINES: usize = 20;
+
+/// The stall watchdog's exit code (EX_TEMPFAIL): the wrapper itself asked
+/// for the restart, so it is always retryable.
+pub const STALL_EXIT_CODE: i32 = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitClass {
+    Clean,
+    Retryable,
+    Fatal,
+}
+
+impl ExitClass {
+    pub fn name(self) -> &'static str {
+        match self {
+            ExitClass::Clean => "clean",
+            ExitClass::Retryable => "retryable",
+            ExitClass::Fatal => "fatal",
+        }
+    }
+}
+
+/// Compiled `restart:` section.
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    pub max_restarts: u32,
+    pub window: Duration,
+    pub backoff_initial: Duration,
+    pub backoff_max: Duration,
+    pub fatal_exit_codes: Vec<i32>,
+    pub clean_exit_codes: Vec<i32>,
+    pub fatal: Vec<Regex>,
+    pub retryable: Vec<Regex>,
+    pub clean: Vec<Regex>,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        compile_restart_policy(None).expect("no patterns to compile")
+    }
+}
+
+pub fn compile_restart_policy(raw: Option<RestartOverride>) -> Result<RestartPolicy> {
+    let raw = raw.unwrap_or_default();
+    Ok(RestartPolicy {
+        max_restarts: raw.max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS),
+        window: Duration::from_secs(raw.window_secs.unwrap_or(DEFAULT_WINDOW_SECS)),
+        backoff_initial: Duration::from_millis(
+            raw.backoff_initial_ms.unwrap_or(DEFAULT_BACKOFF_INITIAL_MS),
+        ),
+        backoff_max: Duration::from_millis(raw.backoff_max_ms.unwrap_or(DEFAULT_BACKOFF_MAX_MS)),
+        fatal_exit_codes: raw
+            .fatal_exit_codes
+            .unwrap_or_else(|| DEFAULT_FATAL_EXIT_CODES.to_vec()),
+        clean_exit_codes: raw.clean_exit_codes.unwrap_or_default(),
+        fatal: compile_regex_list(raw.fatal)?,
+        retryable: compile_regex_list(raw.retryable)?,
+        clean: compile_regex_list(raw.clean)?,
+    })
+}
+
+/// What to do after a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartDecision {
+    /// Restart after this delay.
+    Restart(Duration),
+    /// Too many restarts inside the window: park the agent.
+    CrashLoop,
+    /// Not worth restarting (fatal or clean).
+    Stop,
+}
+
+impl RestartPolicy {
+    /// Class one exit. `fatal_seen` is the run's own `fatal` pattern match;
+    /// `screen` is the tail of the screen the CLI left behind.
+    pub fn classify(&self, exit_code: i32, fatal_seen: bool, screen: &str) -> ExitClass {
+        if fatal_seen || self.fatal.iter().any(|re| re.is_match(screen)) {
+            return ExitClass::Fatal;
+        }
+        if self.clean.iter().any(|re| re.is_match(screen)) {
+            return ExitClass::Clean;
+        }
+        if exit_code == STALL_EXIT_CODE || self.retryable.iter().any(|re| re.is_match(screen)) {
+            return ExitClass::Retryable;
+        }
+        if self.fatal_exit_codes.contains(&exit_code) {
+            ExitClass::Fatal
+        } else if exit_code == 0 || self.clean_exit_codes.contains(&exit_code) {
+            ExitClass::Clean
+        } else {
+            ExitClass::Retryable
+        }
+    }
+
+    /// Decide on a restart given the history so far (oldest first) at `now_ms`.
+    pub fn decide(
+        &self,
+        class: ExitClass,
+        history: &[RestartEntry],
+        now_ms: i64,
+    ) -> RestartDecision {
+        if class != ExitClass::Retryable {
+            return RestartDecision::Stop;
+        }
+        let window_ms = self.window.as_millis() as i64;
+        let recent = history
+            .iter()
+            .filter(|e| now_ms.saturating_sub(e.at) < window_ms)
+            .count() as u32;
+        if self.max_restarts > 0 && recent >= self.max_restarts {
+            return RestartDecision::CrashLoop;
+        }
+        let delay = self
+            .backoff_initial
+            .saturating_mul(2u32.saturating_pow(recent))
+            .min(self.backoff_max);
+        RestartDecision::Restart(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_loader::RegexSource;
+
+    fn entry(at: i64) -> RestartEntry {
+        RestartEntry {
+            at,
+            exit_code: 1,
+            exit_reason: "crashed".into(),
+            class: "retryable".into(),
+            delay_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let policy = compile_restart_policy(Some(RestartOverride {
+            clean_exit_codes: Some(vec![130]),
+            fatal: Some(vec![RegexSource::Pattern("Invalid API key".into())]),
+            retryable: Some(vec![RegexSource::Pattern("ECONNRESET".into())]),
+            ..Default::default()
+        }))
+        .unwrap();
+        assert_eq!(policy.classify(1, false, "> "), ExitClass::Retryable);
+        assert_eq!(policy.classify(1, true, "> "), ExitClass::Fatal);
+        assert_eq!(policy.classify(0, false, "> "), ExitClass::Clean);
+        assert_eq!(policy.classify(130, false, "> "), ExitClass::Clean);
+        assert_eq!(policy.classify(127, false, "not found"), ExitClass::Fatal);
+        assert_eq!(
+            policy.classify(1, false, "Invalid API key"),
+            ExitClass::Fatal
+        );
+        // A retryable pattern beats the exit code, fatal or clean.
+        assert_eq!(
+            policy.classify(0, false, "ECONNRESET"),
+            ExitClass::Retryable
+        );
+        assert_eq!(
+            policy.classify(STALL_EXIT_CODE, false, "> "),
+            ExitClass::Retryable
+        );
+    }
+
+    #[test]
+    fn test_decide_backs_off_then_parks() {
+        let policy = RestartPolicy {
+            max_restarts: 3,
+            ..Default::default()
+        };
+        let now = 1_000_000_000;
+        let mut history = vec![];
+        let mut delays = vec![];
+        loop {
+            match policy.decide(ExitClass::Retryable, &history, now) {
+                RestartDecision::Restart(delay) => {
+                    delays.push(delay.as_millis());
+                    history.push(entry(now));
+                }
+                decision => {
+                    assert_eq!(decision, RestartDecision::CrashLoop);
+                    break;
+                }
+            }
+        }
+        assert_eq!(delays, [1000, 2000, 4000]);
+
+        // Restarts that fell out of the window no longer count.
+        let old = now - policy.window.as_millis() as i64;
+        let history = vec![entry(old), entry(old), entry(old)];
+        assert_eq!(
+            policy.decide(ExitClass::Retryable, &history, now),
+            RestartDecision::Restart(Duration::from_secs(1))
+        );
+        assert_eq!(
+            policy.decide(ExitClass::Fatal, &[], now),
+            RestartDecision::Stop
+        );
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RestartPolicy {
+            max_restarts: 0,
+            ..Default::default()
+        };
+        let history: Vec<_> = (0..40).map(|_| entry(0)).collect();
+        assert_eq!(
+            policy.decide(ExitClass::Retryable, &history, 1),
+            RestartDecision::Restart(Duration::from_secs(60))
+        );
+    }
+}
@@ -0,0 +1,746 @@
+//! Sandboxed spawn (`--sandbox`, `sandbox:` in the CLI config). Linux only.
+//!
+//! With `-y` the CLI's own permission gate is off and the agent can touch
+//! everything the user can. `--sandbox` runs it in unprivileged user, mount and
+//! pid namespaces instead, over a filesystem built from an allowlist:
+//!
+//! - the cwd: read-write
+//! - `readWrite` paths (the CLI's own state, e.g. `~/.claude`): read-write
+//! - `readOnly` paths: the toolchain (`/usr`, `/etc`, ...) by default, plus
+//!   the directory of the CLI binary itself; add more per CLI
+//! - a minimal `/dev` (null, zero, full, random, urandom, tty, its own pts),
+//!   a `/proc` of its own and a private `/tmp`
+//!
+//! Everything else (the rest of `$HOME` included) does not exist in there.
+//! Host processes don't either: their `/proc/<pid>/root` and `cwd` would be
+//! a way back out to the whole filesystem.
+//! `network: false` also gives the agent its own network namespace with only
+//! a loopback interface.
+//!
+//! ```yaml
+//! clis:
+//!   claude:
+//!     sandbox:
+//!       readWrite: ['~/.claude', '~/.claude.json']
+//!       readOnly: ['~/.nvm']
+//!       network: true
+//! ```
+//!
+//! portable-pty has no pre-exec hook, so the PTY runs this binary again as
+//! `<exe> __sandbox-exec <cli> <args...>` with the profile in
+//! `AGENT_YES_SANDBOX`; that helper builds the namespaces (before any other
+//! thread exists — `unshare(CLONE_NEWUSER)` refuses a threaded process). The
+//! new pid namespace only takes effect for children, so the helper forks its
+//! init (pid 1, which builds the filesystem and reaps orphans) and that forks
+//! the CLI; both stay behind, passing SIGTERM, SIGHUP and SIGUSR1/2 down and
+//! the CLI's exit code up. If the sandbox can't be set up the helper exits
+//! 126 rather than running the CLI unconfined, which the restart policy treats
+//! as fatal.
+
+use crate::agent_permissions::SandboxStamp;
+use crate::config_loader::SandboxOverride;
+use anyhow::{anyhow, Result};
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// argv[1] that turns this binary into the sandbox helper.
+pub const SANDBOX_EXEC_ARG: &str = "__sandbox-exec";
+
+/// Env var carrying the profile (JSON) to the helper. Removed before the exec.
+const SANDBOX_ENV: &str = "AGENT_YES_SANDBOX";
+
+/// The helper's exit code when the sandbox can't be built (the usual "found
+/// but cannot execute").
+const SANDBOX_FAILED_EXIT_CODE: i32 = 126;
+
+/// Visible read-only in every sandbox: enough to run a toolchain. `/run` is
+/// left out on purpose — it holds sockets (docker, dbus) that a read-only
+/// mount doesn't stop anyone from connecting to.
+const DEFAULT_READ_ONLY: &[&str] = &[
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/libx32",
+    "/etc",
+    "/opt",
+    "/nix",
+    "/run/systemd/resolve",
+];
+
+/// Compiled `sandbox:` section (and `--sandbox`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sandbox {
+    pub enabled: bool,
+    pub read_only: Vec<String>,
+    pub read_write: Vec<String>,
+    pub network: bool,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        compile_sandbox(None).expect("default paths are absolute")
+    }
+}
+
+pub fn compile_sandbox(raw: Option<SandboxOverride>) -> Result<Sandbox> {
+    let raw = raw.unwrap_or_default();
+    let home = std::env::var("HOME").ok();
+    let expand = |paths: Vec<String>| -> Result<Vec<String>> {
+        paths
+            .iter()
+            .map(|p| expand_path(p, home.as_deref()))
+            .collect()
+    };
+    let mut read_only: Vec<String> = DEFAULT_READ_ONLY.iter().map(|p| p.to_string()).collect();
+    read_only.extend(expand(raw.read_only.unwrap_or_default())?);
+    Ok(Sandbox {
+        enabled: raw.enabled.unwrap_or(false),
+        read_only,
+        read_write: expand(raw.read_write.unwrap_or_default())?,
+        network: raw.network.unwrap_or(true),
+    })
+}
+
+/// `~` and `~/x` against `home`; anything else must already be absolute.
+fn expand_path(path: &str, home: Option<&str>) -> Result<String> {
+    let expanded = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = home.ok_or_else(|| anyhow!("Sandbox path '{}' needs $HOME", path))?;
+            format!("{}{}", home.trim_end_matches('/'), rest)
+        }
+        _ => path.to_string(),
+    };
+    if !expanded.starts_with('/') {
+        return Err(anyhow!(
+            "Sandbox path '{}' must be absolute or start with ~/",
+            path
+        ));
+    }
+    Ok(expanded)
+}
+
+impl Sandbox {
+    /// What the pid record says the agent ran under, or None when unsandboxed.
+    pub fn stamp(&self, cwd: &str) -> Option<SandboxStamp> {
+        if !self.enabled {
+            return None;
+        }
+        let mut read_write = vec![cwd.to_string()];
+        read_write.extend(self.read_write.iter().cloned());
+        Some(SandboxStamp {
+            read_only: self.read_only.clone(),
+            read_write,
+            network: self.network,
+        })
+    }
+}
+
+/// The command the PTY spawns for `binary`: the binary itself, or the sandbox
+/// helper wrapping it.
+pub fn command(binary: &str, sandbox: &Sandbox) -> Result<CommandBuilder> {
+    if !sandbox.enabled {
+        return Ok(CommandBuilder::new(binary));
+    }
+    if !cfg!(target_os = "linux") {
+        return Err(anyhow!("--sandbox needs Linux namespaces"));
+    }
+    // The CLI must be able to see its own binary: its directory, and where a
+    // symlinked install (~/.local/bin/claude → ~/.local/share/claude/...)
+    // really lives.
+    let mut profile = sandbox.clone();
+    let binary_path = Path::new(binary);
+    for path in [
+        Some(binary_path.to_path_buf()),
+        std::fs::canonicalize(binary_path).ok(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if let Some(dir) = path.parent().filter(|d| d.is_absolute()) {
+            profile.read_only.push(dir.to_string_lossy().into_owned());
+        }
+    }
+    let mut cmd = CommandBuilder::new(std::env::current_exe()?);
+    cmd.arg(SANDBOX_EXEC_ARG);
+    cmd.arg(binary);
+    cmd.env(SANDBOX_ENV, serde_json::to_string(&profile)?);
+    Ok(cmd)
+}
+
+/// One bind mount of the sandbox's filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bind {
+    /// Where it shows up inside the sandbox.
+    path: String,
+    /// What gets mounted there: `path` with symlinks resolved on the host.
+    source: PathBuf,
+    writable: bool,
+}
+
+/// The binds for `sandbox` plus the cwd, parents before children so a
+/// read-write path inside a read-only one stays writable. Paths that don't
+/// exist are skipped; a path listed both ways is read-write.
+fn mount_plan(sandbox: &Sandbox, cwd: &str) -> Vec<Bind> {
+    let mut binds: Vec<Bind> = Vec::new();
+    let entries = sandbox
+        .read_only
+        .iter()
+        .map(|p| (p.as_str(), false))
+        .chain(sandbox.read_write.iter().map(|p| (p.as_str(), true)))
+        .chain(std::iter::once((cwd, true)));
+    for (path, writable) in entries {
+        let path = path.trim_end_matches('/');
+        let path = if path.is_empty() { "/" } else { path };
+        let Ok(source) = std::fs::canonicalize(path) else {
+            continue;
+        };
+        match binds.iter_mut().find(|b| b.path == path) {
+            Some(existing) => existing.writable |= writable,
+            None => binds.push(Bind {
+                path: path.to_string(),
+                source,
+                writable,
+            }),
+        }
+    }
+    binds.sort_by_key(|b| Path::new(&b.path).components().count());
+    binds
+}
+
+/// Entry point for the helper: when this process is `__sandbox-exec`, build
+/// the sandbox and exec the CLI. Returns the exit code only on failure.
+/// Must run before the tokio runtime starts its threads.
+pub fn maybe_exec() -> Option<i32> {
+    let mut raw = std::env::args_os().skip(1);
+    if raw.next()? != SANDBOX_EXEC_ARG {
+        return None;
+    }
+    let argv: Vec<_> = raw.collect();
+    let result = (|| -> Result<std::convert::Infallible> {
+        let (binary, args) = argv
+            .split_first()
+            .ok_or_else(|| anyhow!("missing the command to run"))?;
+        let profile: Sandbox = serde_json::from_str(
+            &std::env::var(SANDBOX_ENV).map_err(|_| anyhow!("{} is not set", SANDBOX_ENV))?,
+        )?;
+        let cwd = std::env::current_dir()?.to_string_lossy().into_owned();
+        enter(&profile, &cwd)?;
+        exec(binary, args)
+    })();
+    let e = match result {
+        Ok(never) => match never {},
+        Err(e) => e,
+    };
+    eprintln!("agent-yes: sandbox: {:#}", e);
+    Some(SANDBOX_FAILED_EXIT_CODE)
+}
+
+#[cfg(unix)]
+fn exec(binary: &std::ffi::OsStr, args: &[std::ffi::OsString]) -> Result<std::convert::Infallible> {
+    use std::os::unix::process::CommandExt;
+    let err = std::process::Command::new(binary)
+        .args(args)
+        .env_remove(SANDBOX_ENV)
+        .exec();
+    Err(anyhow!("exec {:?}: {}", binary, err))
+}
+
+#[cfg(not(unix))]
+fn exec(
+    _binary: &std::ffi::OsStr,
+    _args: &[std::ffi::OsString],
+) -> Result<std::convert::Infallible> {
+    Err(anyhow!("--sandbox needs Linux namespaces"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn enter(_sandbox: &Sandbox, _cwd: &str) -> Result<()> {
+    Err(anyhow!("--sandbox needs Linux namespaces"))
+}
+
+/// Build the sandbox around this process, bubblewrap-style:
+///
+/// 1. new user + mount + pid (+ network) namespaces, mapping our uid/gid to
+///    itself; fork into the pid namespace as its init
+/// 2. a tmpfs pivoted in as `/`, the old root kept at `/oldroot`
+/// 3. each bind of the plan from `/oldroot` into a fresh tmpfs at `/newroot`,
+///    then `/dev` and a `proc` of the new pid namespace
+/// 4. `/newroot` pivoted in as `/`, `/oldroot` and the staging tmpfs dropped
+/// 5. fork the process that returns, to exec the CLI as pid 2
+#[cfg(target_os = "linux")]
+fn enter(sandbox: &Sandbox, cwd: &str) -> Result<()> {
+    use anyhow::Context;
+    use std::fs;
+
+    // Resolve symlinks (/bin → usr/bin) while the host root is still `/`.
+    let plan = mount_plan(sandbox, cwd);
+    let home = std::env::var("HOME").ok();
+
+    let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+    let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWPID;
+    if !sandbox.network {
+        flags |= libc::CLONE_NEWNET;
+    }
+    check(unsafe { libc::unshare(flags) })
+        .context("unshare (are unprivileged user namespaces enabled?)")?;
+    // setgroups must be denied before an unprivileged gid_map write; kernels
+    // older than 3.19 don't have the file.
+    match fs::write("/proc/self/setgroups", "deny") {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e).context("/proc/self/setgroups")
+        }
+        _ => {}
+    }
+    fs::write("/proc/self/uid_map", format!("{uid} {uid} 1")).context("uid_map")?;
+    fs::write("/proc/self/gid_map", format!("{gid} {gid} 1")).context("gid_map")?;
+    if !sandbox.network {
+        loopback_up().context("bringing up lo")?;
+    }
+    // The init of the new pid namespace; when it goes, so does everything in
+    // there.
+    fork_supervised(true).context("fork")?;
+
+    // Nothing we mount from here on leaks back to the host.
+    mount(None, "/", None, libc::MS_SLAVE | libc::MS_REC, None)?;
+    mount(
+        Some("tmpfs"),
+        "/tmp",
+        Some("tmpfs"),
+        libc::MS_NOSUID | libc::MS_NODEV,
+        Some("mode=0755"),
+    )?;
+    fs::create_dir("/tmp/newroot")?;
+    fs::create_dir("/tmp/oldroot")?;
+    pivot_root("/tmp", "/tmp/oldroot")?;
+    std::env::set_current_dir("/")?;
+    mount(
+        Some("tmpfs"),
+        "/newroot",
+        Some("tmpfs"),
+        libc::MS_NOSUID | libc::MS_NODEV,
+        Some("mode=0755"),
+    )?;
+
+    // A private /tmp first, so a cwd or path under /tmp lands on top of it.
+    if !plan.iter().any(|b| b.path == "/tmp") {
+        fs::create_dir_all("/newroot/tmp")?;
+        mount(
+            Some("tmpfs"),
+            "/newroot/tmp",
+            Some("tmpfs"),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            Some("mode=1777"),
+        )?;
+    }
+    for bind in &plan {
+        let source = Path::new("/oldroot").join(bind.source.strip_prefix("/")?);
+        let target = format!("/newroot{}", bind.path.trim_end_matches('/'));
+        if source.is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            if let Some(parent) = Path::new(&target).parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if !Path::new(&target).exists() {
+                fs::File::create(&target)?;
+            }
+        }
+        mount(
+            Some(&source.to_string_lossy()),
+            &target,
+            None,
+            libc::MS_BIND | libc::MS_REC,
+            None,
+        )
+        .with_context(|| format!("binding {}", bind.path))?;
+        if !bind.writable {
+            remount_read_only(&target).with_context(|| format!("read-only {}", bind.path))?;
+        }
+    }
+    minimal_dev("/newroot/dev").context("building /dev")?;
+    // Only the sandbox's own processes. This has to happen while the host's
+    // /proc is still mounted: the kernel won't mount a proc that reveals more
+    // than one already visible.
+    fs::create_dir_all("/newroot/proc")?;
+    mount(
+        Some("proc"),
+        "/newroot/proc",
+        Some("proc"),
+        libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+        None,
+    )?;
+    // An empty home beats no home: tools cd there and write dotfiles.
+    if let Some(home) = home.filter(|h| h.starts_with('/')) {
+        let _ = fs::create_dir_all(format!("/newroot{home}"));
+    }
+
+    check(unsafe { libc::umount2(c"/oldroot".as_ptr(), libc::MNT_DETACH) })
+        .context("detaching the host root")?;
+    std::env::set_current_dir("/newroot")?;
+    pivot_root(".", ".")?;
+    check(unsafe { libc::umount2(c".".as_ptr(), libc::MNT_DETACH) })
+        .context("detaching the staging root")?;
+    std::env::set_current_dir("/")?;
+    // The skeleton between the binds is ours to freeze.
+    let _ = mount(
+        None,
+        "/",
+        None,
+        libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY | libc::MS_NOSUID | libc::MS_NODEV,
+        None,
+    );
+    std::env::set_current_dir(cwd).with_context(|| format!("cd {}", cwd))?;
+    // The CLI runs as pid 2: pid 1 drops every signal it has no handler for.
+    fork_supervised(false).context("fork")
+}
+
+/// A tmpfs at `target` with the usual device nodes bound in from the host's
+/// `/dev`, a devpts of its own and the `/dev/fd` links. Not the host's
+/// `/dev`: its `pts` are every other terminal of the user.
+#[cfg(target_os = "linux")]
+fn minimal_dev(target: &str) -> Result<()> {
+    use std::fs;
+    use std::os::unix::fs::symlink;
+
+    fs::create_dir_all(target)?;
+    mount(
+        Some("tmpfs"),
+        target,
+        Some("tmpfs"),
+        libc::MS_NOSUID,
+        Some("mode=0755"),
+    )?;
+    for node in ["null", "zero", "full", "random", "urandom", "tty"] {
+        let source = format!("/oldroot/dev/{node}");
+        if !Path::new(&source).exists() {
+            continue;
+        }
+        let path = format!("{target}/{node}");
+        fs::File::create(&path)?;
+        mount(Some(&source), &path, None, libc::MS_BIND, None)?;
+    }
+    fs::create_dir(format!("{target}/pts"))?;
+    mount(
+        Some("devpts"),
+        &format!("{target}/pts"),
+        Some("devpts"),
+        libc::MS_NOSUID | libc::MS_NOEXEC,
+        Some("newinstance,ptmxmode=0666,mode=620"),
+    )?;
+    symlink("pts/ptmx", format!("{target}/ptmx"))?;
+    fs::create_dir(format!("{target}/shm"))?;
+    mount(
+        Some("tmpfs"),
+        &format!("{target}/shm"),
+        Some("tmpfs"),
+        libc::MS_NOSUID | libc::MS_NODEV,
+        Some("mode=1777"),
+    )?;
+    for (name, link) in [
+        ("fd", "/proc/self/fd"),
+        ("stdin", "/proc/self/fd/0"),
+        ("stdout", "/proc/self/fd/1"),
+        ("stderr", "/proc/self/fd/2"),
+    ] {
+        symlink(link, format!("{target}/{name}"))?;
+    }
+    Ok(())
+}
+
+/// The pid the supervisor passes signals on to.
+#[cfg(target_os = "linux")]
+static SUPERVISED: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(0);
+
+#[cfg(target_os = "linux")]
+extern "C" fn forward_signal(sig: libc::c_int) {
+    let pid = SUPERVISED.load(std::sync::atomic::Ordering::Relaxed);
+    if pid > 0 {
+        unsafe { libc::kill(pid, sig) };
+    }
+}
+
+/// For the terminal's own signals: they reach the whole foreground group,
+/// the CLI included, and a second copy from us would count double.
+#[cfg(target_os = "linux")]
+extern "C" fn swallow_signal(_sig: libc::c_int) {}
+
+/// Fork. The child returns; this process stays behind as its supervisor,
+/// passing signals down, reaping whatever gets reparented to it, and exits
+/// with the child's code (128 + signal when killed) — never returning.
+/// `die_with_parent` kills the child if this process's parent goes first.
+#[cfg(target_os = "linux")]
+fn fork_supervised(die_with_parent: bool) -> Result<()> {
+    let child = unsafe { libc::fork() };
+    check(child)?;
+    if child == 0 {
+        if die_with_parent {
+            unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) };
+        }
+        return Ok(());
+    }
+    SUPERVISED.store(child, std::sync::atomic::Ordering::Relaxed);
+    unsafe {
+        for sig in [libc::SIGTERM, libc::SIGHUP, libc::SIGUSR1, libc::SIGUSR2] {
+            libc::signal(sig, forward_signal as *const () as libc::sighandler_t);
+        }
+        for sig in [libc::SIGINT, libc::SIGQUIT] {
+            libc::signal(sig, swallow_signal as *const () as libc::sighandler_t);
+        }
+    }
+    let code = loop {
+        let mut status = 0;
+        let reaped = unsafe { libc::waitpid(-1, &mut status, 0) };
+        if reaped == -1 {
+            if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            break SANDBOX_FAILED_EXIT_CODE;
+        }
+        if reaped != child {
+            continue;
+        }
+        if libc::WIFEXITED(status) {
+            break libc::WEXITSTATUS(status);
+        }
+        if libc::WIFSIGNALED(status) {
+            break 128 + libc::WTERMSIG(status);
+        }
+    };
+    std::process::exit(code)
+}
+
+#[cfg(target_os = "linux")]
+fn check(ret: libc::c_int) -> std::io::Result<()> {
+    if ret == -1 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn cstring(s: &str) -> Result<std::ffi::CString> {
+    Ok(std::ffi::CString::new(s)?)
+}
+
+#[cfg(target_os = "linux")]
+fn mount(
+    source: Option<&str>,
+    target: &str,
+    fstype: Option<&str>,
+    flags: libc::c_ulong,
+    data: Option<&str>,
+) -> Result<()> {
+    let source = source.map(cstring).transpose()?;
+    let fstype = fstype.map(cstring).transpose()?;
+    let data = data.map(cstring).transpose()?;
+    let c_target = cstring(target)?;
+    let ptr = |s: &Option<std::ffi::CString>| s.as_ref().map_or(std::ptr::null(), |s| s.as_ptr());
+    check(unsafe {
+        libc::mount(
+            ptr(&source),
+            c_target.as_ptr(),
+            ptr(&fstype),
+            flags,
+            ptr(&data).cast(),
+        )
+    })
+    .map_err(|e| anyhow!("mount {}: {}", target, e))
+}
+
+#[cfg(target_os = "linux")]
+fn pivot_root(new_root: &str, put_old: &str) -> Result<()> {
+    let (new_root_c, put_old_c) = (cstring(new_root)?, cstring(put_old)?);
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_pivot_root,
+            new_root_c.as_ptr(),
+            put_old_c.as_ptr(),
+        )
+    };
+    check(ret as libc::c_int).map_err(|e| anyhow!("pivot_root {}: {}", new_root, e))
+}
+
+/// Make `target` and every mount below it read-only. A remount inside a user
+/// namespace must repeat the flags the mount is locked with (nosuid, nodev,
+/// ...), or the kernel refuses it. Runs between the pivots, with the host's
+/// `/proc` under `/oldroot`.
+#[cfg(target_os = "linux")]
+fn remount_read_only(target: &str) -> Result<()> {
+    const ST_RELATIME: libc::c_ulong = 4096;
+    let mountinfo = std::fs::read_to_string("/oldroot/proc/self/mountinfo")?;
+    for mount_point in mounts_under(&mountinfo, target) {
+        let c_path = cstring(&mount_point)?;
+        let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+        check(unsafe { libc::statvfs(c_path.as_ptr(), &mut st) })
+            .map_err(|e| anyhow!("statvfs {}: {}", mount_point, e))?;
+        let mut flags = libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY;
+        for (st_flag, ms_flag) in [
+            (libc::ST_NOSUID, libc::MS_NOSUID),
+            (libc::ST_NODEV, libc::MS_NODEV),
+            (libc::ST_NOEXEC, libc::MS_NOEXEC),
+            (libc::ST_NOATIME, libc::MS_NOATIME),
+            (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+            (ST_RELATIME, libc::MS_RELATIME),
+        ] {
+            if st.f_flag & st_flag != 0 {
+                flags |= ms_flag;
+            }
+        }
+        mount(None, &mount_point, None, flags, None)?;
+    }
+    Ok(())
+}
+
+/// Mount points in `mountinfo` at or below `target`, outermost first.
+#[cfg(any(target_os = "linux", test))]
+fn mounts_under(mountinfo: &str, target: &str) -> Vec<String> {
+    let mut found: Vec<String> = mountinfo
+        .lines()
+        .filter_map(|line| line.split(' ').nth(4))
+        .map(unescape_mountinfo)
+        .filter(|p| {
+            p == target || (p.starts_with(target) && p.as_bytes().get(target.len()) == Some(&b'/'))
+        })
+        .collect();
+    found.dedup();
+    found
+}
+
+/// mountinfo escapes space, tab, newline and backslash as `\ooo`.
+#[cfg(any(target_os = "linux", test))]
+fn unescape_mountinfo(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && i + 3 < bytes.len()
+            && bytes[i + 1..i + 4]
+                .iter()
+                .all(|b| (b'0'..=b'7').contains(b))
+        {
+            let digits = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("0");
+            out.push(u8::from_str_radix(digits, 8).unwrap_or(b'?'));
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// A fresh network namespace starts with `lo` down.
+#[cfg(target_os = "linux")]
+fn loopback_up() -> Result<()> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    check(fd)?;
+    let mut req: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in req.ifr_name.iter_mut().zip(b"lo") {
+        *dst = *src as libc::c_char;
+    }
+    let result = (|| {
+        check(unsafe { libc::ioctl(fd, libc::SIOCGIFFLAGS as _, &mut req) })?;
+        unsafe { req.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short };
+        check(unsafe { libc::ioctl(fd, libc::SIOCSIFFLAGS as _, &req) })
+    })();
+    unsafe { libc::close(fd) };
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_sandbox() {
+        let sandbox = compile_sandbox(Some(SandboxOverride {
+            read_write: Some(vec!["~/.claude".into(), "/srv/cache/".into()]),
+            read_only: Some(vec!["~".into()]),
+            network: Some(false),
+            ..Default::default()
+        }))
+        .unwrap();
+        let home = std::env::var("HOME").unwrap();
+        assert!(!sandbox.enabled);
+        assert!(!sandbox.network);
+        assert_eq!(
+            sandbox.read_write,
+            [
+                format!("{}/.claude", home.trim_end_matches('/')),
+                "/srv/cache/".into()
+            ]
+        );
+        assert!(sandbox.read_only.contains(&"/usr".to_string()));
+        assert_eq!(
+            sandbox.read_only.last(),
+            Some(&home.trim_end_matches('/').to_string())
+        );
+
+        let relative = SandboxOverride {
+            read_write: Some(vec!["projects".into()]),
+            ..Default::default()
+        };
+        assert!(compile_sandbox(Some(relative)).is_err());
+        assert!(Sandbox::default().network);
+        assert_eq!(expand_path("~x/y", Some("/home/a")).ok(), None);
+    }
+
+    #[test]
+    fn test_stamp_includes_the_cwd() {
+        let mut sandbox = Sandbox::default();
+        assert_eq!(sandbox.stamp("/work"), None);
+        sandbox.enabled = true;
+        sandbox.read_write = vec!["/home/a/.claude".into()];
+        let stamp = sandbox.stamp("/work").unwrap();
+        assert_eq!(stamp.read_write, ["/work", "/home/a/.claude"]);
+        assert!(stamp.network);
+    }
+
+    #[test]
+    fn test_mount_plan_orders_parents_first_and_merges_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(dir.path()).unwrap();
+        let root = root.to_string_lossy();
+        std::fs::create_dir_all(format!("{root}/a/b")).unwrap();
+        let sandbox = Sandbox {
+            enabled: true,
+            read_only: vec![
+                format!("{root}/a/b"),
+                format!("{root}/a"),
+                format!("{root}/missing"),
+            ],
+            read_write: vec![format!("{root}/a/b/")],
+            network: true,
+        };
+        let plan = mount_plan(&sandbox, &root);
+        let summary: Vec<_> = plan.iter().map(|b| (b.path.clone(), b.writable)).collect();
+        assert_eq!(
+            summary,
+            [
+                (root.to_string(), true),
+                (format!("{root}/a"), false),
+                (format!("{root}/a/b"), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mounts_under() {
+        let mountinfo = "\
+22 1 0:21 / / rw - ext4 /dev/sda1 rw
+23 22 0:22 / /newroot/usr rw - ext4 /dev/sda1 rw
+24 23 0:23 / /newroot/usr/local rw - ext4 /dev/sda2 rw
+25 22 0:24 / /newroot/usrx rw - ext4 /dev/sda3 rw
+26 23 0:25 / /newroot/usr/my\\040dir rw - tmpfs tmpfs rw";
+        assert_eq!(
+            mounts_under(mountinfo, "/newroot/usr"),
+            ["/newroot/usr", "/newroot/usr/local", "/newroot/usr/my dir"]
+        );
+    }
+}
@@ -494,6 +494,8 @@ mod tests {
             ("POST", "/api/kill"),
             ("POST", "/api/restart"),
             ("POST", "/api/spawn"),
+            ("POST", "/api/rollback"),
+            ("GET", "/api/checkpoints/unknown-kw"),
             ("GET", "/api/notes"),
             ("GET", "/api/spawn-config"),
             ("GET", "/api/graph"),
@@ -1,11 +1,14 @@
 // Native Rust port of the minimal ay-serve API surface the browser console
 // needs over a WebRTC room: /api/ls, /api/ls/subscribe, /api/whoami,
-// /api/version, /api/host, /api/size/:kw, /api/tail/:kw, /api/send.
+// /api/version, /api/host, /api/size/:kw, /api/tail/:kw, /api/events/:kw,
+// /api/transcript/:kw, /api/titles/:kw, /api/usage, /api/send.
 // Everything else 404s — the console tolerates that and degrades.
 //
 // Response shapes mirror ts/serve.ts exactly (see that file for the source of
 // truth); data comes from the same files the TS daemon uses: pids.jsonl,
-// <cwd>/.agent-yes/<pid>.raw.log, and the per-pid stdin FIFOs.
+// <cwd>/.agent-yes/<pid>.raw.log, <pid>.events.jsonl, <pid>.transcript.jsonl,
+// <pid>.titles.jsonl, and the per-pid stdin
+// FIFOs.
 use crate::pid_store::{is_process_alive, PidRecord};
 use crate::serve::host_stats;
 use serde_json::{json, Value};
@@ -15,6 +18,10 @@ use std::time::Duration;
 use tokio::sync::mpsc;
 
 const TAIL_SNAPSHOT_BYTES: u64 = 65_536;
+/// Most bytes of `<pid>.events.jsonl` (or `.transcript.jsonl`) one
+/// /api/events (/api/transcript) response carries; the client pages through a
+/// long history with the returned `next` cursor.
+const EVENTS_PAGE_BYTES: u64 = 1024 * 1024;
 const SSE_PING_MS: u64 = 15_000;
 // A full tick enriches every agent from its live PTY log. One second saturates
 // a core on larger fleets whose logs are all moving, starving the WebRTC data
@@ -600,8 +607,12 @@ fn with_meta(r: &PidRecord) -> Value {
         "exited"
     } else if r.unresponsive {
         "stuck"
+    } else if r.waiting_for_quota.is_some() {
+        "waiting_for_quota"
     } else if !question.is_null() {
         "needs_input"
+    } else if r.status == "completed" {
+        "completed"
     } else if last_active
         .map(|t| now_ms() - t < ACTIVE_WINDOW_MS)
         .unwrap_or(false)
@@ -651,6 +662,9 @@ fn with_meta(r: &PidRecord) -> Value {
             if is_user_typing(r.pid) {
                 b.push(crate::serve::meta::TYPING_BADGE.to_string());
             }
+            if let Some(n) = &r.notification {
+                b.push(crate::serve::meta::notify_badge(n));
+            }
             json!(b)
         },
     );
@@ -915,6 +929,10 @@ fn spawn_tail(log_file: String, raw: bool) -> mpsc::Receiver<Vec<u8>> {
     let (tx, rx) = mpsc::channel::<Vec<u8>>(64);
     tokio::spawn(async move {
         let mut offset: u64 = 0;
+        // The log may predate redaction (or come from the TS wrapper), so redact
+        // again on the way out. A secret split across two polls is held back
+        // until the next one completes it, or released once the log goes quiet.
+        let mut redactor = crate::redact::StreamRedactor::new(crate::redact::global().clone());
         // snapshot: last 64KB of the file
         let snapshot = tokio::task::spawn_blocking({
             let log_file = log_file.clone();
@@ -933,7 +951,7 @@ fn spawn_tail(log_file: String, raw: bool) -> mpsc::Receiver<Vec<u8>> {
         match snapshot {
             Ok((size, buf)) => {
                 offset = size;
-                let text = decode_log(&buf, raw);
+                let text = redactor.feed(&decode_log(&buf, raw));
                 if tx.send(sse_frame(&json!(text))).await.is_err() {
                     return;
                 }
@@ -967,8 +985,12 @@ fn spawn_tail(log_file: String, raw: bool) -> mpsc::Receiver<Vec<u8>> {
             match read {
                 Ok((size, buf)) => {
                     offset = size;
-                    if !buf.is_empty() {
-                        let text = decode_log(&buf, raw);
+                    let text = if buf.is_empty() {
+                        redactor.flush()
+                    } else {
+                        redactor.feed(&decode_log(&buf, raw))
+                    };
+                    if !text.is_empty() {
                         let skip = !raw && text.trim().is_empty();
                         if !skip && tx.send(sse_frame(&json!(text))).await.is_err() {
                             return;
@@ -988,6 +1010,35 @@ fn spawn_tail(log_file: String, raw: bool) -> mpsc::Receiver<Vec<u8>> {
     rx
 }
 
+/// GET /api/events/:kw?since=<offset> — the agent's structured event stream
+/// (written by the wrapper's events.rs). `since` is a byte offset into the
+/// file, as returned in the previous response's `next`; only complete lines
+/// are returned, so a half-written event is picked up by the next poll. A
+/// cursor past the end means the file was pruned and recreated — start over.
+///
+/// GET /api/transcript/:kw?since=<offset> pages `<pid>.transcript.jsonl` (the
+/// wrapper's transcript.rs) the same way, with the lines under `turns`.
+fn read_jsonl_since(path: &std::path::Path, since: u64, key: &str) -> std::io::Result<Value> {
+    let mut f = match std::fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(json!({ key: [], "next": 0 }));
+        }
+        Err(e) => return Err(e),
+    };
+    let size = f.metadata()?.len();
+    let start = if since > size { 0 } else { since };
+    f.seek(SeekFrom::Start(start))?;
+    let mut buf = Vec::new();
+    f.take(EVENTS_PAGE_BYTES).read_to_end(&mut buf)?;
+    let complete = buf.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+    let lines: Vec<Value> = buf[..complete]
+        .split(|b| *b == b'\n')
+        .filter_map(|line| serde_json::from_slice(line).ok())
+        .collect();
+    Ok(json!({ key: lines, "next": start + complete as u64 }))
+}
+
 fn decode_log(buf: &[u8], raw: bool) -> String {
     if raw {
         String::from_utf8_lossy(buf).into_owned()
@@ -1060,7 +1111,55 @@ async fn handle_send(body: &str) -> ApiResponse {
     let Some(fifo) = rec.fifo_file.clone() else {
         return text(409, format!("pid {}: no fifo_file", rec.pid));
     };
-    let trailing = control_code(&code);
+    // Frames only go to a wrapper that advertises them; anything else gets
+    // the plain write it always did (see fifo::FRAMES_VERSION).
+    let frames = rec.frames.is_some_and(|v| v >= 1);
+    // `queue: true` appends to the agent's prompt queue instead of typing now:
+    // one framed write, no control code (see fifo::QUEUE_FRAME_START).
+    let queue = req.get("queue").and_then(|v| v.as_bool()).unwrap_or(false);
+    if queue && msg.trim().is_empty() {
+        return text(400, "queue: empty msg");
+    }
+    if queue && !frames {
+        return text(
+            409,
+            format!("pid {}: its wrapper has no prompt queue", rec.pid),
+        );
+    }
+    // A `/ay …` meta-command is for the wrapper, not the CLI: one framed
+    // write, never typed (see fifo::COMMAND_FRAME_START). An older wrapper
+    // picks it up from the typed line instead.
+    let command = frames && !queue && crate::fifo::is_meta_command(&msg);
+    let trailing = if queue || command {
+        ""
+    } else {
+        control_code(&code)
+    };
+    let msg = if command {
+        format!(
+            "{}{}{}",
+            crate::fifo::COMMAND_FRAME_START,
+            msg.trim(),
+            crate::fifo::QUEUE_FRAME_END
+        )
+    } else if queue {
+        format!(
+            "{}{}{}",
+            crate::fifo::QUEUE_FRAME_START,
+            msg,
+            crate::fifo::QUEUE_FRAME_END
+        )
+    } else if frames && msg.trim_end_matches(['\r', '\n']).contains(['\r', '\n']) {
+        // A multi-line body is typed as one prompt (see fifo::PASTE_FRAME_START).
+        format!(
+            "{}{}{}",
+            crate::fifo::PASTE_FRAME_START,
+            msg,
+            crate::fifo::QUEUE_FRAME_END
+        )
+    } else {
+        msg
+    };
     let write = |data: Vec<u8>| {
         let fifo = fifo.clone();
         tokio::task::spawn_blocking(move || write_fifo(&fifo, &data))
@@ -1086,7 +1185,11 @@ async fn handle_send(body: &str) -> ApiResponse {
     };
     match result {
         Ok(()) => {
-            crate::fifo::touch_stdin_activity(rec.pid);
+            // A queued prompt isn't typed yet — not input activity; nor is
+            // a meta-command.
+            if !queue && !command {
+                crate::fifo::touch_stdin_activity(rec.pid);
+            }
             json_res(
                 200,
                 &json!({
@@ -1095,6 +1198,8 @@ async fn handle_send(body: &str) -> ApiResponse {
                     "cli": rec.cli,
                     "cwd": rec.cwd,
                     "agentId": rec.agent_id,
+                    "queued": queue,
+                    "command": command,
                 }),
             )
         }
@@ -1377,6 +1482,61 @@ pub async fn handle(method: &str, path_with_query: &str, body: &str) -> ApiRespo
                 Err(e) => text(404, e),
             }
         }
+        ("GET", p) if p.starts_with("/api/events/") || p.starts_with("/api/transcript/") => {
+            let (kw, suffix, key) = match p.strip_prefix("/api/events/") {
+                Some(kw) => (kw, "events", "events"),
+                None => (&p["/api/transcript/".len()..], "transcript", "turns"),
+            };
+            let kw = url_decode(kw);
+            let since = q
+                .get("since")
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(0);
+            match resolve_one(&kw) {
+                Ok(r) => {
+                    let Some(dir) = crate::log_files::project_log_dir(&r.cwd) else {
+                        return text(404, format!("pid {}: no cwd", r.pid));
+                    };
+                    let path = dir.join(format!("{}.{}.jsonl", r.pid, suffix));
+                    tokio::task::spawn_blocking(move || read_jsonl_since(&path, since, key))
+                        .await
+                        .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+                        .map(|v| json_res(200, &v))
+                        .unwrap_or_else(|e| text(500, e.to_string()))
+                }
+                Err(e) => text(404, e),
+            }
+        }
+        // The agent's title history as phases with durations (the wrapper's
+        // title_history.rs); the current phase of a live agent runs to now.
+        ("GET", p) if p.starts_with("/api/titles/") => {
+            let kw = url_decode(&p["/api/titles/".len()..]);
+            match resolve_one(&kw) {
+                Ok(r) => {
+                    let Some(path) = crate::title_history::history_path(r.pid, &r.cwd) else {
+                        return text(404, format!("pid {}: no cwd", r.pid));
+                    };
+                    let now = is_process_alive(r.pid)
+                        .then(|| chrono::Utc::now().timestamp_millis() as u64);
+                    let phases = tokio::task::spawn_blocking(move || {
+                        crate::title_history::read_phases(&path, now)
+                    })
+                    .await
+                    .unwrap_or_default();
+                    json_res(200, &json!({ "titles": phases }))
+                }
+                Err(e) => text(404, e),
+            }
+        }
+        ("GET", "/api/usage") => {
+            let cwd = q.get("cwd").cloned();
+            let report = tokio::task::spawn_blocking(move || {
+                crate::serve::usage::usage_json(cwd.as_deref())
+            })
+            .await
+            .unwrap_or_default();
+            json_res(200, &report)
+        }
         ("POST", "/api/send") => handle_send(body).await,
         ("GET", "/api/spawn-config") => crate::serve::control::spawn_config(),
         ("GET", "/api/notes") => json_res(200, &crate::serve::discover::notes(&global_dir())),
@@ -1468,6 +1628,19 @@ pub async fn handle(method: &str, path_with_query: &str, body: &str) -> ApiRespo
                 .map(|v| json_res(200, &v))
                 .unwrap_or_else(|e| text(500, e.to_string()))
         }
+        // Both run git over the agent's whole work tree — blocking pool.
+        ("GET", p) if p.starts_with("/api/checkpoints/") => {
+            let kw = url_decode(&p["/api/checkpoints/".len()..]);
+            tokio::task::spawn_blocking(move || crate::serve::control::checkpoints(&kw))
+                .await
+                .unwrap_or_else(|e| text(500, e.to_string()))
+        }
+        ("POST", "/api/rollback") => {
+            let body = body.to_string();
+            tokio::task::spawn_blocking(move || crate::serve::control::rollback(&body))
+                .await
+                .unwrap_or_else(|e| text(500, e.to_string()))
+        }
         ("POST", "/api/kill") => crate::serve::control::kill(body),
         ("POST", "/api/restart") => crate::serve::control::restart(body),
         // A provisioned spawn (`from` clone / `fork` worktree) can run git for
@@ -1609,6 +1782,43 @@ mod tests {
         assert_eq!(parse_status_text(&["plain text".to_string()]), None);
     }
 
+    #[test]
+    fn events_since_cursor_returns_only_complete_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1.events.jsonl");
+        let first = "{\"at\":1,\"type\":\"state\",\"ready\":true,\"working\":false}\n";
+        std::fs::write(&path, format!("{first}{{\"at\":2,\"type\":\"enter")).unwrap();
+
+        let page = read_jsonl_since(&path, 0, "events").unwrap();
+        assert_eq!(page["events"].as_array().unwrap().len(), 1);
+        assert_eq!(page["events"][0]["type"], "state");
+        let next = page["next"].as_u64().unwrap();
+        assert_eq!(next, first.len() as u64);
+
+        // The torn tail is completed by the writer; the cursor picks it up.
+        let mut f = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        f.write_all(b"_sent\",\"retry\":0}\n").unwrap();
+        let page = read_jsonl_since(&path, next, "events").unwrap();
+        assert_eq!(page["events"][0]["type"], "enter_sent");
+        assert_eq!(page["events"].as_array().unwrap().len(), 1);
+
+        // A cursor past the end (file pruned and recreated) starts over.
+        assert_eq!(
+            read_jsonl_since(&path, 1 << 20, "events").unwrap()["events"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        let missing = read_jsonl_since(&dir.path().join("2.events.jsonl"), 5, "events").unwrap();
+        assert_eq!(missing["next"], 0);
+        let turns = read_jsonl_since(&dir.path().join("2.transcript.jsonl"), 0, "turns").unwrap();
+        assert_eq!(turns, json!({ "turns": [], "next": 0 }));
+    }
+
     #[test]
     fn numeric_keyword_is_identity_not_substring() {
         // Regression: after a fleet restore, one agent's resume prompt listed a
@@ -1,5 +1,7 @@
 // Agent lifecycle routes: /api/kill, /api/restart, /api/spawn, /api/spawn-config.
-// Ports the corresponding handlers in ts/serve.ts.
+// Ports the corresponding handlers in ts/serve.ts. Also the Rust-only
+// checkpoint routes, /api/checkpoints/<kw> and /api/rollback (see
+// rs/src/checkpoint.rs).
 //
 // Scope note: /api/spawn covers plain-cwd spawns AND provisioned ones. `from`
 // (clone) and `fork` (worktree) are served natively by rs/src/serve/ws.rs —
@@ -253,7 +255,17 @@ pub fn spawn_config() -> super::api::ApiResponse {
     }))
 }
 
-/// POST /api/kill {keyword} — force-kill an agent and its children.
+/// How long `/api/kill` lets the wrapper's shutdown ladder (exit command →
+/// Ctrl-C → SIGTERM → SIGKILL, see shutdown.rs) run before force-killing.
+/// Comfortably above the default ladder's ~22s.
+const KILL_LADDER_GRACE: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// POST /api/kill {keyword} — stop an agent and its children.
+///
+/// SIGTERMs the wrapper, which climbs its shutdown ladder and records the step
+/// that worked as `exit_reason`. A wrapper still alive after
+/// KILL_LADDER_GRACE (wedged, or a runtime without the ladder) is force-killed
+/// as before, in the background so the request returns at once.
 pub fn kill(body: &str) -> super::api::ApiResponse {
     let Ok(b) = serde_json::from_str::<Value>(body) else {
         return bad(400, "invalid JSON body");
@@ -272,6 +284,47 @@ pub fn kill(body: &str) -> super::api::ApiResponse {
         Ok(r) => r,
         Err(e) => return bad(404, e),
     };
+    #[cfg(unix)]
+    {
+        let wrapper = rec.wrapper_pid.unwrap_or(rec.pid);
+        if wrapper > 1 && unsafe { libc::kill(wrapper as i32, libc::SIGTERM) } == 0 {
+            let pid = rec.pid;
+            std::thread::spawn(move || {
+                let exited = wait_for_exit(
+                    KILL_LADDER_GRACE,
+                    std::time::Duration::from_millis(500),
+                    || crate::pid_store::is_process_alive(wrapper),
+                );
+                if !exited {
+                    force_kill(&rec);
+                }
+            });
+            return ok_json(json!({ "ok": true, "pid": pid, "signaled": wrapper }));
+        }
+    }
+    let killed = force_kill(&rec);
+    ok_json(json!({ "ok": true, "pid": rec.pid, "killed": killed }))
+}
+
+/// Polls `alive` every `poll` until it turns false (true: the process
+/// exited) or `grace` runs out (false).
+fn wait_for_exit(
+    grace: std::time::Duration,
+    poll: std::time::Duration,
+    mut alive: impl FnMut() -> bool,
+) -> bool {
+    let started = std::time::Instant::now();
+    while started.elapsed() < grace {
+        if !alive() {
+            return true;
+        }
+        std::thread::sleep(poll);
+    }
+    !alive()
+}
+
+/// SIGKILL an agent's process group and pids, and mark it exited.
+fn force_kill(rec: &crate::pid_store::PidRecord) -> Vec<String> {
     let mut killed: Vec<String> = Vec::new();
     #[cfg(unix)]
     {
@@ -297,7 +350,7 @@ pub fn kill(body: &str) -> super::api::ApiResponse {
         }
     }
     super::api::mark_exited(rec.pid, "force-killed via console");
-    ok_json(json!({ "ok": true, "pid": rec.pid, "killed": killed }))
+    killed
 }
 
 /// POST /api/restart {keyword, fresh?} — stop the agent then relaunch it,
@@ -338,6 +391,67 @@ pub fn restart(body: &str) -> super::api::ApiResponse {
     }
 }
 
+/// GET /api/checkpoints/<kw> — the agent's git checkpoints, oldest first.
+pub fn checkpoints(keyword: &str) -> super::api::ApiResponse {
+    let rec = match super::api::resolve_one_all(keyword) {
+        Ok(r) => r,
+        Err(e) => return bad(404, e),
+    };
+    let agent = crate::checkpoint::agent_key(rec.agent_id.as_deref(), rec.pid);
+    let Ok(repo) = crate::checkpoint::repo_root(std::path::Path::new(&rec.cwd)) else {
+        return bad(
+            404,
+            format!("pid {}: {} is not in a git work tree", rec.pid, rec.cwd),
+        );
+    };
+    match crate::checkpoint::list(&repo, &agent) {
+        Ok(list) => ok_json(json!({
+            "pid": rec.pid,
+            "agent": agent,
+            "repo": repo,
+            "checkpoints": list,
+        })),
+        Err(e) => bad(500, format!("{e:#}")),
+    }
+}
+
+/// POST /api/rollback {keyword, n} — restore the agent's working tree to
+/// checkpoint `n`, checkpointing the current tree first.
+pub fn rollback(body: &str) -> super::api::ApiResponse {
+    let Ok(b) = serde_json::from_str::<Value>(body) else {
+        return bad(400, "invalid JSON body");
+    };
+    let Some(keyword) = b
+        .get("keyword")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+    else {
+        return bad(400, "missing keyword");
+    };
+    let Some(n) = b
+        .get("n")
+        .and_then(|v| v.as_u64())
+        .and_then(|n| u32::try_from(n).ok())
+    else {
+        return bad(400, "missing checkpoint number n");
+    };
+    let rec = match super::api::resolve_one_all(keyword) {
+        Ok(r) => r,
+        Err(e) => return bad(404, e),
+    };
+    let agent = crate::checkpoint::agent_key(rec.agent_id.as_deref(), rec.pid);
+    let Ok(repo) = crate::checkpoint::repo_root(std::path::Path::new(&rec.cwd)) else {
+        return bad(
+            404,
+            format!("pid {}: {} is not in a git work tree", rec.pid, rec.cwd),
+        );
+    };
+    match crate::checkpoint::restore(&repo, &agent, n) {
+        Ok(done) => ok_json(json!({ "ok": true, "pid": rec.pid, "rollback": done })),
+        Err(e) => bad(409, format!("{e:#}")),
+    }
+}
+
 /// POST /api/spawn {cli, cwd?, prompt?, yes?, from?, branch?, create?, fork?} —
 /// launch a new agent, provisioning its workspace first when asked.
 ///
@@ -550,6 +664,22 @@ mod tests {
         assert_eq!(kill(r#"{"keyword":""}"#).status, 400);
     }
 
+    #[test]
+    fn kill_grace_waits_for_the_ladder_then_gives_up() {
+        let ms = std::time::Duration::from_millis;
+        // The wrapper exits on the third poll: no force-kill.
+        let mut polls = 0;
+        assert!(wait_for_exit(ms(1_000), ms(1), || {
+            polls += 1;
+            polls < 3
+        }));
+        assert_eq!(polls, 3);
+        // Still alive when the grace runs out: force-kill.
+        let started = std::time::Instant::now();
+        assert!(!wait_for_exit(ms(30), ms(5), || true));
+        assert!(started.elapsed() >= ms(30));
+    }
+
     #[test]
     fn restart_rejects_a_bad_body() {
         assert_eq!(restart("{").status, 400);
@@ -223,9 +223,12 @@ fn snippet_at(text: &str, ql: &str) -> Option<(usize, String)> {
     Some((idx, snippet))
 }
 
-/// One search hit over an agent's rendered screen text.
+/// One search hit over an agent's rendered screen text. Secrets are redacted
+/// before matching, so searching for one finds nothing and a snippet never
+/// shows one.
 pub fn search_hit(pid: u32, cli: &str, cwd: &str, text: &str, ql: &str) -> Option<Value> {
-    let (idx, snippet) = snippet_at(text, ql)?;
+    let text = crate::redact::global().redact(text);
+    let (idx, snippet) = snippet_at(&text, ql)?;
     Some(json!({ "pid": pid, "cli": cli, "cwd": cwd, "snippet": snippet, "match_at": idx }))
 }
 
@@ -117,6 +117,25 @@ static BADGE_DEFS: Lazy<Vec<BadgeDef>> = Lazy::new(|| {
 /// is fresh. Never screen-matched — see TYPING_BADGE in ts/badges.ts.
 pub const TYPING_BADGE: &str = "typing";
 
+/// `notify:<text>`, from the pid record's last desktop notification (OSC
+/// 9/777) — see NOTIFY_BADGE in ts/badges.ts.
+pub const NOTIFY_BADGE: &str = "notify";
+const NOTIFY_BADGE_MAX_CHARS: usize = 40;
+
+/// The `notify` badge id for a notification, capped like ts `notifyBadges`.
+pub fn notify_badge(notification: &str) -> String {
+    let text = if notification.chars().count() > NOTIFY_BADGE_MAX_CHARS {
+        let head: String = notification
+            .chars()
+            .take(NOTIFY_BADGE_MAX_CHARS - 1)
+            .collect();
+        format!("{}…", head)
+    } else {
+        notification.to_string()
+    };
+    format!("{}:{}", NOTIFY_BADGE, text)
+}
+
 pub fn match_badges(lines: &[String]) -> Vec<String> {
     let text = lines.join("\n");
     BADGE_DEFS
@@ -313,6 +332,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn notify_badge_caps_long_text() {
+        assert_eq!(notify_badge("Task finished"), "notify:Task finished");
+        let long = notify_badge(&"é".repeat(100));
+        assert_eq!(
+            long.chars().count(),
+            "notify:".len() + NOTIFY_BADGE_MAX_CHARS
+        );
+        assert!(long.ends_with('…'));
+    }
+
     #[test]
     fn badges_static_and_dynamic() {
         assert_eq!(match_badges(&v(&["/goal active now"])), vec!["goal-active"]);
@@ -20,5 +20,6 @@ pub mod sampler;
 pub mod service;
 pub mod share;
 pub mod shell_env;
+pub mod usage;
 pub mod widget;
 pub mod ws;
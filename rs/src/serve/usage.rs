@@ -0,0 +1,163 @@
+//! GET /api/usage[?cwd=<dir>] — token / cost totals per agent, per cwd and
+//! per local day, folded from the `<pid>.usage.jsonl` ledgers the wrappers
+//! write (see usage.rs). The project dirs come from the pid index, so every
+//! cwd an agent ever registered from is covered, dead agents included.
+
+use crate::log_files::project_log_dir;
+use crate::usage::{read_ledger, LedgerEntry, Usage};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentUsage {
+    pub pid: u32,
+    pub cli: String,
+    pub cwd: String,
+    #[serde(flatten)]
+    pub usage: Usage,
+    /// Unix ms of the latest booked turn.
+    pub last_at: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct UsageReport {
+    /// Heaviest first — "which agent burned the quota".
+    pub agents: Vec<AgentUsage>,
+    pub by_cwd: BTreeMap<String, Usage>,
+    /// Keyed by local date, `YYYY-MM-DD`.
+    pub by_day: BTreeMap<String, Usage>,
+}
+
+fn local_day(at_ms: u64) -> String {
+    use chrono::TimeZone;
+    chrono::Local
+        .timestamp_millis_opt(at_ms as i64)
+        .single()
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Fold ledger entries, tagged with the cwd and pid they came from, into
+/// per-agent, per-cwd and per-day totals.
+pub fn aggregate(entries: &[(String, u32, LedgerEntry)]) -> UsageReport {
+    let mut report = UsageReport::default();
+    let mut agents: BTreeMap<(String, u32), AgentUsage> = BTreeMap::new();
+    for (cwd, pid, entry) in entries {
+        let agent = agents
+            .entry((cwd.clone(), *pid))
+            .or_insert_with(|| AgentUsage {
+                pid: *pid,
+                cli: entry.cli.clone(),
+                cwd: cwd.clone(),
+                usage: Usage::default(),
+                last_at: 0,
+            });
+        agent.usage.add(entry.usage);
+        agent.last_at = agent.last_at.max(entry.at);
+        report
+            .by_cwd
+            .entry(cwd.clone())
+            .or_default()
+            .add(entry.usage);
+        report
+            .by_day
+            .entry(local_day(entry.at))
+            .or_default()
+            .add(entry.usage);
+    }
+    report.agents = agents.into_values().collect();
+    report
+        .agents
+        .sort_by(|a, b| b.usage.tokens.cmp(&a.usage.tokens).then(a.pid.cmp(&b.pid)));
+    report
+}
+
+/// Read every `<pid>.usage.jsonl` under the given project dirs.
+fn collect_ledgers<'a>(cwds: impl IntoIterator<Item = &'a str>) -> Vec<(String, u32, LedgerEntry)> {
+    let mut out = Vec::new();
+    for cwd in cwds {
+        let Some(dir) = project_log_dir(cwd) else {
+            continue;
+        };
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read_dir.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(pid) = name
+                .strip_suffix(".usage.jsonl")
+                .and_then(|p| p.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            for line in read_ledger(&entry.path()) {
+                out.push((cwd.to_string(), pid, line));
+            }
+        }
+    }
+    out
+}
+
+pub fn usage_json(cwd: Option<&str>) -> Value {
+    let cwds: BTreeSet<String> = match cwd {
+        Some(cwd) => BTreeSet::from([cwd.to_string()]),
+        None => crate::serve::api::read_records()
+            .into_iter()
+            .map(|r| r.cwd)
+            .collect(),
+    };
+    let entries = collect_ledgers(cwds.iter().map(String::as_str));
+    serde_json::to_value(aggregate(&entries)).unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate_per_agent_cwd_and_day() {
+        let entry = |at, tokens| LedgerEntry {
+            at,
+            cli: "claude".into(),
+            usage: Usage {
+                tokens,
+                cost_usd: 0.0,
+            },
+        };
+        let day = 86_400_000;
+        let t0 = 1_760_000_000_000;
+        let report = aggregate(&[
+            ("/a".into(), 1, entry(t0, 100)),
+            ("/a".into(), 1, entry(t0 + day, 50)),
+            ("/a".into(), 2, entry(t0, 500)),
+            ("/b".into(), 3, entry(t0, 10)),
+        ]);
+        assert_eq!(report.agents[0].pid, 2);
+        assert_eq!(report.agents[1].usage.tokens, 150);
+        assert_eq!(report.agents[1].last_at, t0 + day);
+        assert_eq!(report.by_cwd["/a"].tokens, 650);
+        assert_eq!(report.by_cwd["/b"].tokens, 10);
+        assert_eq!(report.by_day[&local_day(t0)].tokens, 610);
+        assert_eq!(report.by_day[&local_day(t0 + day)].tokens, 50);
+    }
+
+    #[test]
+    fn test_collect_ledgers_reads_only_usage_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_string_lossy().to_string();
+        let log_dir = dir.path().join(".agent-yes");
+        fs::create_dir_all(&log_dir).unwrap();
+        let line = r#"{"at":1,"cli":"codex","tokens":42}"#;
+        fs::write(log_dir.join("12.usage.jsonl"), format!("{line}\n")).unwrap();
+        fs::write(log_dir.join("12.events.jsonl"), format!("{line}\n")).unwrap();
+        fs::write(log_dir.join("x.usage.jsonl"), format!("{line}\n")).unwrap();
+
+        let entries = collect_ledgers([cwd.as_str()]);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].1, 12);
+        assert_eq!(entries[0].2.cli, "codex");
+        assert_eq!(entries[0].2.usage.tokens, 42);
+    }
+}
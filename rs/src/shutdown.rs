@@ -0,0 +1,162 @@
+//! Graceful shutdown ladder (`shutdown:` in the CLI config).
+//!
+//! Killing the process group outright can cut an agent off mid-write and
+//! loses the session summary the CLI prints on a clean exit. When the wrapper
+//! is told to stop — SIGTERM (`ay stop --method=ladder`, `/api/kill`), an
+//! exhausted `--budget`, `--result-mode exit` or an idle timeout — it climbs
+//! this ladder instead, stopping at the first step the CLI exits on:
+//!
+//! 1. `exit_command`: type the CLI's exit command (claude: `/exit`)
+//! 2. `ctrl_c`: press Ctrl-C twice (claude only quits on the second)
+//! 3. `sigterm`: SIGTERM the CLI's process group
+//! 4. `sigkill`: SIGKILL the process group
+//!
+//! Each step waits its own timeout for the CLI to exit; a timeout of 0 skips
+//! the step (so does `exit_command` for a CLI without one). The step that
+//! worked is recorded as the pid record's `exit_reason` (`shutdown:ctrl_c`)
+//! when the stop came from a signal.
+
+use crate::config_loader::ShutdownOverride;
+use std::time::Duration;
+
+const DEFAULT_EXIT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_CTRL_C_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_TERM_TIMEOUT_SECS: u64 = 5;
+
+/// How long to wait for the kernel to reap the group after SIGKILL.
+pub const KILL_WAIT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownStep {
+    ExitCommand,
+    CtrlC,
+    Sigterm,
+    Sigkill,
+}
+
+impl ShutdownStep {
+    pub fn name(self) -> &'static str {
+        match self {
+            ShutdownStep::ExitCommand => "exit_command",
+            ShutdownStep::CtrlC => "ctrl_c",
+            ShutdownStep::Sigterm => "sigterm",
+            ShutdownStep::Sigkill => "sigkill",
+        }
+    }
+}
+
+/// Compiled `shutdown:` section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownLadder {
+    pub exit_timeout: Duration,
+    pub ctrl_c_timeout: Duration,
+    pub term_timeout: Duration,
+}
+
+impl Default for ShutdownLadder {
+    fn default() -> Self {
+        compile_shutdown_ladder(None)
+    }
+}
+
+pub fn compile_shutdown_ladder(raw: Option<ShutdownOverride>) -> ShutdownLadder {
+    let raw = raw.unwrap_or_default();
+    ShutdownLadder {
+        exit_timeout: Duration::from_secs(
+            raw.exit_timeout_secs.unwrap_or(DEFAULT_EXIT_TIMEOUT_SECS),
+        ),
+        ctrl_c_timeout: Duration::from_secs(
+            raw.ctrl_c_timeout_secs
+                .unwrap_or(DEFAULT_CTRL_C_TIMEOUT_SECS),
+        ),
+        term_timeout: Duration::from_secs(
+            raw.term_timeout_secs.unwrap_or(DEFAULT_TERM_TIMEOUT_SECS),
+        ),
+    }
+}
+
+impl ShutdownLadder {
+    /// The steps to climb, each with how long to wait for the exit after it.
+    /// Always ends in `sigkill`.
+    pub fn steps(&self, has_exit_command: bool) -> Vec<(ShutdownStep, Duration)> {
+        let mut steps = Vec::new();
+        if has_exit_command && !self.exit_timeout.is_zero() {
+            steps.push((ShutdownStep::ExitCommand, self.exit_timeout));
+        }
+        if !self.ctrl_c_timeout.is_zero() {
+            steps.push((ShutdownStep::CtrlC, self.ctrl_c_timeout));
+        }
+        if cfg!(unix) && !self.term_timeout.is_zero() {
+            steps.push((ShutdownStep::Sigterm, self.term_timeout));
+        }
+        steps.push((ShutdownStep::Sigkill, KILL_WAIT));
+        steps
+    }
+}
+
+/// The pid record's `exit_reason` for a stop that climbed the ladder: the
+/// step the CLI exited on, or `exited` when it was gone before the first.
+pub fn exit_reason(step: Option<&str>) -> String {
+    format!("shutdown:{}", step.unwrap_or("exited"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ladder_steps() {
+        let ladder = ShutdownLadder::default();
+        let names = |steps: Vec<(ShutdownStep, Duration)>| {
+            steps.into_iter().map(|(s, _)| s.name()).collect::<Vec<_>>()
+        };
+        let full = ladder.steps(true);
+        assert_eq!(
+            full[0],
+            (ShutdownStep::ExitCommand, Duration::from_secs(10))
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            names(full),
+            ["exit_command", "ctrl_c", "sigterm", "sigkill"]
+        );
+        // No exit command, Ctrl-C disabled: straight to the signals.
+        let ladder = compile_shutdown_ladder(Some(ShutdownOverride {
+            ctrl_c_timeout_secs: Some(0),
+            ..Default::default()
+        }));
+        #[cfg(unix)]
+        assert_eq!(names(ladder.steps(false)), ["sigterm", "sigkill"]);
+        assert_eq!(ladder.steps(false).last().unwrap().0, ShutdownStep::Sigkill);
+    }
+
+    #[test]
+    fn test_zero_timeouts_skip_their_steps() {
+        // An exit command alone doesn't bring its step back at timeout 0.
+        let ladder = compile_shutdown_ladder(Some(ShutdownOverride {
+            exit_timeout_secs: Some(0),
+            term_timeout_secs: Some(0),
+            ..Default::default()
+        }));
+        let steps = ladder.steps(true);
+        assert_eq!(steps[0], (ShutdownStep::CtrlC, Duration::from_secs(5)));
+        assert_eq!(steps[1], (ShutdownStep::Sigkill, KILL_WAIT));
+        assert_eq!(steps.len(), 2);
+        // Everything off: SIGKILL is still the last resort.
+        let ladder = compile_shutdown_ladder(Some(ShutdownOverride {
+            exit_timeout_secs: Some(0),
+            ctrl_c_timeout_secs: Some(0),
+            term_timeout_secs: Some(0),
+        }));
+        assert_eq!(ladder.steps(true), [(ShutdownStep::Sigkill, KILL_WAIT)]);
+    }
+
+    #[test]
+    fn test_exit_reason_names_the_step() {
+        assert_eq!(
+            exit_reason(Some(ShutdownStep::CtrlC.name())),
+            "shutdown:ctrl_c"
+        );
+        assert_eq!(exit_reason(None), "shutdown:exited");
+    }
+}
@@ -0,0 +1,77 @@
+//! A status line for the wrapper's own messages: `/ay` replies, auto-yes
+//! toggles, held-input notices.
+//!
+//! Printing them inline would scroll the child's TUI or leave text smeared
+//! across it until the next full redraw. Instead the message is drawn over the
+//! bottom row in reverse video and, `STATUS_LINE_MS` later, that row is
+//! repainted from the virtual terminal — the child's screen comes back as it
+//! drew it, including anything it wrote to that row in the meantime.
+
+use crate::vterm::VTermProxy;
+use std::time::{Duration, Instant};
+
+/// How long a message stays up.
+pub const STATUS_LINE_MS: u64 = 4_000;
+
+#[derive(Debug, Default)]
+pub struct StatusLine {
+    shown_until: Option<Instant>,
+}
+
+impl StatusLine {
+    /// The bytes that draw `text` over the bottom row.
+    pub fn show(&mut self, vterm: &VTermProxy, text: &str, now: Instant) -> Vec<u8> {
+        self.shown_until = Some(now + Duration::from_millis(STATUS_LINE_MS));
+        let (rows, cols) = vterm.size();
+        let width = usize::from(cols).saturating_sub(2);
+        let text: String = text
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .take(width)
+            .collect();
+        let mut out = format!("\x1b[{rows};1H\x1b[0m\x1b[7m {text:<width$} \x1b[0m").into_bytes();
+        out.extend(vterm.cursor_state());
+        out
+    }
+
+    /// The bytes that take the message down again, once it is due.
+    pub fn clear_due(&mut self, vterm: &VTermProxy, now: Instant) -> Option<Vec<u8>> {
+        if self.shown_until.is_none_or(|t| now < t) {
+            return None;
+        }
+        self.shown_until = None;
+        let (rows, _) = vterm.size();
+        let mut out = format!("\x1b[{rows};1H\x1b[0m\x1b[2K").into_bytes();
+        out.extend(vterm.row_formatted(rows - 1));
+        out.extend(vterm.cursor_state());
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_line_is_drawn_and_taken_down() {
+        let mut child = VTermProxy::new(4, 20);
+        child.process(b"one\r\ntwo\r\n\r\n\x1b[32mbottom\x1b[0m\x1b[2;2H");
+        // The real terminal, replaying what the child and the wrapper wrote.
+        let mut term = VTermProxy::new(4, 20);
+        term.process(&child.state_formatted());
+
+        let t0 = Instant::now();
+        let mut line = StatusLine::default();
+        term.process(&line.show(&child, "auto-yes: ON\r\nand more text than fits", t0));
+        assert_eq!(term.visible_rows()[3], " auto-yes: ON  and  ");
+        assert_eq!(term.cursor_position(), (1, 1));
+
+        assert!(line.clear_due(&child, t0).is_none());
+        let later = t0 + Duration::from_millis(STATUS_LINE_MS);
+        term.process(&line.clear_due(&child, later).unwrap());
+        assert_eq!(term.visible_rows(), child.visible_rows());
+        assert_eq!(term.row_formatted(3), child.row_formatted(3));
+        assert_eq!(term.cursor_position(), (1, 1));
+        assert!(line.clear_due(&child, later).is_none());
+    }
+}
@@ -0,0 +1,269 @@
+//! Terminal-title history: `<cwd>/.agent-yes/<pid>.titles.jsonl`.
+//!
+//! The pid record only keeps the latest title (title_scanner.rs), but the
+//! sequence of titles is the best record of what a run went through. Each
+//! distinct title is appended as `{"at": <unix ms>, "title": "..."}` when it
+//! first shows; a run ends with a bare `{"at": <unix ms>}` so its last phase
+//! gets a duration too. `ayrs` serves the phases as `/api/titles/<kw>` and
+//! `ay status <agent> --history` prints them.
+//!
+//! Titles are compared without their leading spinner glyph — claude animates
+//! `⠂`/`✳` in front of the same summary every second — and only a change of
+//! the rest starts a new phase. The file keeps the newest `MAX_ENTRIES` lines.
+
+use crate::log_files::project_log_dir;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Lines kept when the file is compacted.
+const MAX_ENTRIES: usize = 500;
+/// Compact once the file holds this many lines, so rewrites stay rare.
+const COMPACT_AT: usize = MAX_ENTRIES + MAX_ENTRIES / 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    at: u64,
+    /// None: the run ended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+}
+
+/// One title and how long it stayed up.
+#[allow(dead_code)] // read by ayrs (`/api/titles`), not the wrapper
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Phase {
+    pub at: u64,
+    pub title: String,
+    /// Until the next title, the end of the run, or `now` for the current
+    /// phase of a live agent. None for a run that ended without a marker.
+    pub duration_ms: Option<u64>,
+}
+
+/// The title without the spinner/status glyphs in front of it.
+pub fn phase_label(title: &str) -> &str {
+    title
+        .trim_start_matches(|c: char| !c.is_alphanumeric() && !c.is_ascii_punctuation())
+        .trim()
+}
+
+pub fn history_path(pid: u32, cwd: &str) -> Option<PathBuf> {
+    project_log_dir(cwd).map(|dir| dir.join(format!("{}.titles.jsonl", pid)))
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn read_entries(path: &Path) -> Vec<Entry> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect()
+}
+
+/// The phases recorded in `path`, oldest first. `now` closes the last phase
+/// of an agent that is still running.
+#[allow(dead_code)]
+pub fn read_phases(path: &Path, now: Option<u64>) -> Vec<Phase> {
+    phases(&read_entries(path), now)
+}
+
+#[allow(dead_code)]
+fn phases(entries: &[Entry], now: Option<u64>) -> Vec<Phase> {
+    let mut out = Vec::new();
+    for (i, e) in entries.iter().enumerate() {
+        let Some(title) = &e.title else { continue };
+        let end = entries.get(i + 1).map(|n| n.at).or(now);
+        out.push(Phase {
+            at: e.at,
+            title: title.clone(),
+            duration_ms: end.map(|end| end.saturating_sub(e.at)),
+        });
+    }
+    out
+}
+
+/// Records one agent's titles. Disabled (a no-op) without a project log dir.
+pub struct TitleHistory {
+    file: Option<fs::File>,
+    pub path: Option<PathBuf>,
+    lines: usize,
+    last: Option<String>,
+}
+
+impl TitleHistory {
+    /// Opens (or continues, after a `--robust` restart) the history file.
+    pub fn new(pid: u32, cwd: &str) -> Self {
+        let Some(path) = history_path(pid, cwd) else {
+            return Self::disabled();
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let lines = read_entries(&path).len();
+        match fs::OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(f) => Self {
+                file: Some(f),
+                path: Some(path),
+                lines,
+                last: None,
+            },
+            Err(e) => {
+                warn!("Failed to open title history {:?}: {}", path, e);
+                Self::disabled()
+            }
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            file: None,
+            path: None,
+            lines: 0,
+            last: None,
+        }
+    }
+
+    /// Record `title` if it starts a new phase.
+    pub fn record(&mut self, title: &str) {
+        let label = phase_label(title);
+        if label.is_empty() || self.last.as_deref() == Some(label) {
+            return;
+        }
+        self.last = Some(label.to_string());
+        self.append(&Entry {
+            at: now_ms(),
+            title: Some(label.to_string()),
+        });
+    }
+
+    /// Close the current phase: the run is over.
+    pub fn end(&mut self) {
+        if self.last.take().is_some() {
+            self.append(&Entry {
+                at: now_ms(),
+                title: None,
+            });
+        }
+    }
+
+    fn append(&mut self, entry: &Entry) {
+        let Some(f) = self.file.as_mut() else { return };
+        let Ok(json) = serde_json::to_string(entry) else {
+            return;
+        };
+        if let Err(e) = f.write_all(format!("{}\n", json).as_bytes()) {
+            warn!("Title history {:?} stopped: {}", self.path, e);
+            self.file = None;
+            return;
+        }
+        self.lines += 1;
+        if self.lines >= COMPACT_AT {
+            self.compact();
+        }
+    }
+
+    /// Rewrite the file with its newest `MAX_ENTRIES` lines.
+    fn compact(&mut self) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        let entries = read_entries(&path);
+        let keep = &entries[entries.len().saturating_sub(MAX_ENTRIES)..];
+        let text: String = keep
+            .iter()
+            .filter_map(|e| serde_json::to_string(e).ok())
+            .map(|l| l + "\n")
+            .collect();
+        let tmp = path.with_extension("jsonl.tmp");
+        let result = fs::write(&tmp, text)
+            .and_then(|()| fs::rename(&tmp, &path))
+            .and_then(|()| fs::OpenOptions::new().append(true).open(&path));
+        match result {
+            Ok(f) => {
+                self.file = Some(f);
+                self.lines = keep.len();
+            }
+            Err(e) => warn!("Failed to compact title history {:?}: {}", path, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_label_strips_spinners() {
+        assert_eq!(phase_label("⠂ Fix the flaky test"), "Fix the flaky test");
+        assert_eq!(phase_label("✳ Fix the flaky test"), "Fix the flaky test");
+        assert_eq!(phase_label("[WIP] x"), "[WIP] x");
+        assert_eq!(phase_label("✳ "), "");
+    }
+
+    #[test]
+    fn test_phases_durations() {
+        let e = |at, title: Option<&str>| Entry {
+            at,
+            title: title.map(String::from),
+        };
+        let entries = [
+            e(1_000, Some("Explore")),
+            e(4_000, Some("Fix")),
+            e(9_000, None),
+            e(20_000, Some("Again")),
+        ];
+        let p = phases(&entries, Some(25_000));
+        assert_eq!(p.len(), 3);
+        assert_eq!(
+            (p[0].title.as_str(), p[0].duration_ms),
+            ("Explore", Some(3_000))
+        );
+        assert_eq!(p[1].duration_ms, Some(5_000));
+        assert_eq!(p[2].duration_ms, Some(5_000));
+        assert_eq!(phases(&entries, None)[2].duration_ms, None);
+    }
+
+    #[test]
+    fn test_history_dedups_and_compacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_string_lossy().to_string();
+        let mut h = TitleHistory::new(9, &cwd);
+        h.record("⠂ Fix the flaky test");
+        h.record("⠐ Fix the flaky test");
+        h.record("✳ Fix the flaky test");
+        h.record("✳ Run the suite");
+        h.end();
+        let path = history_path(9, &cwd).unwrap();
+        let p = read_phases(&path, None);
+        assert_eq!(
+            p.iter().map(|p| p.title.as_str()).collect::<Vec<_>>(),
+            ["Fix the flaky test", "Run the suite"]
+        );
+        assert!(p[1].duration_ms.is_some());
+
+        for i in 0..COMPACT_AT {
+            h.record(&format!("step {i}"));
+        }
+        let entries = read_entries(&path);
+        assert!(entries.len() <= COMPACT_AT);
+        assert_eq!(
+            entries.last().unwrap().title.as_deref(),
+            Some(format!("step {}", COMPACT_AT - 1).as_str())
+        );
+        // Still appending after the rewrite.
+        h.record("after");
+        assert_eq!(
+            read_entries(&path).last().unwrap().title.as_deref(),
+            Some("after")
+        );
+        assert!(TitleHistory::new(9, "").path.is_none());
+    }
+}
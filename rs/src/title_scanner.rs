@@ -8,6 +8,12 @@
 //! doing" label for free — surfaced as `title` in the pid registry and shown
 //! by `ay whoami` / `ay ls --json`.
 //!
+//! The same scan picks up desktop notifications: iTerm2's `OSC 9 ; <body>`
+//! and urxvt's `OSC 777 ; notify ; <title> ; <body>`, which CLIs raise when
+//! they finish or need attention. They pass through to the terminal as before;
+//! the wrapper also records them (events, the `notify` badge, the webhook and
+//! the `notification` hook) as the CLI's own "needs attention" signal.
+//!
 //! The scanner is a tiny state machine fed arbitrary chunk boundaries: a title
 //! sequence split across two reads must still parse, and everything that is
 //! not a title sequence must pass through untouched (the scanner never
@@ -25,17 +31,37 @@ enum State {
     Ground,
     Esc,         // saw ESC
     OscParam,    // saw ESC ] — collecting the numeric param
-    OscTitle,    // param was 0/2 and ';' consumed — collecting title chars
+    OscTitle,    // param was 0/2/9/777 and ';' consumed — collecting the text
     OscOther,    // some other OSC — skip to terminator
-    OscTitleEsc, // inside title, saw ESC (maybe ST `ESC \`)
+    OscTitleEsc, // inside the text, saw ESC (maybe ST `ESC \`)
     OscOtherEsc, // inside other OSC, saw ESC
 }
 
+/// A desktop notification raised by the CLI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// OSC 777 only.
+    pub title: Option<String>,
+    pub body: String,
+}
+
+impl Notification {
+    /// One line for the registry badge, the webhook and hooks.
+    pub fn text(&self) -> String {
+        match &self.title {
+            Some(t) if !self.body.is_empty() => format!("{}: {}", t, self.body),
+            Some(t) => t.clone(),
+            None => self.body.clone(),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct TitleScanner {
     state: State,
     param: String,
     title: String,
+    notifications: Vec<Notification>,
 }
 
 impl TitleScanner {
@@ -69,7 +95,7 @@ impl TitleScanner {
                         // OSC 0 (icon+title) and OSC 2 (title) carry the window
                         // title; OSC 1 is icon-only and everything else (8, 52,
                         // 133, …) is unrelated.
-                        if self.param == "0" || self.param == "2" {
+                        if matches!(self.param.as_str(), "0" | "2" | "9" | "777") {
                             self.title.clear();
                             self.state = State::OscTitle;
                         } else {
@@ -82,7 +108,7 @@ impl TitleScanner {
                 },
                 State::OscTitle => match c {
                     '\x07' => {
-                        found = Some(clean_title(&self.title));
+                        found = self.finish().or(found);
                         self.state = State::Ground;
                     }
                     '\x1b' => self.state = State::OscTitleEsc,
@@ -97,7 +123,7 @@ impl TitleScanner {
                 },
                 State::OscTitleEsc => {
                     if c == '\\' {
-                        found = Some(clean_title(&self.title));
+                        found = self.finish().or(found);
                     }
                     // Either way the OSC is over (a bare ESC aborts it).
                     self.state = State::Ground;
@@ -114,7 +140,44 @@ impl TitleScanner {
                 }
             }
         }
-        found.filter(|t| !t.is_empty())
+        found
+    }
+
+    /// Notifications completed since the last call, oldest first.
+    pub fn take_notifications(&mut self) -> Vec<Notification> {
+        std::mem::take(&mut self.notifications)
+    }
+
+    /// A terminated OSC text: the title for 0/2, a queued notification for
+    /// 9/777.
+    fn finish(&mut self) -> Option<String> {
+        let text = std::mem::take(&mut self.title);
+        match self.param.as_str() {
+            "9" => {
+                // ConEmu reuses OSC 9 for numbered commands (`9;4;…` is the
+                // progress bar) — those aren't notifications.
+                let numbered = text
+                    .split_once(';')
+                    .is_some_and(|(n, _)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+                let body = clean_title(&text);
+                if !numbered && !body.is_empty() {
+                    self.notifications.push(Notification { title: None, body });
+                }
+                None
+            }
+            "777" => {
+                let mut parts = text.splitn(3, ';');
+                if parts.next() == Some("notify") {
+                    let title = parts.next().map(clean_title).filter(|t| !t.is_empty());
+                    let body = parts.next().map(clean_title).unwrap_or_default();
+                    if title.is_some() || !body.is_empty() {
+                        self.notifications.push(Notification { title, body });
+                    }
+                }
+                None
+            }
+            _ => Some(clean_title(&text)).filter(|t| !t.is_empty()),
+        }
     }
 }
 
@@ -182,6 +245,44 @@ mod tests {
         assert_eq!(s.feed("\x1b]0;ok\x07"), Some("ok".into()));
     }
 
+    #[test]
+    fn captures_osc9_and_osc777_notifications_across_chunks() {
+        let mut s = TitleScanner::new();
+        assert_eq!(
+            s.feed("\x1b]0;working\x07\x1b]9;Task fin"),
+            Some("working".into())
+        );
+        assert!(s.take_notifications().is_empty());
+        assert_eq!(
+            s.feed("ished\x07\x1b]777;notify;Claude;Needs your input\x1b\\"),
+            None
+        );
+        assert_eq!(
+            s.take_notifications(),
+            vec![
+                Notification {
+                    title: None,
+                    body: "Task finished".into()
+                },
+                Notification {
+                    title: Some("Claude".into()),
+                    body: "Needs your input".into()
+                },
+            ]
+        );
+        assert!(s.take_notifications().is_empty());
+        // The title that precedes a notification in the same chunk still wins.
+        assert_eq!(s.feed("\x1b]2;t\x07\x1b]9;n\x07"), Some("t".into()));
+        assert_eq!(s.take_notifications()[0].text(), "n");
+    }
+
+    #[test]
+    fn ignores_conemu_osc9_commands_and_other_osc777() {
+        let mut s = TitleScanner::new();
+        s.feed("\x1b]9;4;1;50\x07\x1b]777;preexec\x07\x1b]9;\x07");
+        assert!(s.take_notifications().is_empty());
+    }
+
     #[test]
     fn strips_control_chars_and_empty_titles() {
         let mut s = TitleScanner::new();
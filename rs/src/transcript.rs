@@ -0,0 +1,620 @@
+//! Structured transcript of a session: `<cwd>/.agent-yes/<pid>.transcript.jsonl`.
+//!
+//! The raw and rendered logs are terminal dumps. This turns the rendered
+//! scrollback of a CLI whose screen layout we know into conversation turns —
+//! the user's prompts, the assistant's text, tool calls with their arguments,
+//! tool output and errors — so `ay hist` and the console can show the
+//! conversation instead of the screen. `ayrs` serves the file as
+//! `/api/transcript/<kw>?since=<offset>`.
+//!
+//! The parse is by leading glyph, like the screen metadata in serve/meta.rs:
+//!
+//! - claude: `> ` prompt, `● ` text or `● Tool(args)`, `⎿ ` tool output;
+//! - codex: `› ` prompt, `• ` text or `• Ran <command>` (and the other action
+//!   verbs), `└ ` output, `■ ` error;
+//! - opencode (best effort): `┃ ` prompt, `$ <command>` / `→ Read <path>` /
+//!   `← Edit <path>` tool lines, `Error:` lines, indented text.
+//!
+//! Indented lines continue the turn above. Everything else — spinners, the
+//! input box, the footer — is dropped. Other CLIs get no transcript.
+//!
+//! The scrollback is re-parsed (redacted) whenever the agent settles at its
+//! prompt and once more at exit; only the turns after the ones already written
+//! are appended, so the file grows one turn per line like the event stream.
+//! Every line is `{"at": <unix ms>, "cli": <binary>, "role": "<snake_case>",
+//! ...fields}`; `cli` is the binary, so a `glm` session reads as claude's.
+
+use crate::events::json_line;
+use crate::log_files::project_log_dir;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::warn;
+
+/// Most non-footer lines under claude's input box (its hints and mode line).
+const INPUT_BOX_TAIL: usize = 4;
+
+/// Turns kept in memory to line a fresh parse up against what's on disk.
+const MATCH_WINDOW: usize = 400;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "role", rename_all = "snake_case")]
+pub enum Turn {
+    User { text: String },
+    Assistant { text: String },
+    ToolCall { name: String, args: String },
+    ToolOutput { text: String },
+    Error { text: String },
+}
+
+/// Which screen layout to parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Claude,
+    Codex,
+    Opencode,
+}
+
+impl Dialect {
+    /// By the binary the CLI runs (`glm` runs claude).
+    pub fn for_binary(binary: &str) -> Option<Self> {
+        match binary {
+            "claude" => Some(Dialect::Claude),
+            "codex" => Some(Dialect::Codex),
+            "opencode" => Some(Dialect::Opencode),
+            _ => None,
+        }
+    }
+}
+
+static RE_CLAUDE_TOOL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([A-Z][\w.:-]*(?: [A-Z][\w.:-]*)?)\((.*)$").unwrap());
+static RE_CODEX_TOOL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(Ran|Running|Explored|Exploring|Edited|Editing|Added|Deleted|Read|Searched|Called|Calling|Updated Plan|Waited)\b ?(.*)$",
+    )
+    .unwrap()
+});
+static RE_OPENCODE_TOOL: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[→←✱%] (\w+) ?(.*)$").unwrap());
+static RE_RULE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[╭╰]?─{3,}").unwrap());
+static RE_FOOTER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)esc to (interrupt|cancel)|\? for shortcuts|⏎ send|context left|until auto-compact",
+    )
+    .unwrap()
+});
+
+/// One line's role in the turn structure.
+enum Line<'a> {
+    /// Opens a turn; the rest of the line is its first text.
+    Start(Turn),
+    /// Continues the open turn with this (dedented) text.
+    More(&'a str),
+    /// Continues an open turn of the same speaker, or opens one: opencode
+    /// marks each line of a message rather than its first.
+    Prose {
+        user: bool,
+        text: &'a str,
+    },
+    Blank,
+    /// Closes the open turn and is itself dropped.
+    Other,
+}
+
+fn text_turn(kind: fn(String) -> Turn, s: &str) -> Line<'static> {
+    Line::Start(kind(s.trim_end().to_string()))
+}
+
+fn tool(name: &str, args: &str) -> Line<'static> {
+    Line::Start(Turn::ToolCall {
+        name: name.to_string(),
+        args: args.trim_end().to_string(),
+    })
+}
+
+fn output_or_error(s: &str) -> Line<'static> {
+    if s.starts_with("Error") {
+        text_turn(|text| Turn::Error { text }, s)
+    } else {
+        text_turn(|text| Turn::ToolOutput { text }, s)
+    }
+}
+
+/// Claude's tool args without the call's closing paren.
+fn close_paren(s: &str) -> &str {
+    let s = s.trim_end();
+    s.strip_suffix(')').unwrap_or(s)
+}
+
+fn indented(line: &str) -> Option<&str> {
+    line.starts_with("  ").then(|| line.trim_start())
+}
+
+fn classify_claude(line: &str) -> Line<'_> {
+    if line.trim().is_empty() {
+        return Line::Blank;
+    }
+    if let Some(s) = line.strip_prefix("> ").or(line.strip_prefix("❯ ")) {
+        return text_turn(|text| Turn::User { text }, s);
+    }
+    if let Some(s) = line.strip_prefix("● ").or(line.strip_prefix("⏺ ")) {
+        return match RE_CLAUDE_TOOL.captures(s) {
+            Some(c) => tool(&c[1], close_paren(&c[2])),
+            None => text_turn(|text| Turn::Assistant { text }, s),
+        };
+    }
+    let t = line.trim_start();
+    if let Some(s) = t.strip_prefix('⎿') {
+        return output_or_error(s.trim_start());
+    }
+    match indented(line) {
+        Some(s) if !RE_FOOTER.is_match(s) => Line::More(s),
+        _ => Line::Other,
+    }
+}
+
+fn classify_codex(line: &str) -> Line<'_> {
+    if line.trim().is_empty() {
+        return Line::Blank;
+    }
+    if RE_FOOTER.is_match(line) {
+        return Line::Other;
+    }
+    if let Some(s) = line.strip_prefix("› ") {
+        return text_turn(|text| Turn::User { text }, s);
+    }
+    if let Some(s) = line.strip_prefix("• ") {
+        return match RE_CODEX_TOOL.captures(s) {
+            Some(c) => tool(&c[1], &c[2]),
+            None => text_turn(|text| Turn::Assistant { text }, s),
+        };
+    }
+    if let Some(s) = line.strip_prefix("■ ") {
+        return text_turn(|text| Turn::Error { text }, s);
+    }
+    let t = line.trim_start();
+    if let Some(s) = t.strip_prefix('└') {
+        return output_or_error(s.trim_start());
+    }
+    match indented(line) {
+        Some(s) => Line::More(s),
+        None => Line::Other,
+    }
+}
+
+fn classify_opencode(line: &str) -> Line<'_> {
+    let t = line.trim();
+    if t.is_empty() {
+        return Line::Blank;
+    }
+    if RE_FOOTER.is_match(t) {
+        return Line::Other;
+    }
+    if let Some(s) = t.strip_prefix('┃') {
+        return match s.trim() {
+            "" => Line::Blank,
+            text => Line::Prose { user: true, text },
+        };
+    }
+    if let Some(s) = t.strip_prefix("$ ") {
+        return tool("bash", s);
+    }
+    if let Some(c) = RE_OPENCODE_TOOL.captures(t) {
+        return tool(&c[1], &c[2]);
+    }
+    if t.starts_with("Error:") {
+        return text_turn(|text| Turn::Error { text }, t);
+    }
+    match indented(line) {
+        Some(text) => Line::Prose { user: false, text },
+        None => Line::Other,
+    }
+}
+
+/// Append a continuation line to `turn`. Tool args that wrapped are joined
+/// with a space; text keeps its line breaks (and a held blank line).
+fn extend(turn: &mut Turn, more: &str, blank: bool) {
+    match turn {
+        Turn::ToolCall { args, .. } => {
+            if !args.is_empty() {
+                args.push(' ');
+            }
+            args.push_str(close_paren(more));
+        }
+        Turn::User { text }
+        | Turn::Assistant { text }
+        | Turn::ToolOutput { text }
+        | Turn::Error { text } => {
+            if blank {
+                text.push('\n');
+            }
+            text.push('\n');
+            text.push_str(more.trim_end());
+        }
+    }
+}
+
+/// The scrollback above the CLI's input box: claude frames the box with
+/// rules, codex and opencode leave their composer as the last prompt line
+/// with only the footer below it.
+fn conversation_lines(dialect: Dialect, lines: &[&str]) -> usize {
+    let is_chrome = |l: &&str| l.trim().is_empty() || RE_FOOTER.is_match(l);
+    match dialect {
+        Dialect::Claude => {
+            // Only a rule among the last few lines frames the input box; one
+            // further up is the welcome banner or a rule in the conversation.
+            let Some(last) = lines
+                .iter()
+                .rposition(|l| RE_RULE.is_match(l.trim()))
+                .filter(|&i| {
+                    lines[i + 1..].iter().filter(|l| !is_chrome(l)).count() <= INPUT_BOX_TAIL
+                })
+            else {
+                return lines.len();
+            };
+            // The box's top rule, when there's one a few lines above.
+            let from = last.saturating_sub(20);
+            lines[from..last]
+                .iter()
+                .rposition(|l| RE_RULE.is_match(l.trim()))
+                .map_or(last, |i| from + i)
+        }
+        Dialect::Codex | Dialect::Opencode => {
+            let prompt = if dialect == Dialect::Codex {
+                "› "
+            } else {
+                "┃"
+            };
+            match lines
+                .iter()
+                .rposition(|l| l.trim_start().starts_with(prompt))
+            {
+                Some(i) if lines[i + 1..].iter().all(is_chrome) => i,
+                _ => lines.len(),
+            }
+        }
+    }
+}
+
+/// The turns on a rendered screen, oldest first.
+pub fn parse(dialect: Dialect, screen: &str) -> Vec<Turn> {
+    let lines: Vec<&str> = screen.lines().collect();
+    let end = conversation_lines(dialect, &lines);
+    let classify = match dialect {
+        Dialect::Claude => classify_claude,
+        Dialect::Codex => classify_codex,
+        Dialect::Opencode => classify_opencode,
+    };
+    let mut turns: Vec<Turn> = Vec::new();
+    let mut open = false;
+    let mut blank = false;
+    for line in &lines[..end] {
+        match classify(line) {
+            Line::Start(turn) => {
+                turns.push(turn);
+                open = true;
+                blank = false;
+            }
+            Line::More(s) if open => {
+                if let Some(turn) = turns.last_mut() {
+                    extend(turn, s, blank);
+                }
+                blank = false;
+            }
+            Line::Prose { user, text } => {
+                match turns.last_mut() {
+                    Some(turn @ Turn::User { .. }) if open && user => extend(turn, text, blank),
+                    Some(turn @ Turn::Assistant { .. }) if open && !user => {
+                        extend(turn, text, blank)
+                    }
+                    _ => {
+                        let text = text.trim_end().to_string();
+                        turns.push(if user {
+                            Turn::User { text }
+                        } else {
+                            Turn::Assistant { text }
+                        });
+                    }
+                }
+                open = true;
+                blank = false;
+            }
+            Line::Blank => blank = true,
+            Line::More(_) | Line::Other => open = false,
+        }
+    }
+    turns.retain(|t| match t {
+        Turn::ToolCall { name, .. } => !name.is_empty(),
+        Turn::User { text } | Turn::Assistant { text } => !text.trim().is_empty(),
+        Turn::ToolOutput { .. } | Turn::Error { .. } => true,
+    });
+    turns
+}
+
+/// Where `parsed` picks up after `written`: the index just past the longest
+/// run of `parsed` that ends like `written` does (the latest such run, after a
+/// resize repaint). 0 when nothing lines up — the screen was cleared.
+fn resume_at(written: &[Turn], parsed: &[Turn]) -> usize {
+    let Some(last) = written.last() else {
+        return 0;
+    };
+    let mut best: Option<(usize, usize)> = None;
+    for (p, turn) in parsed.iter().enumerate() {
+        if turn != last {
+            continue;
+        }
+        let run = written
+            .iter()
+            .rev()
+            .zip(parsed[..=p].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if best.is_none_or(|(_, m)| run >= m) {
+            best = Some((p, run));
+        }
+    }
+    best.map_or(0, |(p, _)| p + 1)
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    cli: &'a str,
+    #[serde(flatten)]
+    turn: &'a Turn,
+}
+
+/// Appends the turns of one agent. Disabled (every update a no-op) for CLIs
+/// without a [`Dialect`] and when there is no project log dir.
+pub struct TranscriptLog {
+    file: Option<fs::File>,
+    pub path: Option<PathBuf>,
+    dialect: Option<Dialect>,
+    cli: String,
+    /// The tail of what's been written, to line the next parse up against.
+    written: Vec<Turn>,
+}
+
+impl TranscriptLog {
+    pub fn new(pid: u32, cwd: &str, binary: &str) -> Self {
+        let dialect = Dialect::for_binary(binary);
+        let Some(dir) = project_log_dir(cwd).filter(|_| dialect.is_some()) else {
+            return Self::disabled();
+        };
+        let path = dir.join(format!("{}.transcript.jsonl", pid));
+        let _ = fs::create_dir_all(&dir);
+        match fs::OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(f) => Self {
+                file: Some(f),
+                path: Some(path),
+                dialect,
+                cli: binary.to_string(),
+                written: Vec::new(),
+            },
+            Err(e) => {
+                warn!("Failed to open transcript {:?}: {}", path, e);
+                Self::disabled()
+            }
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            file: None,
+            path: None,
+            dialect: None,
+            cli: String::new(),
+            written: Vec::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Re-parse `scrollback` and append the turns not written yet.
+    pub fn update(&mut self, scrollback: &str) {
+        let (Some(dialect), Some(f)) = (self.dialect, self.file.as_mut()) else {
+            return;
+        };
+        let parsed = parse(dialect, scrollback);
+        let new = &parsed[resume_at(&self.written, &parsed)..];
+        if new.is_empty() {
+            return;
+        }
+        let text: String = new
+            .iter()
+            .filter_map(|turn| {
+                json_line(&Entry {
+                    cli: &self.cli,
+                    turn,
+                })
+            })
+            .collect();
+        // One write per update so a concurrent reader sees whole lines.
+        if let Err(e) = f.write_all(text.as_bytes()) {
+            warn!("Transcript {:?} stopped: {}", self.path, e);
+            self.file = None;
+            return;
+        }
+        self.written.extend_from_slice(new);
+        let excess = self.written.len().saturating_sub(MATCH_WINDOW);
+        self.written.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(t: &str) -> Turn {
+        Turn::User { text: t.into() }
+    }
+    fn assistant(t: &str) -> Turn {
+        Turn::Assistant { text: t.into() }
+    }
+    fn call(n: &str, a: &str) -> Turn {
+        Turn::ToolCall {
+            name: n.into(),
+            args: a.into(),
+        }
+    }
+    fn output(t: &str) -> Turn {
+        Turn::ToolOutput { text: t.into() }
+    }
+
+    const CLAUDE: &str = "\
+╭───────────────────────────────╮
+│ ✻ Welcome to Claude Code!     │
+╰───────────────────────────────╯
+
+> fix the flaky test
+  in serve/api.rs
+
+● I'll look at the test first.
+
+● Read(src/serve/api.rs)
+  ⎿  Read 120 lines
+
+● Bash(cargo test --lib
+      serve::api)
+  ⎿  Error: test failed
+     assertion left == right
+
+● The cursor was off by one. Fixed it.
+
+  All tests pass now.
+
+✻ Worked for 2m 3s
+
+───────────────────────────────────
+> next prompt being typed
+───────────────────────────────────
+  ? for shortcuts
+";
+
+    #[test]
+    fn test_parse_claude() {
+        assert_eq!(
+            parse(Dialect::Claude, CLAUDE),
+            vec![
+                user("fix the flaky test\nin serve/api.rs"),
+                assistant("I'll look at the test first."),
+                call("Read", "src/serve/api.rs"),
+                output("Read 120 lines"),
+                call("Bash", "cargo test --lib serve::api"),
+                Turn::Error {
+                    text: "Error: test failed\nassertion left == right".into()
+                },
+                assistant("The cursor was off by one. Fixed it.\n\nAll tests pass now."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_codex() {
+        let screen = "\
+› add a --dry-run flag
+
+• I'll add the flag to the parser.
+
+• Ran cargo build
+  └ Compiling agent-yes v1.0.0
+    Finished dev profile
+
+■ stream disconnected before completion
+
+• Working (3s • esc to interrupt)
+
+›
+  ⏎ send   ? for shortcuts   80% context left
+";
+        assert_eq!(
+            parse(Dialect::Codex, screen),
+            vec![
+                user("add a --dry-run flag"),
+                assistant("I'll add the flag to the parser."),
+                call("Ran", "cargo build"),
+                output("Compiling agent-yes v1.0.0\nFinished dev profile"),
+                Turn::Error {
+                    text: "stream disconnected before completion".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_opencode() {
+        let screen = "\
+┃  list the files
+┃  in src
+┃
+
+$ ls
+→ Read src/main.rs
+  There are two files.
+";
+        assert_eq!(
+            parse(Dialect::Opencode, screen),
+            vec![
+                user("list the files\nin src"),
+                call("bash", "ls"),
+                call("Read", "src/main.rs"),
+                assistant("There are two files."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resume_at() {
+        let (a, b, c) = (user("a"), assistant("b"), user("c"));
+        assert_eq!(resume_at(&[], &[a.clone(), b.clone()]), 0);
+        assert_eq!(
+            resume_at(&[a.clone(), b.clone()], &[a.clone(), b.clone()]),
+            2
+        );
+        // The oldest turns scrolled out of the scrollback.
+        assert_eq!(
+            resume_at(&[a.clone(), b.clone()], &[b.clone(), c.clone()]),
+            1
+        );
+        // A resize repainted the conversation: resume after the repaint.
+        assert_eq!(
+            resume_at(
+                &[a.clone(), b.clone()],
+                &[a.clone(), b.clone(), a.clone(), b.clone(), c.clone()]
+            ),
+            4
+        );
+        // Cleared screen: everything on it is new.
+        assert_eq!(resume_at(&[a], &[c]), 0);
+    }
+
+    #[test]
+    fn test_transcript_log_appends_only_new_turns() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_string_lossy().to_string();
+        let mut log = TranscriptLog::new(7, &cwd, "claude");
+        log.update("> hi\n\n● Hello.\n");
+        log.update("> hi\n\n● Hello.\n\n> bye\n\n● Bye.\n");
+        log.update("> hi\n\n● Hello.\n\n> bye\n\n● Bye.\n");
+
+        let text = fs::read_to_string(dir.path().join(".agent-yes/7.transcript.jsonl")).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["cli"], "claude");
+        assert_eq!(lines[0]["role"], "user");
+        assert_eq!(lines[0]["text"], "hi");
+        assert!(lines[0]["at"].as_u64().unwrap() > 0);
+        assert_eq!(lines[3]["role"], "assistant");
+        assert_eq!(lines[3]["text"], "Bye.");
+
+        assert!(!TranscriptLog::new(7, &cwd, "bash").is_enabled());
+        assert!(!TranscriptLog::new(7, "", "claude").is_enabled());
+    }
+}
@@ -0,0 +1,389 @@
+//! Token / cost ledger (`usage:` in the CLI config) and `--budget`.
+//!
+//! The CLIs print their usage counters on screen — claude's spinner line reads
+//! `(6m 30s · ↓ 19.5k tokens)`. The `usage.tokens` / `usage.cost` extractors
+//! pull those numbers off the rendered screen (capture group 1, the last match
+//! wins) and the ledger turns successive readings into increments: a counter
+//! that climbs adds the difference; one that drops has started over (a new
+//! turn, a restarted CLI) and adds its whole new value. Counts are only as
+//! precise as the screen — `19.5k` books 19500.
+//!
+//! Increments are appended at the end of every turn to
+//! `<cwd>/.agent-yes/<pid>.usage.jsonl`, one
+//! `{"at": <unix ms>, "cli": ..., "tokens": ..., "cost_usd": ...}` line each.
+//! A `--robust` restart reloads the running total from it, `ayrs` aggregates
+//! the files per agent, cwd and day (`/api/usage`, see serve/usage.rs), and
+//! the total is mirrored to the agent's pid record as `usage`, which
+//! `ay ls --json` shows. Unlike the logs, ledgers are never pruned.
+
+use crate::config_loader::{compile_regex_list, UsageOverride};
+use crate::log_files::project_log_dir;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Typed once when a `wrap-up` budget is crossed.
+pub const WRAP_UP_PROMPT: &str = "[agent-yes: the token budget for this session is used up. \
+     Wrap up now: finish or checkpoint the current step, commit what is done, \
+     summarise what remains, and then stop.]";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub tokens: u64,
+    /// Only counted when the CLI shows a cost counter (`usage.cost`).
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cost_usd: f64,
+}
+
+fn is_zero(v: &f64) -> bool {
+    *v == 0.0
+}
+
+impl Usage {
+    pub fn is_empty(&self) -> bool {
+        self.tokens == 0 && self.cost_usd == 0.0
+    }
+
+    pub fn add(&mut self, other: Usage) {
+        self.tokens += other.tokens;
+        self.cost_usd += other.cost_usd;
+    }
+
+    /// "12345 tokens", with the cost when the CLI shows one.
+    pub fn describe(&self) -> String {
+        if self.cost_usd > 0.0 {
+            format!("{} tokens, ${:.2}", self.tokens, self.cost_usd)
+        } else {
+            format!("{} tokens", self.tokens)
+        }
+    }
+}
+
+/// Compiled `usage:` extractors.
+#[derive(Debug, Clone, Default)]
+pub struct UsageCounters {
+    pub tokens: Vec<Regex>,
+    pub cost: Vec<Regex>,
+}
+
+impl UsageCounters {
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty() || !self.cost.is_empty()
+    }
+
+    /// The counters currently on screen: (tokens, cost in USD).
+    pub fn read(&self, screen: &str) -> (Option<f64>, Option<f64>) {
+        let last = |rxs: &[Regex]| {
+            rxs.iter().find_map(|rx| {
+                let caps = rx.captures_iter(screen).last()?;
+                parse_count(caps.get(1)?.as_str())
+            })
+        };
+        (last(&self.tokens), last(&self.cost))
+    }
+}
+
+pub fn compile_usage_counters(raw: Option<UsageOverride>) -> Result<UsageCounters> {
+    let Some(raw) = raw else {
+        return Ok(UsageCounters::default());
+    };
+    Ok(UsageCounters {
+        tokens: compile_regex_list(raw.tokens)?,
+        cost: compile_regex_list(raw.cost)?,
+    })
+}
+
+/// `19.5k` → 19500, `1.2M` → 1200000, `12,345` → 12345, `$0.42` → 0.42.
+pub fn parse_count(s: &str) -> Option<f64> {
+    let s = s.trim().trim_start_matches('$').replace(',', "");
+    let (num, mult) = match s.chars().last()? {
+        'k' | 'K' => (&s[..s.len() - 1], 1e3),
+        'm' | 'M' => (&s[..s.len() - 1], 1e6),
+        'b' | 'B' => (&s[..s.len() - 1], 1e9),
+        _ => (s.as_str(), 1.0),
+    };
+    let v = num.trim().parse::<f64>().ok()? * mult;
+    (v.is_finite() && v >= 0.0).then_some(v)
+}
+
+/// Turns readings of one on-screen counter into increments.
+#[derive(Debug, Default)]
+struct Counter {
+    last: Option<f64>,
+}
+
+impl Counter {
+    fn observe(&mut self, v: f64) -> f64 {
+        let delta = match self.last {
+            Some(last) if v >= last => v - last,
+            _ => v,
+        };
+        self.last = Some(v);
+        delta
+    }
+}
+
+/// One line of `<pid>.usage.jsonl`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub at: u64,
+    pub cli: String,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+/// The running usage of one agent. Without a project log dir (an empty cwd,
+/// as in `--replay`) it still counts but persists nothing.
+pub struct Ledger {
+    path: Option<PathBuf>,
+    cli: String,
+    committed: Usage,
+    pending: Usage,
+    tokens: Counter,
+    cost: Counter,
+}
+
+impl Ledger {
+    /// Open the ledger for `pid`, picking up what earlier runs under the same
+    /// wrapper pid (`--robust` restarts) already booked.
+    pub fn new(pid: u32, cwd: &str, cli: &str) -> Self {
+        let path = project_log_dir(cwd).map(|dir| dir.join(format!("{}.usage.jsonl", pid)));
+        let mut committed = Usage::default();
+        if let Some(path) = &path {
+            for entry in read_ledger(path) {
+                committed.add(entry.usage);
+            }
+        }
+        Self {
+            path,
+            cli: cli.to_string(),
+            committed,
+            pending: Usage::default(),
+            tokens: Counter::default(),
+            cost: Counter::default(),
+        }
+    }
+
+    /// Feed the counters on the current screen.
+    pub fn observe(&mut self, counters: &UsageCounters, screen: &str) {
+        let (tokens, cost) = counters.read(screen);
+        if let Some(v) = tokens {
+            self.pending.tokens += self.tokens.observe(v).round() as u64;
+        }
+        if let Some(v) = cost {
+            self.pending.cost_usd += self.cost.observe(v);
+        }
+    }
+
+    /// Everything booked so far, including the turn in progress.
+    pub fn total(&self) -> Usage {
+        let mut total = self.committed;
+        total.add(self.pending);
+        total
+    }
+
+    /// Book the pending increments (end of a turn, end of a run). Returns the
+    /// new total, or None when there was nothing to book.
+    pub fn commit(&mut self) -> Option<Usage> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let usage = std::mem::take(&mut self.pending);
+        self.committed.add(usage);
+        if let Some(path) = &self.path {
+            let entry = LedgerEntry {
+                at: now_ms(),
+                cli: self.cli.clone(),
+                usage,
+            };
+            if let Err(e) = append_entry(path, &entry) {
+                warn!("Failed to append usage ledger {:?}: {}", path, e);
+            }
+        }
+        Some(self.committed)
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn append_entry(path: &Path, entry: &LedgerEntry) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut f = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    f.write_all(format!("{}\n", serde_json::to_string(entry)?).as_bytes())
+}
+
+/// Parse a ledger file; a missing file or a torn line reads as nothing.
+pub fn read_ledger(path: &Path) -> Vec<LedgerEntry> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    text.lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect()
+}
+
+/// `--budget`: a token count (`500k`, `2M`) or a dollar amount (`$5`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Budget {
+    Tokens(u64),
+    Usd(f64),
+}
+
+impl Budget {
+    pub fn parse(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid budget: {} (expected e.g. 500k, 2M or $5)", s);
+        let v = parse_count(s).filter(|v| *v > 0.0).ok_or_else(invalid)?;
+        Ok(if s.trim().starts_with('$') {
+            Budget::Usd(v)
+        } else {
+            Budget::Tokens(v.round() as u64)
+        })
+    }
+
+    pub fn is_exceeded_by(&self, usage: &Usage) -> bool {
+        match *self {
+            Budget::Tokens(limit) => usage.tokens >= limit,
+            Budget::Usd(limit) => usage.cost_usd >= limit,
+        }
+    }
+
+    /// The `--budget` value that parses back to this budget.
+    pub fn to_arg(self) -> String {
+        match self {
+            Budget::Tokens(limit) => limit.to_string(),
+            Budget::Usd(limit) => format!("${}", limit),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            Budget::Tokens(limit) => format!("{} tokens", limit),
+            Budget::Usd(limit) => format!("${:.2}", limit),
+        }
+    }
+}
+
+/// What happens when the budget is crossed (`--budget-action`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BudgetAction {
+    /// Type `WRAP_UP_PROMPT` once the agent is back at its prompt.
+    #[default]
+    WrapUp,
+    /// Switch auto-yes off; prompts wait for a human.
+    Pause,
+    /// Exit the CLI like an idle timeout (exit reason `budget`).
+    Stop,
+}
+
+impl BudgetAction {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "wrap-up" => Ok(BudgetAction::WrapUp),
+            "pause" => Ok(BudgetAction::Pause),
+            "stop" => Ok(BudgetAction::Stop),
+            other => Err(anyhow!(
+                "Unknown budget action '{}' (expected wrap-up, pause or stop)",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BudgetAction::WrapUp => "wrap-up",
+            BudgetAction::Pause => "pause",
+            BudgetAction::Stop => "stop",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counters() -> UsageCounters {
+        compile_usage_counters(Some(
+            serde_yaml::from_str(
+                r#"
+tokens: ['[↓↑]\s*([\d.,]+[kKmM]?)\s+tokens']
+cost: ['\$(\d+\.\d+) spent']
+"#,
+            )
+            .unwrap(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(parse_count("19.5k"), Some(19500.0));
+        assert_eq!(parse_count("1.2M"), Some(1_200_000.0));
+        assert_eq!(parse_count("12,345"), Some(12345.0));
+        assert_eq!(parse_count("$0.42"), Some(0.42));
+        assert_eq!(parse_count("lots"), None);
+        assert_eq!(parse_count(""), None);
+    }
+
+    #[test]
+    fn test_ledger_books_increments_and_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_string_lossy().to_string();
+        let c = counters();
+        let spinner = |n: &str| format!("✶ Thinking… (12s · ↓ {} tokens)\n· esc to interrupt", n);
+
+        let mut ledger = Ledger::new(7, &cwd, "claude");
+        ledger.observe(&c, &spinner("1.2k"));
+        ledger.observe(&c, &spinner("3.5k"));
+        // The counter dropped: a new turn started, its whole value counts.
+        ledger.observe(&c, &spinner("800"));
+        assert_eq!(ledger.total().tokens, 4300);
+        assert_eq!(ledger.commit().unwrap().tokens, 4300);
+        assert_eq!(ledger.commit(), None);
+        ledger.observe(&c, "$0.10 spent");
+        ledger.observe(&c, "$0.25 spent");
+        ledger.commit();
+
+        // A restart under the same pid picks the total back up.
+        let reopened = Ledger::new(7, &cwd, "claude");
+        assert_eq!(reopened.total().tokens, 4300);
+        assert!((reopened.total().cost_usd - 0.25).abs() < 1e-9);
+
+        let lines = read_ledger(&dir.path().join(".agent-yes/7.usage.jsonl"));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].cli, "claude");
+        assert_eq!(lines[0].usage.tokens, 4300);
+    }
+
+    #[test]
+    fn test_budget_parse_and_check() {
+        assert_eq!(Budget::parse("500k").unwrap(), Budget::Tokens(500_000));
+        assert_eq!(Budget::parse("$5").unwrap(), Budget::Usd(5.0));
+        for b in [Budget::Tokens(500_000), Budget::Usd(2.5)] {
+            assert_eq!(Budget::parse(&b.to_arg()).unwrap(), b);
+        }
+        assert!(Budget::parse("0").is_err());
+        assert!(Budget::parse("soon").is_err());
+        let used = Usage {
+            tokens: 600_000,
+            cost_usd: 1.0,
+        };
+        assert!(Budget::Tokens(500_000).is_exceeded_by(&used));
+        assert!(!Budget::Usd(5.0).is_exceeded_by(&used));
+        assert_eq!(BudgetAction::parse("pause").unwrap(), BudgetAction::Pause);
+        assert!(BudgetAction::parse("panic").is_err());
+    }
+}
@@ -91,6 +91,9 @@ impl vt100_ctt::Callbacks for ResponseCollector {
     }
 }
 
+/// Rows of normal-buffer scrollback kept by the emulator.
+pub const SCROLLBACK_ROWS: usize = 10000;
+
 /// Virtual terminal proxy — wraps vt100-ctt::Parser to provide a rendered
 /// terminal screen buffer and auto-respond to terminal queries.
 pub struct VTermProxy {
@@ -106,7 +109,8 @@ impl VTermProxy {
         let rows = rows.max(1);
         let cols = cols.max(1);
         let collector = ResponseCollector::default();
-        let parser = vt100_ctt::Parser::new_with_callbacks(rows, cols, 10000, collector.clone());
+        let parser =
+            vt100_ctt::Parser::new_with_callbacks(rows, cols, SCROLLBACK_ROWS, collector.clone());
         Self { parser, collector }
     }
 
@@ -146,6 +150,40 @@ impl VTermProxy {
         lines.join("\n")
     }
 
+    /// The visible rows as plain text, top to bottom.
+    pub fn visible_rows(&self) -> Vec<String> {
+        let screen = self.parser.screen();
+        screen.rows(0, screen.size().1).collect()
+    }
+
+    /// Rows in the normal-buffer scrollback; stops growing at
+    /// `SCROLLBACK_ROWS`.
+    pub fn scrollback_len(&mut self) -> usize {
+        self.parser.screen_mut().set_scrollback(usize::MAX);
+        let len = self.parser.screen().scrollback();
+        self.parser.screen_mut().set_scrollback(0);
+        len
+    }
+
+    /// Scrollback rows from absolute index `from` (0 = oldest) to the newest,
+    /// as plain text. Same viewport walk as `dump_scrollback`.
+    pub fn scrollback_rows(&mut self, from: usize) -> Vec<String> {
+        let (rows, cols) = self.parser.screen().size();
+        let max = self.scrollback_len();
+        let mut out = Vec::new();
+        let mut at = from;
+        while at < max {
+            // At offset `max - at` the window's first row is absolute row `at`.
+            let off = max - at;
+            self.parser.screen_mut().set_scrollback(off);
+            let take = off.min(rows.max(1) as usize);
+            out.extend(self.parser.screen().rows(0, cols).take(take));
+            at += take;
+        }
+        self.parser.screen_mut().set_scrollback(0);
+        out
+    }
+
     /// Get the current cursor position (0-based row, col).
     pub fn cursor_position(&self) -> (u16, u16) {
         self.parser.screen().cursor_position()
@@ -161,6 +199,40 @@ impl VTermProxy {
         self.parser.screen().alternate_screen()
     }
 
+    /// True if the child turned on bracketed paste (DECSET 2004), so injected
+    /// multi-line text can be wrapped in paste brackets (see paste.rs).
+    pub fn bracketed_paste(&self) -> bool {
+        self.parser.screen().bracketed_paste()
+    }
+
+    /// Visible row `row` (0-based) as the child drew it, colors included —
+    /// for repainting a row something else was drawn over.
+    pub fn row_formatted(&self, row: u16) -> Vec<u8> {
+        let screen = self.parser.screen();
+        let (_, cols) = screen.size();
+        screen
+            .rows_formatted(0, cols)
+            .nth(usize::from(row))
+            .unwrap_or_default()
+    }
+
+    /// Escape codes that put the cursor and the drawing attributes back where
+    /// the child left them. Unlike DECSC/DECRC this doesn't touch the cursor
+    /// the child may have saved itself.
+    pub fn cursor_state(&self) -> Vec<u8> {
+        let screen = self.parser.screen();
+        let (row, col) = screen.cursor_position();
+        let mut out = format!("\x1b[{};{}H", row + 1, col + 1).into_bytes();
+        out.extend(screen.attributes_formatted());
+        out
+    }
+
+    /// The whole screen, input modes included, as the child left it — for a
+    /// terminal that missed the output in between.
+    pub fn state_formatted(&self) -> Vec<u8> {
+        self.parser.screen().state_formatted()
+    }
+
     /// Render the full normal-buffer history (scrollback + visible screen) as
     /// plain text — the rust equivalent of the TS `XtermProxy.render()`.
     ///
@@ -412,6 +484,20 @@ mod tests {
         assert_eq!(vt.contents(), visible);
     }
 
+    #[test]
+    fn test_scrollback_rows_from_index() {
+        // 3-row screen, 10 lines + a trailing newline: lines 1-8 scrolled off.
+        let mut vt = VTermProxy::new(3, 80);
+        for i in 1..=10 {
+            vt.process(format!("line {i}\r\n").as_bytes());
+        }
+        assert_eq!(vt.scrollback_len(), 8);
+        assert_eq!(vt.scrollback_rows(5), ["line 6", "line 7", "line 8"]);
+        assert_eq!(vt.scrollback_rows(0).len(), 8);
+        assert!(vt.scrollback_rows(8).is_empty());
+        assert_eq!(vt.visible_rows(), ["line 9", "line 10", ""]);
+    }
+
     #[test]
     fn test_dump_scrollback_no_scrollback_returns_visible() {
         let mut vt = VTermProxy::new(24, 80);
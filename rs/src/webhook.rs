@@ -11,6 +11,9 @@ pub fn notify(status: &str, details: &str, cwd: &str) {
     };
 
     let host = hostname();
+    // `details` can be screen text (a summary, a prompt): never ship a secret
+    // to a third-party URL.
+    let details = crate::redact::global().redact(details);
     let message = format!("[{}] {}:{} {}", status, host, cwd, details);
     let encoded = percent_encode(&message);
     let url = url_template.replace("%s", &encoded);
@@ -659,3 +659,56 @@ fn test_cwd_flag_is_forwarded_to_the_cli() {
         stderr
     );
 }
+
+/// `--sandbox`: the agent gets a pid namespace and a `/proc` of its own. With
+/// the host's `/proc`, `/proc/<wrapper pid>/root` is the whole host
+/// filesystem again, allowlist or not. Runs the sandbox helper directly.
+#[cfg(target_os = "linux")]
+#[test]
+fn test_sandbox_cannot_reach_host_processes_through_proc() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work");
+    let hidden = dir.path().join("hidden");
+    fs::create_dir_all(&work).unwrap();
+    fs::create_dir_all(&hidden).unwrap();
+    let secret = hidden.canonicalize().unwrap().join("secret");
+    fs::write(&secret, "s3cr3t\n").unwrap();
+
+    let profile = serde_json::json!({
+        "enabled": true,
+        "read_only": ["/usr", "/bin", "/lib", "/lib64", "/etc"],
+        "read_write": [],
+        "network": true,
+    });
+    let script = r#"
+echo "pid=$$ ppid=$PPID"
+cat "/proc/$PPID/root$SECRET" "/proc/$HOST_PID/root$SECRET" 2>/dev/null
+for p in /proc/[0-9]*; do cat "$p/root$SECRET" 2>/dev/null; done
+test -e "/proc/$HOST_PID/status" && echo "host pid visible"
+ls /dev/pts
+"#;
+    let output = cargo_bin_cmd!("agent-yes")
+        .current_dir(&work)
+        .env("AGENT_YES_SANDBOX", profile.to_string())
+        .env("SECRET", &secret)
+        .env("HOST_PID", std::process::id().to_string())
+        .args(["__sandbox-exec", "/bin/sh", "-c", script])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.code() == Some(126) && stderr.contains("unshare") {
+        eprintln!("skipping: no unprivileged user namespaces here ({stderr})");
+        return;
+    }
+    assert!(
+        output.status.success(),
+        "stdout: {stdout}\nstderr: {stderr}"
+    );
+    // The CLI is pid 2 under the namespace's init; the host is out of sight.
+    assert!(stdout.starts_with("pid=2 ppid=1\n"), "{stdout}");
+    assert!(!stdout.contains("s3cr3t"), "{stdout}");
+    assert!(!stdout.contains("host pid visible"), "{stdout}");
+    // A devpts of its own: only ptmx, none of the host's terminals.
+    assert!(stdout.ends_with("ptmx\n"), "{stdout}");
+}
@@ -1,5 +1,5 @@
 import { afterEach, describe, expect, it } from "bun:test";
-import { derivePermissions, permissionBadge } from "./agentPermissions.ts";
+import { derivePermissions, permissionBadge, postureLabel } from "./agentPermissions.ts";
 import { allowsSkipPermissions } from "./workspaceConfig.ts";
 
 // Issue #236. The pid record is the only place the answer to "was this agent
@@ -81,6 +81,23 @@ describe("permissionBadge", () => {
   });
 });
 
+describe("postureLabel", () => {
+  const base = { skip_permissions: false, robust: false, auto_continue: false };
+  const sandbox = { read_write: ["/work"], read_only: ["/usr"], network: true };
+
+  it("tells sandboxed yolo apart from unsandboxed yolo", () => {
+    expect(postureLabel({ ...base, skip_permissions: true })).toBe("yolo");
+    expect(postureLabel({ ...base, skip_permissions: true, sandbox })).toBe("yolo sandboxed");
+    expect(postureLabel({ ...base, sandbox })).toBe("sandboxed");
+  });
+
+  it("has nothing to flag for a gated, unsandboxed agent", () => {
+    expect(postureLabel(base)).toBeNull();
+    expect(postureLabel({ ...base, sandbox: null })).toBeNull();
+    expect(postureLabel(undefined)).toBeNull();
+  });
+});
+
 describe("allowsSkipPermissions", () => {
   const original = process.env.AGENT_YES_ALLOW_SKIP_PERMISSIONS;
   afterEach(() => {
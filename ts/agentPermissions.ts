@@ -25,6 +25,26 @@ export interface AgentPermissions {
   robust: boolean;
   /** agent-yes auto-continue: resume the prior session on restart. */
   auto_continue: boolean;
+  /**
+   * Permission prompts went through the Rust runtime's approval policy instead
+   * of blanket auto-yes (decisions audited in `<pid>.approvals.jsonl`). Absent
+   * from records stamped by the TS runtime, which has no policy layer.
+   */
+  approval_policy?: boolean;
+  /**
+   * The CLI ran in the Rust runtime's `--sandbox` (user + mount namespaces):
+   * it saw only these paths. Absent when it ran with the user's full access.
+   */
+  sandbox?: SandboxStamp | null;
+}
+
+/** The sandbox profile an agent ran under (rs/src/sandbox.rs). */
+export interface SandboxStamp {
+  /** The cwd first, then the configured `readWrite` paths. */
+  read_write: string[];
+  read_only: string[];
+  /** False when the agent had its own network namespace (loopback only). */
+  network: boolean;
 }
 
 /**
@@ -78,3 +98,17 @@ export function permissionBadge(p: AgentPermissions | null | undefined): "skip"
   if (!p) return null;
   return p.skip_permissions ? "skip" : "prompt";
 }
+
+/**
+ * Posture for `ay ls`: what the agent could do unattended, and where. `yolo`
+ * is the permission gate off with the whole home directory in reach;
+ * `yolo sandboxed` is the gate off inside `--sandbox`; `sandboxed` is a gated
+ * agent in a sandbox. Null for an ordinary gated agent — nothing to flag.
+ */
+export function postureLabel(
+  p: AgentPermissions | null | undefined,
+): "yolo" | "yolo sandboxed" | "sandboxed" | null {
+  if (!p) return null;
+  if (p.skip_permissions) return p.sandbox ? "yolo sandboxed" : "yolo";
+  return p.sandbox ? "sandboxed" : null;
+}
@@ -4,6 +4,8 @@ import {
   badgeLabel,
   BADGE_DEFS,
   matchBadges,
+  NOTIFY_BADGE,
+  notifyBadges,
   TYPING_BADGE,
   type BadgeDef,
 } from "./badges.ts";
@@ -176,6 +178,18 @@ describe("badgeLabel", () => {
   });
 });
 
+describe("notifyBadges", () => {
+  it("turns the record's notification into a capped notify chip", () => {
+    expect(notifyBadges(null)).toEqual([]);
+    expect(notifyBadges("Claude: Needs your input")).toEqual(["notify:Claude: Needs your input"]);
+    expect(badgeDef("notify:Claude: Needs your input")).toBe(NOTIFY_BADGE);
+    expect(badgeLabel("notify:Task finished")).toBe("🔔 Task finished");
+    const [long] = notifyBadges("x".repeat(100));
+    expect(long!.length).toBe("notify:".length + 40);
+    expect(long!.endsWith("…")).toBe(true);
+  });
+});
+
 describe("TYPING_BADGE", () => {
   it("is never produced by screen matching (its pattern can't match)", () => {
     // Presence is derived from the stdin-activity marker, not the rendered
@@ -120,11 +120,35 @@ export const TYPING_BADGE: BadgeDef = {
   pattern: /(?!)/, // never matches; presence comes from stdin activity, not the screen
 };
 
+/** `notify:<text>` from the pid record's `notification` — the CLI raised a desktop notification. */
+export const NOTIFY_BADGE: BadgeDef = {
+  id: "notify",
+  label: "🔔 $1",
+  title: "The CLI raised a desktop notification (OSC 9/777) and hasn't resumed work since",
+  pattern: /(?!)/, // never matches; comes from the pid record, not the screen
+};
+
+/** Chip text is capped — a notification body can be a whole sentence. */
+const NOTIFY_BADGE_MAX_CHARS = 40;
+
+/** The `notify` badge id for a pid record's notification ([] when none). */
+export function notifyBadges(notification?: string | null): string[] {
+  if (!notification) return [];
+  const text =
+    notification.length > NOTIFY_BADGE_MAX_CHARS
+      ? `${notification.slice(0, NOTIFY_BADGE_MAX_CHARS - 1)}…`
+      : notification;
+  return [`${NOTIFY_BADGE.id}:${text}`];
+}
+
 export function badgeDef(id: string, defs: BadgeDef[] = BADGE_DEFS): BadgeDef | undefined {
   // Dynamic ids carry their captured text after a ":" ("shells:4 shells") —
   // strip it so the base def resolves.
   const base = id.includes(":") ? id.slice(0, id.indexOf(":")) : id;
-  return defs.find((d) => d.id === base) ?? (base === TYPING_BADGE.id ? TYPING_BADGE : undefined);
+  return (
+    defs.find((d) => d.id === base) ??
+    [TYPING_BADGE, NOTIFY_BADGE].find((d) => d.id === base)
+  );
 }
 
 /**
@@ -33,7 +33,12 @@ export interface GlobalPidRecord {
   cwd: string;
   log_file: string | null;
   fifo_file?: string | null;
-  status: "active" | "idle" | "exited";
+  // "completed" is written by the Rust wrapper once the agent finished its task
+  // (back at a quiet prompt after working, nothing queued) and reverts to
+  // "active" when it picks up work again. See rs/src/completion.rs.
+  // "crashloop" is "exited" after the Rust restart policy gave up on an agent
+  // that kept crashing; kept listed for a day. See rs/src/restart_policy.rs.
+  status: "active" | "idle" | "completed" | "exited" | "crashloop";
   // Set by the Rust supervisor when the agent produced no PTY output after a
   // high-signal poke / while a "working" spinner is frozen — i.e. it looks
   // wedged. Orthogonal to `status` (which stays "active"); cleared on recovery
@@ -53,6 +58,10 @@ export interface GlobalPidRecord {
   // started from a human shell. Builds the agent>subagent tree: a child links to
   // its parent via child.parent_pid === parent.wrapper_pid. See buildAgentForest.
   parent_pid?: number | null;
+  // The agent_id this agent took over from when that one hit a long usage
+  // limit (cross-CLI failover, see rs/src/failover.rs). Its wrapper is this
+  // agent's parent_pid. Null for everything that wasn't handed a task.
+  handoff_from?: string | null;
   // Stable id minted once at registration so a share grant or `ay <cmd> <id>`
   // can reference this agent without its ephemeral pid. Mirrors Rust's `agent_id`
   // (snake_case). Currently per-process; cross-restart re-binding is a follow-up
@@ -71,6 +80,41 @@ export interface GlobalPidRecord {
   // "what is this agent doing" without reading its screen. Mirrors Rust's
   // `title`.
   title?: string | null;
+  // The last desktop notification the child CLI raised (OSC 9 / OSC 777,
+  // picked up by the same scanner as `title`), "title: body" for OSC 777.
+  // Shown as the `notify` badge; cleared once the agent is working again.
+  // Mirrors Rust's `notification`.
+  notification?: string | null;
+  // Tokens (and USD, when the CLI shows a cost counter) this agent has used,
+  // across robust restarts — the running total of its usage ledger
+  // (<cwd>/.agent-yes/<pid>.usage.jsonl). Written by the Rust wrapper; see
+  // rs/src/usage.rs.
+  usage?: { tokens: number; cost_usd?: number } | null;
+  // Unix ms this agent is sleeping until: a usage-limit banner named its reset
+  // time, so the Rust auto-retry waits for it instead of backing off blindly.
+  // Orthogonal to `status`; cleared when the wait ends and on exit. Surfaced by
+  // the ls/status live-state derivation as `waiting_for_quota`. See
+  // rs/src/reset_time.rs.
+  waiting_for_quota?: number | null;
+  // Prompt-queue progress: `sent` of `total` queued prompts fed so far (repeated
+  // -p, --prompt-file, `ay send --queue`). Written by the Rust wrapper; `ay ls`
+  // shows it as "3 of 7". See rs/src/prompt_queue.rs.
+  queue?: { sent: number; total: number } | null;
+  // Every `--robust` restart so far, oldest first: when, the exit it followed,
+  // how the restart policy classed it and the backoff it waited. Written by the
+  // Rust wrapper; see rs/src/restart_policy.rs.
+  restarts?: {
+    at: number;
+    exit_code: number;
+    exit_reason: string;
+    class: "retryable" | "fatal" | "clean";
+    delay_ms: number;
+  }[];
+  // The FIFO frames (queue, paste, `/ay` meta-command) this agent's wrapper
+  // takes out of its input. Written by the Rust wrapper; absent for the TS
+  // wrapper and older Rust ones, which `ay send` writes to unframed. See
+  // FRAMES_VERSION in rs/src/fifo.rs.
+  frames?: number | null;
 }
 
 /**
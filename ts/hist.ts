@@ -2,7 +2,8 @@
  * `ay hist` — CLI surface over {@link ./histStore.ts}.
  *
  * Reads past coding-agent conversations (Claude Code, Codex) that already exist
- * on disk. Distinct from `ay tail`, which follows the live PTY log of a process
+ * on disk, and the transcripts agent-yes parsed from the screen of the other
+ * CLIs it ran (opencode, …). Distinct from `ay tail`, which follows the live PTY log of a process
  * agent-yes spawned; `ay hist` reads the CLI's own durable transcript, so it
  * still works for sessions that already exited.
  */
@@ -63,7 +64,8 @@ export async function cmdHist(rest: string[]): Promise<number> {
   const y = yargs(rest)
     .usage(
       "Usage: ay hist [options]\n\n" +
-        "Tail past coding-agent conversations (Claude Code, Codex) from this machine.\n" +
+        "Tail past coding-agent conversations (Claude Code, Codex, and screen transcripts\n" +
+        "agent-yes recorded for other CLIs) from this machine.\n" +
         "Defaults to sessions for the current directory, newest last.\n\n" +
         "Pagination: rerun with --before <cursor> using the cursor printed at the end.",
     )
@@ -79,7 +81,7 @@ export async function cmdHist(rest: string[]): Promise<number> {
     })
     .option("cwd", { type: "string", description: "Scope to this directory instead of $PWD" })
     .option("source", {
-      choices: ["claude", "codex"] as const,
+      choices: ["claude", "codex", "agent-yes"] as const,
       description: "Only one agent's transcripts",
     })
     .option("role", {
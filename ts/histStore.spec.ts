@@ -186,6 +186,27 @@ describe("projectRecord", () => {
     expect(projectRecord(JSON.stringify({ type: "ai-title" }), "claude")).toBeNull();
     expect(projectRecord(JSON.stringify({ type: "session_meta" }), "codex")).toBeNull();
   });
+
+  it("reads agent-yes screen transcripts, tools only when asked", () => {
+    const at = Date.parse("2026-08-04T00:00:00Z");
+    const turn = (rec: object) => JSON.stringify({ at, cli: "opencode", ...rec });
+    expect(projectRecord(turn({ role: "user", text: "list files" }), "agent-yes")).toEqual({
+      ts: "2026-08-04T00:00:00.000Z",
+      role: "user",
+      text: "list files",
+    });
+    const call = turn({ role: "tool_call", name: "bash", args: "ls" });
+    expect(projectRecord(call, "agent-yes")).toBeNull();
+    expect(projectRecord(call, "agent-yes", { includeTools: true })?.text).toBe("[tool: bash(ls)]");
+    expect(projectRecord(turn({ role: "error", text: "boom" }), "agent-yes")?.text).toBe(
+      "(error) boom",
+    );
+    expect(
+      projectRecord(turn({ role: "assistant", text: "hi" }), "agent-yes", {
+        skipClis: ["opencode"],
+      }),
+    ).toBeNull();
+  });
 });
 
 describe("discoverTranscripts", () => {
@@ -224,6 +245,21 @@ describe("discoverTranscripts", () => {
     expect(await discoverTranscripts({ home })).toEqual([]);
   });
 
+  it("finds agent-yes transcripts in the project's .agent-yes dir", async () => {
+    const cwd = path.join(home, "repo");
+    await mkdir(path.join(cwd, ".agent-yes"), { recursive: true });
+    await writeFile(path.join(cwd, ".agent-yes", "4242.transcript.jsonl"), '{"at":1}\n');
+    await writeFile(path.join(cwd, ".agent-yes", "4242.events.jsonl"), '{"at":1}\n');
+    const scoped = await discoverTranscripts({ home, cwd });
+    expect(scoped.map((f) => [f.source, f.sessionId])).toEqual([["agent-yes", "4242"]]);
+
+    // Unscoped, the projects come from the pid index.
+    expect(await discoverTranscripts({ home })).toEqual([]);
+    await mkdir(path.join(home, ".agent-yes"), { recursive: true });
+    await writeFile(path.join(home, ".agent-yes", "pids.jsonl"), JSON.stringify({ cwd }) + "\n");
+    expect((await discoverTranscripts({ home })).map((f) => f.sessionId)).toEqual(["4242"]);
+  });
+
   it("returns empty when no agent transcripts exist at all", async () => {
     expect(await discoverTranscripts({ home })).toEqual([]);
   });
@@ -282,6 +318,23 @@ describe("histPage", () => {
     expect(page.scanned).toBe(2);
   });
 
+  it("skips agent-yes turns of CLIs whose own transcript is read", async () => {
+    const cwd = path.join(home, "repo");
+    await mkdir(path.join(cwd, ".agent-yes"), { recursive: true });
+    const at = Date.parse("2026-08-04T00:00:00Z");
+    await writeFile(
+      path.join(cwd, ".agent-yes", "7.transcript.jsonl"),
+      [
+        JSON.stringify({ at, cli: "claude", role: "user", text: "dup" }),
+        JSON.stringify({ at: at + 1, cli: "opencode", role: "user", text: "screen-only" }),
+      ].join("\n") + "\n",
+    );
+    const page = await histPage({ home, cwd, limit: 10 });
+    expect(page.records.map((r) => r.text)).toEqual(["screen-only"]);
+    const only = await histPage({ home, cwd, limit: 10, sources: ["agent-yes"] });
+    expect(only.records.map((r) => r.text)).toEqual(["dup", "screen-only"]);
+  });
+
   it("returns a cursor that pages backwards without overlap", async () => {
     await writeClaudeSession(
       "s1",
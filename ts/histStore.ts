@@ -1,6 +1,8 @@
 /**
  * `ay hist` — read the tail of *coding-agent conversation transcripts* that
- * already exist on this machine (Claude Code, Codex), newest-last.
+ * already exist on this machine (Claude Code, Codex), newest-last — plus the
+ * transcripts agent-yes parses from the screen of the CLIs that keep none it
+ * can read (`<cwd>/.agent-yes/<pid>.transcript.jsonl`, see rs/src/transcript.rs).
  *
  * This is deliberately NOT `ay tail`. `ay tail` follows the live PTY log of an
  * agent process agent-yes spawned; `ay hist` reads the durable JSONL transcript
@@ -21,20 +23,27 @@
  *    v1 ships with zero index, zero daemon, zero cache invalidation.
  */
 
-import { readdir, open, stat } from "fs/promises";
+import { readdir, readFile, open, stat } from "fs/promises";
 import type { FileHandle } from "fs/promises";
 import { homedir } from "os";
 import path from "path";
+import { getGlobalPidIndexPath } from "./globalPidIndex.ts";
 
 const NEWLINE = 0x0a;
 
-/** Which coding agent produced a transcript. */
-export type HistSource = "claude" | "codex";
+/**
+ * Which coding agent produced a transcript. `agent-yes` is the screen-parsed
+ * transcript agent-yes writes for any CLI it wraps.
+ */
+export type HistSource = "claude" | "codex" | "agent-yes";
+
+/** Sources whose CLI writes its own transcript. */
+const NATIVE_SOURCES: readonly HistSource[] = ["claude", "codex"];
 
 export interface TranscriptFile {
   path: string;
   source: HistSource;
-  /** Session identifier — the file's basename for both supported sources. */
+  /** Session identifier — the file's basename (agent-yes: the wrapper pid). */
   sessionId: string;
   mtimeMs: number;
   size: number;
@@ -78,6 +87,35 @@ export function codexSessionsDir(home = homedir()): string {
   return path.join(home, ".codex", "sessions");
 }
 
+const AGENT_YES_TRANSCRIPT = ".transcript.jsonl";
+
+/**
+ * Project directories agent-yes has run in: every distinct cwd in the global
+ * pid index. Only consulted without `--cwd`, since screen transcripts live in
+ * each project's `.agent-yes/` rather than under one root.
+ */
+async function agentYesProjects(home: string, explicitHome: boolean): Promise<string[]> {
+  const index = explicitHome
+    ? path.join(home, ".agent-yes", "pids.jsonl")
+    : getGlobalPidIndexPath();
+  let text = "";
+  try {
+    text = await readFile(index, "utf8");
+  } catch {
+    return [];
+  }
+  const cwds = new Set<string>();
+  for (const line of text.split("\n")) {
+    try {
+      const cwd = JSON.parse(line)?.cwd;
+      if (typeof cwd === "string" && cwd) cwds.add(cwd);
+    } catch {
+      // a torn line from a concurrent append
+    }
+  }
+  return [...cwds];
+}
+
 async function listJsonlDeep(dir: string, out: string[] = [], depth = 0): Promise<string[]> {
   // Codex nests transcripts under YYYY/MM/DD; Claude is flat under the project
   // slug. A depth cap keeps a stray symlink from turning discovery into a walk
@@ -110,7 +148,7 @@ export interface DiscoverOpts {
  */
 export async function discoverTranscripts(opts: DiscoverOpts = {}): Promise<TranscriptFile[]> {
   const home = opts.home ?? homedir();
-  const sources = opts.sources ?? (["claude", "codex"] as const);
+  const sources = opts.sources ?? (["claude", "codex", "agent-yes"] as const);
   const found: TranscriptFile[] = [];
 
   if (sources.includes("claude")) {
@@ -155,6 +193,33 @@ export async function discoverTranscripts(opts: DiscoverOpts = {}): Promise<Tran
     }
   }
 
+  if (sources.includes("agent-yes")) {
+    const projects = opts.cwd ? [opts.cwd] : await agentYesProjects(home, opts.home !== undefined);
+    for (const cwd of projects) {
+      const dir = path.join(cwd, ".agent-yes");
+      let names: string[] = [];
+      try {
+        names = await readdir(dir);
+      } catch {
+        continue;
+      }
+      for (const name of names) {
+        if (!name.endsWith(AGENT_YES_TRANSCRIPT)) continue;
+        const file = path.join(dir, name);
+        const st = await stat(file).catch(() => null);
+        if (!st?.isFile() || st.size === 0) continue;
+        found.push({
+          path: file,
+          source: "agent-yes",
+          sessionId: name.slice(0, -AGENT_YES_TRANSCRIPT.length),
+          mtimeMs: st.mtimeMs,
+          size: st.size,
+          cwd,
+        });
+      }
+    }
+  }
+
   found.sort((a, b) => b.mtimeMs - a.mtimeMs);
 
   if (opts.cwd && sources.includes("codex")) {
@@ -302,6 +367,29 @@ export interface ProjectOpts {
   includeTools?: boolean;
   /** Include Claude sub-agent (sidechain) turns. Off by default: high volume. */
   includeSidechains?: boolean;
+  /**
+   * CLIs whose agent-yes transcript turns to drop, because their own transcript
+   * is being read too and would repeat them.
+   */
+  skipClis?: readonly string[];
+}
+
+/** Render one agent-yes transcript turn; null for tool plumbing without `--tools`. */
+function agentYesText(rec: Record<string, any>, includeTools: boolean): string | null {
+  const text = typeof rec.text === "string" ? rec.text.trim() : "";
+  switch (rec.role) {
+    case "user":
+    case "assistant":
+      return text || null;
+    case "error":
+      return text ? `(error) ${text}` : null;
+    case "tool_call":
+      return includeTools ? `[tool: ${rec.name ?? "?"}${rec.args ? `(${rec.args})` : ""}]` : null;
+    case "tool_output":
+      return includeTools ? `[tool result]${text ? `\n${text}` : ""}` : null;
+    default:
+      return null;
+  }
 }
 
 /**
@@ -334,6 +422,19 @@ export function projectRecord(
     return { ts: typeof rec.timestamp === "string" ? rec.timestamp : null, role, text };
   }
 
+  // agent-yes screen transcripts: one turn per line, `at` in unix ms, and the
+  // CLI that produced it.
+  if (source === "agent-yes") {
+    if (typeof rec.cli === "string" && opts.skipClis?.includes(rec.cli)) return null;
+    const text = agentYesText(rec, opts.includeTools ?? false);
+    if (!text) return null;
+    return {
+      ts: typeof rec.at === "number" ? new Date(rec.at).toISOString() : null,
+      role: rec.role === "user" ? "user" : "assistant",
+      text,
+    };
+  }
+
   // Codex writes each turn twice: as a raw `response_item` and as a rendered
   // `event_msg`. The event stream is the clean one, so we read only that and
   // avoid emitting every turn twice.
@@ -468,8 +569,13 @@ export async function histPage(q: HistQuery): Promise<HistPage> {
   // `--before` accepts either an encoded cursor or a bare ISO timestamp typed by
   // hand; only the former can page without losing timestamp ties.
   const cursor = q.cursor ?? (q.before ? decodeCursor(q.before) : null);
+  // A claude session wrapped by agent-yes has both transcripts; read its own.
+  const sources = q.sources ?? NATIVE_SOURCES;
+  const skipClis = q.skipClis ?? NATIVE_SOURCES.filter((s) => sources.includes(s));
   const tails = await Promise.all(
-    considered.map((f) => tailTranscript(f, { ...q, limit: perFile, cursor: cursor ?? undefined })),
+    considered.map((f) =>
+      tailTranscript(f, { ...q, skipClis, limit: perFile, cursor: cursor ?? undefined }),
+    ),
   );
   const merged = tails.flat();
 
@@ -10,7 +10,15 @@
  * `needsInput.ts`.
  */
 
-export type LiveState = "active" | "idle" | "stopped" | "needs_input" | "stuck";
+export type LiveState =
+  | "active"
+  | "idle"
+  | "stopped"
+  | "needs_input"
+  | "stuck"
+  | "waiting_for_quota"
+  | "completed"
+  | "crashloop";
 
 /** The observable state of one agent at a single tick. */
 export interface LsAgentState {
@@ -38,7 +38,7 @@ export interface ChildObservation {
   parent_started_at?: number;
   cli: string;
   cwd: string;
-  /** `deriveLiveState` state: active | idle | stopped | needs_input | stuck. */
+  /** `deriveLiveState` state: active | idle | completed | stopped | needs_input | stuck. */
   state: string;
   /** Compact question when state === "needs_input", else null. */
   question: string | null;
@@ -195,11 +195,14 @@ export function stepRouter(
         }
         break;
       }
+      // A child the Rust wrapper marked `completed` is idle with its task done —
+      // same episode, same edge.
+      case "completed":
       case "idle": {
         cs.inNeedsInput = false;
         cs.needsInputQuestion = null;
         // Continue the episode if we were already idle; else start it now.
-        if (p?.state === "idle" && p.idleSince != null) {
+        if ((p?.state === "idle" || p?.state === "completed") && p.idleSince != null) {
           cs.idleSince = p.idleSince;
           cs.idleEmitted = p.idleEmitted;
         } else {
@@ -4,8 +4,19 @@
 // running its own group cleanup (SIGKILL by an OOM killer / oxmgr force-restart /
 // a panic). It targets the recorded pgid of a CONFIRMED-DEAD wrapper — never
 // ppid==1 — so it is container-safe and never touches an unrelated process.
+// An agent the Rust wrapper gave resource limits also records its cgroup, which
+// the sweep kills whole (nothing can leave a cgroup, unlike a process group).
 
-import { appendFile, mkdir, readdir, readFile, rename, unlink, writeFile } from "fs/promises";
+import {
+  appendFile,
+  mkdir,
+  readdir,
+  readFile,
+  rename,
+  rmdir,
+  unlink,
+  writeFile,
+} from "fs/promises";
 import path from "path";
 import { agentYesHome } from "./agentYesHome.ts";
 
@@ -74,7 +85,7 @@ export async function sweep(): Promise<void> {
   for (const line of content.split("\n")) {
     const t = line.trim();
     if (!t) continue;
-    let entry: { wpid?: unknown; pgid?: unknown };
+    let entry: { wpid?: unknown; pgid?: unknown; cgroup?: unknown };
     try {
       entry = JSON.parse(t);
     } catch {
@@ -95,6 +106,7 @@ export async function sweep(): Promise<void> {
         // ESRCH = nothing left alive in that group
       }
     }
+    if (typeof entry.cgroup === "string") await killCgroup(entry.cgroup);
   }
   try {
     const tmp = registryPath() + ".tmp";
@@ -105,6 +117,40 @@ export async function sweep(): Promise<void> {
   }
 }
 
+/** SIGKILL every process in the cgroup at `dir` and remove it: `cgroup.kill`
+ *  (Linux 5.14+), else each pid in `cgroup.procs`. Skips anything that isn't a
+ *  cgroup. Mirrors rs/src/reaper.rs `kill_cgroup`. Best-effort. */
+async function killCgroup(dir: string): Promise<void> {
+  let procs: string;
+  try {
+    procs = await readFile(path.join(dir, "cgroup.procs"), "utf8");
+  } catch {
+    return; // gone already, or not a cgroup
+  }
+  try {
+    await writeFile(path.join(dir, "cgroup.kill"), "1");
+  } catch {
+    for (const pid of procs.split("\n").map(Number)) {
+      if (!Number.isInteger(pid) || pid <= 1) continue;
+      try {
+        process.kill(pid, "SIGKILL");
+      } catch {
+        // already gone
+      }
+    }
+  }
+  // rmdir succeeds once the last member is gone; the kill takes a moment to land.
+  for (let i = 0; i < 50; i++) {
+    try {
+      await rmdir(dir);
+      return;
+    } catch (e) {
+      if ((e as NodeJS.ErrnoException).code === "ENOENT") return;
+      await new Promise((r) => setTimeout(r, 10));
+    }
+  }
+}
+
 /** Prune typing-activity markers (`activity/<pid>.stdin`) left by hard-killed
  *  agents whose own exit cleanup never ran. Independent of the reaper registry
  *  above, so it catches every dead pid. A leaked marker is harmless (it ages out
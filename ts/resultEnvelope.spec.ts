@@ -1,6 +1,12 @@
 import path from "node:path";
 import { describe, expect, it } from "vitest";
-import { buildStoredResult, normalizeEnvelope, resultPath, resultsDir } from "./resultEnvelope.ts";
+import {
+  buildStoredResult,
+  normalizeEnvelope,
+  resultPath,
+  resultsDir,
+  screenResultPath,
+} from "./resultEnvelope.ts";
 
 describe("normalizeEnvelope", () => {
   it("passes a JSON object through unchanged", () => {
@@ -44,3 +50,11 @@ describe("resultPath", () => {
     expect(resultPath(777)).toBe(path.join(resultsDir(), "777.json"));
   });
 });
+
+describe("screenResultPath", () => {
+  it("points at the wrapper's capture in the project's .agent-yes dir", () => {
+    expect(screenResultPath("/work/repo", 777)).toBe(
+      path.join("/work/repo", ".agent-yes", "777.result.md"),
+    );
+  });
+});
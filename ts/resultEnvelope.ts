@@ -65,6 +65,16 @@ export function resultPath(pid: number): string {
   return path.join(resultsDir(), `${pid}.json`);
 }
 
+/**
+ * The Rust wrapper's own capture: when it sees the task through (worked, back
+ * at a quiet prompt, nothing queued) it cuts the last assistant message out of
+ * the scrollback into `<cwd>/.agent-yes/<pid>.result.md` (rs/src/completion.rs).
+ * `ay result` falls back to it when the agent never deposited an envelope.
+ */
+export function screenResultPath(cwd: string, pid: number): string {
+  return path.join(cwd, ".agent-yes", `${pid}.result.md`);
+}
+
 /**
  * Coerce raw write-side input into an envelope payload. If it parses as JSON we
  * keep it as-is (object, array, or scalar — the agent owns the shape). If it
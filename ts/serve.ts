@@ -29,13 +29,17 @@ import {
 import path from "path";
 import yargs from "yargs";
 import {
+  commandFrame,
   controlCodeFromName,
   deriveLiveStatus,
   extractBadges,
   extractNeedsInput,
   extractTaskCounts,
+  isMetaCommand,
   isUserTyping,
   listRecords,
+  pasteFrame,
+  queueFrame,
   readNotes,
   readLogForRender,
   readPtysize,
@@ -46,13 +50,14 @@ import {
   renderRawLogLines,
   resolveOne,
   snapshotStatus,
+  takesFrames,
   writeToIpc,
   type CommonOpts,
 } from "./subcommands.ts";
 import { answerAsk, listAsks } from "./askApi.ts";
 import { importProvisionModule } from "./ws.ts";
 import { permissionBadge } from "./agentPermissions.ts";
-import { TYPING_BADGE } from "./badges.ts";
+import { notifyBadges, TYPING_BADGE } from "./badges.ts";
 import { isTermToken, mintTermToken, verifyTermToken, type TermScope } from "./termToken.ts";
 import { isCallbackRevoked, loadCallbackSecretReadOnly } from "./callback.ts";
 import { CLAUDE_SESSION_PIN_ENV } from "./sessionEnv.ts";
@@ -2230,9 +2235,24 @@ export async function cmdServe(rest: string[]): Promise<number> {
       // Precedence: exited stays exited; the Rust supervisor's unresponsive flag is
       // an authoritative wedge signal (`stuck`); then a blocked menu (`needs_input`);
       // else the base live status — so the console's dot matches `ay ls`. (A dead
-      // agent is never unresponsive — Rust clears the flag on exit.)
+      // agent is never unresponsive — Rust clears the flag on exit.) An agent
+      // sleeping until a usage-limit reset reads `waiting_for_quota`; one the Rust
+      // wrapper marked done with its task reads `completed`; a dead one its restart
+      // policy parked reads `crashloop`.
       status:
-        status === "exited" ? status : r.unresponsive ? "stuck" : question ? "needs_input" : status,
+        status === "exited"
+          ? r.status === "crashloop"
+            ? "crashloop"
+            : status
+          : r.unresponsive
+            ? "stuck"
+            : r.waiting_for_quota
+              ? "waiting_for_quota"
+              : question
+                ? "needs_input"
+                : r.status === "completed"
+                  ? "completed"
+                  : status,
       // The pending menu/question text when needs_input, for the console to show
       // WHAT the agent is waiting on. Null otherwise.
       question,
@@ -2250,7 +2270,11 @@ export async function cmdServe(rest: string[]): Promise<number> {
         status === "exited"
           ? []
           : await logBadges(r.log_file).then(async (b) =>
-              (await isUserTyping(r.pid)) ? [...b, TYPING_BADGE.id] : b,
+              [
+                ...b,
+                ...((await isUserTyping(r.pid)) ? [TYPING_BADGE.id] : []),
+                ...notifyBadges(r.notification),
+              ],
             ),
       // Per-agent resource window (CPU-seconds/RSS/procs) from the background
       // sampler. Null until the sampler's first pass lands; the console degrades
@@ -3732,20 +3756,21 @@ export async function cmdServe(rest: string[]): Promise<number> {
       }
     }
 
-    // POST /api/send  body: {keyword, msg, code?, from?}
+    // POST /api/send  body: {keyword, msg, code?, from?, queue?}
     if (req.method === "POST" && p === "/api/send") {
       let body: {
         keyword: string;
         msg: string;
         code?: string;
         from?: MailParty | null;
+        queue?: boolean;
       };
       try {
         body = (await req.json()) as typeof body;
       } catch {
         return new Response("invalid JSON body", { status: 400 });
       }
-      const { keyword, msg = "", code = "enter", from = null } = body;
+      const { keyword, msg = "", code = "enter", from = null, queue = false } = body;
       if (!keyword || typeof keyword !== "string") {
         return new Response("missing keyword", { status: 400 });
       }
@@ -3758,8 +3783,27 @@ export async function cmdServe(rest: string[]): Promise<number> {
         const record = await resolveOne(keyword, defaultOpts());
         if (!record.fifo_file)
           return new Response(`pid ${record.pid}: no fifo_file`, { status: 409 });
-        const trailing = controlCodeFromName(code.toLowerCase());
         const fifo = record.fifo_file;
+        // Frames only go to a wrapper that advertises them (see takesFrames).
+        const frames = takesFrames(record);
+        // `queue: true` appends to the agent's prompt queue (typed later, at a
+        // quiet prompt) in one framed write — no control code, no stdin stamp.
+        if (queue) {
+          if (!msg.trim()) return new Response("queue: empty msg", { status: 400 });
+          if (!frames)
+            return new Response(`pid ${record.pid}: its wrapper has no prompt queue`, {
+              status: 409,
+            });
+          await writeToIpc(fifo, queueFrame(msg));
+          return Response.json({ ok: true, pid: record.pid, cli: record.cli, queued: true });
+        }
+        // A `/ay …` meta-command is run by the wrapper itself, never typed.
+        if (frames && isMetaCommand(msg)) {
+          await writeToIpc(fifo, commandFrame(msg));
+          return Response.json({ ok: true, pid: record.pid, cli: record.cli, command: true });
+        }
+        const trailing = controlCodeFromName(code.toLowerCase());
+        const typed = frames ? pasteFrame(msg) : msg;
         // One transaction: the body and its Enter must reach the agent with
         // nothing spliced between them. The ~200ms settle gap below is a wide
         // window for another writer (a second viewer, an `ay send`) to land
@@ -3768,11 +3812,11 @@ export async function cmdServe(rest: string[]): Promise<number> {
           record.pid,
           async () => {
             if (msg && trailing) {
-              await writeToIpc(fifo, msg);
+              await writeToIpc(fifo, typed);
               await new Promise((r) => setTimeout(r, 200));
               await writeToIpc(fifo, trailing);
             } else {
-              await writeToIpc(fifo, msg + trailing);
+              await writeToIpc(fifo, typed + trailing);
             }
           },
           (why) =>
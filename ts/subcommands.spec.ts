@@ -1037,6 +1037,31 @@ describe("subcommands.cmdSend writes bytes to FIFO", () => {
     expect(controlCodeFromName("none")).toBe("");
   });
 
+  it("frames a multi-line body as one paste, leaves a single line alone", async () => {
+    const { pasteFrame } = await loadModule();
+    expect(pasteFrame("fix it\nthen test")).toBe("\x1b_ay-paste;fix it\nthen test\x1b\\");
+    expect(pasteFrame("one line")).toBe("one line");
+    expect(pasteFrame("one line\n")).toBe("one line\n");
+  });
+
+  it("frames writes only for a wrapper that advertises frames", async () => {
+    const { takesFrames } = await loadModule();
+    expect(takesFrames({ frames: 1 })).toBe(true);
+    expect(takesFrames({ frames: null })).toBe(false);
+    expect(takesFrames({})).toBe(false);
+  });
+
+  it("frames /ay meta-commands for the wrapper, leaves other slash commands alone", async () => {
+    const { isMetaCommand, commandFrame } = await loadModule();
+    expect(isMetaCommand("/ay status")).toBe(true);
+    expect(isMetaCommand(" /auto\n")).toBe(true);
+    expect(isMetaCommand("/ayy")).toBe(false);
+    expect(isMetaCommand("/compact")).toBe(false);
+    expect(commandFrame("/ay queue add run the tests\n")).toBe(
+      "\x1b_ay-cmd;/ay queue add run the tests\x1b\\",
+    );
+  });
+
   it.skipIf(!itUnix)(
     "routes a bare 'exit' to the graceful /exit, not the literal word",
     async () => {
@@ -29,7 +29,8 @@ import {
   recordMessage,
   recordOutbox,
 } from "./messageLog.ts";
-import { badgeLabel, matchBadges, TYPING_BADGE } from "./badges.ts";
+import { postureLabel } from "./agentPermissions.ts";
+import { badgeLabel, matchBadges, notifyBadges, TYPING_BADGE } from "./badges.ts";
 import {
   classifyNeedsInput,
   isWorkingScreen,
@@ -59,6 +60,7 @@ import {
   normalizeEnvelope,
   resultPath,
   resultsDir,
+  screenResultPath,
   type StoredResult,
 } from "./resultEnvelope.ts";
 import { loadSharedCliDefaults } from "./configShared.ts";
@@ -67,6 +69,7 @@ import type { AgentCliConfig } from "./index.ts";
 import yargs from "yargs";
 import { type ResolvedRemote, readRemotes, resolveRemoteSpec } from "./remotes.ts";
 import { isWebrtcSpec } from "./webrtcLink.ts";
+import { readTitlePhases } from "./titleHistory.ts";
 import { withIpcLock } from "./ipcLock.ts";
 
 // ---------------------------------------------------------------------------
@@ -444,6 +447,8 @@ const SUBCOMMANDS = new Set([
   "callback",
   "reap",
   "gc",
+  "patterns",
+  "rollback",
   "dsh-legacy",
   "help",
 ]);
@@ -739,6 +744,22 @@ export async function runSubcommand(argv: string[]): Promise<number | null> {
         }
         return 0;
       }
+      case "patterns": {
+        // Native to the Rust runner: it owns the vterm + pattern engine the
+        // explanation has to reproduce. Forward argv verbatim.
+        const { getRustBinary } = await import("./rustBinary.ts");
+        const { spawnSync } = await import("child_process");
+        const bin = await getRustBinary();
+        return spawnSync(bin, ["patterns", ...rest], { stdio: "inherit" }).status ?? 1;
+      }
+      case "rollback": {
+        // Native to the Rust runner too: it takes the checkpoints (see
+        // rs/src/checkpoint.rs), so it owns their layout. Forward argv verbatim.
+        const { getRustBinary } = await import("./rustBinary.ts");
+        const { spawnSync } = await import("child_process");
+        const bin = await getRustBinary();
+        return spawnSync(bin, ["rollback", ...rest], { stdio: "inherit" }).status ?? 1;
+      }
       case "help":
         return cmdHelp(managerCommands);
       default:
@@ -870,6 +891,8 @@ export async function cmdHelp(managerCommands = true): Promise<number> {
       `  ay stop <keyword>                   graceful shutdown (/exit for claude/codex)\n` +
       `  ay exit <keyword> [reason]          graceful shutdown, recording who/why (= 'ay send <kw> exit')\n` +
       `  ay restart <keyword> [--fresh]      stop (if live) + relaunch resuming the session; --fresh replays the prompt\n` +
+      `  ay rollback <keyword> [n]           list the git checkpoints taken before auto-approved actions,\n` +
+      `                                        or restore the working tree to checkpoint n\n` +
       `  ay status <keyword>                 agent status snapshot\n` +
       `  ay notify watch --unread            get notified when sub-agents finish/stuck/crash (writes to inbox)\n` +
       `  ay whoami [--json]                  (inside an agent) your own registry identity + reply address\n` +
@@ -877,6 +900,7 @@ export async function cmdHelp(managerCommands = true): Promise<number> {
       `  ay result set '<json>'              (inside an agent) deposit your result envelope\n` +
       `  ay reap                             kill process groups leaked by dead agents\n` +
       `  ay gc                               remove old-version binary cache dirs and report freed space\n` +
+      `  ay patterns debug <log|cast>        replay a saved log and explain which patterns fired (or why not)\n` +
       `  ay dsh-legacy [args...]              launch the DeepSeek Harness terminal client (dsh-tui)\n` +
       wsLines +
       `\n` +
@@ -1544,7 +1568,7 @@ async function runAllRemotesLs(opts: {
     const enriched = await Promise.all(
       localResult.value.records.map(async (r) => {
         const { state, question } = await deriveLiveState(r);
-        const alive = state !== "stopped";
+        const alive = state !== "stopped" && state !== "crashloop";
         const [tasks, badges, git, typing] = alive
           ? await Promise.all([
               r.log_file ? extractTaskCounts(r.log_file) : Promise.resolve(null),
@@ -1559,7 +1583,11 @@ async function runAllRemotesLs(opts: {
           question,
           last_active_at: await deriveLastActiveAt(r),
           tasks,
-          badges: typing ? [...(badges as string[]), TYPING_BADGE.id] : badges,
+          badges: [
+            ...(badges as string[]),
+            ...(typing ? [TYPING_BADGE.id] : []),
+            ...(alive ? notifyBadges(r.notification) : []),
+          ],
           git,
         };
       }),
@@ -1676,19 +1704,30 @@ export async function deriveLiveStatus(r: GlobalPidRecord): Promise<"active" | "
 export async function deriveLiveState(
   r: GlobalPidRecord,
 ): Promise<{ state: LiveState; question: string | null }> {
+  // Parked by the Rust restart policy after crashing too often: stopped, but
+  // worth a look rather than a plain `stopped`.
+  if (r.status === "crashloop") return { state: "crashloop", question: null };
   const base = await deriveLiveStatus(r);
   if (base === "exited") return { state: "stopped", question: null };
   // The Rust supervisor flagged this agent unresponsive (no PTY output after a
   // poke / a frozen "working" spinner) — an authoritative wedge signal, so it
   // wins over the log-tail heuristics (needs_input / stuck) below.
   if (r.unresponsive) return { state: "stuck", question: null };
+  // Parked on a usage limit until its parsed reset time — quiet on purpose, and
+  // the limit banner is on screen, so neither idle nor needs_input fits.
+  if (r.waiting_for_quota) return { state: "waiting_for_quota", question: null };
   // A blocked menu overrides active/idle (alive + quiet, but waiting for an answer).
   if (r.log_file) {
     const ni = await extractNeedsInput(r.log_file, r.cli);
     if (ni) return { state: "needs_input", question: ni.question };
-    // Quiet long enough to read "idle", but the screen still shows a busy marker
-    // => wedged mid-stream, not finished. Surface as `stuck`, not `idle`.
-    if (base === "idle" && (await isAgentStuck(r))) return { state: "stuck", question: null };
+  }
+  // The Rust wrapper saw the task through: worked, back at a quiet prompt, and
+  // nothing queued. Distinct from idle (never given a task, or just quiet).
+  if (r.status === "completed") return { state: "completed", question: null };
+  // Quiet long enough to read "idle", but the screen still shows a busy marker
+  // => wedged mid-stream, not finished. Surface as `stuck`, not `idle`.
+  if (r.log_file && base === "idle" && (await isAgentStuck(r))) {
+    return { state: "stuck", question: null };
   }
   return { state: base, question: null };
 }
@@ -2037,7 +2076,7 @@ async function cmdLs(rest: string[]): Promise<number> {
       // Same live-state derivation as the --json path: stopped/idle/active, with
       // needs_input when the agent is parked on an unanswered menu.
       const displayStatus: string = (await deriveLiveState(r)).state;
-      const alive = displayStatus !== "stopped";
+      const alive = displayStatus !== "stopped" && displayStatus !== "crashloop";
       const note = notes.get(r.pid);
       // Task progress ("2/5"), status-flag chips ("goal"/"retry"/"limit"), and the
       // git dirty/sync tag ("±3 ⑂2 ↓1") — the same three decorations the console's
@@ -2051,12 +2090,23 @@ async function cmdLs(rest: string[]): Promise<number> {
           ])
         : [null, [], null, false];
       const taskBadge = tasks ? `${tasks.done}/${tasks.total} ` : "";
-      const flagStr = badgeLabels(typing ? [...(flags as string[]), TYPING_BADGE.id] : flags);
+      // Prompt-queue position: "q 3 of 7" = working on the 3rd of 7 prompts.
+      const queueBadge = alive && r.queue ? `q ${r.queue.sent} of ${r.queue.total} ` : "";
+      const flagStr = badgeLabels([
+        ...(flags as string[]),
+        ...(typing ? [TYPING_BADGE.id] : []),
+        ...(alive ? notifyBadges(r.notification) : []),
+      ]);
       const branchStr = branchLabel(git);
       const gitStr = gitLabel(git);
-      // task badge, flag chips, then the git group (⎇branch + dirty/sync tag) —
-      // compact, single-spaced.
+      // Permission posture ("yolo", "yolo sandboxed"), stamped at spawn — shown
+      // for stopped agents too, it's what they ran with.
+      const posture = postureLabel(r.permissions);
+      // posture, task badge, flag chips, then the git group (⎇branch +
+      // dirty/sync tag) — compact, single-spaced.
       const deco =
+        (posture ? `[${posture}] ` : "") +
+        queueBadge +
         taskBadge +
         (flagStr ? flagStr + " " : "") +
         (branchStr ? branchStr + " " : "") +
@@ -2085,7 +2135,7 @@ async function cmdLs(rest: string[]): Promise<number> {
         cwd: shortenPath(r.cwd),
         label,
         hasNote,
-        _alive: displayStatus !== "stopped",
+        _alive: alive,
       };
     }),
   );
@@ -3349,6 +3399,12 @@ async function cmdSend(rest: string[]): Promise<number> {
       description:
         "Fire-and-forget: skip the paste-settle wait and submit confirmation, don't retry a swallowed Enter (also: AGENT_YES_SEND_NO_WAIT=1)",
     })
+    .option("queue", {
+      type: "boolean",
+      default: false,
+      description:
+        "Append to the agent's prompt queue instead of typing now — sent when it is next idle at its prompt",
+    })
     .option("raw", {
       type: "boolean",
       default: false,
@@ -3517,6 +3573,32 @@ async function cmdSend(rest: string[]): Promise<number> {
   const fullBody = prefix + body + suffix;
   const noWait = Boolean(argv.noWait) || process.env.AGENT_YES_SEND_NO_WAIT === "1";
 
+  // Frames only go to a wrapper that advertises them; the TS wrapper and older
+  // Rust ones get the plain write they always did (and spot `/ay …` in the
+  // typed line themselves).
+  const frames = takesFrames(record);
+
+  // `/ay …` is for the wrapper, not the CLI: one framed write, no typing
+  // backoff, no Enter. The reply lands in the agent's events log.
+  if (frames && isMetaCommand(body) && !argv.queue) {
+    await writeToIpc(fifoPath, commandFrame(body));
+    process.stdout.write(`command sent to pid ${record.pid} (${record.cli}): ${body.trim()}\n`);
+    return 0;
+  }
+
+  // --queue: hand the body to the agent's prompt queue in one framed write. It
+  // is typed later, at a quiet prompt, so neither the typing backoff nor the
+  // submit-confirm below applies.
+  if (argv.queue) {
+    if (!frames)
+      throw new Error(
+        `pid ${record.pid}: its wrapper has no prompt queue (an older agent-yes, or one started with --no-rust); send without --queue`,
+      );
+    await writeToIpc(fifoPath, queueFrame(fullBody));
+    process.stdout.write(`queued for pid ${record.pid} (${record.cli}): ${truncate(body, 80)}\n`);
+    return 0;
+  }
+
   // Back off while the user is typing at the target's terminal — injecting our
   // body mid-line fuses into their text and submits a mangled line. Only for a
   // real text body; skipped for --force (caller means it), --no-wait
@@ -3547,11 +3629,12 @@ async function cmdSend(rest: string[]): Promise<number> {
   // The body and its Enter are ONE transaction: every gap between them (the
   // paste-settle wait, the submit-confirm retries) is a window where another
   // writer's bytes would land mid-message. See ts/ipcLock.ts.
+  const typed = frames ? pasteFrame(fullBody) : fullBody;
   await withIpcLock(
     record.pid,
     async () => {
       if (fullBody && trailing) {
-        await writeToIpc(fifoPath, fullBody);
+        await writeToIpc(fifoPath, typed);
         if (canConfirm && record.log_file) {
           // Wait for the paste to actually finish rendering — a long/multi-line body
           // can take longer than any fixed guess, and sending Enter mid-paste gets
@@ -3563,7 +3646,7 @@ async function cmdSend(rest: string[]): Promise<number> {
           await writeToIpc(fifoPath, trailing);
         }
       } else {
-        await writeToIpc(fifoPath, fullBody + trailing);
+        await writeToIpc(fifoPath, typed + trailing);
       }
     },
     (why) =>
@@ -4067,6 +4150,54 @@ export function controlCodeFromName(name: string): string {
   }
 }
 
+/**
+ * Whether an agent's wrapper takes FIFO frames (queue, paste, meta-command)
+ * out of its input — the Rust wrapper advertises `frames` in its pid record.
+ * The TS wrapper and older Rust ones would type a frame into the CLI.
+ * Mirrors FRAMES_VERSION in rs/src/fifo.rs.
+ */
+export function takesFrames(record: Pick<GlobalPidRecord, "frames">): boolean {
+  return (record.frames ?? 0) >= 1;
+}
+
+/**
+ * Wrap a prompt as a prompt-queue frame: written to an agent's FIFO, the Rust
+ * wrapper appends it to the agent's queue (fed one per return to a quiet prompt)
+ * instead of typing it now. Mirrors QUEUE_FRAME_START/END in rs/src/fifo.rs.
+ */
+export function queueFrame(prompt: string): string {
+  return `\x1b_ay-queue;${prompt}\x1b\\`;
+}
+
+/**
+ * Frame a multi-line body so the Rust wrapper types it as ONE prompt (a
+ * bracketed paste, or the CLI's pasteFallback) instead of line by line, where
+ * each newline would submit a fragment. Single-line text is returned as-is.
+ * Mirrors PASTE_FRAME_START in rs/src/fifo.rs.
+ */
+export function pasteFrame(text: string): string {
+  return /[\r\n]/.test(text.replace(/[\r\n]+$/, "")) ? `\x1b_ay-paste;${text}\x1b\\` : text;
+}
+
+/**
+ * Whether a body is one of the Rust wrapper's own meta-commands (`/ay status`,
+ * `/ay queue add …`, the older `/auto`) rather than text for the CLI.
+ * Mirrors is_meta_command in rs/src/fifo.rs.
+ */
+export function isMetaCommand(text: string): boolean {
+  const t = text.trim();
+  return t === "/auto" || t === "/ay" || t.startsWith("/ay ");
+}
+
+/**
+ * Frame a meta-command: written to an agent's FIFO, the Rust wrapper runs it
+ * and shows the reply on its status line (and in the agent's events log) —
+ * the CLI never sees it. Mirrors COMMAND_FRAME_START in rs/src/fifo.rs.
+ */
+export function commandFrame(text: string): string {
+  return `\x1b_ay-cmd;${text.trim()}\x1b\\`;
+}
+
 export async function writeToIpc(ipcPath: string, payload: string): Promise<void> {
   if (process.platform === "win32") {
     const { connect } = await import("net");
@@ -4136,12 +4267,13 @@ export async function writeToIpc(ipcPath: string, payload: string): Promise<void
 
 async function cmdStop(rest: string[]): Promise<number> {
   const y = yargs(rest)
-    .usage("Usage: ay stop <keyword> [--method=graceful|double-ctrl-c|auto]")
+    .usage("Usage: ay stop <keyword> [--method=graceful|double-ctrl-c|ladder|auto]")
     .option("method", {
       type: "string",
       default: "auto",
       description:
-        "Shutdown strategy: auto (per-CLI), graceful (/exit-style), double-ctrl-c (force)",
+        "Shutdown strategy: auto (per-CLI), graceful (/exit-style), double-ctrl-c (force), " +
+        "ladder (SIGTERM the wrapper: exit command → Ctrl-C → SIGTERM → SIGKILL)",
     })
     .option("all", { type: "boolean", default: false, description: "Include exited agents" })
     .option("latest", { type: "boolean", default: false, description: "Use most recent match" })
@@ -4159,7 +4291,8 @@ async function cmdStop(rest: string[]): Promise<number> {
     cwdScope: typeof argv.cwd === "string" ? path.resolve(argv.cwd) : null,
   };
   const keyword = argv._[0] !== undefined ? String(argv._[0]) : undefined;
-  if (!keyword) throw new Error("usage: ay stop <keyword> [--method=auto|graceful|double-ctrl-c]");
+  if (!keyword)
+    throw new Error("usage: ay stop <keyword> [--method=auto|graceful|double-ctrl-c|ladder]");
 
   const record = await resolveOne(keyword, opts);
 
@@ -4175,11 +4308,21 @@ async function cmdStop(rest: string[]): Promise<number> {
     return 0;
   }
 
+  const method = String(argv.method).toLowerCase();
+
+  // The Rust wrapper answers SIGTERM with its shutdown ladder (rs/src/shutdown.rs)
+  // and records the step that worked as `exit_reason` (e.g. `shutdown:ctrl_c`).
+  if (method === "ladder") {
+    process.kill(record.wrapper_pid ?? record.pid, "SIGTERM");
+    process.stdout.write(`stopping pid ${record.pid} (${record.cli}) via shutdown ladder\n`);
+    process.stderr.write(`\n  ay status ${record.pid}                # confirm it exited\n`);
+    return 0;
+  }
+
   if (!record.fifo_file) {
     throw new Error(`pid ${record.pid}: no fifo_file — cannot send shutdown command`);
   }
 
-  const method = String(argv.method).toLowerCase();
   const graceful = GRACEFUL_EXIT_COMMANDS[record.cli];
 
   let payload: string;
@@ -4939,6 +5082,9 @@ export async function snapshotStatus(record: GlobalPidRecord): Promise<StatusSna
     if (ni) {
       state = "needs_input";
       question = ni.question;
+    } else if (record.status === "completed") {
+      // Finished its task (see deriveLiveState).
+      state = "completed";
     } else if (state === "idle" && (await isAgentStuck(record, logMtimeMs))) {
       // Quiet long enough to read "idle", but still showing a busy marker: wedged.
       state = "stuck";
@@ -4998,6 +5144,11 @@ async function cmdStatus(rest: string[]): Promise<number> {
     .option("interval", { type: "number", default: 2, description: "Poll interval in seconds" })
     .option("latest", { type: "boolean", default: false, description: "Use most recent match" })
     .option("cwd", { type: "string", description: "Restrict to agents under this dir" })
+    .option("history", {
+      type: "boolean",
+      default: false,
+      description: "Add `history`: every terminal title the run went through, with its duration",
+    })
     .help(false)
     .version(false)
     .exitProcess(false);
@@ -5014,7 +5165,7 @@ async function cmdStatus(rest: string[]): Promise<number> {
 
   if (!keyword)
     throw new Error(
-      "usage: ay status <keyword> [--watch | --wait | --wait-idle] [--timeout=Ns] [--cwd=DIR] [--latest]",
+      "usage: ay status <keyword> [--watch | --wait | --wait-idle] [--timeout=Ns] [--cwd=DIR] [--latest] [--history]",
     );
 
   {
@@ -5037,13 +5188,19 @@ async function cmdStatus(rest: string[]): Promise<number> {
 
   const record = await resolveOne(keyword, opts);
 
-  const emit = (snap: StatusSnapshot, ts?: number): void => {
-    const out = ts !== undefined ? { ts, ...snap } : snap;
+  const emit = async (snap: StatusSnapshot, ts?: number): Promise<void> => {
+    let out: object = ts !== undefined ? { ts, ...snap } : snap;
+    // Phases of a live agent run to now; a stopped one's end at its exit marker.
+    if (argv.history) {
+      const now = snap.state === "stopped" ? null : Date.now();
+      out = { ...out, history: await readTitlePhases(record.cwd, record.pid, now) };
+    }
     process.stdout.write(JSON.stringify(out) + "\n");
   };
 
   // --wait: return as soon as the ball is in the operator's court — a blocking
-  // question (needs_input), a finished/quiet agent (idle), or an exit (stopped).
+  // question (needs_input), a finished/quiet agent (completed/idle), or an exit
+  // (stopped).
   // This is the fan-out primitive: a sub-agent that stops to ask no longer hides
   // behind "idle" until someone happens to look.
   if (wait) {
@@ -5054,15 +5211,16 @@ async function cmdStatus(rest: string[]): Promise<number> {
       // (it would otherwise have read as `idle`, which this loop already wakes on).
       if (
         snap.state === "needs_input" ||
+        snap.state === "completed" ||
         snap.state === "idle" ||
         snap.state === "stuck" ||
         snap.state === "stopped"
       ) {
-        emit(snap);
+        await emit(snap);
         return 0;
       }
       if (timeoutMs !== null && Date.now() - startedAt >= timeoutMs) {
-        emit(snap);
+        await emit(snap);
         return 2;
       }
       await new Promise((r) => setTimeout(r, intervalMs));
@@ -5075,16 +5233,17 @@ async function cmdStatus(rest: string[]): Promise<number> {
       const snap = await snapshotStatus(record);
       // A wedged agent reads as `stuck` rather than `idle`; still treat it as
       // "quiet, your turn" so `--wait-idle` doesn't hang on a stalled stream.
-      if (snap.state === "idle" || snap.state === "stuck") {
-        emit(snap);
+      // A finished one reads as `completed` — quiet too.
+      if (snap.state === "idle" || snap.state === "stuck" || snap.state === "completed") {
+        await emit(snap);
         return 0;
       }
       if (snap.state === "stopped") {
-        emit(snap);
+        await emit(snap);
         return 1;
       }
       if (timeoutMs !== null && Date.now() - startedAt >= timeoutMs) {
-        emit(snap);
+        await emit(snap);
         return 2;
       }
       await new Promise((r) => setTimeout(r, intervalMs));
@@ -5092,7 +5251,7 @@ async function cmdStatus(rest: string[]): Promise<number> {
   }
 
   if (!watch) {
-    emit(await snapshotStatus(record));
+    await emit(await snapshotStatus(record));
     return 0;
   }
 
@@ -5116,7 +5275,7 @@ async function cmdStatus(rest: string[]): Promise<number> {
       snap.question !== prev.question ||
       snap.exit_code !== prev.exit_code
     ) {
-      emit(snap, Date.now());
+      await emit(snap, Date.now());
       prev = {
         state: snap.state,
         activity: snap.activity,
@@ -5160,6 +5319,22 @@ async function loadStoredResult(pid: number): Promise<StoredResult | null> {
   }
 }
 
+/**
+ * Load the Rust wrapper's screen capture (`<cwd>/.agent-yes/<pid>.result.md`)
+ * as an envelope (`{ summary }`), or null if it never wrote one.
+ */
+async function loadScreenResult(record: GlobalPidRecord): Promise<StoredResult | null> {
+  const file = screenResultPath(record.cwd, record.pid);
+  try {
+    const [text, st] = await Promise.all([readFile(file, "utf8"), stat(file)]);
+    const summary = text.trim();
+    if (!summary) return null;
+    return buildStoredResult(record.pid, { summary }, Math.round(st.mtimeMs));
+  } catch {
+    return null;
+  }
+}
+
 /**
  * `ay result` — two modes:
  *
@@ -5170,6 +5345,10 @@ async function loadStoredResult(pid: number): Promise<StoredResult | null> {
  *   ay result <keyword> [--wait]   read side, run by the parent. Resolves the
  *                                  agent and emits the stored envelope as JSON.
  *
+ * Without an envelope, an agent the Rust wrapper saw through to `completed`
+ * (or that has since stopped) yields its last assistant message, captured from
+ * the screen, as `{ summary }`.
+ *
  * Read-side exit codes (so an orchestrator can branch without parsing):
  *   0  envelope found and emitted
  *   1  agent stopped WITHOUT depositing one (it's done; there's no result)
@@ -5253,10 +5432,20 @@ async function cmdResult(rest: string[]): Promise<number> {
       return 0;
     }
     const snap = await snapshotStatus(record);
+    if (snap.state === "completed") {
+      // Finished its task without an envelope: the wrapper's screen capture is
+      // the answer. (Only once completed — an older capture from a previous
+      // turn must not answer a task still in flight.)
+      const captured = await loadScreenResult(record);
+      if (captured) {
+        emitFound(captured);
+        return 0;
+      }
+    }
     if (snap.state === "stopped") {
       // Done, but never deposited an envelope. Re-check once: the agent may have
       // written the file in the same tick it exited (race), so prefer the file.
-      const last = await loadStoredResult(record.pid);
+      const last = (await loadStoredResult(record.pid)) ?? (await loadScreenResult(record));
       if (last) {
         emitFound(last);
         return 0;
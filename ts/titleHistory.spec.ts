@@ -0,0 +1,31 @@
+import { describe, expect, it } from "vitest";
+import { parseTitlePhases, titleHistoryPath } from "./titleHistory.ts";
+
+describe("parseTitlePhases", () => {
+  const text = [
+    JSON.stringify({ at: 1_000, title: "Explore" }),
+    JSON.stringify({ at: 4_000, title: "Fix" }),
+    JSON.stringify({ at: 9_000 }),
+    JSON.stringify({ at: 20_000, title: "Again" }),
+    '{"at":21',
+  ].join("\n");
+
+  it("times each phase until the next line or the end marker", () => {
+    expect(parseTitlePhases(text, 25_000)).toEqual([
+      { at: 1_000, title: "Explore", duration_ms: 3_000 },
+      { at: 4_000, title: "Fix", duration_ms: 5_000 },
+      { at: 20_000, title: "Again", duration_ms: 5_000 },
+    ]);
+  });
+
+  it("leaves the last phase open for a stopped agent without a marker", () => {
+    expect(parseTitlePhases(text, null).at(-1)?.duration_ms).toBeNull();
+    expect(parseTitlePhases("", 1)).toEqual([]);
+  });
+});
+
+describe("titleHistoryPath", () => {
+  it("sits next to the agent's other sidecars", () => {
+    expect(titleHistoryPath("/repo", 42)).toBe("/repo/.agent-yes/42.titles.jsonl");
+  });
+});
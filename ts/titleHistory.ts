@@ -0,0 +1,56 @@
+/**
+ * Terminal-title history — the phases a run went through.
+ *
+ * The Rust wrapper appends each distinct title to
+ * `<cwd>/.agent-yes/<pid>.titles.jsonl` as `{"at": <unix ms>, "title": "..."}`
+ * and closes a run with a bare `{"at": <unix ms>}` (rs/src/title_history.rs).
+ * A phase lasts until the next line; the current phase of a live agent runs to
+ * now. Same shape as the Rust daemon's `/api/titles/<kw>`.
+ *
+ * Pure parsing here, so it is unit-testable like `resultEnvelope.ts`; the fs
+ * read lives in `readTitlePhases`.
+ */
+
+import { readFile } from "fs/promises";
+import path from "path";
+
+export interface TitlePhase {
+  at: number;
+  title: string;
+  /** null for the last phase of a run that ended without a marker. */
+  duration_ms: number | null;
+}
+
+export function titleHistoryPath(cwd: string, pid: number): string {
+  return path.join(cwd, ".agent-yes", `${pid}.titles.jsonl`);
+}
+
+/** Phases from the file's text, oldest first. `now` closes a live agent's last phase. */
+export function parseTitlePhases(text: string, now: number | null): TitlePhase[] {
+  const entries: { at: number; title?: string }[] = [];
+  for (const line of text.split("\n")) {
+    try {
+      const e = JSON.parse(line);
+      if (typeof e?.at === "number") entries.push(e);
+    } catch {
+      // a torn line from a concurrent append
+    }
+  }
+  const phases: TitlePhase[] = [];
+  entries.forEach((e, i) => {
+    if (typeof e.title !== "string") return;
+    const end = entries[i + 1]?.at ?? now;
+    phases.push({ at: e.at, title: e.title, duration_ms: end === null ? null : Math.max(0, end - e.at) });
+  });
+  return phases;
+}
+
+/** The agent's title phases; empty when it recorded none (e.g. the TS runtime). */
+export async function readTitlePhases(
+  cwd: string,
+  pid: number,
+  now: number | null,
+): Promise<TitlePhase[]> {
+  const text = await readFile(titleHistoryPath(cwd, pid), "utf8").catch(() => "");
+  return parseTitlePhases(text, now);
+}